@@ -14,6 +14,10 @@ function! s:set_total_size(msg) abort
   let g:clap.display.initial_size = a:msg.total
 endfunction
 
+function! s:set_actions(msg) abort
+  let g:clap.provider.actions = a:msg.actions
+endfunction
+
 function! s:init_display(msg) abort
   if empty(g:clap.input.get())
     if g:clap.provider.id ==# 'blines'
@@ -48,6 +52,11 @@ function! clap#client#handle(msg) abort
     return
   endif
 
+  " The final message of the streaming filtering, e.g., `{ "event": "done", "total_matched": N }`.
+  if has_key(decoded, 'event')
+    return
+  endif
+
   if has_key(decoded, 'force_execute') && has_key(s:handlers, decoded.id)
     let Handler = remove(s:handlers, decoded.id)
     call Handler(get(decoded, 'result', v:null), get(decoded, 'error', v:null))
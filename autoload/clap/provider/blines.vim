@@ -50,7 +50,12 @@ if clap#maple#is_available()
     call g:clap.display.set_lines_lazy(s:format(a:lines))
   endfunction
   function! s:blines.init() abort
-    call clap#client#notify_on_init('on_init', {})
+    " The preview with the following lines is made from the buffer content instead of the file
+    " on disk, which is stale if the buffer is modified.
+    let params = exists('g:clap_provider_blines_context_after')
+          \ ? { 'buffer_lines': g:clap.start.get_lines() }
+          \ : {}
+    call clap#client#notify_on_init('on_init', params)
   endfunction
 else
   function! s:blines.init() abort
@@ -103,12 +108,20 @@ function! s:blines.on_typed() abort
   endif
 endfunction
 
+function! s:blines_on_move_async() abort
+  let params = {}
+  if exists('g:clap_provider_blines_context_after')
+    let params['context_after'] = g:clap_provider_blines_context_after
+  endif
+  call clap#client#call_on_move('on_move', function('clap#impl#on_move#handler'), params)
+endfunction
+
 " if Source() is 1,000,000+ lines, it could be very slow, e.g.,
 " `blines` provider, so we did a hard code for blines provider here.
 let s:blines.source_type = g:__t_func_list
 let s:blines.syntax = 'clap_blines'
 let s:blines['sink*'] = function('s:blines_sink_star')
-let s:blines.on_move_async = function('clap#impl#on_move#async')
+let s:blines.on_move_async = function('s:blines_on_move_async')
 let g:clap#provider#blines# = s:blines
 
 let &cpoptions = s:save_cpo
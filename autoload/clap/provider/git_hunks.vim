@@ -0,0 +1,39 @@
+" Author: liuchengxu <xuliuchengxlc@gmail.com>
+" Description: Navigate the hunks of uncommitted changes, `:Clap git_hunks ++staged` for the staged ones.
+
+let s:save_cpo = &cpoptions
+set cpoptions&vim
+
+let s:git_hunks = {}
+
+function! s:git_hunks.sink(selected) abort
+  let matched = matchlist(a:selected, '^\(.\{-}\):\(\d\+\):')
+  if empty(matched)
+    return
+  endif
+  call clap#sink#open_file(matched[1], str2nr(matched[2]), 1)
+endfunction
+
+function! s:git_hunks.on_typed() abort
+  call clap#client#call('git_hunks/on_typed', function('clap#state#handle_response_on_typed'), {
+        \ 'provider_id': g:clap.provider.id,
+        \ 'query': g:clap.input.get(),
+        \ })
+endfunction
+
+function! s:git_hunks.on_move_async() abort
+  call clap#client#call_with_lnum('git_hunks/on_move', function('clap#impl#on_move#handler'))
+endfunction
+
+function! s:git_hunks.init() abort
+  let staged = has_key(g:clap.context, 'staged') ? v:true : v:false
+  call clap#client#call_on_init(
+        \ 'git_hunks/on_init', function('clap#state#handle_response_on_typed'), clap#client#init_params({'staged': staged}))
+endfunction
+
+let s:git_hunks.enable_rooter = v:true
+
+let g:clap#provider#git_hunks# = s:git_hunks
+
+let &cpoptions = s:save_cpo
+unlet s:save_cpo
@@ -80,6 +80,16 @@ function! s:loclist.on_move() abort
   call clap#preview#highlight_header()
 endfunction
 
+function! s:loclist.on_move_async() abort
+  call clap#client#call('loclist', function('clap#impl#on_move#handler'), {
+        \ 'curline': g:clap.display.getcurline(),
+        \ 'cwd': clap#rooter#working_dir(),
+        \ 'winwidth': winwidth(g:clap.display.winid),
+        \ 'winheight': winheight(g:clap.display.winid),
+        \ 'winid': g:clap.start.winid,
+        \ })
+endfunction
+
 let s:loclist.syntax = 'qf'
 let g:clap#provider#loclist# = s:loclist
 
@@ -6,18 +6,26 @@
 //! 2. sort the all lines with a match result.
 //! 3. print the top rated filtered lines to stdout.
 
+mod score_cache;
 mod source;
+pub mod stats;
+mod windowed;
 mod worker;
 
 use std::sync::Arc;
 
+use parking_lot::Mutex;
 use rayon::prelude::*;
 
 use icon::Icon;
 use matcher::{Bonus, ClapItem, MatchScope, Matcher, MatcherBuilder};
-use source::MatchedItems;
+use utility::println_json_with_length;
 
-pub use self::source::Source;
+pub use self::score_cache::{CachedMatch, ScoreCache};
+pub use self::source::{BoundedLines, CommandLines, MatchedItems, Source};
+pub use self::windowed::{
+    WindowedFile, WindowedLine, WindowedMatches, Windows, DEFAULT_WINDOW_SIZE,
+};
 pub use self::worker::iterator::dyn_run;
 pub use self::worker::par_iterator::{par_dyn_run, par_dyn_run_list, ParSource};
 pub use matcher;
@@ -30,6 +38,12 @@ pub struct FilterContext {
     number: Option<usize>,
     winwidth: Option<usize>,
     matcher_builder: MatcherBuilder,
+    /// Number of items per rayon task in the parallel filtering, chosen automatically if not set.
+    batch_size: Option<usize>,
+    /// Print the score of each result.
+    show_score: bool,
+    /// Counters of the memory held by the filtering.
+    counters: Arc<stats::Counters>,
 }
 
 impl FilterContext {
@@ -44,6 +58,9 @@ impl FilterContext {
             number,
             winwidth,
             matcher_builder,
+            batch_size: None,
+            show_score: false,
+            counters: Default::default(),
         }
     }
 
@@ -57,6 +74,21 @@ impl FilterContext {
         self
     }
 
+    pub fn batch_size(mut self, batch_size: Option<usize>) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn show_score(mut self, show_score: bool) -> Self {
+        self.show_score = show_score;
+        self
+    }
+
+    pub fn counters(mut self, counters: Arc<stats::Counters>) -> Self {
+        self.counters = counters;
+        self
+    }
+
     pub fn icon(mut self, icon: Icon) -> Self {
         self.icon = icon;
         self
@@ -73,8 +105,39 @@ impl FilterContext {
     }
 }
 
+/// Final message of the streaming filtering, telling the client that no more results will come.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Done {
+    pub total_matched: usize,
+    /// Whether the run was terminated before the source is exhausted, e.g., by a timeout.
+    pub partial: bool,
+}
+
+impl Done {
+    /// Prints `{ "event": "done", "total_matched": N }`, plus `"partial": true` if the run was
+    /// terminated early.
+    pub fn print(self) {
+        let Self {
+            total_matched,
+            partial,
+        } = self;
+        let event = "done";
+        if partial {
+            println_json_with_length!(event, total_matched, partial);
+        } else {
+            println_json_with_length!(event, total_matched);
+        }
+    }
+}
+
 /// Performs the synchorous filtering on a small scale of source in parallel.
-pub fn par_filter(source_items: Vec<SourceItem>, fuzzy_matcher: &Matcher) -> Vec<MatchedItem> {
+pub fn par_filter(
+    source_items: Vec<SourceItem>,
+    fuzzy_matcher: &Matcher,
+    counters: &stats::Counters,
+) -> Vec<MatchedItem> {
+    counters.reset_candidates();
+    counters.add_candidates(source_items.len());
     let matched_items: MatchedItems = source_items
         .into_par_iter()
         .filter_map(|item| {
@@ -90,7 +153,10 @@ pub fn par_filter(source_items: Vec<SourceItem>, fuzzy_matcher: &Matcher) -> Vec
 pub fn par_filter_items(
     source_items: &[Arc<dyn ClapItem>],
     fuzzy_matcher: &Matcher,
+    counters: &stats::Counters,
 ) -> Vec<MatchedItem> {
+    counters.reset_candidates();
+    counters.add_candidates(source_items.len());
     let matched_items: MatchedItems = source_items
         .into_par_iter()
         .filter_map(|item| fuzzy_matcher.match_item(item.clone()))
@@ -98,3 +164,67 @@ pub fn par_filter_items(
         .into();
     matched_items.par_sort().inner()
 }
+
+/// Same as [`par_filter_items`], but the match results are looked up in `score_cache` first.
+///
+/// `fingerprint` is the fingerprint of `source_items` and the matcher configuration, see
+/// [`ScoreCache::fingerprint`]. The cache is only locked for the lookups and insertions, not
+/// during the filtering.
+pub fn par_filter_items_cached(
+    source_items: &[Arc<dyn ClapItem>],
+    fuzzy_matcher: &Matcher,
+    query: &str,
+    fingerprint: u64,
+    score_cache: &Mutex<ScoreCache>,
+    counters: &stats::Counters,
+) -> Vec<MatchedItem> {
+    if !score_cache.lock().is_enabled() {
+        return par_filter_items(source_items, fuzzy_matcher, counters);
+    }
+
+    counters.reset_candidates();
+    counters.add_candidates(source_items.len());
+
+    let keys = source_items
+        .par_iter()
+        .map(|item| ScoreCache::key(query, item.raw_text()))
+        .collect::<Vec<_>>();
+
+    let cached_matches = {
+        let mut score_cache = score_cache.lock();
+        score_cache.set_source(fingerprint);
+        keys.iter()
+            .map(|key| score_cache.get(*key))
+            .collect::<Vec<_>>()
+    };
+
+    let results = source_items
+        .par_iter()
+        .zip(cached_matches.into_par_iter())
+        .map(|(item, cached_match)| match cached_match {
+            Some(cached_match) => {
+                let matched_item = cached_match
+                    .map(|(score, indices)| MatchedItem::new(item.clone(), score, indices));
+                (matched_item, true)
+            }
+            None => (fuzzy_matcher.match_item(item.clone()), false),
+        })
+        .collect::<Vec<_>>();
+
+    let mut score_cache = score_cache.lock();
+    // Another source may have been switched to during the filtering.
+    let is_same_source = score_cache.source() == Some(fingerprint);
+    let mut matched_items = Vec::with_capacity(results.len());
+    for (key, (matched_item, cached)) in keys.into_iter().zip(results) {
+        if !cached && is_same_source {
+            let cached_match = matched_item
+                .as_ref()
+                .map(|matched_item| (matched_item.score, matched_item.indices.clone()));
+            score_cache.insert(key, cached_match);
+        }
+        matched_items.extend(matched_item);
+    }
+    drop(score_cache);
+
+    MatchedItems::from(matched_items).par_sort().inner()
+}
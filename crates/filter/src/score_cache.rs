@@ -0,0 +1,264 @@
+//! Bounded cache of the match results keyed on the query and candidate, which saves the scoring
+//! when the same source is filtered by the same query repeatedly, e.g., reopening `files`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use types::Score;
+use utility::calculate_hash;
+
+/// Cached match result of a candidate, `None` if the candidate does not match the query.
+pub type CachedMatch = Option<(Score, Vec<usize>)>;
+
+#[derive(Debug, Default)]
+pub struct ScoreCache {
+    /// Maximum number of the cached results, the cache is disabled if it's 0.
+    capacity: usize,
+    /// Fingerprint of the source the cached results are computed from.
+    source: Option<u64>,
+    entries: HashMap<u64, CachedMatch>,
+    /// Keys in the insertion order, the oldest one is evicted once the cache is full.
+    order: VecDeque<u64>,
+    /// Number of the lookups returning a cached result.
+    hits: AtomicUsize,
+}
+
+impl ScoreCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the fingerprint of the raw text of all candidates, it's supposed to be computed
+    /// once per source.
+    pub fn source_fingerprint<'a>(candidates: impl Iterator<Item = &'a str>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        candidates.for_each(|candidate| candidate.hash(&mut hasher));
+        hasher.finish()
+    }
+
+    /// Returns the fingerprint of the source of `source_fingerprint` filtered with `settings`,
+    /// which must cover everything else affecting the match results apart from the query, e.g.,
+    /// the whole configuration of matcher.
+    pub fn fingerprint(settings: impl Hash, source_fingerprint: u64) -> u64 {
+        calculate_hash(&(settings, source_fingerprint))
+    }
+
+    /// Returns the fingerprint of the current source.
+    pub fn source(&self) -> Option<u64> {
+        self.source
+    }
+
+    /// Switches to the source of `fingerprint`, all the cached results are invalidated if the
+    /// source has changed.
+    pub fn set_source(&mut self, fingerprint: u64) {
+        if self.source != Some(fingerprint) {
+            self.source.replace(fingerprint);
+            self.entries.clear();
+            self.order.clear();
+        }
+    }
+
+    /// Returns the key of the match result of `candidate` for `query`, `candidate` is the raw
+    /// text of item as the match and bonus texts are both derived from it.
+    pub fn key(query: &str, candidate: &str) -> u64 {
+        calculate_hash(&(query, candidate))
+    }
+
+    /// Returns the cached match result of `key`.
+    pub fn get(&self, key: u64) -> Option<CachedMatch> {
+        let cached = self.entries.get(&key).cloned();
+        if cached.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        cached
+    }
+
+    pub fn insert(&mut self, key: u64, cached_match: CachedMatch) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        if self.entries.insert(key, cached_match).is_none() {
+            self.order.push_back(key);
+        }
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::par_filter_items_cached;
+    use matcher::MatcherBuilder;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use types::{ClapItem, SourceItem};
+
+    fn items(lines: &[&str]) -> Vec<Arc<dyn ClapItem>> {
+        lines
+            .iter()
+            .map(|line| Arc::new(SourceItem::from(line.to_string())) as Arc<dyn ClapItem>)
+            .collect()
+    }
+
+    #[test]
+    fn test_second_filter_run_hits_cache() {
+        let source_items = items(&["src/lib.rs", "src/main.rs", "README.md", "Cargo.toml"]);
+        let matcher = MatcherBuilder::default().build("src".into());
+        let fingerprint = |items: &[Arc<dyn ClapItem>]| {
+            let source_fingerprint =
+                ScoreCache::source_fingerprint(items.iter().map(|item| item.raw_text()));
+            ScoreCache::fingerprint("files", source_fingerprint)
+        };
+
+        let cache = Mutex::new(ScoreCache::new(100));
+
+        let first = par_filter_items_cached(
+            &source_items,
+            &matcher,
+            "src",
+            fingerprint(&source_items),
+            &cache,
+            &Default::default(),
+        );
+        assert_eq!(cache.lock().hits(), 0);
+        assert_eq!(cache.lock().len(), source_items.len());
+
+        let second = par_filter_items_cached(
+            &source_items,
+            &matcher,
+            "src",
+            fingerprint(&source_items),
+            &cache,
+            &Default::default(),
+        );
+        assert_eq!(cache.lock().hits(), source_items.len());
+
+        let results = |matched_items: Vec<types::MatchedItem>| {
+            matched_items
+                .into_iter()
+                .map(|m| (m.item.raw_text().to_string(), m.score, m.indices))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(results(first), results(second));
+
+        // The cached results are invalidated once the source is changed.
+        let changed_items = items(&["src/lib.rs", "src/bin.rs"]);
+        cache.lock().set_source(fingerprint(&changed_items));
+        assert!(cache.lock().is_empty());
+        par_filter_items_cached(
+            &changed_items,
+            &matcher,
+            "src",
+            fingerprint(&changed_items),
+            &cache,
+            &Default::default(),
+        );
+        assert_eq!(cache.lock().hits(), source_items.len());
+    }
+
+    #[test]
+    fn test_matcher_config_in_fingerprint() {
+        let source_items = items(&["Src/lib.rs", "src/main.rs"]);
+        let cache = Mutex::new(ScoreCache::new(100));
+
+        let filter = |matcher_builder: MatcherBuilder| {
+            let fingerprint = ScoreCache::fingerprint(
+                ("files", utility::calculate_hash(&matcher_builder)),
+                ScoreCache::source_fingerprint(source_items.iter().map(|item| item.raw_text())),
+            );
+            let matcher = matcher_builder.build("src".into());
+            par_filter_items_cached(
+                &source_items,
+                &matcher,
+                "src",
+                fingerprint,
+                &cache,
+                &Default::default(),
+            )
+            .len()
+        };
+
+        assert_eq!(filter(MatcherBuilder::default()), 2);
+        // The results of the default case matching are never reused.
+        assert_eq!(
+            filter(MatcherBuilder::default().case_matching(types::CaseMatching::Respect)),
+            1
+        );
+        assert_eq!(cache.lock().hits(), 0);
+    }
+
+    #[test]
+    fn test_cache_keyed_on_raw_text() {
+        // The same match text, but the indices of the item with a hidden filter text are cleared.
+        let source_items = items(&["src/lib.rs", "lib.rs\x1fsrc/lib.rs"]);
+        let matcher = MatcherBuilder::default().build("lib".into());
+        let fingerprint = ScoreCache::fingerprint(
+            "files",
+            ScoreCache::source_fingerprint(source_items.iter().map(|item| item.raw_text())),
+        );
+        let cache = Mutex::new(ScoreCache::new(100));
+
+        let filter = || {
+            let mut results = par_filter_items_cached(
+                &source_items,
+                &matcher,
+                "lib",
+                fingerprint,
+                &cache,
+                &Default::default(),
+            )
+            .into_iter()
+            .map(|m| (m.item.raw_text().to_string(), m.indices))
+            .collect::<Vec<_>>();
+            results.sort();
+            results
+        };
+
+        let first = filter();
+        assert_eq!(cache.lock().len(), 2);
+        assert_eq!(filter(), first);
+        assert_eq!(cache.lock().hits(), 2);
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let mut cache = ScoreCache::new(2);
+        for key in 0..5 {
+            cache.insert(key, Some((key as Score, vec![])));
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(0), None);
+        assert_eq!(cache.get(4), Some(Some((4, vec![]))));
+
+        let mut disabled = ScoreCache::new(0);
+        disabled.insert(0, None);
+        assert!(disabled.is_empty());
+    }
+}
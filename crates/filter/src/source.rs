@@ -1,21 +1,230 @@
 use std::io::BufRead;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use rayon::prelude::*;
 use subprocess::Exec;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStdout, Command};
 
 use matcher::Matcher;
-use types::{ClapItem, MatchedItem, SourceItem};
+use types::{BufferLine, ClapItem, MatchedItem, SourceItem, FIELD_SEPARATOR};
+
+/// Maximum number of lines read from stdin ahead of the filtering when waiting with a timeout.
+pub const STDIN_BUFFER_CAPACITY: usize = 1024;
+
+/// Interval of checking the interruption flag while waiting for the next line.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Minimum size in bytes of the chunk of lines sharing a buffer in a file source.
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Iterator of lines read in a background thread into a bounded channel.
+///
+/// Only used to stop waiting on a stream stalled without being closed, which a blocking read
+/// can not do, the stdin without a timeout is read directly as the lines are consumed. The
+/// reading stops once the channel is full until the consumer catches up, which keeps the memory
+/// bounded when the producer is faster than the filtering.
+pub struct BoundedLines {
+    receiver: Receiver<String>,
+    /// Stop waiting if no new line arrives within this duration.
+    timeout: Option<Duration>,
+    /// Set once the iteration is ended by the timeout instead of the end of stream.
+    timed_out: Arc<AtomicBool>,
+}
+
+impl BoundedLines {
+    pub fn new<R: BufRead + Send + 'static>(reader: R, capacity: usize) -> Self {
+        let (sender, receiver) = sync_channel(capacity);
+        std::thread::spawn(move || {
+            for line in reader.lines().filter_map(Result::ok) {
+                // The receiver has been dropped.
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            receiver,
+            timeout: None,
+            timed_out: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Ends the iteration if no new line arrives within `timeout` after the last one, so that
+    /// a producer stalling without closing the stream won't block forever.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns a flag telling whether the iteration has been ended by the timeout, i.e., the
+    /// stream is incomplete.
+    pub fn timed_out(&self) -> Arc<AtomicBool> {
+        self.timed_out.clone()
+    }
+
+    /// Same with [`Iterator::next`], but stops waiting once `interrupted` is set.
+    ///
+    /// The lines are read in another thread, a blocking read there is not necessarily
+    /// interrupted by the signal, hence the flag is polled while waiting.
+    pub fn next_until(&mut self, interrupted: &AtomicBool) -> Option<String> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let wait = match deadline {
+                Some(deadline) => deadline
+                    .saturating_duration_since(Instant::now())
+                    .min(INTERRUPT_POLL_INTERVAL),
+                None => INTERRUPT_POLL_INTERVAL,
+            };
+            match self.receiver.recv_timeout(wait) {
+                Ok(line) => return Some(line),
+                Err(RecvTimeoutError::Disconnected) => return None,
+                Err(RecvTimeoutError::Timeout) => {
+                    if interrupted.load(Ordering::SeqCst) {
+                        return None;
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        self.timed_out.store(true, Ordering::SeqCst);
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lines from stdin with the capacity of [`STDIN_BUFFER_CAPACITY`].
+    pub fn stdin() -> Self {
+        Self::new(
+            std::io::BufReader::new(std::io::stdin()),
+            STDIN_BUFFER_CAPACITY,
+        )
+    }
+}
+
+impl Iterator for BoundedLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.timeout {
+            Some(timeout) => match self.receiver.recv_timeout(timeout) {
+                Ok(line) => Some(line),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.timed_out.store(true, Ordering::SeqCst);
+                    None
+                }
+                Err(RecvTimeoutError::Disconnected) => None,
+            },
+            None => self.receiver.recv().ok(),
+        }
+    }
+}
+
+/// Lines of the stdout of a command, read asynchronously as the command produces them.
+///
+/// The command is killed once this is dropped, so the reading can be cancelled in the middle of
+/// the stream by dropping the future reading the lines.
+#[derive(Debug)]
+pub struct CommandLines {
+    _child: Child,
+    reader: BufReader<ChildStdout>,
+}
+
+impl CommandLines {
+    pub fn spawn(mut cmd: Command) -> std::io::Result<Self> {
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped; qed");
+        Ok(Self {
+            _child: child,
+            reader: BufReader::new(stdout),
+        })
+    }
+
+    /// Returns the next line, `None` once the stdout is closed.
+    ///
+    /// The lines of invalid UTF-8 are skipped.
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        loop {
+            let mut buf = Vec::new();
+            if self.reader.read_until(b'\n', &mut buf).await? == 0 {
+                return Ok(None);
+            }
+            if buf.ends_with(b"\n") {
+                buf.pop();
+                if buf.ends_with(b"\r") {
+                    buf.pop();
+                }
+            }
+            if let Ok(line) = String::from_utf8(buf) {
+                return Ok(Some(line));
+            }
+        }
+    }
+}
+
+/// Reads the next line of `reader`, stops once `interrupted` is set when a read is interrupted by
+/// a signal, which is retried otherwise.
+///
+/// Unlike [`BufRead::lines`], which always retries the interrupted reads. The lines of invalid
+/// UTF-8 are skipped.
+fn next_line_until(reader: &mut impl BufRead, interrupted: &AtomicBool) -> Option<String> {
+    let mut buf = Vec::new();
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+                if interrupted.load(Ordering::SeqCst) {
+                    return None;
+                }
+                continue;
+            }
+            Err(_) => return None,
+        };
+
+        if available.is_empty() {
+            if buf.is_empty() {
+                return None;
+            }
+        } else if let Some(newline) = available.iter().position(|b| *b == b'\n') {
+            buf.extend_from_slice(&available[..newline]);
+            reader.consume(newline + 1);
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        } else {
+            let len = available.len();
+            buf.extend_from_slice(available);
+            reader.consume(len);
+            continue;
+        }
+
+        match String::from_utf8(std::mem::take(&mut buf)) {
+            Ok(line) => return Some(line),
+            Err(_) => continue,
+        }
+    }
+}
 
 /// Source is anything that can produce an iterator of String.
 #[derive(Debug)]
 pub enum Source<I: Iterator<Item = Arc<dyn ClapItem>>> {
     List(I),
-    Stdin,
+    /// Lines from stdin, ends if no new line arrives within the timeout if any.
+    Stdin(Option<Duration>),
     File(PathBuf),
     Exec(Box<Exec>),
+    /// Lines streamed from a command in the async runtime, only available to
+    /// [`Source::matched_items_async`].
+    Command(Box<Command>),
 }
 
 impl<I: Iterator<Item = Arc<dyn ClapItem>>> From<PathBuf> for Source<I> {
@@ -30,6 +239,12 @@ impl<I: Iterator<Item = Arc<dyn ClapItem>>> From<Exec> for Source<I> {
     }
 }
 
+impl<I: Iterator<Item = Arc<dyn ClapItem>>> From<Command> for Source<I> {
+    fn from(cmd: Command) -> Self {
+        Self::Command(Box::new(cmd))
+    }
+}
+
 #[derive(Debug)]
 pub struct MatchedItems(Vec<MatchedItem>);
 
@@ -41,6 +256,40 @@ impl MatchedItems {
         Self(items)
     }
 
+    /// Same with [`Self::par_sort`], but the shorter item goes first among the items of equal
+    /// score, then the one coming first in the source.
+    pub fn par_sort_prefer_shorter(self) -> Self {
+        let mut items = self.0;
+        items.par_sort_by(|v1, v2| {
+            v2.score
+                .cmp(&v1.score)
+                .then_with(|| v1.item.raw_text().len().cmp(&v2.item.raw_text().len()))
+        });
+        Self(items)
+    }
+
+    /// Returns the item with highest score without sorting all the items, the one coming first
+    /// in the source wins among the items of equal score.
+    ///
+    /// The shorter item wins before the source order if `prefer_shorter` is true, the same as
+    /// the first item of [`Self::par_sort_prefer_shorter`].
+    pub fn best(self, prefer_shorter: bool) -> Option<MatchedItem> {
+        self.0.into_iter().reduce(|best, item| {
+            let ordering = item.score.cmp(&best.score).then_with(|| {
+                if prefer_shorter {
+                    best.item.raw_text().len().cmp(&item.item.raw_text().len())
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            });
+            if ordering.is_gt() {
+                item
+            } else {
+                best
+            }
+        })
+    }
+
     pub fn inner(self) -> Vec<MatchedItem> {
         self.0
     }
@@ -57,32 +306,544 @@ impl<I: Iterator<Item = Arc<dyn ClapItem>>> Source<I> {
     ///
     /// This is kind of synchronous filtering, can be used for multi-staged processing.
     pub fn matched_items(self, matcher: Matcher) -> Result<MatchedItems> {
-        let clap_item_stream: Box<dyn Iterator<Item = Arc<dyn ClapItem>>> = match self {
+        self.matched_items_until(matcher, &AtomicBool::new(false))
+    }
+
+    /// Returns the items of this source, except [`Source::Command`] which is only available
+    /// asynchronously.
+    pub fn items<'a>(self) -> Result<Box<dyn Iterator<Item = Arc<dyn ClapItem>> + 'a>>
+    where
+        I: 'a,
+    {
+        let clap_item_stream: Box<dyn Iterator<Item = Arc<dyn ClapItem>> + 'a> = match self {
             Self::List(list) => Box::new(list),
-            Self::Stdin => Box::new(
+            Self::Stdin(None) => Box::new(
                 std::io::stdin()
                     .lock()
                     .lines()
                     .filter_map(Result::ok)
                     .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
             ),
-            Self::File(path) => Box::new(
-                std::io::BufReader::new(std::fs::File::open(path)?)
-                    .lines()
-                    .filter_map(Result::ok)
+            Self::Stdin(timeout) => Box::new(
+                BoundedLines::stdin()
+                    .timeout(timeout)
                     .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
             ),
+            Self::File(path) => file_items(&path)?,
             Self::Exec(exec) => Box::new(
                 std::io::BufReader::new(exec.stream_stdout()?)
                     .lines()
                     .filter_map(Result::ok)
                     .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
             ),
+            Self::Command(_) => return Err(command_source_error()),
+        };
+        Ok(clap_item_stream)
+    }
+
+    /// Same with [`Self::matched_items`], but stops reading the source once `interrupted` is set,
+    /// the items read so far are still filtered.
+    ///
+    /// The waiting for the next line of [`Source::Stdin`] and [`Source::Exec`] is stopped too.
+    pub fn matched_items_until<'a>(
+        self,
+        matcher: Matcher,
+        interrupted: &'a AtomicBool,
+    ) -> Result<MatchedItems>
+    where
+        I: 'a,
+    {
+        let clap_item_stream: Box<dyn Iterator<Item = Arc<dyn ClapItem>> + 'a> = match self {
+            Self::Stdin(None) => {
+                let mut reader = std::io::stdin().lock();
+                Box::new(
+                    std::iter::from_fn(move || next_line_until(&mut reader, interrupted))
+                        .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
+                )
+            }
+            Self::Stdin(timeout) => {
+                let mut lines = BoundedLines::stdin().timeout(timeout);
+                Box::new(
+                    std::iter::from_fn(move || lines.next_until(interrupted))
+                        .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
+                )
+            }
+            Self::Exec(exec) => {
+                let mut reader = std::io::BufReader::new(exec.stream_stdout()?);
+                Box::new(
+                    std::iter::from_fn(move || next_line_until(&mut reader, interrupted))
+                        .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
+                )
+            }
+            source => source.items()?,
         };
 
         Ok(clap_item_stream
+            .take_while(|_| !interrupted.load(Ordering::SeqCst))
             .filter_map(|item| matcher.match_item(item))
             .collect::<Vec<_>>()
             .into())
     }
+
+    /// Same with [`Self::matched_items`], but the lines of [`Source::Command`] are filtered as
+    /// soon as the command produces them.
+    ///
+    /// The command is killed once the returned future is dropped, e.g., the job running this
+    /// filtering is cancelled.
+    pub async fn matched_items_async(self, matcher: Matcher) -> Result<MatchedItems> {
+        let cmd = match self {
+            Self::Command(cmd) => cmd,
+            source => return source.matched_items(matcher),
+        };
+
+        let mut lines = CommandLines::spawn(*cmd)?;
+
+        let mut matched_items = Vec::new();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(matched_item) = matcher.match_item(Arc::new(SourceItem::from(line))) {
+                matched_items.push(matched_item);
+            }
+        }
+
+        Ok(matched_items.into())
+    }
+}
+
+/// Returns the items of the file at `path`.
+///
+/// The file is streamed in chunks of complete lines, each item references a line of the chunk
+/// shared by the lines instead of owning a copy, unless the chunk is not valid UTF-8 or
+/// contains the [`FIELD_SEPARATOR`], in which case each line of the chunk is read into a
+/// [`SourceItem`].
+pub(crate) fn file_items(
+    path: &Path,
+) -> std::io::Result<Box<dyn Iterator<Item = Arc<dyn ClapItem>>>> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let chunks = std::iter::from_fn(move || {
+        let mut chunk = Vec::with_capacity(FILE_CHUNK_SIZE);
+        while chunk.len() < FILE_CHUNK_SIZE {
+            // The stream ends on a read error, the same as the lines failing to be read.
+            match reader.read_until(b'\n', &mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+        (!chunk.is_empty()).then_some(chunk)
+    });
+    Ok(Box::new(chunks.flat_map(chunk_items)))
+}
+
+/// Returns the items of the lines in `chunk`.
+fn chunk_items(chunk: Vec<u8>) -> Box<dyn Iterator<Item = Arc<dyn ClapItem>>> {
+    let chunk = match String::from_utf8(chunk) {
+        Ok(content) if !content.contains(FIELD_SEPARATOR) => {
+            return Box::new(
+                BufferLine::split(Arc::new(content))
+                    .map(|line| Arc::new(line) as Arc<dyn ClapItem>),
+            );
+        }
+        Ok(content) => content.into_bytes(),
+        Err(e) => e.into_bytes(),
+    };
+
+    // To avoid Err(Custom { kind: InvalidData, error: "stream did not contain valid UTF-8" })
+    // The line stream can contain invalid UTF-8 data.
+    Box::new(
+        BufRead::lines(std::io::Cursor::new(chunk))
+            .filter_map(Result::ok)
+            .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
+    )
+}
+
+/// Error of consuming [`Source::Command`] synchronously.
+pub(crate) fn command_source_error() -> anyhow::Error {
+    anyhow!("Source::Command can only be filtered asynchronously")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use types::AsAny;
+
+    /// Endless reader producing one line per read.
+    struct FastProducer(Arc<AtomicUsize>);
+
+    impl Read for FastProducer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let line = b"line\n";
+            buf[..line.len()].copy_from_slice(line);
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(line.len())
+        }
+    }
+
+    #[test]
+    fn test_matched_items_until_interrupted() {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let read = AtomicUsize::new(0);
+        let items = (0..100).map(|i| Arc::new(format!("src/{i}.rs")) as Arc<dyn ClapItem>);
+        let matcher = matcher::MatcherBuilder::default().build("src".into());
+
+        let interrupt = interrupted.clone();
+        let matched_items = Source::List(items.inspect(move |_| {
+            // Interrupted on reading the 10th item.
+            if read.fetch_add(1, Ordering::SeqCst) + 1 == 10 {
+                interrupt.store(true, Ordering::SeqCst);
+            }
+        }))
+        .matched_items_until(matcher, &interrupted)
+        .unwrap()
+        .inner();
+
+        assert_eq!(matched_items.len(), 9);
+        assert!(matched_items
+            .iter()
+            .all(|matched_item| matched_item.item.raw_text() != "src/9.rs"));
+    }
+
+    /// Reader blocked until being interrupted by a signal.
+    struct Interrupted;
+
+    impl Read for Interrupted {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::Interrupted.into())
+        }
+    }
+
+    #[test]
+    fn test_next_line_until_interrupted() {
+        let interrupted = AtomicBool::new(false);
+        let mut reader = std::io::BufReader::new(std::io::Cursor::new(b"foo\r\nbar\n\xff\nbaz"));
+        assert_eq!(
+            std::iter::from_fn(|| next_line_until(&mut reader, &interrupted)).collect::<Vec<_>>(),
+            vec!["foo", "bar", "baz"]
+        );
+
+        // The interrupted read is retried unless the flag is set.
+        let mut reader = std::io::BufReader::new(Interrupted);
+        interrupted.store(true, Ordering::SeqCst);
+        assert_eq!(next_line_until(&mut reader, &interrupted), None);
+    }
+
+    /// Reader stalling without closing the stream.
+    struct Stalled;
+
+    impl Read for Stalled {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(Duration::from_secs(3600));
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_bounded_lines_interrupted() {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let mut lines = BoundedLines::new(std::io::BufReader::new(Stalled), 1);
+
+        std::thread::spawn({
+            let interrupted = interrupted.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(100));
+                interrupted.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let now = Instant::now();
+        assert_eq!(lines.next_until(&interrupted), None);
+        assert!(now.elapsed() < Duration::from_secs(10));
+        assert!(!lines.timed_out().load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_best() {
+        let matched_items = || {
+            MatchedItems::from(
+                [
+                    ("src/lib.rs", 10),
+                    ("a/lib.rs", 20),
+                    ("lib.rs", 20),
+                    ("b.rs", 5),
+                ]
+                .into_iter()
+                .map(|(text, score)| {
+                    MatchedItem::new(Arc::new(text.to_string()), score, Vec::new())
+                })
+                .collect::<Vec<_>>(),
+            )
+        };
+        let best_text = |prefer_shorter: bool| {
+            matched_items()
+                .best(prefer_shorter)
+                .map(|matched_item| matched_item.item.raw_text().to_string())
+        };
+
+        assert_eq!(best_text(false), Some("a/lib.rs".into()));
+        assert_eq!(best_text(true), Some("lib.rs".into()));
+        assert_eq!(
+            matched_items()
+                .best(true)
+                .map(|matched_item| matched_item.item.raw_text().to_string()),
+            matched_items()
+                .par_sort_prefer_shorter()
+                .inner()
+                .first()
+                .map(|matched_item| matched_item.item.raw_text().to_string())
+        );
+        assert!(MatchedItems::from(Vec::new()).best(false).is_none());
+    }
+
+    #[test]
+    fn test_par_sort_prefer_shorter() {
+        let matched_items = || -> MatchedItems {
+            [
+                ("src/foo_long.rs", 10),
+                ("src/foo.rs", 10),
+                ("src/goo.rs", 10),
+                ("bar", 20),
+            ]
+            .into_iter()
+            .map(|(text, score)| MatchedItem::new(Arc::new(text), score, Default::default()))
+            .collect::<Vec<_>>()
+            .into()
+        };
+        let texts = |matched_items: MatchedItems| {
+            matched_items
+                .inner()
+                .into_iter()
+                .map(|matched_item| (matched_item.item.raw_text().to_string(), matched_item.score))
+                .collect::<Vec<_>>()
+        };
+
+        let ranked = texts(matched_items().par_sort());
+        assert_eq!(ranked[0], ("bar".to_string(), 20));
+        assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+
+        assert_eq!(
+            texts(matched_items().par_sort_prefer_shorter()),
+            vec![
+                ("bar".to_string(), 20),
+                ("src/foo.rs".to_string(), 10),
+                ("src/goo.rs".to_string(), 10),
+                ("src/foo_long.rs".to_string(), 10),
+            ]
+        );
+    }
+
+    /// Reader producing a few lines and then stalling without closing the stream.
+    pub(crate) struct StallingProducer(pub usize);
+
+    impl Read for StallingProducer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0 == 0 {
+                loop {
+                    std::thread::sleep(Duration::from_secs(3600));
+                }
+            }
+            self.0 -= 1;
+            let line = b"line\n";
+            buf[..line.len()].copy_from_slice(line);
+            Ok(line.len())
+        }
+    }
+
+    #[test]
+    fn test_bounded_lines_timeout() {
+        let reader = std::io::BufReader::new(StallingProducer(3));
+        let now = std::time::Instant::now();
+        let mut bounded_lines =
+            BoundedLines::new(reader, 8).timeout(Some(Duration::from_millis(100)));
+        let timed_out = bounded_lines.timed_out();
+        let lines = bounded_lines.by_ref().collect::<Vec<_>>();
+        assert_eq!(lines, vec!["line"; 3]);
+        assert!(timed_out.load(Ordering::SeqCst));
+        assert!(now.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Returns a command printing a line per 10ms endlessly, the lines are also appended to `log`.
+    fn endless_command(log: &std::path::Path) -> Command {
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c").arg(format!(
+            "i=0; while true; do echo \"line $i\" | tee -a {}; i=$((i+1)); sleep 0.01; done",
+            log.display()
+        ));
+        cmd
+    }
+
+    #[tokio::test]
+    async fn test_command_source_cancellation() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+
+        // The lines are ingested while the command is still running.
+        let mut lines = CommandLines::spawn(endless_command(&log)).unwrap();
+        for i in 0..3 {
+            assert_eq!(lines.next_line().await.unwrap(), Some(format!("line {i}")));
+        }
+        drop(lines);
+
+        let matcher = matcher::MatcherBuilder::default().build("line".into());
+        let filtering = Source::<std::iter::Empty<Arc<dyn ClapItem>>>::from(endless_command(&log))
+            .matched_items_async(matcher);
+        assert!(tokio::time::timeout(Duration::from_millis(100), filtering)
+            .await
+            .is_err());
+
+        // The commands are killed once cancelled.
+        let written_lines = || std::fs::read_to_string(&log).unwrap().lines().count();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let written = written_lines();
+        assert!(written > 3);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(written_lines(), written);
+    }
+
+    #[test]
+    fn test_bounded_lines() {
+        let capacity = 8;
+        let produced = Arc::new(AtomicUsize::new(0));
+        let reader = std::io::BufReader::new(FastProducer(produced.clone()));
+
+        let mut lines = BoundedLines::new(reader, capacity);
+        for consumed in 1..=50 {
+            assert_eq!(lines.next().as_deref(), Some("line"));
+            std::thread::sleep(Duration::from_millis(2));
+            // The line being held by the blocking send and the one being read are in flight.
+            let buffered = produced.load(Ordering::SeqCst) - consumed;
+            assert!(buffered <= capacity + 2, "{buffered} lines are buffered");
+        }
+    }
+    /// Returns the items of the file at `path` read line by line into [`SourceItem`]s.
+    fn owned_file_items(path: &Path) -> Vec<Arc<dyn ClapItem>> {
+        std::io::BufReader::new(std::fs::File::open(path).unwrap())
+            .lines()
+            .filter_map(Result::ok)
+            .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>)
+            .collect()
+    }
+
+    fn raw_texts(items: &[Arc<dyn ClapItem>]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| item.raw_text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_file_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines.txt");
+
+        std::fs::write(&path, "src/lib.rs\r\n\nsrc/main.rs\nREADME.md").unwrap();
+        let items = file_items(&path).unwrap().collect::<Vec<_>>();
+        assert_eq!(raw_texts(&items), raw_texts(&owned_file_items(&path)));
+        assert!(items
+            .iter()
+            .all(|item| AsAny::as_any(&**item).is::<BufferLine>()));
+
+        let matcher = matcher::MatcherBuilder::default().build("srs".into());
+        let matched = |items: Vec<Arc<dyn ClapItem>>| {
+            items
+                .into_iter()
+                .filter_map(|item| matcher.match_item(item))
+                .map(|matched_item| (matched_item.score, matched_item.indices))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matched(items), matched(owned_file_items(&path)));
+
+        // Fall back to the owned lines.
+        std::fs::write(&path, b"display\x1ffilter\n\xff\nsrc/lib.rs\n").unwrap();
+        let items = file_items(&path).unwrap().collect::<Vec<_>>();
+        assert_eq!(raw_texts(&items), raw_texts(&owned_file_items(&path)));
+        assert_eq!(items.len(), 2);
+        assert!(AsAny::as_any(&*items[0]).is::<SourceItem>());
+
+        // The lines spanning multiple chunks are kept intact, only the chunk containing the
+        // invalid UTF-8 falls back to the owned lines.
+        let mut content = (0..20_000)
+            .map(|i| format!("crates/module_{i}/src/lib.rs\n"))
+            .collect::<String>()
+            .into_bytes();
+        content.extend_from_slice(b"\xff\nsrc/main.rs\n");
+        std::fs::write(&path, content).unwrap();
+        let items = file_items(&path).unwrap().collect::<Vec<_>>();
+        assert_eq!(raw_texts(&items), raw_texts(&owned_file_items(&path)));
+        assert_eq!(items.len(), 20_001);
+        assert!(AsAny::as_any(&*items[0]).is::<BufferLine>());
+        assert!(AsAny::as_any(&*items[20_000]).is::<SourceItem>());
+    }
+
+    #[cfg(feature = "bench")]
+    mod alloc_counter {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            /// Number of the allocations and the bytes allocated by this thread.
+            static ALLOCATED: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+        }
+
+        /// Allocator counting the allocations of each thread.
+        struct CountingAllocator;
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = ALLOCATED.try_with(|allocated| {
+                    let (count, bytes) = allocated.get();
+                    allocated.set((count + 1, bytes + layout.size()));
+                });
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: CountingAllocator = CountingAllocator;
+
+        /// Returns the result of `f` and the number of allocations and bytes allocated by the
+        /// current thread in `f`.
+        pub fn allocated_by<T>(f: impl FnOnce() -> T) -> (T, (usize, usize)) {
+            let (start_count, start_bytes) = ALLOCATED.with(Cell::get);
+            let result = f();
+            let (count, bytes) = ALLOCATED.with(Cell::get);
+            (result, (count - start_count, bytes - start_bytes))
+        }
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn bench_file_items() {
+        use std::time::Instant;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines.txt");
+        let content = (0..500_000)
+            .map(|i| format!("crates/module_{}/src/file_{i}.rs\n", i % 97))
+            .collect::<String>();
+        std::fs::write(&path, content).unwrap();
+
+        let now = Instant::now();
+        let (owned, (owned_count, owned_bytes)) =
+            alloc_counter::allocated_by(|| owned_file_items(&path));
+        println!(
+            "owned: {owned_count} allocations, {owned_bytes} bytes, elapsed: {:?}",
+            now.elapsed()
+        );
+
+        let now = Instant::now();
+        let (shared, (shared_count, shared_bytes)) =
+            alloc_counter::allocated_by(|| file_items(&path).unwrap().collect::<Vec<_>>());
+        println!(
+            "shared: {shared_count} allocations, {shared_bytes} bytes, elapsed: {:?}",
+            now.elapsed()
+        );
+
+        assert_eq!(raw_texts(&shared), raw_texts(&owned));
+        assert!(shared_count * 2 <= owned_count);
+        assert!(shared_bytes < owned_bytes);
+    }
 }
@@ -0,0 +1,140 @@
+//! Opt-in counters of the memory held by the filtering, for diagnosing huge sources.
+//!
+//! Each session owns its [`Counters`] and passes them to the filtering, so the sessions running
+//! concurrently do not mix up their numbers. The counters are only updated with the `stats`
+//! feature enabled, otherwise all the updates are no-ops and [`Counters::snapshot`] always
+//! returns the zeroed [`Stats`].
+
+use serde::Serialize;
+
+/// Whether the counters are actually updated.
+pub const ENABLED: bool = cfg!(feature = "stats");
+
+/// A snapshot of the counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Number of candidates read by the ongoing or latest filtering, i.e., all the items of the
+    /// source processed so far, counted before matching and truncating to the displayed results.
+    pub candidates: usize,
+    /// Maximum of `candidates` since the counters were created.
+    pub peak_candidates: usize,
+    /// Bytes of the display text buffered for sending to Vim last time.
+    pub display_bytes: usize,
+}
+
+#[cfg(feature = "stats")]
+mod counters {
+    use super::Stats;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counters updated by the filterings of one session.
+    #[derive(Debug, Default)]
+    pub struct Counters {
+        candidates: AtomicUsize,
+        peak_candidates: AtomicUsize,
+        display_bytes: AtomicUsize,
+    }
+
+    impl Counters {
+        /// Resets the candidates at the start of a new filtering.
+        pub fn reset_candidates(&self) {
+            self.candidates.store(0, Ordering::Relaxed);
+        }
+
+        /// Counts `n` more candidates held in memory.
+        pub fn add_candidates(&self, n: usize) {
+            let current = self.candidates.fetch_add(n, Ordering::Relaxed) + n;
+            self.peak_candidates.fetch_max(current, Ordering::Relaxed);
+        }
+
+        /// Records the display text buffered for sending to Vim.
+        pub fn record_display_lines(&self, lines: &[String]) {
+            self.display_bytes
+                .store(lines.iter().map(|l| l.len()).sum(), Ordering::Relaxed);
+        }
+
+        pub fn snapshot(&self) -> Stats {
+            Stats {
+                candidates: self.candidates.load(Ordering::Relaxed),
+                peak_candidates: self.peak_candidates.load(Ordering::Relaxed),
+                display_bytes: self.display_bytes.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "stats"))]
+mod counters {
+    use super::Stats;
+
+    /// Counters updated by the filterings of one session.
+    #[derive(Debug, Default)]
+    pub struct Counters;
+
+    impl Counters {
+        #[inline(always)]
+        pub fn reset_candidates(&self) {}
+
+        #[inline(always)]
+        pub fn add_candidates(&self, _n: usize) {}
+
+        #[inline(always)]
+        pub fn record_display_lines(&self, _lines: &[String]) {}
+
+        #[inline(always)]
+        pub fn snapshot(&self) -> Stats {
+            Stats::default()
+        }
+    }
+}
+
+pub use self::counters::Counters;
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use super::*;
+    use crate::{par_filter, par_filter_items};
+    use matcher::MatcherBuilder;
+    use std::sync::Arc;
+    use types::ClapItem;
+
+    #[test]
+    fn test_stats_counters() {
+        let counters = Counters::default();
+
+        let lines = (0..1000)
+            .map(|i| format!("src/module_{i}.rs"))
+            .collect::<Vec<_>>();
+
+        let matched_items = par_filter(
+            lines.iter().cloned().map(Into::into).collect(),
+            &MatcherBuilder::default().build("module".into()),
+            &counters,
+        );
+        assert_eq!(matched_items.len(), 1000);
+        assert_eq!(counters.snapshot().candidates, 1000);
+
+        let items = lines
+            .iter()
+            .take(10)
+            .map(|line| Arc::new(line.clone()) as Arc<dyn ClapItem>)
+            .collect::<Vec<_>>();
+        par_filter_items(
+            &items,
+            &MatcherBuilder::default().build("module".into()),
+            &counters,
+        );
+        let stats = counters.snapshot();
+        assert_eq!(stats.candidates, 10);
+        assert_eq!(stats.peak_candidates, 1000);
+
+        counters.record_display_lines(&lines[..2]);
+        assert_eq!(
+            counters.snapshot().display_bytes,
+            "src/module_0.rs".len() + "src/module_1.rs".len()
+        );
+
+        // The counters of another session are not affected.
+        assert_eq!(Counters::default().snapshot(), Stats::default());
+    }
+}
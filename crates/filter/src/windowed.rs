@@ -0,0 +1,243 @@
+//! Filtering a single file too large to be read into memory, e.g., a huge log file.
+//!
+//! The file is indexed once by [`LineIndex`], then read window by window on each filtering, so
+//! that only the lines of current window and the best matched items are held in memory.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use matcher::Matcher;
+use types::{ClapItem, MatchedItem};
+use utility::{LineIndex, LINE_INDEX_STEP};
+
+use crate::worker::top_k::TopK;
+
+/// Default size of a window in bytes.
+pub const DEFAULT_WINDOW_SIZE: usize = 4 * 1024 * 1024;
+
+/// Line of a [`WindowedFile`] with its location in the file.
+#[derive(Debug, Clone)]
+pub struct WindowedLine {
+    text: String,
+    /// 1-based line number.
+    lnum: usize,
+    /// Byte offset of the start of line.
+    byte_offset: u64,
+}
+
+impl WindowedLine {
+    pub fn lnum(&self) -> usize {
+        self.lnum
+    }
+
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+}
+
+impl ClapItem for WindowedLine {
+    fn raw_text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Result of filtering a [`WindowedFile`].
+#[derive(Debug)]
+pub struct WindowedMatches {
+    /// Best matched items in the ranked order, each item is a [`WindowedLine`].
+    pub matched_items: Vec<MatchedItem>,
+    pub total_matched: usize,
+    /// Maximum bytes of a window read into memory.
+    pub peak_window_bytes: usize,
+}
+
+/// File indexed by lines and read window by window.
+#[derive(Debug)]
+pub struct WindowedFile {
+    path: PathBuf,
+    index: LineIndex,
+    /// Maximum bytes of a window, a window always has at least one block of
+    /// [`LINE_INDEX_STEP`] lines.
+    window_size: usize,
+}
+
+impl WindowedFile {
+    /// Opens the file at `path` and builds its [`LineIndex`].
+    pub fn open(path: impl AsRef<Path>, window_size: usize) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let index = LineIndex::build(&path)?;
+        Ok(Self {
+            path,
+            index,
+            window_size: window_size.max(1),
+        })
+    }
+
+    pub fn index(&self) -> &LineIndex {
+        &self.index
+    }
+
+    /// Returns the iterator of windows, each window is a list of consecutive lines.
+    pub fn windows(&self) -> std::io::Result<Windows<'_>> {
+        Ok(Windows {
+            file: File::open(&self.path)?,
+            index: &self.index,
+            window_size: self.window_size,
+            next_block: 0,
+        })
+    }
+
+    /// Returns the best `number` items matching `matcher`.
+    pub fn matched_items(
+        &self,
+        matcher: &Matcher,
+        number: usize,
+    ) -> std::io::Result<WindowedMatches> {
+        let mut top_k = TopK::new(number);
+        let mut total_matched = 0;
+        let mut peak_window_bytes = 0;
+
+        for window in self.windows()? {
+            let window = window?;
+
+            let window_bytes = window.iter().map(|line| line.text.len()).sum::<usize>();
+            peak_window_bytes = peak_window_bytes.max(window_bytes);
+
+            let matched_items = window
+                .into_par_iter()
+                .filter_map(|line| matcher.match_item(Arc::new(line)))
+                .collect::<Vec<_>>();
+
+            total_matched += matched_items.len();
+            for matched_item in matched_items {
+                top_k.push(matched_item);
+            }
+        }
+
+        Ok(WindowedMatches {
+            matched_items: top_k.into_items(),
+            total_matched,
+            peak_window_bytes,
+        })
+    }
+}
+
+/// Iterator of the windows of a [`WindowedFile`].
+#[derive(Debug)]
+pub struct Windows<'a> {
+    file: File,
+    index: &'a LineIndex,
+    window_size: usize,
+    /// Index of the first block of next window.
+    next_block: usize,
+}
+
+impl Windows<'_> {
+    fn read_window(&mut self, start: u64) -> std::io::Result<Vec<WindowedLine>> {
+        let first_block = self.next_block;
+        let mut end = start;
+        while let Some((_, block_end)) = self.index.block_range(self.next_block) {
+            if self.next_block > first_block && (block_end - start) as usize > self.window_size {
+                break;
+            }
+            end = block_end;
+            self.next_block += 1;
+        }
+
+        let mut buf = vec![0u8; (end - start) as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut buf)?;
+
+        let mut byte_offset = start;
+        Ok(buf
+            .split_inclusive(|byte| *byte == b'\n')
+            .enumerate()
+            .map(|(idx, line)| {
+                let line_start = byte_offset;
+                byte_offset += line.len() as u64;
+                let line = match line.strip_suffix(b"\n") {
+                    Some(stripped) => stripped.strip_suffix(b"\r").unwrap_or(stripped),
+                    None => line,
+                };
+                WindowedLine {
+                    text: String::from_utf8_lossy(line).into_owned(),
+                    lnum: first_block * LINE_INDEX_STEP + idx + 1,
+                    byte_offset: line_start,
+                }
+            })
+            .collect())
+    }
+}
+
+impl Iterator for Windows<'_> {
+    type Item = std::io::Result<Vec<WindowedLine>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, _) = self.index.block_range(self.next_block)?;
+        Some(self.read_window(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use types::AsAny;
+
+    #[test]
+    fn test_windowed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_windowed_file");
+        let mut content = (0..200_000)
+            .map(|i| {
+                if i % 9973 == 0 {
+                    format!("{i} ERROR connection reset by peer\r\n")
+                } else {
+                    format!("{i} INFO request served in {}ms\n", i % 100)
+                }
+            })
+            .collect::<String>();
+        // The last line is not terminated.
+        content.pop();
+        std::fs::write(&path, &content).unwrap();
+
+        let window_size = 64 * 1024;
+        let windowed_file = WindowedFile::open(&path, window_size).unwrap();
+        assert_eq!(windowed_file.index().lines(), 200_000);
+
+        let matcher = matcher::MatcherBuilder::default().build("'ERROR".into());
+        let WindowedMatches {
+            matched_items,
+            total_matched,
+            peak_window_bytes,
+        } = windowed_file.matched_items(&matcher, 10).unwrap();
+
+        let expected = BufReader::new(File::open(&path).unwrap())
+            .lines()
+            .map(Result::unwrap)
+            .filter(|line| line.contains("ERROR"))
+            .collect::<Vec<_>>();
+        assert_eq!(total_matched, expected.len());
+        assert_eq!(matched_items.len(), 10);
+
+        // Each line can be located by its line number and byte offset.
+        let all_lines = content.lines().collect::<Vec<_>>();
+        for matched_item in &matched_items {
+            let line = AsAny::as_any(&*matched_item.item)
+                .downcast_ref::<WindowedLine>()
+                .unwrap();
+            assert!(expected.contains(&line.text));
+            assert_eq!(all_lines[line.lnum() - 1], line.text);
+            let offset = line.byte_offset() as usize;
+            assert_eq!(&content[offset..offset + line.text.len()], line.text);
+        }
+
+        // Only a window is read into memory at a time.
+        assert!(peak_window_bytes <= window_size);
+        assert!(peak_window_bytes * 10 < content.len());
+    }
+}
@@ -1,6 +1,7 @@
 //! Convert the source item stream to an iterator and run the filtering sequentially.
 
 use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -11,8 +12,9 @@ use icon::{Icon, ICON_LEN};
 use types::{ClapItem, MatchedItem, Query, Score, SourceItem};
 use utility::{println_json, println_json_with_length};
 
-use crate::source::MatchedItems;
-use crate::{FilterContext, Source};
+use crate::source::BoundedLines;
+use crate::stats::Counters;
+use crate::{Done, FilterContext, Source};
 
 /// The constant to define the length of `top_` queues.
 const ITEMS_TO_SHOW: usize = 40;
@@ -104,16 +106,18 @@ fn select_top_items_to_show(
     }
 }
 
-/// Returns the index of best score in `top_scores`.
+/// Returns the index of the last item scoring not lower than `score` in `top_scores`.
 ///
-/// Best results are stored in front, the bigger the better.
+/// Best results are stored in front, the bigger the better. A new item goes after the existing
+/// items of equal score, so the top items are in the final ranked order and an item not scoring
+/// higher than the worst one of the full queue leaves the queue unchanged.
 #[inline]
 fn find_best_score_idx(top_scores: &[Score; ITEMS_TO_SHOW], score: Score) -> Option<usize> {
     top_scores
         .iter()
         .enumerate()
         .rev() // .rev(), because worse items are at the end.
-        .find(|&(_, &other_score)| other_score > score)
+        .find(|&(_, &other_score)| other_score >= score)
         .map(|(idx, _)| idx)
 }
 
@@ -128,15 +132,18 @@ pub struct Watcher {
     icon: Icon,
     /// Lines we sent last time.
     last_lines: Vec<String>,
+    /// Counters of the memory held by the filtering.
+    counters: Arc<Counters>,
 }
 
 impl Watcher {
-    pub fn new(initial_total: usize, icon: Icon) -> Self {
+    pub fn new(initial_total: usize, icon: Icon, counters: Arc<Counters>) -> Self {
         Self {
             past: Instant::now(),
             total: initial_total,
             icon,
             last_lines: Vec::with_capacity(ITEMS_TO_SHOW),
+            counters,
         }
     }
 
@@ -151,8 +158,10 @@ impl Watcher {
             if now > self.past + UPDATE_INTERVAL {
                 let mut indices = Vec::with_capacity(ITEMS_TO_SHOW);
                 let mut lines = Vec::with_capacity(ITEMS_TO_SHOW);
+                let mut ids = Vec::with_capacity(ITEMS_TO_SHOW);
                 for &idx in top_results.iter() {
                     let matched_item = std::ops::Index::index(buffer, idx);
+                    ids.push(matched_item.id());
                     let text = if let Some(icon_kind) = self.icon.icon_kind() {
                         indices.push(matched_item.shifted_indices(ICON_LEN));
                         icon_kind.add_icon_to_text(matched_item.display_text())
@@ -169,7 +178,8 @@ impl Watcher {
                 const method: &str = "s:process_filter_message";
                 if self.last_lines != lines.as_slice() {
                     let icon_added = self.icon.enabled();
-                    println_json_with_length!(total, lines, indices, method, icon_added);
+                    println_json_with_length!(total, lines, indices, ids, method, icon_added);
+                    self.counters.record_display_lines(&lines);
                     self.past = now;
                     self.last_lines = lines;
                 } else {
@@ -198,7 +208,11 @@ impl Watcher {
 /// VecDeque for this iterator.
 ///
 /// So, this particular function won't work in parallel context at all.
-fn dyn_collect_all(mut iter: impl Iterator<Item = MatchedItem>, icon: Icon) -> Vec<MatchedItem> {
+fn dyn_collect_all(
+    mut iter: impl Iterator<Item = MatchedItem>,
+    icon: Icon,
+    counters: Arc<Counters>,
+) -> Vec<MatchedItem> {
     let mut buffer = Vec::with_capacity({
         let (low, high) = iter.size_hint();
         high.unwrap_or(low)
@@ -211,7 +225,7 @@ fn dyn_collect_all(mut iter: impl Iterator<Item = MatchedItem>, icon: Icon) -> V
         Err((t, top_scores, top_results)) => (t, top_scores, top_results),
     };
 
-    let mut watcher = Watcher::new(total, icon);
+    let mut watcher = Watcher::new(total, icon, counters);
 
     // Now we have the full queue and can just pair `.pop_back()` with `.insert()` to keep
     // the queue with best results the same size.
@@ -245,6 +259,7 @@ fn dyn_collect_number(
     mut iter: impl Iterator<Item = MatchedItem>,
     number: usize,
     icon: Icon,
+    counters: Arc<Counters>,
 ) -> (usize, Vec<MatchedItem>) {
     // To not have problems with queues after sorting and truncating the buffer,
     // buffer has the lowest bound of `ITEMS_TO_SHOW * 2`, not `number * 2`.
@@ -257,7 +272,7 @@ fn dyn_collect_number(
         Err((t, top_scores, top_results)) => (t, top_scores, top_results),
     };
 
-    let mut watcher = Watcher::new(total, icon);
+    let mut watcher = Watcher::new(total, icon, counters);
 
     // Now we have the full queue and can just pair `.pop_back()` with
     // `.insert()` to keep the queue with best results the same size.
@@ -272,7 +287,8 @@ fn dyn_collect_number(
         watcher.try_notify(&top_results, &buffer);
 
         if buffer.len() == buffer.capacity() {
-            buffer.par_sort_unstable_by(|v1, v2| v2.score.partial_cmp(&v1.score).unwrap());
+            // Stable sort to keep the source order among the items of equal score.
+            buffer.par_sort_by(|v1, v2| v2.score.cmp(&v1.score));
 
             for (idx, MatchedItem { score, .. }) in buffer[..ITEMS_TO_SHOW].iter().enumerate() {
                 top_scores[idx] = *score;
@@ -287,76 +303,173 @@ fn dyn_collect_number(
     (watcher.total, buffer)
 }
 
+/// Sorts the items in the same order as the top items sent on the fly, the item with higher
+/// score goes first and the one coming first in the source goes first among the items of equal
+/// score.
+fn sort_in_source_order(matched_items: &mut [MatchedItem]) {
+    matched_items.par_sort_by(|v1, v2| v2.score.cmp(&v1.score));
+}
+
 /// Returns the ranked results after applying fuzzy filter given the query string and a list of candidates.
+///
+/// The results are followed by the [`Done`] message once the source is exhausted.
 pub fn dyn_run<I: Iterator<Item = Arc<dyn ClapItem>>>(
     query: &str,
     filter_context: FilterContext,
     source: Source<I>,
 ) -> Result<()> {
-    let FilterContext {
-        icon,
-        number,
-        winwidth,
-        matcher_builder,
-    } = filter_context;
-
-    let query: Query = query.into();
-    let matcher = matcher_builder.build(query);
+    let mut timed_out = None;
 
     let clap_item_stream: Box<dyn Iterator<Item = Arc<dyn ClapItem>>> = match source {
         Source::List(list) => Box::new(list),
-        Source::Stdin => Box::new(
+        Source::Stdin(None) => Box::new(
             std::io::stdin()
                 .lock()
                 .lines()
                 .filter_map(Result::ok)
                 .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
         ),
-        Source::File(path) => Box::new(
-            std::io::BufReader::new(std::fs::File::open(path)?)
-                .lines()
-                .filter_map(Result::ok)
-                .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
-        ),
+        Source::Stdin(timeout) => {
+            let lines = BoundedLines::stdin().timeout(timeout);
+            timed_out = Some(lines.timed_out());
+            Box::new(lines.map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>))
+        }
+        Source::File(path) => crate::source::file_items(&path)?,
         Source::Exec(exec) => Box::new(
             std::io::BufReader::new(exec.stream_stdout()?)
                 .lines()
                 .filter_map(Result::ok)
                 .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>),
         ),
+        Source::Command(_) => return Err(crate::source::command_source_error()),
     };
 
-    let matched_item_stream = clap_item_stream.filter_map(|item| matcher.match_item(item));
+    dyn_run_items(
+        query.into(),
+        filter_context,
+        clap_item_stream,
+        timed_out.as_deref(),
+    )
+    .print();
+
+    Ok(())
+}
 
-    if let Some(number) = number {
-        let (total_matched, matched_items) = dyn_collect_number(matched_item_stream, number, icon);
-        let mut matched_items = MatchedItems::from(matched_items).par_sort().inner();
+/// Prints the ranked results of `clap_item_stream`, returns the [`Done`] message to be printed.
+///
+/// `timed_out` tells whether the stream was ended by a timeout.
+fn dyn_run_items(
+    query: Query,
+    filter_context: FilterContext,
+    clap_item_stream: impl Iterator<Item = Arc<dyn ClapItem>>,
+    timed_out: Option<&AtomicBool>,
+) -> Done {
+    let FilterContext {
+        icon,
+        number,
+        winwidth,
+        matcher_builder,
+        show_score,
+        counters,
+        ..
+    } = filter_context;
+
+    let matcher = matcher_builder.build(query);
+
+    counters.reset_candidates();
+
+    let matched_item_stream = clap_item_stream
+        .inspect(|_| counters.add_candidates(1))
+        .filter_map(|item| matcher.match_item(item));
+
+    let total_matched = if let Some(number) = number {
+        let (total_matched, mut matched_items) =
+            dyn_collect_number(matched_item_stream, number, icon, counters.clone());
+        sort_in_source_order(&mut matched_items);
         matched_items.truncate(number);
 
         printer::print_dyn_matched_items(
             matched_items,
             total_matched,
             None,
-            winwidth.unwrap_or(100),
+            printer::normalize_winwidth(winwidth),
             icon,
+            show_score,
         );
+
+        total_matched
     } else {
-        let matched_items = dyn_collect_all(matched_item_stream, icon);
-        let matched_items = MatchedItems::from(matched_items).par_sort().inner();
+        let mut matched_items = dyn_collect_all(matched_item_stream, icon, counters.clone());
+        sort_in_source_order(&mut matched_items);
 
         matched_items.iter().for_each(|matched_item| {
             let indices = &matched_item.indices;
             let text = matched_item.display_text();
-            println_json!(text, indices);
+            let id = matched_item.id();
+            if show_score {
+                let score = matched_item.score;
+                println_json!(text, indices, id, score);
+            } else {
+                println_json!(text, indices, id);
+            }
         });
-    }
 
-    Ok(())
+        matched_items.len()
+    };
+
+    Done {
+        total_matched,
+        partial: timed_out.is_some_and(|timed_out| timed_out.load(Ordering::SeqCst)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::source::tests::StallingProducer;
+
+    #[test]
+    fn test_done_terminates_stream() {
+        let items = |lines: BoundedLines| {
+            lines.map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>)
+        };
+
+        // The stream stalling without being closed is ended by the timeout.
+        let lines = BoundedLines::new(std::io::BufReader::new(StallingProducer(3)), 8)
+            .timeout(Some(Duration::from_millis(100)));
+        let timed_out = lines.timed_out();
+        let done = dyn_run_items(
+            "line".into(),
+            FilterContext::default().number(Some(10)),
+            items(lines),
+            Some(&*timed_out),
+        );
+        assert_eq!(
+            done,
+            Done {
+                total_matched: 3,
+                partial: true
+            }
+        );
+
+        // The stream is complete.
+        let lines = BoundedLines::new(std::io::Cursor::new("line\nfoo\nline\n"), 8)
+            .timeout(Some(Duration::from_secs(5)));
+        let timed_out = lines.timed_out();
+        let done = dyn_run_items(
+            "line".into(),
+            FilterContext::default(),
+            items(lines),
+            Some(&*timed_out),
+        );
+        assert_eq!(
+            done,
+            Done {
+                total_matched: 2,
+                partial: false
+            }
+        );
+    }
 
     #[test]
     // This is a very time-consuming test,
@@ -1,2 +1,3 @@
 pub mod iterator;
 pub mod par_iterator;
+pub(crate) mod top_k;
@@ -16,11 +16,27 @@ use icon::Icon;
 use types::{ClapItem, FileNameItem, GrepItem, MatchedItem, Query, SourceItem};
 use utility::println_json_with_length;
 
-use crate::FilterContext;
+use super::top_k::TopK;
+use crate::stats::Counters;
+use crate::{Done, FilterContext};
 
 /// Refresh the top filtered results per 200 ms.
 const UPDATE_INTERVAL: Duration = Duration::from_millis(200);
 
+/// Number of lines per rayon task when the size of source is unknown.
+const DEFAULT_BATCH_SIZE: usize = 16;
+
+/// Rough average length of a line, used for estimating the number of lines in a file.
+const AVERAGE_LINE_LEN: u64 = 64;
+
+/// Returns a batch size that keeps each thread busy with enough tasks given the
+/// estimated number of total items.
+fn auto_batch_size(estimated_total: Option<usize>) -> usize {
+    estimated_total
+        .map(|total| (total / (rayon::current_num_threads() * 64)).clamp(1, 1024))
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+}
+
 /// Parallelable source.
 #[derive(Debug)]
 pub enum ParSource {
@@ -38,6 +54,14 @@ pub fn par_dyn_run(
 
     match par_source {
         ParSource::File(file) => {
+            let filter_context = if filter_context.batch_size.is_some() {
+                filter_context
+            } else {
+                let estimated_total = std::fs::metadata(&file)
+                    .ok()
+                    .map(|metadata| (metadata.len() / AVERAGE_LINE_LEN) as usize);
+                filter_context.batch_size(Some(auto_batch_size(estimated_total)))
+            };
             par_dyn_run_inner::<Empty<_>, _>(
                 query,
                 filter_context,
@@ -71,68 +95,47 @@ pub fn par_dyn_run_list<'a, 'b: 'a>(
 struct BestItems {
     /// Time of last notification.
     past: Instant,
-    items: Vec<MatchedItem>,
-    last_lines: Vec<String>,
-    max_capacity: usize,
+    top_k: TopK,
     icon: Icon,
     winwidth: usize,
+    counters: Arc<Counters>,
 }
 
 impl BestItems {
-    fn new(max_capacity: usize, icon: Icon, winwidth: usize) -> Self {
+    fn new(max_capacity: usize, icon: Icon, winwidth: usize, counters: Arc<Counters>) -> Self {
         Self {
             past: Instant::now(),
-            items: Vec::with_capacity(max_capacity),
-            last_lines: Vec::with_capacity(max_capacity),
-            max_capacity,
+            top_k: TopK::new(max_capacity),
             icon,
             winwidth,
+            counters,
         }
     }
 
+    /// Pushes a new matched item and sends the best items periodically, the lines are only
+    /// sent if the best items have changed since the last notification.
     fn try_push_and_notify(&mut self, new: MatchedItem, matched: usize, processed: usize) {
-        if self.items.len() <= self.max_capacity {
-            self.items.push(new);
-            self.items.sort_unstable_by(|a, b| b.score.cmp(&a.score));
+        self.top_k.push(new);
 
-            let now = Instant::now();
-            if now > self.past + UPDATE_INTERVAL {
+        let periodic = matched.is_multiple_of(16) || processed.is_multiple_of(16);
+        if !self.top_k.is_changed() && !periodic {
+            return;
+        }
+
+        let now = Instant::now();
+        if now > self.past + UPDATE_INTERVAL {
+            if self.top_k.take_changed() {
                 let display_lines =
-                    printer::decorate_lines(self.items.clone(), self.winwidth, self.icon);
+                    printer::decorate_lines(self.top_k.items().to_vec(), self.winwidth, self.icon);
                 display_lines.print_on_dyn_run(matched, processed);
-                self.last_lines = display_lines.lines;
-                self.past = now;
-            }
-        } else {
-            let last = self
-                .items
-                .last_mut()
-                .expect("Max capacity is non-zero; qed");
-
-            if new.score > last.score {
-                *last = new;
-                self.items.sort_unstable_by(|a, b| b.score.cmp(&a.score));
+                self.counters.record_display_lines(&display_lines.lines);
+            } else {
+                #[allow(non_upper_case_globals)]
+                const method: &str = "s:process_filter_message";
+                println_json_with_length!(matched, processed, method);
             }
 
-            if matched % 16 == 0 || processed % 16 == 0 {
-                let now = Instant::now();
-                if now > self.past + UPDATE_INTERVAL {
-                    let display_lines =
-                        printer::decorate_lines(self.items.clone(), self.winwidth, self.icon);
-
-                    // TODO: the lines are the same, but the highlights are not.
-                    if self.last_lines != display_lines.lines.as_slice() {
-                        display_lines.print_on_dyn_run(matched, processed);
-                        self.last_lines = display_lines.lines;
-                    } else {
-                        #[allow(non_upper_case_globals)]
-                        const method: &str = "s:process_filter_message";
-                        println_json_with_length!(matched, processed, method);
-                    }
-
-                    self.past = now;
-                }
-            }
+            self.past = now;
         }
     }
 }
@@ -148,24 +151,61 @@ fn par_dyn_run_inner<I: IntoParallelIterator<Item = Arc<dyn ClapItem>>, R: Read
     filter_context: FilterContext,
     parallel_source: ParSourceInner<I, R>,
 ) -> Result<()> {
+    let winwidth = printer::normalize_winwidth(filter_context.winwidth);
+    let icon = filter_context.icon;
+    let show_score = filter_context.show_score;
+
+    let (matched_items, total_matched, total_processed) =
+        par_match_items(query, filter_context, parallel_source)?;
+
+    printer::print_dyn_matched_items(
+        matched_items,
+        total_matched,
+        Some(total_processed),
+        winwidth,
+        icon,
+        show_score,
+    );
+
+    Done {
+        total_matched,
+        partial: false,
+    }
+    .print();
+
+    Ok(())
+}
+
+/// Returns a tuple of (best matched items, total matched, total processed).
+fn par_match_items<I: IntoParallelIterator<Item = Arc<dyn ClapItem>>, R: Read + Send>(
+    query: Query,
+    filter_context: FilterContext,
+    parallel_source: ParSourceInner<I, R>,
+) -> Result<(Vec<MatchedItem>, usize, usize)> {
     let FilterContext {
         icon,
         number,
         winwidth,
         matcher_builder,
+        batch_size,
+        counters,
+        ..
     } = filter_context;
 
     let matcher = matcher_builder.build(query);
 
-    let winwidth = winwidth.unwrap_or(100);
+    let winwidth = printer::normalize_winwidth(winwidth);
     let number = number.unwrap_or(100);
 
     let matched_count = AtomicUsize::new(0);
     let processed_count = AtomicUsize::new(0);
 
-    let best_items = Mutex::new(BestItems::new(number, icon, winwidth));
+    let best_items = Mutex::new(BestItems::new(number, icon, winwidth, counters.clone()));
+
+    counters.reset_candidates();
 
     let process_item = |item: Arc<dyn ClapItem>, processed: usize| {
+        counters.add_candidates(1);
         if let Some(matched_item) = matcher.match_item(item) {
             let matched = matched_count.fetch_add(1, Ordering::SeqCst);
 
@@ -184,13 +224,25 @@ fn par_dyn_run_inner<I: IntoParallelIterator<Item = Arc<dyn ClapItem>>, R: Read
             });
         }
         ParSourceInner::Lines(reader) => {
+            let batch_size = batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1);
+
             // To avoid Err(Custom { kind: InvalidData, error: "stream did not contain valid UTF-8" })
             // The line stream can contain invalid UTF-8 data.
-            std::io::BufReader::new(reader)
+            let mut lines = std::io::BufReader::new(reader)
                 .lines()
-                .filter_map(Result::ok)
-                .par_bridge()
-                .for_each(|line: String| {
+                .filter_map(Result::ok);
+
+            let batches = std::iter::from_fn(move || {
+                let batch: Vec<String> = lines.by_ref().take(batch_size).collect();
+                if batch.is_empty() {
+                    None
+                } else {
+                    Some(batch)
+                }
+            });
+
+            batches.par_bridge().for_each(|batch: Vec<String>| {
+                batch.into_iter().for_each(|line: String| {
                     let processed = processed_count.fetch_add(1, Ordering::SeqCst);
                     let item: Arc<dyn ClapItem> = match matcher.match_scope() {
                         MatchScope::GrepLine => {
@@ -210,22 +262,68 @@ fn par_dyn_run_inner<I: IntoParallelIterator<Item = Arc<dyn ClapItem>>, R: Read
                         _ => Arc::new(SourceItem::from(line)),
                     };
                     process_item(item, processed);
-                });
+                })
+            });
         }
     }
 
     let total_matched = matched_count.into_inner();
     let total_processed = processed_count.into_inner();
 
-    let matched_items = best_items.into_inner().items;
+    let matched_items = best_items.into_inner().top_k.into_items();
 
-    printer::print_dyn_matched_items(
-        matched_items,
-        total_matched,
-        Some(total_processed),
-        winwidth,
-        icon,
-    );
+    Ok((matched_items, total_matched, total_processed))
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_lines(n: usize) -> String {
+        (0..n)
+            .map(|i| format!("crates/module_{}/src/file_{i}.rs", i % 97))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn run_with_batch_size(query: &str, lines: &str, batch_size: usize) -> Vec<(i32, String)> {
+        let filter_context = FilterContext::default()
+            .number(Some(lines.lines().count() + 1))
+            .batch_size(Some(batch_size));
+        let (matched_items, total_matched, total_processed) = par_match_items::<Empty<_>, _>(
+            query.into(),
+            filter_context,
+            ParSourceInner::Lines(lines.as_bytes()),
+        )
+        .unwrap();
+        assert_eq!(total_matched, matched_items.len());
+        assert_eq!(total_processed, lines.lines().count());
+        let mut results = matched_items
+            .into_iter()
+            .map(|matched_item| (matched_item.score, matched_item.item.raw_text().to_string()))
+            .collect::<Vec<_>>();
+        results.sort();
+        results
+    }
+
+    #[test]
+    fn test_batch_size_does_not_affect_results() {
+        let lines = generate_lines(5_000);
+        let expected = run_with_batch_size("mod1fil", &lines, 1);
+        assert!(!expected.is_empty());
+        for batch_size in [7, 64, 1024, 10_000] {
+            assert_eq!(run_with_batch_size("mod1fil", &lines, batch_size), expected);
+        }
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn bench_batch_size() {
+        let lines = generate_lines(500_000);
+        for batch_size in [1, 16, 64, 256, 1024] {
+            let now = Instant::now();
+            run_with_batch_size("mod1fil", &lines, batch_size);
+            println!("batch_size: {batch_size}, elapsed: {:?}", now.elapsed());
+        }
+    }
 }
@@ -0,0 +1,142 @@
+//! Top-K buffer of the best matched items in the streaming filtering.
+
+use types::{MatchedItem, Score};
+
+/// Best matched items in the final ranked order, the item with higher score goes first and the
+/// one coming first in the stream goes first among the items of equal score.
+///
+/// The items only change when a new item scores higher than the watermark, so the items kept
+/// in the buffer are never reordered among themselves.
+#[derive(Debug)]
+pub struct TopK {
+    items: Vec<MatchedItem>,
+    capacity: usize,
+    /// Whether the items have changed since the last [`TopK::take_changed`].
+    changed: bool,
+}
+
+impl TopK {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+            capacity,
+            changed: false,
+        }
+    }
+
+    /// Returns the lowest score of the full buffer, an item can not enter the buffer unless it
+    /// scores higher.
+    pub fn watermark(&self) -> Option<Score> {
+        if self.items.len() < self.capacity {
+            None
+        } else {
+            self.items.last().map(|item| item.score)
+        }
+    }
+
+    /// Inserts `item` at its ranked position, returns `true` if the top items have changed.
+    pub fn push(&mut self, item: MatchedItem) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+
+        if let Some(watermark) = self.watermark() {
+            if item.score <= watermark {
+                return false;
+            }
+            self.items.pop();
+        }
+
+        let idx = self.items.partition_point(|x| x.score >= item.score);
+        self.items.insert(idx, item);
+        self.changed = true;
+
+        true
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Returns whether the items have changed and resets the change state.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn items(&self) -> &[MatchedItem] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<MatchedItem> {
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn texts(items: &[MatchedItem]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| item.item.raw_text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_late_high_score_item() {
+        let stream = [
+            ("a", 5),
+            ("b", 3),
+            ("c", 5),
+            ("d", 1),
+            ("e", 4),
+            ("f", 5),
+            ("late", 9),
+            ("g", 2),
+        ];
+
+        let mut top_k = TopK::new(3);
+        let mut emitted = Vec::new();
+        for (text, score) in stream {
+            top_k.push(MatchedItem::new(Arc::new(text), score, Vec::new()));
+            if top_k.take_changed() {
+                emitted.push(texts(top_k.items()));
+            }
+        }
+
+        assert_eq!(
+            emitted,
+            vec![
+                vec!["a"],
+                vec!["a", "b"],
+                vec!["a", "c", "b"],
+                vec!["a", "c", "e"],
+                vec!["a", "c", "f"],
+                vec!["late", "a", "c"],
+            ]
+        );
+
+        // No snapshot is emitted twice.
+        assert!(emitted.windows(2).all(|w| w[0] != w[1]));
+
+        // The items kept across the snapshots are never reordered.
+        for w in emitted.windows(2) {
+            let kept = w[1].iter().filter(|x| w[0].contains(x)).collect::<Vec<_>>();
+            let previous = w[0].iter().filter(|x| w[1].contains(x)).collect::<Vec<_>>();
+            assert_eq!(kept, previous);
+        }
+
+        // The last snapshot is the final ranked order.
+        let mut ranked = stream.to_vec();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1));
+        let ranked = ranked
+            .iter()
+            .take(3)
+            .map(|(text, _)| *text)
+            .collect::<Vec<_>>();
+        assert_eq!(emitted.last().unwrap(), &ranked);
+        assert_eq!(top_k.watermark(), Some(5));
+    }
+}
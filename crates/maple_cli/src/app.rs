@@ -29,6 +29,9 @@ pub enum RunCmd {
     /// Interact with the cache info.
     #[clap(name = "cache", subcommand)]
     Cache(command::cache::Cache),
+    /// List the files using the native walker.
+    #[clap(name = "files")]
+    Files(command::files::Files),
     /// Fuzzy filter the input.
     #[clap(name = "filter")]
     Filter(command::filter::Filter),
@@ -38,6 +41,12 @@ pub enum RunCmd {
     /// Generate vim help tags.
     #[clap(name = "helptags")]
     Helptags(command::helptags::Helptags),
+    /// Print the preview of a provider line without a live session.
+    #[clap(name = "preview")]
+    Preview(command::preview::Preview),
+    /// List all the providers and whether they are available.
+    #[clap(name = "providers")]
+    Providers(command::providers::Providers),
     /// Start the forerunner job of grep.
     #[clap(name = "ripgrep-forerunner")]
     RipGrepForerunner(command::grep::RipGrepForerunner),
@@ -84,6 +93,13 @@ pub struct Params {
     /// Specify the path of the config file.
     #[clap(long, parse(from_os_str))]
     pub config_file: Option<std::path::PathBuf>,
+
+    /// Read the input and write the output via the Unix domain socket at this path instead of
+    /// stdio.
+    ///
+    /// Connects to the socket if it's being listened on, otherwise listens on it.
+    #[clap(long, parse(from_os_str))]
+    pub socket: Option<std::path::PathBuf>,
 }
 
 impl Params {
@@ -118,16 +134,23 @@ impl RunCmd {
             .build_global()
             .expect("Failed to configure the rayon global thread pool");
 
+        if let Some(ref socket) = params.socket {
+            crate::socket::redirect_stdio_to_socket(socket)?;
+        }
+
         match self {
             Self::Blines(blines) => blines.run(params),
             Self::Cache(cache) => cache.run(),
             Self::Ctags(ctags) => ctags.run(params),
             Self::DumbJump(dumb_jump) => dumb_jump.run(),
             Self::Exec(exec) => exec.run(params),
+            Self::Files(files) => files.run(params),
             Self::Filter(filter) => filter.run(params),
             Self::Grep(grep) => grep.run(params),
             Self::Gtags(gtags) => gtags.run(params),
             Self::Helptags(helptags) => helptags.run(),
+            Self::Preview(preview) => preview.run(params).await,
+            Self::Providers(providers) => providers.run(),
             Self::RipGrepForerunner(rip_grep_forerunner) => rip_grep_forerunner.run(params),
             Self::Rpc(rpc) => rpc.run(params).await,
         }
@@ -0,0 +1,153 @@
+//! Lists the entries of an archive as the filter source.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Supported archive formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn from_extension(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_lowercase();
+        if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if file_name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+
+    fn from_magic(path: &Path) -> Option<Self> {
+        let mut header = Vec::with_capacity(262);
+        File::open(path)
+            .ok()?
+            .take(262)
+            .read_to_end(&mut header)
+            .ok()?;
+
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(Self::Zip)
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::TarGz)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Detects the archive kind of `path` by the extension, then by the magic number.
+    pub fn detect(path: &Path) -> Option<Self> {
+        Self::from_extension(path).or_else(|| Self::from_magic(path))
+    }
+}
+
+fn list_tar_entries(reader: impl Read) -> Result<Vec<String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_dir() {
+            entries.push(entry.path()?.to_string_lossy().into_owned());
+        }
+    }
+    Ok(entries)
+}
+
+fn list_zip_entries(file: File) -> Result<Vec<String>> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for idx in 0..archive.len() {
+        let entry = archive.by_index(idx)?;
+        if !entry.is_dir() {
+            entries.push(entry.name().to_string());
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the paths of file entries in the archive `path`.
+pub fn list_entries(path: &Path, kind: ArchiveKind) -> Result<Vec<String>> {
+    let file = File::open(path)?;
+    let entries = match kind {
+        ArchiveKind::Zip => list_zip_entries(file),
+        ArchiveKind::Tar => list_tar_entries(BufReader::new(file)),
+        ArchiveKind::TarGz => list_tar_entries(flate2::read::GzDecoder::new(BufReader::new(file))),
+    };
+    entries.with_context(|| format!("Failed to read the archive {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn create_zip(path: &Path, entries: &[&str]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        writer.add_directory("src/", options).unwrap();
+        for entry in entries {
+            writer.start_file(*entry, options).unwrap();
+            writer.write_all(entry.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn create_tar(path: &Path, entries: &[&str]) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        for entry in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(entry.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, entry, entry.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_list_archive_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let entries = ["src/lib.rs", "src/main.rs", "Cargo.toml"];
+
+        let zip_path = dir.join("entries.zip");
+        create_zip(&zip_path, &entries);
+        assert_eq!(ArchiveKind::detect(&zip_path), Some(ArchiveKind::Zip));
+        assert_eq!(
+            list_entries(&zip_path, ArchiveKind::Zip).unwrap(),
+            entries.to_vec()
+        );
+
+        // Detected by the magic number without the extension.
+        let tar_path = dir.join("entries_tar");
+        create_tar(&tar_path, &entries);
+        assert_eq!(ArchiveKind::detect(&tar_path), Some(ArchiveKind::Tar));
+        assert_eq!(
+            list_entries(&tar_path, ArchiveKind::Tar).unwrap(),
+            entries.to_vec()
+        );
+
+        let corrupt_path = dir.join("corrupt.zip");
+        std::fs::write(&corrupt_path, b"PK\x03\x04 not really a zip").unwrap();
+        let err = list_entries(&corrupt_path, ArchiveKind::Zip).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to read the archive"));
+
+        let plain_path = dir.join("entries.txt");
+        std::fs::write(&plain_path, "src/lib.rs\n").unwrap();
+        assert_eq!(ArchiveKind::detect(&plain_path), None);
+    }
+}
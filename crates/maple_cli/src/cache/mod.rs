@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::prelude::*;
 
@@ -137,6 +137,14 @@ impl CacheInfo {
         }
     }
 
+    /// Finds the usable digest whose cache file is `cached_path`.
+    pub fn find_digest_by_path(&self, cached_path: &Path) -> Option<Digest> {
+        self.digests
+            .iter()
+            .find(|d| d.cached_path == cached_path && d.is_usable())
+            .cloned()
+    }
+
     /// Pushes `digest` to the digests queue with max capacity constraint.
     ///
     /// Also writes the memory cached info back to the disk.
@@ -27,6 +27,16 @@ pub struct Blines {
 
     #[clap(long)]
     par_run: bool,
+
+    /// Rank the matches in the comments lower, the comment syntax is inferred from the extension.
+    #[clap(long)]
+    deprioritize_comments: bool,
+
+    /// Only match the lines added or modified in the git working tree.
+    ///
+    /// All the lines are matched if the file is not tracked by git.
+    #[clap(long)]
+    only_changed: bool,
 }
 
 #[derive(Debug)]
@@ -58,6 +68,12 @@ impl Blines {
     pub fn run(&self, params: Params) -> Result<()> {
         let source_file = std::fs::File::open(&self.input)?;
 
+        let changed_lines = if self.only_changed {
+            crate::tools::git::changed_lines(&self.input)
+        } else {
+            None
+        };
+
         let index = AtomicUsize::new(0);
         let blines_item_stream = || {
             std::io::BufReader::new(source_file)
@@ -65,7 +81,10 @@ impl Blines {
                 .filter_map(|x| {
                     x.ok().and_then(|line: String| {
                         let index = index.fetch_add(1, Ordering::SeqCst);
-                        if line.trim().is_empty() {
+                        let is_changed = changed_lines
+                            .as_ref()
+                            .is_none_or(|changed_lines| changed_lines.contains(index + 1));
+                        if line.trim().is_empty() || !is_changed {
                             None
                         } else {
                             let item: Arc<dyn ClapItem> = Arc::new(BlinesItem {
@@ -84,9 +103,11 @@ impl Blines {
             .extension()
             .and_then(|s| s.to_str().map(|s| s.to_string()))
         {
-            params
-                .into_filter_context()
-                .bonuses(vec![Bonus::Language(extension.into())])
+            let mut bonuses = vec![Bonus::Language(extension.as_str().into())];
+            if self.deprioritize_comments {
+                bonuses.push(Bonus::Comment(extension.into()));
+            }
+            params.into_filter_context().bonuses(bonuses)
         } else {
             params.into_filter_context()
         };
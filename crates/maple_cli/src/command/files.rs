@@ -0,0 +1,480 @@
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::Parser;
+use ignore::WalkBuilder;
+use parking_lot::Mutex;
+
+use crate::app::Params;
+
+/// Directories always skipped by default, regardless of the ignore files.
+pub const DEFAULT_SKIP_DIRS: &[&str] = &[".git", ".hg", ".svn", "node_modules", "target"];
+
+/// Project-local ignore file in the gitignore syntax, specific to clap.
+pub const CLAP_IGNORE_FILENAME: &str = ".clapignore";
+
+/// Number of the leading bytes sampled for detecting the binary files.
+const BINARY_SAMPLE_SIZE: u64 = 8 * 1024;
+
+/// Returns `true` if there is a NUL byte in the leading bytes of file `path`, same heuristic as
+/// ripgrep.
+///
+/// The file is not considered as binary if it can't be read.
+pub fn is_binary_file(path: &Path) -> bool {
+    let mut sample = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(BINARY_SAMPLE_SIZE).read_to_end(&mut sample))
+        .is_ok_and(|_| sample.contains(&0))
+}
+
+/// Identity of a directory, two paths reaching the same directory via symlinks share it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum DirId {
+    /// Device and inode.
+    #[cfg(unix)]
+    Inode(u64, u64),
+    /// Canonical path, on the platforms without the inode semantics.
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+impl DirId {
+    /// Returns the identity of the directory `path` points to, the symlinks are resolved.
+    fn of(path: &Path) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let metadata = std::fs::metadata(path).ok()?;
+            Some(Self::Inode(metadata.dev(), metadata.ino()))
+        }
+
+        #[cfg(not(unix))]
+        {
+            std::fs::canonicalize(path).ok().map(Self::Path)
+        }
+    }
+}
+
+/// How the binary files are handled, mirroring the options of ripgrep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryMode {
+    /// Skip the binary files.
+    #[default]
+    Skip,
+    /// Include the binary files, ripgrep stops searching a binary file at the first NUL byte
+    /// and notes the match instead of printing it.
+    Match,
+    /// Treat the binary files as text.
+    Text,
+}
+
+impl std::str::FromStr for BinaryMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl<T: AsRef<str>> From<T> for BinaryMode {
+    fn from(s: T) -> Self {
+        match s.as_ref().to_lowercase().as_str() {
+            "match" => Self::Match,
+            "text" => Self::Text,
+            _ => Self::Skip,
+        }
+    }
+}
+
+impl BinaryMode {
+    /// Returns the option of ripgrep for this mode.
+    pub fn rg_flag(&self) -> Option<&'static str> {
+        match self {
+            Self::Skip => None,
+            Self::Match => Some("--binary"),
+            Self::Text => Some("--text"),
+        }
+    }
+}
+
+/// Options of walking the files in a directory.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Include the hidden files.
+    pub hidden: bool,
+    /// Do not respect the ignore files, e.g., `.gitignore`, `.clapignore`.
+    pub no_ignore: bool,
+    /// Names of directories to skip.
+    pub skip_dirs: HashSet<String>,
+    /// Whether the binary files are skipped.
+    pub binary: BinaryMode,
+    /// Follow the symbolic links, each directory is visited only once even if there is a cycle.
+    pub follow_symlinks: bool,
+}
+
+impl WalkOptions {
+    /// Returns the options with the default skip directories.
+    pub fn with_default_skip_dirs() -> Self {
+        Self {
+            hidden: false,
+            no_ignore: false,
+            skip_dirs: DEFAULT_SKIP_DIRS
+                .iter()
+                .map(|dir| dir.to_string())
+                .collect(),
+            binary: BinaryMode::Skip,
+            follow_symlinks: false,
+        }
+    }
+
+    /// Returns the files under `dir` relative to `dir`.
+    pub fn walk(self, dir: &Path) -> impl Iterator<Item = PathBuf> {
+        let Self {
+            hidden,
+            no_ignore,
+            skip_dirs,
+            binary,
+            follow_symlinks,
+        } = self;
+        let root = dir.to_path_buf();
+
+        // The walker only detects a symlink pointing to an ancestor, a directory reachable via
+        // multiple paths would still be listed more than once.
+        let visited_dirs = Mutex::new(HashSet::new());
+        if follow_symlinks {
+            visited_dirs.lock().extend(DirId::of(dir));
+        }
+
+        let mut builder = WalkBuilder::new(dir);
+        if !no_ignore {
+            // Layered on top of the standard ignore files, with a higher precedence.
+            builder.add_custom_ignore_filename(CLAP_IGNORE_FILENAME);
+        }
+
+        builder
+            .standard_filters(!no_ignore)
+            .hidden(!hidden)
+            .follow_links(follow_symlinks)
+            .filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+                if !is_dir || entry.depth() == 0 {
+                    return true;
+                }
+                // Prune the walker at the skipped directories.
+                if entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| skip_dirs.contains(name))
+                {
+                    return false;
+                }
+                // Prune the walker at the directories already visited.
+                if follow_symlinks {
+                    if let Some(dir_id) = DirId::of(entry.path()) {
+                        return visited_dirs.lock().insert(dir_id);
+                    }
+                }
+                true
+            })
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .filter(move |entry| binary != BinaryMode::Skip || !is_binary_file(entry.path()))
+            .map(move |entry| {
+                entry
+                    .path()
+                    .strip_prefix(&root)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|_| entry.path().to_path_buf())
+            })
+    }
+}
+
+/// List the files using the native walker.
+#[derive(Parser, Debug, Clone)]
+pub struct Files {
+    /// Specify the directory to walk, the current working directory by default.
+    #[clap(long, parse(from_os_str))]
+    dir: Option<PathBuf>,
+
+    /// Include the hidden files.
+    #[clap(long)]
+    hidden: bool,
+
+    /// Do not respect the ignore files, e.g., `.gitignore`, `.clapignore`.
+    #[clap(long)]
+    no_ignore: bool,
+
+    /// Skip the directories with this name, can be specified multiple times.
+    #[clap(long)]
+    skip_dir: Vec<String>,
+
+    /// Do not skip the built-in default directories, e.g., `target`, `node_modules`.
+    #[clap(long)]
+    no_default_skip_dirs: bool,
+
+    /// How the binary files are handled, `skip`, `match` or `text`.
+    ///
+    /// The binary files, detected by a NUL byte in the leading bytes, are skipped by default.
+    #[clap(long, parse(from_str), default_value = "skip")]
+    binary: BinaryMode,
+
+    /// List the files tracked by git, the same as `git ls-files` including the submodules,
+    /// instead of walking the directory.
+    ///
+    /// The untracked files are excluded even if they are not ignored, only `--binary` applies
+    /// to the tracked files. Falls back to walking the directory if it's not in a git repo.
+    #[clap(long)]
+    git_tracked: bool,
+
+    /// Follow the symbolic links.
+    ///
+    /// Each directory is listed only once, even if it's reachable via multiple paths or there
+    /// is a symlink cycle.
+    #[clap(long)]
+    follow_symlinks: bool,
+
+    /// Print the paths delimited by NUL instead of newline.
+    #[clap(long, short = '0')]
+    null: bool,
+}
+
+impl Files {
+    /// Returns the names of directories to skip.
+    fn skip_dirs(&self) -> HashSet<String> {
+        let defaults: &[&str] = if self.no_default_skip_dirs {
+            &[]
+        } else {
+            DEFAULT_SKIP_DIRS
+        };
+        defaults
+            .iter()
+            .map(|dir| dir.to_string())
+            .chain(self.skip_dir.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the files under `dir` relative to `dir`.
+    fn walk(&self, dir: &Path) -> Box<dyn Iterator<Item = PathBuf>> {
+        if self.git_tracked {
+            if let Some(files) = crate::tools::git::tracked_files(dir) {
+                let root = dir.to_path_buf();
+                let binary = self.binary;
+                return Box::new(files.into_iter().filter(move |path| {
+                    binary != BinaryMode::Skip || !is_binary_file(&root.join(path))
+                }));
+            }
+            tracing::debug!(?dir, "Not in a git repo, walking the directory instead");
+        }
+
+        let files = WalkOptions {
+            hidden: self.hidden,
+            no_ignore: self.no_ignore,
+            skip_dirs: self.skip_dirs(),
+            binary: self.binary,
+            follow_symlinks: self.follow_symlinks,
+        }
+        .walk(dir);
+        Box::new(files)
+    }
+
+    pub fn run(&self, Params { number, .. }: Params) -> Result<()> {
+        let dir = match self.dir {
+            Some(ref dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+
+        let stdout = std::io::stdout();
+        let mut lock = stdout.lock();
+        let delimiter = if self.null { '\0' } else { '\n' };
+        for path in self.walk(&dir).take(number.unwrap_or(usize::MAX)) {
+            write!(lock, "{}{delimiter}", path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        for subdir in ["src", "target/debug", "node_modules/foo"] {
+            std::fs::create_dir_all(dir.join(subdir)).unwrap();
+        }
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.join("target/debug/maple"), "").unwrap();
+        std::fs::write(dir.join("node_modules/foo/index.js"), "").unwrap();
+
+        let walk = |args: &[&str]| {
+            let files = Files::parse_from(std::iter::once("files").chain(args.iter().copied()));
+            let mut paths = files.walk(&dir).collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(walk(&[]), vec![PathBuf::from("src/lib.rs")]);
+        assert_eq!(
+            walk(&["--no-default-skip-dirs", "--skip-dir", "node_modules"]),
+            vec![
+                PathBuf::from("src/lib.rs"),
+                PathBuf::from("target/debug/maple")
+            ]
+        );
+        assert!(walk(&["--skip-dir", "src"]).is_empty());
+    }
+
+    #[test]
+    fn test_clap_ignore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        // `.gitignore` only takes effect in a git repo.
+        for subdir in [".git", "src", "logs", "fixtures"] {
+            std::fs::create_dir_all(dir.join(subdir)).unwrap();
+        }
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.join("logs/debug.log"), "").unwrap();
+        std::fs::write(dir.join("fixtures/large.json"), "").unwrap();
+        std::fs::write(dir.join(".gitignore"), "logs/\n").unwrap();
+        std::fs::write(dir.join(CLAP_IGNORE_FILENAME), "fixtures/\n").unwrap();
+
+        let walk = |args: &[&str]| {
+            let files = Files::parse_from(std::iter::once("files").chain(args.iter().copied()));
+            let mut paths = files.walk(&dir).collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(walk(&[]), vec![PathBuf::from("src/lib.rs")]);
+        assert_eq!(
+            walk(&["--no-ignore"]),
+            vec![
+                PathBuf::from("fixtures/large.json"),
+                PathBuf::from("logs/debug.log"),
+                PathBuf::from("src/lib.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_git_tracked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.join("src/untracked.rs"), "").unwrap();
+        std::fs::write(dir.join("debug.log"), "").unwrap();
+        std::fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+
+        let walk = |args: &[&str]| {
+            let files = Files::parse_from(std::iter::once("files").chain(args.iter().copied()));
+            let mut paths = files.walk(&dir).collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+
+        // Not in a git repo yet, fall back to walking the directory.
+        assert_eq!(
+            walk(&["--git-tracked"]),
+            vec![
+                PathBuf::from("debug.log"),
+                PathBuf::from("src/lib.rs"),
+                PathBuf::from("src/untracked.rs"),
+            ]
+        );
+
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+        git(&["init", "-q"]);
+        git(&["add", ".gitignore", "src/lib.rs"]);
+
+        assert_eq!(
+            walk(&["--git-tracked"]),
+            vec![PathBuf::from(".gitignore"), PathBuf::from("src/lib.rs")]
+        );
+        // The untracked but not ignored file is listed by walking.
+        assert_eq!(
+            walk(&[]),
+            vec![
+                PathBuf::from("src/lib.rs"),
+                PathBuf::from("src/untracked.rs")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_binary_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(dir.join("lib.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.join("logo.png"), b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR").unwrap();
+
+        let walk = |args: &[&str]| {
+            let files = Files::parse_from(std::iter::once("files").chain(args.iter().copied()));
+            let mut paths = files.walk(&dir).collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+
+        assert!(is_binary_file(&dir.join("logo.png")));
+        assert!(!is_binary_file(&dir.join("lib.rs")));
+
+        assert_eq!(walk(&[]), vec![PathBuf::from("lib.rs")]);
+        assert_eq!(
+            walk(&["--binary", "text"]),
+            vec![PathBuf::from("lib.rs"), PathBuf::from("logo.png")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_cycle() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::create_dir_all(dir.join("c")).unwrap();
+        std::fs::write(dir.join("a/b/lib.rs"), "").unwrap();
+        std::fs::write(dir.join("c/main.rs"), "").unwrap();
+        // Cycle back to an ancestor.
+        symlink("..", dir.join("a/b/up")).unwrap();
+        // Cycle between the siblings.
+        symlink("../c", dir.join("a/to_c")).unwrap();
+        symlink("../a", dir.join("c/to_a")).unwrap();
+        // Another path to an existing directory.
+        symlink("a/b", dir.join("alias")).unwrap();
+
+        let walk = |args: &[&str]| {
+            let files = Files::parse_from(std::iter::once("files").chain(args.iter().copied()));
+            let mut paths = files.walk(&dir).collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+
+        let paths = walk(&["--follow-symlinks"]);
+        let mut deduped = paths.clone();
+        deduped.dedup();
+        assert_eq!(paths, deduped);
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().any(|path| path.ends_with("lib.rs")));
+        assert!(paths.iter().any(|path| path.ends_with("main.rs")));
+
+        assert_eq!(
+            walk(&[]),
+            vec![PathBuf::from("a/b/lib.rs"), PathBuf::from("c/main.rs")]
+        );
+    }
+}
@@ -1,30 +1,459 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{ArgGroup, Parser};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use subprocess::Exec;
 
-use filter::{FilterContext, ParSource, Source};
-use matcher::{Bonus, ClapItem, FuzzyAlgorithm, MatchScope, MatcherBuilder};
+use filter::{
+    BoundedLines, CaseMatching, FilterContext, MatchedItem, MatchedItems, ParSource, Query, Source,
+    SourceItem, WindowedFile, WindowedLine, WindowedMatches, DEFAULT_WINDOW_SIZE,
+};
+use matcher::score_expr::ScoreExpr;
+use matcher::{
+    Bonus, CaseFolding, ClapItem, FuzzyAlgorithm, MatchScope, Matcher, MatcherBuilder, Score,
+    ScoreBreakdown,
+};
+use types::AsAny;
+use utility::println_json;
 
 use crate::app::Params;
+use crate::archive::{self, ArchiveKind};
+use crate::cache::{CacheInfo, Digest};
+use crate::datastore::CACHE_INFO_IN_MEMORY;
 use crate::paths::AbsPathBuf;
+use crate::process::ShellCommand;
+use crate::recent_files::read_recent_files;
+
+/// Set on receiving SIGINT during the sync filtering.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code after flushing the partial results on SIGINT, 128 + SIGINT by the shell convention.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Replaces the default SIGINT handler which exits immediately with setting [`INTERRUPTED`].
+///
+/// `SA_RESTART` is not set, a read blocked on the input fails with `EINTR` instead of being
+/// restarted transparently, so that the flag can be checked.
+#[cfg(unix)]
+fn catch_interrupt() {
+    extern "C" fn on_sigint(_signum: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    // SAFETY: the handler only stores an atomic, which is async-signal-safe.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) != 0 {
+            tracing::debug!(
+                error = ?std::io::Error::last_os_error(),
+                "Failed to install the SIGINT handler"
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn catch_interrupt() {}
 
 fn parse_bonus(s: &str) -> Bonus {
-    if s.to_lowercase().as_str() == "filename" {
-        Bonus::FileName
+    match s.to_lowercase().as_str() {
+        "filename" => Bonus::FileName,
+        "acronym" => Bonus::Acronym,
+        "path-components" => Bonus::PathComponents,
+        _ => Bonus::None,
+    }
+}
+
+/// Minimum bytes of the source to be filtered in parallel by `--par auto`, about 200,000 lines.
+const PAR_RUN_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Whether the source is filtered in parallel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParMode {
+    /// Filter in parallel if the source is large and there are multiple cores.
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+impl std::str::FromStr for ParMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl<T: AsRef<str>> From<T> for ParMode {
+    fn from(s: T) -> Self {
+        match s.as_ref().to_lowercase().as_str() {
+            "on" => Self::On,
+            "off" => Self::Off,
+            _ => Self::Auto,
+        }
+    }
+}
+
+impl ParMode {
+    /// Returns `true` if a source of `size` bytes is filtered in parallel on `cores` cores.
+    ///
+    /// `size` is `None` if the source size is unknown or it can't be filtered in parallel.
+    fn is_parallel(&self, size: Option<u64>, cores: usize) -> bool {
+        match self {
+            Self::On => true,
+            Self::Off => false,
+            Self::Auto => cores > 1 && size.is_some_and(|size| size >= PAR_RUN_THRESHOLD),
+        }
+    }
+}
+
+/// Returns `true` if `cache_path` is the complete cache file of `digest`.
+fn is_valid_cache(cache_path: &Path, digest: &Digest) -> bool {
+    digest.cached_path == cache_path
+        && std::fs::File::open(cache_path)
+            .and_then(crate::utils::count_lines)
+            .map(|total| total == digest.total)
+            .unwrap_or(false)
+}
+
+/// Returns the digest of `cache_path` in `cache_info`, which must be the digest of `shell_cmd`
+/// if specified.
+fn find_cache_digest(
+    cache_info: &mut CacheInfo,
+    cache_path: &Path,
+    shell_cmd: Option<&ShellCommand>,
+) -> Option<Digest> {
+    let digest = match shell_cmd {
+        Some(shell_cmd) => cache_info.find_digest_usable(shell_cmd),
+        None => cache_info.find_digest_by_path(cache_path),
+    };
+    match digest {
+        Some(digest) if is_valid_cache(cache_path, &digest) => Some(digest),
+        _ => {
+            tracing::debug!(
+                ?cache_path,
+                ?shell_cmd,
+                "Cache mismatch, fallback to the normal input"
+            );
+            None
+        }
+    }
+}
+
+/// Returns an iterator of the non-empty items delimited by NUL in `reader`.
+fn split_null_delimited(reader: impl BufRead) -> impl Iterator<Item = String> {
+    reader
+        .split(b'\0')
+        .filter_map(Result::ok)
+        .filter(|item| !item.is_empty())
+        .map(|item| String::from_utf8_lossy(&item).into_owned())
+}
+
+/// Item of `--json-input`, e.g., `{"text": "Git commit", "keywords": ["save"]}`.
+#[derive(Debug, Deserialize)]
+struct JsonItem {
+    text: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+}
+
+impl ClapItem for JsonItem {
+    fn raw_text(&self) -> &str {
+        &self.text
+    }
+
+    fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+}
+
+/// Parses `line` as a [`JsonItem`], the line which is not a valid one is used as plain text.
+fn parse_json_item(line: String) -> Arc<dyn ClapItem> {
+    match serde_json::from_str::<JsonItem>(&line) {
+        Ok(json_item) => Arc::new(json_item),
+        Err(_) => Arc::new(SourceItem::from(line)),
+    }
+}
+
+/// Result of `--save-results`, one JSON array `[score, [indices], "text"]` per line.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedResult(Score, Vec<usize>, String);
+
+/// Writes the ranked `matched_items` to `path`, in the order of rank.
+fn save_results(path: &Path, matched_items: &[MatchedItem]) -> Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    for matched_item in matched_items {
+        let saved = SavedResult(
+            matched_item.score,
+            matched_item.indices.clone(),
+            matched_item.item.raw_text().to_string(),
+        );
+        writeln!(writer, "{}", serde_json::to_string(&saved)?)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads the ranked results written by [`save_results`] from `path`.
+fn load_results(path: &Path) -> Result<Vec<MatchedItem>> {
+    BufReader::new(std::fs::File::open(path)?)
+        .lines()
+        .map(|line| {
+            let SavedResult(score, indices, text) = serde_json::from_str(&line?)?;
+            Ok(MatchedItem::new(
+                Arc::new(SourceItem::from(text)),
+                score,
+                indices,
+            ))
+        })
+        .collect()
+}
+
+/// Returns the key of `item` for the dedup, the leading `./` is stripped and the case is folded
+/// if `ignore_case` is true.
+fn dedup_key(item: &str, ignore_case: bool) -> Cow<'_, str> {
+    let mut key = item;
+    while let Some(stripped) = key.strip_prefix("./") {
+        key = stripped.trim_start_matches('/');
+    }
+    if ignore_case {
+        key.to_lowercase().into()
     } else {
-        Bonus::None
+        key.into()
+    }
+}
+
+/// Pseudo-random number generator for the sampling, SplitMix64.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
     }
 }
 
+/// Returns a uniform random sample of at most `size` items in random order, the items are
+/// consumed in one pass by the reservoir sampling.
+///
+/// The sample is the same given the same `seed`.
+fn sample_items<T>(items: impl Iterator<Item = T>, size: usize, seed: u64) -> Vec<T> {
+    let mut rng = SplitMix64(seed);
+
+    let mut reservoir = Vec::new();
+    for (idx, item) in items.enumerate() {
+        if reservoir.len() < size {
+            reservoir.push(item);
+        } else {
+            let pos = rng.below(idx + 1);
+            if pos < size {
+                reservoir[pos] = item;
+            }
+        }
+    }
+
+    // The reservoir is still in the order of source partially.
+    for idx in (1..reservoir.len()).rev() {
+        reservoir.swap(idx, rng.below(idx + 1));
+    }
+
+    reservoir
+}
+
+/// Removes the duplicates in `items`, the first seen one is kept.
+fn dedup_items(items: Vec<Arc<dyn ClapItem>>, ignore_case: bool) -> Vec<Arc<dyn ClapItem>> {
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(dedup_key(item.raw_text(), ignore_case).into_owned()))
+        .collect()
+}
+
+/// Returns the number of leading query chars matched by at least one of `items`.
+fn matched_prefix_len(
+    items: &[Arc<dyn ClapItem>],
+    matcher_builder: &MatcherBuilder,
+    query: &str,
+) -> usize {
+    let prefix_ends = query
+        .char_indices()
+        .map(|(idx, c)| idx + c.len_utf8())
+        .collect::<Vec<_>>();
+
+    // Search from the longest prefix.
+    prefix_ends
+        .iter()
+        .rposition(|&end| {
+            let matcher = matcher_builder.clone().build(query[..end].into());
+            items
+                .iter()
+                .any(|item| matcher.match_item(item.clone()).is_some())
+        })
+        .map_or(0, |pos| pos + 1)
+}
+
+/// Returns the number of chars of the positive terms in `query` found at `indices` of `text`.
+///
+/// It equals the length of the query terms for a full match, less for a partial one, e.g.,
+/// a match tolerating the typos.
+fn matched_query_len(query: &str, text: &str, indices: &[usize]) -> usize {
+    let Query {
+        fuzzy_terms,
+        exact_terms,
+        ..
+    } = query.into();
+
+    let mut unmatched = fuzzy_terms
+        .iter()
+        .map(|term| term.word.as_str())
+        .chain(exact_terms.iter().map(|term| term.word.as_str()))
+        .flat_map(|word| word.chars().flat_map(char::to_lowercase))
+        .collect::<Vec<_>>();
+    let total = unmatched.len();
+
+    // The indices are char positions.
+    let chars = text.chars().collect::<Vec<_>>();
+    for c in indices
+        .iter()
+        .filter_map(|&idx| chars.get(idx).copied())
+        .flat_map(char::to_lowercase)
+    {
+        if let Some(pos) = unmatched.iter().position(|&x| x == c) {
+            unmatched.swap_remove(pos);
+        }
+    }
+
+    total - unmatched.len()
+}
+
+/// Indices of a matched item highlighted by the `term`-th term of query.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct TermIndices {
+    term: usize,
+    indices: Vec<usize>,
+}
+
+/// Returns the indices of `matched_item` grouped by the query `terms` they are matched by.
+///
+/// The terms without any highlighted char, e.g., the inverse terms, are omitted.
+fn group_indices_by_term(
+    matched_item: &MatchedItem,
+    matcher_builder: &MatcherBuilder,
+    terms: &[&str],
+) -> Vec<TermIndices> {
+    terms
+        .iter()
+        .enumerate()
+        .filter_map(|(term, word)| {
+            let term_matched_item = matcher_builder
+                .clone()
+                .build((*word).into())
+                .match_item(matched_item.item.clone())?;
+            let indices = term_matched_item
+                .indices
+                .into_iter()
+                .filter(|idx| matched_item.indices.contains(idx))
+                .collect::<Vec<_>>();
+            (!indices.is_empty()).then_some(TermIndices { term, indices })
+        })
+        .collect()
+}
+
+/// Returns the length of the positive terms in `query`.
+fn query_terms_len(query: &str) -> usize {
+    let Query {
+        fuzzy_terms,
+        exact_terms,
+        ..
+    } = query.into();
+    fuzzy_terms
+        .iter()
+        .map(|term| term.word.chars().count())
+        .chain(exact_terms.iter().map(|term| term.word.chars().count()))
+        .sum()
+}
+
+/// Returns the name of `bonus` without the data it carries.
+fn bonus_name(bonus: &Bonus) -> String {
+    let debug = format!("{bonus:?}");
+    match debug.split_once('(') {
+        Some((name, _)) => name.to_string(),
+        None => debug,
+    }
+}
+
+/// Returns the terms of `query` parsed in the extended search syntax.
+fn parsed_query_json(query: &str) -> serde_json::Value {
+    let Query {
+        fuzzy_terms,
+        exact_terms,
+        inverse_terms,
+    } = Query::from(query);
+    let term = |ty: String, word: String| json!({ "type": ty, "word": word });
+    json!({
+        "fuzzy_terms": fuzzy_terms
+            .into_iter()
+            .map(|t| term(format!("{:?}", t.ty), t.word))
+            .collect::<Vec<_>>(),
+        "exact_terms": exact_terms
+            .into_iter()
+            .map(|t| term(format!("{:?}", t.ty), t.word))
+            .collect::<Vec<_>>(),
+        "inverse_terms": inverse_terms
+            .into_iter()
+            .map(|t| term(format!("{:?}", t.ty), t.word))
+            .collect::<Vec<_>>(),
+    })
+}
+
 /// Execute the shell command
 #[derive(Parser, Debug, Clone)]
+#[clap(group(
+    ArgGroup::new("mode")
+        .multiple(false)
+        .args(&[
+            "load-results",
+            "windowed",
+            "shuffle",
+            "null",
+            "json-input",
+            "first-match-only",
+            "explain",
+            "count-matches",
+            "group-indices",
+            "tabstop",
+            "dedup",
+            "dedup-ci",
+        ])
+))]
+#[clap(group(
+    ArgGroup::new("query-kind")
+        .multiple(false)
+        .args(&["or", "glob-query", "path-segments"])
+))]
 pub struct Filter {
     /// Initial query string
-    #[clap(index = 1, long)]
+    #[clap(index = 1)]
     query: String,
 
     /// Fuzzy matching algorithm
@@ -44,13 +473,52 @@ pub struct Filter {
     recent_files: Option<PathBuf>,
 
     /// Read input from a file instead of stdin, only absolute file path is supported.
+    ///
+    /// If the file is an archive (zip, tar, tar.gz), the paths of its entries are the input.
     #[clap(long)]
     input: Option<AbsPathBuf>,
 
+    /// Filter `--input` window by window instead of reading it into memory, for a huge file.
+    ///
+    /// At most `--number` results (100 by default) are printed along with their line number and
+    /// byte offset in the file.
+    #[clap(long, requires = "input")]
+    windowed: bool,
+
+    /// Read input from the cache file created by a previous run of `--cmd`.
+    ///
+    /// The cache file must be recorded in the cache digests, as the cache of `--cmd` if specified,
+    /// and complete, otherwise falls back to the normal input. The cache is filtered in parallel
+    /// like the cached source of the providers unless `--par off`.
+    #[clap(long, parse(from_os_str))]
+    resume_from_cache: Option<PathBuf>,
+
     /// Apply the filter on the full line content or parial of it.
     #[clap(long, parse(from_str), default_value = "full")]
     match_scope: MatchScope,
 
+    /// Maximum number of typos tolerated when the strict fuzzy matching fails.
+    #[clap(long, default_value = "0")]
+    fuzziness: usize,
+
+    /// Case folding in the case-insensitive matching, `simple`, `full` or `turkic`.
+    ///
+    /// `full` applies the Unicode full case folding, e.g., `strasse` matches `Straße`, `turkic`
+    /// additionally folds `I` to `ı` and `İ` to `i`.
+    #[clap(long, parse(from_str), default_value = "simple")]
+    case_folding: CaseFolding,
+
+    /// Ignore the combining diacritics in the fuzzy matching, e.g., `cafe` matches `café`.
+    #[clap(long)]
+    accent_insensitive: bool,
+
+    /// Expression computing the final score instead of the built-in scoring.
+    ///
+    /// The variables `base_score`, `len`, `path_depth` and `is_recent` are available, e.g.,
+    /// `base_score - 10 * path_depth`. An invalid expression is rejected before filtering.
+    #[clap(long, parse(try_from_str))]
+    score_expr: Option<ScoreExpr>,
+
     /// Add a bonus to the score of base matching algorithm.
     #[clap(long, parse(from_str = parse_bonus), default_value = "none")]
     bonus: Bonus,
@@ -59,14 +527,216 @@ pub struct Filter {
     #[clap(long)]
     sync: bool,
 
+    /// Same with `--par on`.
     #[clap(long)]
     par_run: bool,
+
+    /// Whether to filter in parallel, `auto`, `on` or `off`.
+    ///
+    /// With `auto`, the source is filtered in parallel if it's a large file and there are
+    /// multiple cores, sequentially otherwise.
+    #[clap(long, parse(from_str), default_value = "auto")]
+    par: ParMode,
+
+    /// Number of lines per task when `--par-run` is on, chosen by the source size if not set.
+    #[clap(long)]
+    batch_size: Option<usize>,
+
+    /// Print the score components of each matched item, implies `--sync`.
+    #[clap(long)]
+    explain: bool,
+
+    /// Print the score of each result as a separate field, unlike `--explain` the score is not
+    /// broken down into the components.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "explain",
+            "count-matches",
+            "group-indices",
+            "tabstop",
+            "first-match-only",
+        ]
+    )]
+    show_score: bool,
+
+    /// Read the input items delimited by NUL instead of newline, implies `--sync`.
+    ///
+    /// The results are also printed NUL-delimited if `--number` is not specified.
+    #[clap(long, short = '0')]
+    null: bool,
+
+    /// Read each input line as a JSON object `{"text": ..., "keywords": [...]}`, implies
+    /// `--sync`.
+    ///
+    /// The keywords are matched against when the text does not match but never displayed.
+    #[clap(long)]
+    json_input: bool,
+
+    /// Print the number of leading query chars matched by the best result, implies `--sync`.
+    ///
+    /// It's less than the query length when no item matches the whole query.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "load-results",
+            "windowed",
+            "shuffle",
+            "null",
+            "json-input",
+            "first-match-only",
+            "explain",
+            "group-indices",
+            "tabstop",
+            "dedup",
+            "dedup-ci",
+        ]
+    )]
+    show_partial: bool,
+
+    /// Annotate each result with the number of query chars it matched, implies `--sync`.
+    ///
+    /// `full_match` is false when `matched_len` is less than the length of the query.
+    #[clap(long)]
+    count_matches: bool,
+
+    /// Print the indices grouped by the query terms they are matched by, implies `--sync`.
+    ///
+    /// The indices are emitted as `[{"term": 0, "indices": [...]}, ...]`, the terms are
+    /// numbered in the order of query, across the alternatives of `--or`.
+    #[clap(long)]
+    group_indices: bool,
+
+    /// Match any of the `|`-separated alternatives in the query, implies `--sync`.
+    ///
+    /// The score of an item is the best score among the alternatives.
+    #[clap(long)]
+    or: bool,
+
+    /// Treat the query as a glob pattern instead of the fuzzy terms, implies `--sync`.
+    ///
+    /// `*` matches any run of chars and `?` matches a single char, the pattern can match
+    /// anywhere unless it's anchored by a leading `^` or a trailing `$`.
+    #[clap(long)]
+    glob_query: bool,
+
+    /// Treat `/` in the query as a boundary aligned with `/` in the item, implies `--sync`.
+    ///
+    /// Each `/`-separated segment is matched fuzzily within a path component, e.g., `s/m`
+    /// matches `src/main.rs` but not `smith.rs`.
+    #[clap(long)]
+    path_segments: bool,
+
+    /// Among the items of equal score, rank the shorter one higher, implies `--sync`.
+    ///
+    /// The items of equal score and length are ranked in the order of source.
+    #[clap(long)]
+    prefer_shorter: bool,
+
+    /// Print only the text of the best matched item, or nothing if no item matches, implies
+    /// `--sync`.
+    ///
+    /// The items are not ranked, the tie is broken the same way as `--prefer-shorter` does if
+    /// specified, or by the order of source.
+    #[clap(long)]
+    first_match_only: bool,
+
+    /// Finish reading stdin if no new line arrives within this many milliseconds.
+    ///
+    /// Wait until stdin is closed if not specified.
+    #[clap(long)]
+    stdin_timeout: Option<u64>,
+
+    /// Print the display columns of the highlights with the tabs expanded to this width,
+    /// implies `--sync`.
+    ///
+    /// The columns are emitted as `display_columns` alongside the char-based `indices`.
+    #[clap(long)]
+    tabstop: Option<usize>,
+
+    /// Print a uniform random sample of `--number` items instead of the first ones, only
+    /// taking effect when the query is empty.
+    ///
+    /// All the items are printed in random order if `--number` is not specified.
+    #[clap(long)]
+    shuffle: bool,
+
+    /// Seed of the random sampling of `--shuffle`, a random one is used if not specified.
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Drop the duplicate items, keeping the first seen one, implies `--sync`.
+    ///
+    /// The leading `./` is ignored, e.g., `./src/lib.rs` duplicates `src/lib.rs`.
+    #[clap(long)]
+    dedup: bool,
+
+    /// Same with `--dedup`, but the items differing only in case are also duplicates.
+    ///
+    /// E.g., `README.md` and `readme.md` from the sources of a case-insensitive filesystem, the
+    /// casing seen first is displayed.
+    #[clap(long)]
+    dedup_ci: bool,
+
+    /// Save the ranked results with the scores and indices to this file, implies `--sync`.
+    ///
+    /// All the results are saved regardless of `--number`.
+    #[clap(long, parse(from_os_str))]
+    save_results: Option<PathBuf>,
+
+    /// Display the results saved by `--save-results` instead of filtering, the query and the
+    /// input are ignored.
+    #[clap(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["cmd", "input", "resume-from-cache", "save-results"]
+    )]
+    load_results: Option<PathBuf>,
+
+    /// Print the resolved configuration and the parsed query as a leading JSON object before
+    /// the results, for verifying the flags take effect as expected.
+    #[clap(long)]
+    prompt_echo: bool,
 }
 
 impl Filter {
-    /// Firstly try building the Source from shell command, then the input file, finally reading the source from stdin.
-    fn generate_source<I: Iterator<Item = Arc<dyn ClapItem>>>(&self) -> Source<I> {
-        if let Some(ref cmd_str) = self.cmd {
+    /// Returns the digest of the cache file specified by `--resume-from-cache` if it's still
+    /// valid.
+    fn cache_digest(&self) -> Option<Digest> {
+        let cache_path = self.resume_from_cache.as_ref()?;
+
+        let shell_cmd = match self.cmd {
+            Some(ref cmd_str) => {
+                let cwd = match self.cmd_dir {
+                    Some(ref dir) => dir.into(),
+                    None => std::env::current_dir().ok()?,
+                };
+                Some(ShellCommand::new(cmd_str.clone(), cwd))
+            }
+            None => None,
+        };
+
+        let mut cache_info = CACHE_INFO_IN_MEMORY.lock();
+        find_cache_digest(&mut cache_info, cache_path, shell_cmd.as_ref())
+    }
+
+    /// Returns the cache file specified by `--resume-from-cache` if it's still valid.
+    ///
+    /// The cache file is validated by counting its lines, it's resolved once in [`Self::run`]
+    /// and passed down as `cache`, which is used instead of the source if specified.
+    fn usable_cache(&self) -> Option<PathBuf> {
+        self.cache_digest().map(|digest| digest.cached_path)
+    }
+
+    /// Firstly try building the Source from the cache file, then shell command, then the input file,
+    /// finally reading the source from stdin.
+    fn generate_source<I: Iterator<Item = Arc<dyn ClapItem>>>(
+        &self,
+        cache: Option<&Path>,
+    ) -> Source<I> {
+        if let Some(cache_file) = cache {
+            cache_file.to_path_buf().into()
+        } else if let Some(ref cmd_str) = self.cmd {
             if let Some(ref dir) = self.cmd_dir {
                 Exec::shell(cmd_str).cwd(dir).into()
             } else {
@@ -76,12 +746,14 @@ impl Filter {
             self.input
                 .as_ref()
                 .map(|i| i.deref().clone().into())
-                .unwrap_or(Source::<I>::Stdin)
+                .unwrap_or_else(|| Source::<I>::Stdin(self.stdin_timeout()))
         }
     }
 
-    fn generate_par_source(&self) -> ParSource {
-        if let Some(ref cmd_str) = self.cmd {
+    fn generate_par_source(&self, cache: Option<&Path>) -> ParSource {
+        if let Some(cache_file) = cache {
+            ParSource::File(cache_file.to_path_buf())
+        } else if let Some(ref cmd_str) = self.cmd {
             let exec = if let Some(ref dir) = self.cmd_dir {
                 Exec::shell(cmd_str).cwd(dir)
             } else {
@@ -98,18 +770,266 @@ impl Filter {
         }
     }
 
-    fn get_bonuses(&self) -> Vec<Bonus> {
-        use std::io::BufRead;
+    /// Returns the bytes of the source if it can be filtered in parallel.
+    ///
+    /// Only the metadata of source file is probed, the output of `--cmd` is unknown until it's
+    /// executed.
+    fn par_source_size(&self) -> Option<u64> {
+        if self.cmd.is_some() {
+            return None;
+        }
+        std::fs::metadata(self.input.as_ref()?.deref())
+            .map(|metadata| metadata.len())
+            .ok()
+    }
 
+    /// Returns `true` if the source is filtered in parallel.
+    ///
+    /// A valid cache is always filtered in parallel unless `--par off`, the same as the cached
+    /// source of the providers.
+    fn is_par_run(&self, cache: Option<&Path>) -> bool {
+        if self.par_run || (self.par != ParMode::Off && cache.is_some()) {
+            return true;
+        }
+        // Probe the source only when it's needed.
+        let size = match self.par {
+            ParMode::Auto => self.par_source_size(),
+            _ => None,
+        };
+        self.par.is_parallel(size, rayon::current_num_threads())
+    }
+
+    /// Returns the archive kind of `--input` if it's an archive.
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        match self.input {
+            Some(ref input) if self.cmd.is_none() => ArchiveKind::detect(input),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of `--input` if it's an archive.
+    fn archive_entries(&self) -> Result<Option<Vec<Arc<dyn ClapItem>>>> {
+        match (self.input.as_ref(), self.archive_kind()) {
+            (Some(input), Some(kind)) => Ok(Some(
+                archive::list_entries(input, kind)?
+                    .into_iter()
+                    .map(|entry| Arc::new(entry) as Arc<dyn ClapItem>)
+                    .collect(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the source of items, which are the entries of `--input` if it's an archive.
+    fn items_source(
+        &self,
+        cache: Option<&Path>,
+    ) -> Result<Source<std::vec::IntoIter<Arc<dyn ClapItem>>>> {
+        match self.archive_entries()? {
+            Some(entries) => Ok(Source::List(entries.into_iter())),
+            None => Ok(self.generate_source(cache)),
+        }
+    }
+
+    fn stdin_timeout(&self) -> Option<Duration> {
+        self.stdin_timeout.map(Duration::from_millis)
+    }
+
+    fn source_reader(&self, cache: Option<&Path>) -> Result<Box<dyn Read>> {
+        let reader: Box<dyn Read> = if let Some(cache_file) = cache {
+            Box::new(std::fs::File::open(cache_file)?)
+        } else if let Some(ref cmd_str) = self.cmd {
+            let exec = match self.cmd_dir {
+                Some(ref dir) => Exec::shell(cmd_str).cwd(dir),
+                None => Exec::shell(cmd_str),
+            };
+            Box::new(exec.stream_stdout()?)
+        } else if let Some(ref input) = self.input {
+            Box::new(std::fs::File::open(input)?)
+        } else {
+            Box::new(std::io::stdin())
+        };
+        Ok(reader)
+    }
+
+    /// Reads all the lines from the source.
+    fn read_lines(&self, cache: Option<&Path>) -> Result<Box<dyn Iterator<Item = String>>> {
+        let reads_stdin = cache.is_none() && self.cmd.is_none() && self.input.is_none();
+        let lines: Box<dyn Iterator<Item = String>> = if reads_stdin && self.stdin_timeout.is_some()
+        {
+            Box::new(BoundedLines::stdin().timeout(self.stdin_timeout()))
+        } else {
+            Box::new(
+                BufReader::new(self.source_reader(cache)?)
+                    .lines()
+                    .filter_map(Result::ok),
+            )
+        };
+        Ok(lines)
+    }
+
+    /// Reads all the items from the source.
+    fn read_items(&self, cache: Option<&Path>) -> Result<Vec<Arc<dyn ClapItem>>> {
+        if let Some(entries) = self.archive_entries()? {
+            return Ok(entries);
+        }
+        Ok(self
+            .read_lines(cache)?
+            .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>)
+            .collect())
+    }
+
+    /// Reads all the items of `--json-input` from the source.
+    fn read_json_items(&self, cache: Option<&Path>) -> Result<Vec<Arc<dyn ClapItem>>> {
+        Ok(self.read_lines(cache)?.map(parse_json_item).collect())
+    }
+
+    /// Reads the NUL-delimited items from the source.
+    fn read_null_delimited_items(&self, cache: Option<&Path>) -> Result<Vec<Arc<dyn ClapItem>>> {
+        let reader = BufReader::new(self.source_reader(cache)?);
+        Ok(split_null_delimited(reader)
+            .map(|item| Arc::new(SourceItem::from(item)) as Arc<dyn ClapItem>)
+            .collect())
+    }
+
+    /// Returns the terms of query in order, the query is a single term for `--glob-query` and
+    /// `--path-segments`.
+    fn query_terms(&self) -> Vec<&str> {
+        if self.glob_query || self.path_segments {
+            vec![self.query.as_str()]
+        } else if self.or {
+            self.query
+                .split(|c: char| c == '|' || c.is_whitespace())
+                .filter(|word| !word.is_empty())
+                .collect()
+        } else {
+            self.query.split_whitespace().collect()
+        }
+    }
+
+    fn build_matcher(&self, matcher_builder: MatcherBuilder) -> Matcher {
+        if self.glob_query {
+            matcher_builder.build_glob(&self.query)
+        } else if self.path_segments {
+            matcher_builder.build_path_segments(&self.query)
+        } else if self.or {
+            matcher_builder.build_any(
+                self.query
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|alternative| !alternative.is_empty())
+                    .map(Into::into),
+            )
+        } else {
+            matcher_builder.build(self.query.as_str().into())
+        }
+    }
+
+    /// Returns `true` if the results are printed after the whole source is filtered.
+    fn is_sync(&self) -> bool {
+        self.sync
+            || self.or
+            || self.glob_query
+            || self.path_segments
+            || self.group_indices
+            || self.prefer_shorter
+            || self.first_match_only
+            || self.save_results.is_some()
+    }
+
+    /// Sorts the matched items by score, breaking the ties as specified.
+    ///
+    /// The ranked items are also saved if `--save-results` is specified.
+    fn rank(&self, matched_items: MatchedItems) -> Result<Vec<MatchedItem>> {
+        let ranked = if self.prefer_shorter {
+            matched_items.par_sort_prefer_shorter().inner()
+        } else {
+            matched_items.par_sort().inner()
+        };
+        if let Some(ref path) = self.save_results {
+            save_results(path, &ranked)?;
+        }
+        Ok(ranked)
+    }
+
+    /// Returns the best matched item of the source.
+    fn first_match(
+        &self,
+        matcher_builder: MatcherBuilder,
+        cache: Option<&Path>,
+    ) -> Result<Option<MatchedItem>> {
+        Ok(self
+            .items_source(cache)?
+            .matched_items(self.build_matcher(matcher_builder))?
+            .best(self.prefer_shorter))
+    }
+
+    /// Returns the configuration the filtering runs with, printed by `--prompt-echo`.
+    fn resolved_config(
+        &self,
+        case_matching: CaseMatching,
+        cache: Option<&Path>,
+    ) -> serde_json::Value {
+        let (query_kind, parsed_query) = if self.glob_query {
+            ("glob", json!(self.query))
+        } else if self.path_segments {
+            (
+                "path_segments",
+                json!(self.query.split('/').collect::<Vec<_>>()),
+            )
+        } else if self.or {
+            let alternatives = self
+                .query
+                .split('|')
+                .map(str::trim)
+                .filter(|alternative| !alternative.is_empty())
+                .map(parsed_query_json)
+                .collect::<Vec<_>>();
+            ("or", json!(alternatives))
+        } else {
+            ("extended", parsed_query_json(&self.query))
+        };
+
+        let sync = self.is_sync()
+            || self.explain
+            || self.null
+            || self.json_input
+            || self.show_partial
+            || self.count_matches
+            || self.dedup
+            || self.dedup_ci
+            || self.tabstop.is_some();
+        let mode = if sync {
+            "sync"
+        } else if self.archive_kind().is_none() && self.is_par_run(cache) {
+            "par"
+        } else {
+            "dyn"
+        };
+
+        json!({
+            "event": "config",
+            "query": self.query,
+            "query_kind": query_kind,
+            "parsed_query": parsed_query,
+            "algo": format!("{:?}", self.algo),
+            "match_scope": format!("{:?}", self.match_scope),
+            "bonuses": self.get_bonuses().iter().map(bonus_name).collect::<Vec<_>>(),
+            "case_matching": format!("{case_matching:?}"),
+            "case_folding": format!("{:?}", self.case_folding),
+            "accent_insensitive": self.accent_insensitive,
+            "score_expr": self.score_expr.as_ref().map(|score_expr| score_expr.source()),
+            "fuzziness": self.fuzziness,
+            "mode": mode,
+        })
+    }
+
+    fn get_bonuses(&self) -> Vec<Bonus> {
         let mut bonuses = vec![self.bonus.clone()];
         if let Some(ref recent_files) = self.recent_files {
             // Ignore the error cases.
-            if let Ok(file) = std::fs::File::open(recent_files) {
-                let lines: Vec<String> = std::io::BufReader::new(file)
-                    .lines()
-                    .filter_map(|x| x.ok())
-                    .collect();
-                bonuses.push(Bonus::RecentFiles(lines.into()));
+            if let Some(recent_files) = read_recent_files(recent_files) {
+                bonuses.push(Bonus::RecentFiles(recent_files));
             }
         }
 
@@ -126,33 +1046,724 @@ impl Filter {
             ..
         }: Params,
     ) -> Result<()> {
+        if self.archive_kind().is_some() && (self.windowed || self.null || self.json_input) {
+            return Err(anyhow::anyhow!(
+                "--windowed, --null and --json-input can not be used with an archive --input"
+            ));
+        }
+
+        let cache = self.usable_cache();
+        let cache = cache.as_deref();
+
+        if self.prompt_echo {
+            println!("{}", self.resolved_config(case_matching, cache));
+        }
+
         let matcher_builder = MatcherBuilder::default()
             .bonuses(self.get_bonuses())
             .match_scope(self.match_scope)
             .fuzzy_algo(self.algo)
-            .case_matching(case_matching);
+            .case_matching(case_matching)
+            .fuzziness(self.fuzziness)
+            .case_folding(self.case_folding)
+            .accent_insensitive(self.accent_insensitive)
+            .score_expr(self.score_expr.clone());
 
-        if self.sync {
-            let ranked = self
-                .generate_source::<std::iter::Empty<_>>()
-                .matched_items(matcher_builder.build(self.query.as_str().into()))?
-                .par_sort()
-                .inner();
+        if let Some(ref path) = self.load_results {
+            printer::print_sync_filter_results(
+                load_results(path)?,
+                number,
+                printer::normalize_winwidth(winwidth),
+                icon,
+                self.show_score,
+            );
+        } else if self.windowed {
+            let input = self
+                .input
+                .as_ref()
+                .expect("--windowed requires --input; qed");
+            let WindowedMatches {
+                matched_items,
+                total_matched,
+                ..
+            } = WindowedFile::open(input.deref(), DEFAULT_WINDOW_SIZE)?
+                .matched_items(&self.build_matcher(matcher_builder), number.unwrap_or(100))?;
+
+            for matched_item in matched_items {
+                if let Some(line) =
+                    AsAny::as_any(&*matched_item.item).downcast_ref::<WindowedLine>()
+                {
+                    let text = matched_item.display_text();
+                    let indices = &matched_item.indices;
+                    let lnum = line.lnum();
+                    let byte_offset = line.byte_offset();
+                    println_json!(text, indices, lnum, byte_offset);
+                }
+            }
+            println_json!(total_matched);
+        } else if self.shuffle && self.query.is_empty() {
+            let seed = self.seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+            let sample = sample_items(
+                self.items_source(cache)?.items()?,
+                number.unwrap_or(usize::MAX),
+                seed,
+            )
+            .into_iter()
+            .map(|item| MatchedItem::new(item, Default::default(), Default::default()))
+            .collect();
+
+            printer::print_sync_filter_results(
+                sample,
+                number,
+                printer::normalize_winwidth(winwidth),
+                icon,
+                self.show_score,
+            );
+        } else if self.null {
+            let ranked = self.rank(
+                Source::List(self.read_null_delimited_items(cache)?.into_iter())
+                    .matched_items(self.build_matcher(matcher_builder))?,
+            )?;
+
+            if number.is_some() {
+                printer::print_sync_filter_results(
+                    ranked,
+                    number,
+                    printer::normalize_winwidth(winwidth),
+                    icon,
+                    self.show_score,
+                );
+            } else {
+                let stdout = std::io::stdout();
+                let mut lock = stdout.lock();
+                for matched_item in ranked {
+                    write!(lock, "{}\0", matched_item.display_text())?;
+                }
+            }
+        } else if self.json_input {
+            let ranked = self.rank(
+                Source::List(self.read_json_items(cache)?.into_iter())
+                    .matched_items(self.build_matcher(matcher_builder))?,
+            )?;
+            printer::print_sync_filter_results(
+                ranked,
+                number,
+                printer::normalize_winwidth(winwidth),
+                icon,
+                self.show_score,
+            );
+        } else if self.first_match_only {
+            if let Some(matched_item) = self.first_match(matcher_builder, cache)? {
+                println!("{}", matched_item.display_text());
+            }
+        } else if self.explain {
+            let matcher = self.build_matcher(matcher_builder);
+            let ranked = self.rank(self.items_source(cache)?.matched_items(matcher.clone())?)?;
+
+            for matched_item in ranked.iter().take(number.unwrap_or(usize::MAX)) {
+                if let Some(ScoreBreakdown {
+                    exact_score,
+                    fuzzy_score,
+                    bonus_scores,
+                    callback_score,
+                }) = matcher.score_breakdown(matched_item.item.clone())
+                {
+                    let text = matched_item.display_text();
+                    let indices = &matched_item.indices;
+                    let score = matched_item.score;
+                    println_json!(
+                        text,
+                        indices,
+                        score,
+                        exact_score,
+                        fuzzy_score,
+                        bonus_scores,
+                        callback_score
+                    );
+                }
+            }
+        } else if self.count_matches {
+            let items = self.read_items(cache)?;
+            let ranked = self.rank(
+                Source::List(items.clone().into_iter())
+                    .matched_items(self.build_matcher(matcher_builder.clone()))?,
+            )?;
+
+            let query_len = query_terms_len(&self.query);
+            for matched_item in ranked.iter().take(number.unwrap_or(usize::MAX)) {
+                let text = matched_item.display_text();
+                let indices = &matched_item.indices;
+                let matched_len = matched_query_len(&self.query, &text, indices);
+                let full_match = matched_len == query_len;
+                println_json!(text, indices, matched_len, full_match);
+            }
+
+            if self.show_partial {
+                let matched_prefix_len = if ranked.is_empty() {
+                    matched_prefix_len(&items, &matcher_builder, &self.query)
+                } else {
+                    self.query.chars().count()
+                };
+                println_json!(matched_prefix_len);
+            }
+        } else if self.show_partial {
+            let items = self.read_items(cache)?;
+            let ranked = self.rank(
+                Source::List(items.clone().into_iter())
+                    .matched_items(self.build_matcher(matcher_builder.clone()))?,
+            )?;
+
+            let matched_prefix_len = if ranked.is_empty() {
+                matched_prefix_len(&items, &matcher_builder, &self.query)
+            } else {
+                self.query.chars().count()
+            };
+
+            printer::print_sync_filter_results(
+                ranked,
+                number,
+                printer::normalize_winwidth(winwidth),
+                icon,
+                self.show_score,
+            );
+            println_json!(matched_prefix_len);
+        } else if self.dedup || self.dedup_ci {
+            let items = dedup_items(self.read_items(cache)?, self.dedup_ci);
+            let ranked = self.rank(
+                Source::List(items.into_iter())
+                    .matched_items(self.build_matcher(matcher_builder))?,
+            )?;
+
+            printer::print_sync_filter_results(
+                ranked,
+                number,
+                printer::normalize_winwidth(winwidth),
+                icon,
+                self.show_score,
+            );
+        } else if self.group_indices {
+            let ranked = self.rank(
+                self.items_source(cache)?
+                    .matched_items(self.build_matcher(matcher_builder.clone()))?,
+            )?;
+
+            let terms = self.query_terms();
+            for matched_item in ranked.iter().take(number.unwrap_or(usize::MAX)) {
+                let text = matched_item.display_text();
+                let indices = if terms.len() > 1 {
+                    group_indices_by_term(matched_item, &matcher_builder, &terms)
+                } else {
+                    vec![TermIndices {
+                        term: 0,
+                        indices: matched_item.indices.clone(),
+                    }]
+                };
+                println_json!(text, indices);
+            }
+        } else if let Some(tabstop) = self.tabstop {
+            let ranked = self.rank(
+                self.items_source(cache)?
+                    .matched_items(self.build_matcher(matcher_builder))?,
+            )?;
+
+            for matched_item in ranked.iter().take(number.unwrap_or(usize::MAX)) {
+                let text = matched_item.display_text();
+                let indices = &matched_item.indices;
+                let display_columns = printer::display_column_ranges(&text, indices, tabstop);
+                println_json!(text, indices, display_columns);
+            }
+        } else if self.is_sync() {
+            // On Ctrl-C, stop reading the source and print the results of the items read so far.
+            catch_interrupt();
+
+            let ranked = self.rank(
+                self.items_source(cache)?
+                    .matched_items_until(self.build_matcher(matcher_builder), &INTERRUPTED)?,
+            )?;
 
-            printer::print_sync_filter_results(ranked, number, winwidth.unwrap_or(100), icon);
-        } else if self.par_run {
+            printer::print_sync_filter_results(
+                ranked,
+                number,
+                printer::normalize_winwidth(winwidth),
+                icon,
+                self.show_score,
+            );
+
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                std::io::stdout().flush()?;
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+        } else if self.archive_kind().is_none() && self.is_par_run(cache) {
             filter::par_dyn_run(
                 &self.query,
-                FilterContext::new(icon, number, winwidth, matcher_builder),
-                self.generate_par_source(),
+                FilterContext::new(icon, number, winwidth, matcher_builder)
+                    .batch_size(self.batch_size)
+                    .show_score(self.show_score),
+                self.generate_par_source(cache),
             )?;
         } else {
-            filter::dyn_run::<std::iter::Empty<_>>(
+            filter::dyn_run(
                 &self.query,
-                FilterContext::new(icon, number, winwidth, matcher_builder),
-                self.generate_source(),
+                FilterContext::new(icon, number, winwidth, matcher_builder)
+                    .show_score(self.show_score),
+                self.items_source(cache)?,
             )?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_from_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let original = dir.join("original");
+        let cache = dir.join("cached");
+        let content = "src/lib.rs\nsrc/main.rs\ncrates/filter/src/lib.rs\nREADME.md\n";
+        std::fs::write(&original, content).unwrap();
+        std::fs::write(&cache, content).unwrap();
+
+        let filter_results = |source: Source<std::iter::Empty<_>>| {
+            let matcher = MatcherBuilder::default().build("lib".into());
+            source
+                .matched_items(matcher)
+                .unwrap()
+                .par_sort()
+                .inner()
+                .into_iter()
+                .map(|matched_item| {
+                    (
+                        matched_item.display_text().to_string(),
+                        matched_item.score,
+                        matched_item.indices,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let shell_cmd = ShellCommand::new("cat original".into(), dir.clone());
+        let digest = Digest::new(shell_cmd.clone(), 4, cache.clone());
+        let mut cache_info: CacheInfo =
+            serde_json::from_value(serde_json::json!({ "digests": [digest] })).unwrap();
+
+        // A cache file recorded in the digests is accepted with or without the command.
+        let found = find_cache_digest(&mut cache_info, &cache, None).unwrap();
+        assert_eq!(found.cached_path, cache);
+        let found = find_cache_digest(&mut cache_info, &cache, Some(&shell_cmd)).unwrap();
+        assert_eq!(found.cached_path, cache);
+
+        // The cache of another command, a file not recorded in the digests or an incomplete
+        // cache falls back to the normal input.
+        let other_cmd = ShellCommand::new("cat other".into(), dir.clone());
+        assert!(find_cache_digest(&mut cache_info, &cache, Some(&other_cmd)).is_none());
+        assert!(find_cache_digest(&mut cache_info, &original, None).is_none());
+        assert!(!is_valid_cache(&original, &digest));
+        assert!(!is_valid_cache(&dir.join("nonexistent_cache"), &digest));
+        let truncated = Digest::new(shell_cmd, 3, cache.clone());
+        assert!(!is_valid_cache(&cache, &truncated));
+
+        let from_original =
+            Filter::parse_from(["filter", "lib", "--input", original.to_str().unwrap()]);
+        let from_unrecorded_cache = Filter::parse_from([
+            "filter",
+            "lib",
+            "--resume-from-cache",
+            original.to_str().unwrap(),
+        ]);
+        assert!(from_unrecorded_cache.usable_cache().is_none());
+
+        // Filtering the cache yields the same results as filtering the original.
+        let from_cache: Source<std::iter::Empty<_>> = found.cached_path.into();
+        assert_eq!(
+            filter_results(from_original.generate_source(None)),
+            filter_results(from_cache)
+        );
+    }
+
+    #[test]
+    fn test_matched_prefix_len() {
+        let items = ["src/lib.rs", "src/main.rs", "README.md"]
+            .into_iter()
+            .map(|item| Arc::new(item) as Arc<dyn ClapItem>)
+            .collect::<Vec<_>>();
+        let matcher_builder = MatcherBuilder::default();
+
+        assert_eq!(matched_prefix_len(&items, &matcher_builder, "mainxyz"), 4);
+        assert_eq!(matched_prefix_len(&items, &matcher_builder, "main"), 4);
+        assert_eq!(matched_prefix_len(&items, &matcher_builder, "zzz"), 0);
+        assert_eq!(
+            matched_prefix_len(&items, &matcher_builder, "lib\u{e9}\u{e9}"),
+            3
+        );
+    }
+
+    #[test]
+    fn test_matched_query_len() {
+        let matched_len_of = |text: &'static str, query: &str, fuzziness: usize| {
+            let matched_item = MatcherBuilder::default()
+                .fuzziness(fuzziness)
+                .build(query.into())
+                .match_item(Arc::new(text))
+                .unwrap();
+            matched_query_len(query, &matched_item.display_text(), &matched_item.indices)
+        };
+        let matched_len =
+            |query: &str, fuzziness: usize| matched_len_of("src/main.rs", query, fuzziness);
+
+        assert_eq!(query_terms_len("main"), 4);
+        assert_eq!(matched_len("main", 0), 4);
+        assert_eq!(matched_len("srcmain", 0), 7);
+        // Exact and inverse terms.
+        assert_eq!(query_terms_len("'main !lib"), 4);
+        assert_eq!(matched_len("'main !lib", 0), 4);
+        // Partial match tolerating a typo.
+        assert_eq!(matched_len("maxn", 1), 3);
+        // The indices are char positions in the non-ASCII text.
+        assert_eq!(matched_len_of("ソース/main.rs", "main", 0), 4);
+        assert_eq!(matched_len_of("ソース/main.rs", "スmain", 0), 5);
+    }
+
+    #[test]
+    fn test_group_indices_by_term() {
+        let group_indices = |args: &[&str]| {
+            let filter = Filter::parse_from(["filter"].iter().chain(args));
+            let matcher_builder = MatcherBuilder::default();
+            let matched_item = filter
+                .build_matcher(matcher_builder.clone())
+                .match_item(Arc::new("src/main.rs"))
+                .unwrap();
+            group_indices_by_term(&matched_item, &matcher_builder, &filter.query_terms())
+        };
+
+        assert_eq!(
+            group_indices(&["src main !lib"]),
+            vec![
+                TermIndices {
+                    term: 0,
+                    indices: vec![0, 1, 2]
+                },
+                TermIndices {
+                    term: 1,
+                    indices: vec![4, 5, 6, 7]
+                },
+            ]
+        );
+
+        // Only the terms of the best alternative are highlighted.
+        assert_eq!(
+            group_indices(&["lib | src 'main", "--or"]),
+            vec![
+                TermIndices {
+                    term: 1,
+                    indices: vec![0, 1, 2]
+                },
+                TermIndices {
+                    term: 2,
+                    indices: vec![4, 5, 6, 7]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_items() {
+        let items = [
+            "README.md",
+            "./src/lib.rs",
+            "readme.md",
+            "src/lib.rs",
+            "./README.MD",
+        ]
+        .into_iter()
+        .map(|item| Arc::new(item) as Arc<dyn ClapItem>)
+        .collect::<Vec<_>>();
+        let texts = |items: Vec<Arc<dyn ClapItem>>| {
+            items
+                .iter()
+                .map(|item| item.raw_text().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            texts(dedup_items(items.clone(), false)),
+            vec!["README.md", "./src/lib.rs", "readme.md", "./README.MD"]
+        );
+        assert_eq!(
+            texts(dedup_items(items, true)),
+            vec!["README.md", "./src/lib.rs"]
+        );
+        assert_eq!(dedup_key(".//./src/lib.rs", false), "src/lib.rs");
+    }
+
+    #[test]
+    fn test_sample_items() {
+        let sample = |size: usize, seed: u64| sample_items(0..1000, size, seed);
+
+        assert_eq!(sample(10, 42).len(), 10);
+        assert_eq!(sample(10, 42), sample(10, 42));
+        assert_ne!(sample(10, 42), sample(10, 7));
+
+        let mut distinct = sample(10, 42);
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 10);
+
+        // All the items are kept in random order if the source is smaller.
+        let mut all = sample_items(0..5, 10, 42);
+        assert_eq!(all.len(), 5);
+        all.sort_unstable();
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_null_delimited_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        std::fs::write(&path, "src/new\nline.rs\0src/lib.rs\0\0README.md\0").unwrap();
+
+        assert_eq!(
+            split_null_delimited(BufReader::new(std::fs::File::open(&path).unwrap()))
+                .collect::<Vec<_>>(),
+            vec!["src/new\nline.rs", "src/lib.rs", "README.md"]
+        );
+
+        let filter = Filter::parse_from([
+            "filter",
+            "line",
+            "--null",
+            "--input",
+            path.to_str().unwrap(),
+        ]);
+        let items = filter.read_null_delimited_items(None).unwrap();
+        assert_eq!(items.len(), 3);
+
+        let ranked = Source::List(items.into_iter())
+            .matched_items(MatcherBuilder::default().build("line".into()))
+            .unwrap()
+            .inner();
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].display_text(), "src/new\nline.rs");
+    }
+
+    #[test]
+    fn test_json_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        std::fs::write(
+            &path,
+            [
+                r#"{"text": "Git commit", "keywords": ["save", "checkpoint"]}"#,
+                r#"{"text": "Git push"}"#,
+                "Save all buffers",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let filter = Filter::parse_from([
+            "filter",
+            "checkpt",
+            "--json-input",
+            "--input",
+            path.to_str().unwrap(),
+        ]);
+        let items = filter.read_json_items(None).unwrap();
+        assert_eq!(items.len(), 3);
+
+        let ranked = Source::List(items.into_iter())
+            .matched_items(MatcherBuilder::default().build("checkpt".into()))
+            .unwrap()
+            .inner();
+        assert_eq!(ranked.len(), 1);
+        // Matched via the hidden keyword.
+        assert_eq!(ranked[0].display_text(), "Git commit");
+        assert!(ranked[0].indices.is_empty());
+    }
+
+    #[test]
+    fn test_save_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results");
+        let items = [
+            "src/lib.rs",
+            "src/main.rs",
+            "crates/filter/src/lib.rs",
+            "README.md",
+        ]
+        .into_iter()
+        .map(|item| Arc::new(SourceItem::from(item.to_string())) as Arc<dyn ClapItem>)
+        .collect::<Vec<_>>();
+
+        let filter =
+            Filter::parse_from(["filter", "lib", "--save-results", path.to_str().unwrap()]);
+        assert!(filter.is_sync());
+        let ranked = filter
+            .rank(
+                Source::List(items.into_iter())
+                    .matched_items(MatcherBuilder::default().build("lib".into()))
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(ranked.len(), 2);
+
+        let loaded = load_results(&path).unwrap();
+        let display = |matched_items: Vec<MatchedItem>| {
+            let display_lines = printer::decorate_lines(matched_items, 40, Default::default());
+            (
+                display_lines.lines,
+                display_lines.indices,
+                display_lines.ids,
+            )
+        };
+        assert_eq!(
+            loaded
+                .iter()
+                .map(|matched_item| matched_item.score)
+                .collect::<Vec<_>>(),
+            ranked
+                .iter()
+                .map(|matched_item| matched_item.score)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(display(loaded), display(ranked));
+    }
+
+    #[test]
+    fn test_first_match_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        std::fs::write(&path, "b/lib.rs\na/lib.rs\nsrc/main.rs\nREADME.md\n").unwrap();
+
+        let first_match = |query: &str| {
+            let filter = Filter::parse_from([
+                "filter",
+                query,
+                "--first-match-only",
+                "--input",
+                path.to_str().unwrap(),
+            ]);
+            assert!(filter.is_sync());
+            filter
+                .first_match(MatcherBuilder::default(), None)
+                .unwrap()
+                .map(|matched_item| matched_item.display_text().to_string())
+        };
+
+        assert_eq!(first_match("main"), Some("src/main.rs".into()));
+        assert_eq!(first_match("readme"), Some("README.md".into()));
+        // The tie is broken by the order of source.
+        assert_eq!(first_match("lib"), Some("b/lib.rs".into()));
+        assert_eq!(first_match("xyz"), None);
+    }
+
+    #[test]
+    fn test_par_mode() {
+        let filter = |args: &[&str]| Filter::parse_from(["filter", "lib"].iter().chain(args));
+        assert_eq!(filter(&[]).par, ParMode::Auto);
+        assert_eq!(filter(&["--par", "off"]).par, ParMode::Off);
+        assert!(filter(&["--par-run"]).is_par_run(None));
+        assert!(!filter(&["--par", "off"]).is_par_run(None));
+
+        let large = Some(PAR_RUN_THRESHOLD);
+        let small = Some(PAR_RUN_THRESHOLD - 1);
+        assert!(ParMode::Auto.is_parallel(large, 8));
+        assert!(!ParMode::Auto.is_parallel(large, 1));
+        assert!(!ParMode::Auto.is_parallel(small, 8));
+        assert!(!ParMode::Auto.is_parallel(None, 8));
+        assert!(ParMode::On.is_parallel(small, 1));
+        assert!(!ParMode::Off.is_parallel(large, 8));
+    }
+
+    #[test]
+    fn test_prompt_echo() {
+        let filter = Filter::parse_from([
+            "filter",
+            "src 'lib !test",
+            "--algo",
+            "skim",
+            "--match-scope",
+            "filename",
+            "--bonus",
+            "acronym",
+            "--fuzziness",
+            "1",
+            "--case-folding",
+            "full",
+            "--sync",
+            "--prompt-echo",
+        ]);
+        assert!(filter.prompt_echo);
+
+        let config = filter.resolved_config(CaseMatching::Respect, None);
+        assert_eq!(config["event"], "config");
+        assert_eq!(config["algo"], "Skim");
+        assert_eq!(config["match_scope"], "FileName");
+        assert_eq!(config["bonuses"], json!(["Acronym"]));
+        assert_eq!(config["case_matching"], "Respect");
+        assert_eq!(config["case_folding"], "Full");
+        assert_eq!(config["fuzziness"], 1);
+        assert_eq!(config["mode"], "sync");
+        assert_eq!(config["query_kind"], "extended");
+        assert_eq!(
+            config["parsed_query"],
+            json!({
+                "fuzzy_terms": [{ "type": "Fuzzy", "word": "src" }],
+                "exact_terms": [{ "type": "Exact", "word": "lib" }],
+                "inverse_terms": [{ "type": "InverseExact", "word": "test" }],
+            })
+        );
+
+        assert_eq!(config["score_expr"], serde_json::Value::Null);
+
+        let config = Filter::parse_from(["filter", "foo", "--score-expr", "base_score - len"])
+            .resolved_config(CaseMatching::Smart, None);
+        assert_eq!(config["score_expr"], "base_score - len");
+        assert!(Filter::try_parse_from(["filter", "foo", "--score-expr", "base_score +"]).is_err());
+
+        let config = Filter::parse_from(["filter", "foo | bar", "--or", "--par", "off"])
+            .resolved_config(CaseMatching::Smart, None);
+        assert_eq!(config["query_kind"], "or");
+        assert_eq!(config["parsed_query"].as_array().unwrap().len(), 2);
+        assert_eq!(config["algo"], "Fzy");
+        assert_eq!(config["mode"], "sync");
+
+        let config = Filter::parse_from(["filter", "foo", "--par", "off"])
+            .resolved_config(CaseMatching::Smart, None);
+        assert_eq!(config["mode"], "dyn");
+    }
+
+    #[test]
+    fn test_conflicting_modes() {
+        let parse = |args: &[&str]| Filter::try_parse_from(["filter", "foo"].iter().chain(args));
+
+        for args in [
+            &["--explain", "--count-matches"][..],
+            &["--null", "--json-input"],
+            &["--first-match-only", "--group-indices"],
+            &["--dedup", "--tabstop", "4"],
+            &["--or", "--glob-query"],
+            &["--show-partial", "--explain"],
+            &["--show-score", "--explain"],
+            &["--load-results", "/tmp/results", "--cmd", "ls"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} should be rejected");
+        }
+
+        for args in [
+            &["--count-matches", "--show-partial"][..],
+            &["--dedup", "--prefer-shorter", "--or"],
+            &["--explain", "--save-results", "/tmp/results"],
+        ] {
+            assert!(parse(args).is_ok(), "{args:?} should be accepted");
+        }
+    }
+}
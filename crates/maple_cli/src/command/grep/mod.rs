@@ -2,9 +2,12 @@ mod forerunner;
 
 pub use self::forerunner::RipGrepForerunner;
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use clap::Parser;
@@ -14,13 +17,16 @@ use subprocess::Exec;
 
 use filter::Source;
 use icon::Icon;
-use matcher::MatchScope;
+use matcher::{ClapItem, MatchScope, Matcher, MatcherBuilder};
+use types::MatchedItem;
 
 use crate::app::Params;
 use crate::cache::Digest;
+use crate::command::files::BinaryMode;
 use crate::process::shell_command;
 use crate::process::{CacheableCommand, ShellCommand};
-use crate::tools::ripgrep::Match;
+use crate::tools::git::ChangedFiles;
+use crate::tools::ripgrep::{ColumnEncoding, Match};
 
 const RG_ARGS: &[&str] = &[
     "rg",
@@ -40,7 +46,7 @@ const RG_EXEC_CMD: &str = "rg --column --line-number --no-heading --color=never
 #[derive(Parser, Debug, Clone)]
 pub struct Grep {
     /// Specify the query string for GREP_CMD.
-    #[clap(index = 1, long)]
+    #[clap(index = 1)]
     grep_query: String,
 
     /// Specify the grep command to run, normally rg will be used.
@@ -77,6 +83,155 @@ pub struct Grep {
 
     #[clap(long)]
     par_run: bool,
+
+    /// Unit of the column number in the output of `--sync`, `byte` or `utf16`.
+    #[clap(long, parse(from_str), default_value = "byte")]
+    column_encoding: ColumnEncoding,
+
+    /// Filter the grep lines and group them by file, each group led by a file path header.
+    ///
+    /// Positions of the headers, which are not selectable, are emitted in the field `headers`.
+    #[clap(long)]
+    group_by_file: bool,
+
+    /// Keep at most NUM best matched lines of each file, ignored with `--sync`.
+    #[clap(long, name = "MAX_PER_FILE")]
+    max_per_file: Option<usize>,
+
+    /// Rank the lines whose file path matches the query before the lines of which only the
+    /// content matches, ignored with `--sync`.
+    ///
+    /// Each tier is sorted by score on its own.
+    #[clap(long)]
+    path_first: bool,
+
+    /// How the binary files are handled, `skip`, `match` or `text`, same as the options of rg.
+    ///
+    /// The cache is only used for the default `skip`.
+    #[clap(long, parse(from_str), default_value = "skip")]
+    binary: BinaryMode,
+
+    /// Only match the lines added or modified in the git working tree, ignored with `--sync`.
+    ///
+    /// All the lines of a file are matched if it's not tracked by git.
+    #[clap(long)]
+    only_changed: bool,
+}
+
+/// Keeps the first `max_per_file` grep lines of each file in the sorted `ranked`.
+fn limit_per_file(ranked: Vec<MatchedItem>, max_per_file: usize) -> Vec<MatchedItem> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    ranked
+        .into_iter()
+        .filter(|matched_item| {
+            let line = matched_item.item.raw_text();
+            match pattern::parse_grep_item(line) {
+                Some((end_of_path, _)) => {
+                    let count = counts.entry(line[..end_of_path].to_string()).or_default();
+                    *count += 1;
+                    *count <= max_per_file
+                }
+                None => true,
+            }
+        })
+        .collect()
+}
+
+/// Returns the matched grep lines in two tiers, the lines whose path matches `path_matcher`
+/// go before the lines of which only the content matches `content_matcher`.
+fn rank_path_first(
+    items: Vec<Arc<dyn ClapItem>>,
+    path_matcher: &Matcher,
+    content_matcher: &Matcher,
+) -> Vec<MatchedItem> {
+    let mut tiered: Vec<(bool, MatchedItem)> = items
+        .into_par_iter()
+        .filter_map(|item| {
+            let line = item.raw_text();
+            let path_match = pattern::parse_grep_item(line).and_then(|(end_of_path, _)| {
+                path_matcher.match_item(Arc::new(line[..end_of_path].to_string()))
+            });
+            let content_match = content_matcher.match_item(item.clone());
+
+            match (path_match, content_match) {
+                (Some(path_match), content_match) => {
+                    // The path starts at the beginning of line, the indices are as is.
+                    let mut indices = path_match.indices;
+                    if let Some(content_match) = content_match {
+                        indices.extend(content_match.indices);
+                    }
+                    Some((true, MatchedItem::new(item, path_match.score, indices)))
+                }
+                (None, Some(content_match)) => Some((false, content_match)),
+                (None, None) => None,
+            }
+        })
+        .collect();
+
+    tiered.par_sort_by(|(path_matched1, v1), (path_matched2, v2)| {
+        path_matched2
+            .cmp(path_matched1)
+            .then_with(|| v2.score.cmp(&v1.score))
+    });
+
+    tiered
+        .into_iter()
+        .map(|(_, matched_item)| matched_item)
+        .collect()
+}
+
+/// Filtered grep lines grouped by file.
+#[derive(Debug, Default)]
+struct GroupedLines {
+    /// File path headers and the grep lines without the file path.
+    lines: Vec<String>,
+    indices: Vec<Vec<usize>>,
+    /// Positions of headers in `lines`.
+    headers: Vec<usize>,
+}
+
+/// Grep line without the file path and its match indices.
+type GroupLine = (String, Vec<usize>);
+
+/// Groups the sorted grep lines by file.
+///
+/// The groups are ordered by their best line, the lines in a group are sorted by score.
+fn group_by_file(ranked: Vec<MatchedItem>) -> GroupedLines {
+    let mut groups: Vec<(String, Vec<GroupLine>)> = Vec::new();
+
+    for matched_item in ranked {
+        let line = matched_item.display_text();
+        let end_of_path = match pattern::parse_grep_item(&line) {
+            Some((end_of_path, _)) => end_of_path,
+            None => continue,
+        };
+        let path = &line[..end_of_path];
+        let offset = path.chars().count() + 1;
+        let rest = line[end_of_path + 1..].to_string();
+        let indices = matched_item
+            .indices
+            .iter()
+            .filter_map(|idx| idx.checked_sub(offset))
+            .collect();
+
+        match groups.iter_mut().find(|(p, _)| p == path) {
+            Some((_, lines)) => lines.push((rest, indices)),
+            None => groups.push((path.to_string(), vec![(rest, indices)])),
+        }
+    }
+
+    let mut grouped_lines = GroupedLines::default();
+    for (path, lines) in groups {
+        grouped_lines.headers.push(grouped_lines.lines.len());
+        grouped_lines.lines.push(path);
+        grouped_lines.indices.push(Vec::new());
+        for (line, indices) in lines {
+            grouped_lines.lines.push(line);
+            grouped_lines.indices.push(indices);
+        }
+    }
+    grouped_lines
 }
 
 impl Grep {
@@ -91,8 +246,12 @@ impl Grep {
             return Ok(());
         }
 
-        if self.sync {
+        if self.group_by_file {
+            self.group_run(params)?;
+        } else if self.sync {
             self.sync_run(params)?;
+        } else if self.max_per_file.is_some() || self.path_first || self.only_changed {
+            self.limited_run(params)?;
         } else if self.par_run {
             self.par_run(params)?;
         } else {
@@ -124,6 +283,11 @@ impl Grep {
             grep_cmd.push_str(g);
         }
 
+        if let Some(flag) = self.binary.rg_flag() {
+            grep_cmd.push(' ');
+            grep_cmd.push_str(flag);
+        }
+
         // Force using json format.
         grep_cmd.push_str(" --json ");
         grep_cmd.push_str(&self.grep_query);
@@ -145,6 +309,7 @@ impl Grep {
                 .execute()?;
 
         let enable_icon = !matches!(icon, Icon::Null);
+        let column_encoding = self.column_encoding;
 
         let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = execute_info
             .lines
@@ -152,7 +317,7 @@ impl Grep {
             .filter_map(|s| {
                 Match::try_from(s.as_str())
                     .ok()
-                    .map(|mat| mat.build_grep_line(enable_icon))
+                    .map(|mat| mat.build_grep_line_with_encoding(enable_icon, column_encoding))
             })
             .unzip();
 
@@ -175,41 +340,128 @@ impl Grep {
         Ok(())
     }
 
-    /// Runs grep using the dyn filter.
-    ///
-    /// Firstly try using the cache.
-    fn dyn_run(&self, params: Params) -> Result<()> {
-        let no_cache = params.no_cache;
-
-        let do_dyn_filter = |source: Source<std::iter::Empty<_>>| {
-            filter::dyn_run(
-                &self.grep_query,
-                params
-                    .into_filter_context()
-                    .match_scope(MatchScope::GrepLine),
-                source,
-            )
-        };
+    /// Returns the rg command of `--binary`.
+    fn rg_exec_cmd(&self) -> Cow<'static, str> {
+        match self.binary.rg_flag() {
+            Some(flag) => RG_EXEC_CMD
+                .replacen("rg ", &format!("rg {flag} "), 1)
+                .into(),
+            None => RG_EXEC_CMD.into(),
+        }
+    }
 
-        let source: Source<std::iter::Empty<_>> = if let Some(ref tempfile) = self.input {
+    /// Returns the source of grep lines, firstly try using the cache.
+    fn source(&self, no_cache: bool) -> Source<std::iter::Empty<Arc<dyn ClapItem>>> {
+        let no_cache = no_cache || self.binary != BinaryMode::Skip;
+        if let Some(ref tempfile) = self.input {
             Source::File(tempfile.clone())
         } else if let Some(ref dir) = self.cmd_dir {
             if !no_cache {
                 let shell_cmd = rg_shell_command(dir);
                 if let Some(digest) = shell_cmd.cache_digest() {
-                    return do_dyn_filter(Source::File(digest.cached_path));
+                    return Source::File(digest.cached_path);
                 }
             }
-            Exec::shell(RG_EXEC_CMD).cwd(dir).into()
+            Exec::shell(self.rg_exec_cmd().as_ref()).cwd(dir).into()
+        } else {
+            Exec::shell(self.rg_exec_cmd().as_ref()).into()
+        }
+    }
+
+    /// Runs grep using the dyn filter.
+    fn dyn_run(&self, params: Params) -> Result<()> {
+        let source = self.source(params.no_cache);
+        filter::dyn_run(
+            &self.grep_query,
+            params
+                .into_filter_context()
+                .match_scope(MatchScope::GrepLine),
+            source,
+        )
+    }
+
+    /// Returns all the grep lines matching the query, the best first.
+    ///
+    /// Only the best `--max-per-file` lines of each file are kept if specified.
+    fn ranked_items(&self, params: &Params) -> Result<Vec<MatchedItem>> {
+        let matcher_builder = MatcherBuilder::default().case_matching(params.case_matching);
+        let matcher = matcher_builder
+            .clone()
+            .match_scope(MatchScope::GrepLine)
+            .build(self.grep_query.as_str().into());
+
+        let items = self.source(params.no_cache).items()?;
+        let items: Vec<Arc<dyn ClapItem>> = if self.only_changed {
+            let dir = match self.cmd_dir {
+                Some(ref dir) => dir.clone(),
+                None => std::env::current_dir()?,
+            };
+            let mut changed_files = ChangedFiles::new(dir);
+            items
+                .filter(|item| {
+                    pattern::extract_grep_position(item.raw_text()).is_none_or(
+                        |(path, line_number, _, _)| changed_files.is_changed(&path, line_number),
+                    )
+                })
+                .collect()
+        } else {
+            items.collect()
+        };
+
+        let ranked = if self.path_first {
+            let path_matcher = matcher_builder.build(self.grep_query.as_str().into());
+            rank_path_first(items, &path_matcher, &matcher)
         } else {
-            Exec::shell(RG_EXEC_CMD).into()
+            Source::List(items.into_iter())
+                .matched_items(matcher)?
+                .par_sort()
+                .inner()
         };
 
-        do_dyn_filter(source)
+        Ok(match self.max_per_file {
+            Some(max_per_file) => limit_per_file(ranked, max_per_file),
+            None => ranked,
+        })
+    }
+
+    /// Filters the grep lines and prints the best ones of each file.
+    fn limited_run(&self, params: Params) -> Result<()> {
+        let ranked = self.ranked_items(&params)?;
+
+        printer::print_sync_filter_results(
+            ranked,
+            params.number,
+            printer::normalize_winwidth(params.winwidth),
+            params.icon,
+            false,
+        );
+
+        Ok(())
+    }
+
+    /// Filters the grep lines and prints them grouped by file.
+    fn group_run(&self, params: Params) -> Result<()> {
+        let ranked = self.ranked_items(&params)?;
+
+        let total = ranked.len();
+        let GroupedLines {
+            lines,
+            indices,
+            headers,
+        } = group_by_file(
+            ranked
+                .into_iter()
+                .take(params.number.unwrap_or(usize::MAX))
+                .collect(),
+        );
+
+        utility::println_json!(total, lines, indices, headers);
+
+        Ok(())
     }
 
     fn par_run(&self, params: Params) -> Result<()> {
-        let no_cache = params.no_cache;
+        let no_cache = params.no_cache || self.binary != BinaryMode::Skip;
 
         let par_dyn_dun = |par_source: ParSource| {
             filter::par_dyn_run(
@@ -230,9 +482,9 @@ impl Grep {
                     return par_dyn_dun(ParSource::File(digest.cached_path));
                 }
             }
-            ParSource::Exec(Box::new(Exec::shell(RG_EXEC_CMD).cwd(dir)))
+            ParSource::Exec(Box::new(Exec::shell(self.rg_exec_cmd().as_ref()).cwd(dir)))
         } else {
-            ParSource::Exec(Box::new(Exec::shell(RG_EXEC_CMD)))
+            ParSource::Exec(Box::new(Exec::shell(self.rg_exec_cmd().as_ref())))
         };
 
         // TODO: Improve the responsiveness of ripgrep as it can emit the items after some time.
@@ -264,6 +516,8 @@ impl RgTokioCommand {
 
         let std_cmd = rg_command(&self.shell_cmd.cwd);
         let mut tokio_cmd = tokio::process::Command::from(std_cmd);
+        // Stop rg too in case of the job is cancelled.
+        tokio_cmd.kill_on_drop(true);
         crate::process::tokio::write_stdout_to_file(&mut tokio_cmd, &cache_file).await?;
 
         let digest = crate::cache::store_cache_digest(self.shell_cmd.clone(), cache_file)?;
@@ -321,6 +575,160 @@ mod tests {
         (total, cache_path)
     }
 
+    #[test]
+    fn test_limit_per_file() {
+        let matcher = MatcherBuilder::default()
+            .match_scope(MatchScope::GrepLine)
+            .build("error".into());
+        let lines = (1..=100)
+            .map(|lnum| format!("src/errors.rs:{lnum}:1:    Error{lnum}(String),"))
+            .chain([
+                "src/lib.rs:1:1:use anyhow::Error;".to_string(),
+                "src/main.rs:3:5:    return Err(error);".to_string(),
+            ]);
+        let ranked = Source::List(lines.map(|line| Arc::new(line) as Arc<dyn ClapItem>))
+            .matched_items(matcher)
+            .unwrap()
+            .par_sort()
+            .inner();
+        assert_eq!(ranked.len(), 102);
+
+        let limited = limit_per_file(ranked.clone(), 3);
+        let lines_of = |path: &str| {
+            limited
+                .iter()
+                .filter(|matched_item| matched_item.item.raw_text().starts_with(path))
+                .count()
+        };
+        assert_eq!(lines_of("src/errors.rs:"), 3);
+        assert_eq!(lines_of("src/lib.rs:"), 1);
+        assert_eq!(lines_of("src/main.rs:"), 1);
+
+        // The best lines are kept in order.
+        let best_of_errors = ranked
+            .iter()
+            .filter(|matched_item| matched_item.item.raw_text().starts_with("src/errors.rs:"))
+            .take(3)
+            .map(|matched_item| matched_item.item.raw_text())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            limited
+                .iter()
+                .filter(|matched_item| matched_item.item.raw_text().starts_with("src/errors.rs:"))
+                .map(|matched_item| matched_item.item.raw_text())
+                .collect::<Vec<_>>(),
+            best_of_errors
+        );
+    }
+
+    #[test]
+    fn test_rank_path_first() {
+        let matcher_builder = MatcherBuilder::default();
+        let content_matcher = matcher_builder
+            .clone()
+            .match_scope(MatchScope::GrepLine)
+            .build("config".into());
+        let path_matcher = matcher_builder.build("config".into());
+
+        let items = [
+            "src/main.rs:3:5:    let config = Config::load();",
+            "src/config.rs:1:1:use std::path::PathBuf;",
+            "src/lib.rs:9:1:pub mod config;",
+            "README.md:1:1:# Project",
+            "src/config/mod.rs:5:5:    let config = parse();",
+        ]
+        .into_iter()
+        .map(|line| Arc::new(line) as Arc<dyn ClapItem>)
+        .collect();
+
+        let ranked = rank_path_first(items, &path_matcher, &content_matcher)
+            .into_iter()
+            .map(|matched_item| matched_item.item.raw_text().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(ranked.len(), 4);
+        // The path matches come first even if the content doesn't match.
+        let (path_matches, content_matches) = ranked.split_at(2);
+        assert!(path_matches
+            .iter()
+            .all(|line| line.starts_with("src/config")));
+        assert!(content_matches
+            .iter()
+            .all(|line| line.starts_with("src/main.rs") || line.starts_with("src/lib.rs")));
+    }
+
+    #[test]
+    fn test_rg_exec_cmd_binary() {
+        let rg_exec_cmd = |args: &[&str]| {
+            Grep::parse_from(["grep", "query"].iter().chain(args))
+                .rg_exec_cmd()
+                .into_owned()
+        };
+
+        assert_eq!(rg_exec_cmd(&[]), RG_EXEC_CMD);
+        assert_eq!(
+            rg_exec_cmd(&["--binary", "text"]),
+            "rg --text --column --line-number --no-heading --color=never --smart-case '' ."
+        );
+    }
+
+    #[test]
+    fn test_group_by_file() {
+        let matcher = MatcherBuilder::default()
+            .match_scope(MatchScope::GrepLine)
+            .build("error".into());
+        let ranked = Source::List(
+            [
+                "src/lib.rs:1:1:use anyhow::Error;",
+                "src/main.rs:3:5:    return Err(error);",
+                "src/lib.rs:9:5:    error!(\"unexpected error\");",
+                "src/main.rs:8:1:fn main() {}",
+            ]
+            .into_iter()
+            .map(|line| Arc::new(line) as Arc<dyn ClapItem>),
+        )
+        .matched_items(matcher)
+        .unwrap()
+        .par_sort()
+        .inner();
+
+        let best_path = ranked[0]
+            .display_text()
+            .split(':')
+            .next()
+            .unwrap()
+            .to_string();
+
+        let GroupedLines {
+            lines,
+            indices,
+            headers,
+        } = group_by_file(ranked);
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            headers,
+            vec![0, if best_path == "src/lib.rs" { 3 } else { 2 }]
+        );
+        assert_eq!(lines[0], best_path);
+        for header in headers {
+            assert!(lines[header] == "src/lib.rs" || lines[header] == "src/main.rs");
+            // The header is not matched.
+            assert!(indices[header].is_empty());
+        }
+
+        // The indices are still pointing to the matched text in the line without the path.
+        for (line, indices) in lines.iter().zip(indices.iter()) {
+            if !indices.is_empty() {
+                let matched = indices
+                    .iter()
+                    .map(|&idx| line.chars().nth(idx).unwrap().to_ascii_lowercase())
+                    .collect::<String>();
+                assert_eq!(matched, "error");
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_create_grep_cache_async() {
         let dir = std::env::current_dir().unwrap();
@@ -3,8 +3,11 @@ pub mod cache;
 pub mod ctags;
 pub mod dumb_jump;
 pub mod exec;
+pub mod files;
 pub mod filter;
 pub mod grep;
 pub mod gtags;
 pub mod helptags;
+pub mod preview;
+pub mod providers;
 pub mod rpc;
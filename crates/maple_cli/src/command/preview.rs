@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use serde_json::json;
+
+use crate::app::Params;
+use crate::paths::AbsPathBuf;
+
+/// Print the preview of a provider line, same as what the provider renders on moving to the
+/// line in a session.
+#[derive(Parser, Debug, Clone)]
+pub struct Preview {
+    /// Id of the provider, e.g., `files`, `grep`.
+    #[clap(long)]
+    provider: String,
+
+    /// Line of the provider to preview.
+    ///
+    /// `path:lnum` is also accepted by the providers of grep lines.
+    #[clap(long)]
+    target: String,
+
+    /// Working directory of the provider, the current directory by default.
+    #[clap(long)]
+    cwd: Option<AbsPathBuf>,
+
+    /// Query used for locating the match in the preview.
+    #[clap(long)]
+    query: Option<String>,
+
+    /// Number of lines around the target line.
+    #[clap(long, default_value = "5")]
+    preview_size: u64,
+}
+
+/// Returns `target` as a grep line if it's `path:lnum`.
+fn to_grep_line(target: &str) -> String {
+    if pattern::extract_grep_position(target).is_some() {
+        return target.to_string();
+    }
+    match target.rsplit_once(':') {
+        Some((_path, lnum)) if !lnum.is_empty() && lnum.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{target}:1:")
+        }
+        _ => target.to_string(),
+    }
+}
+
+impl Preview {
+    async fn preview(&self, winwidth: Option<usize>) -> Result<serde_json::Value> {
+        let cwd = match self.cwd {
+            Some(ref cwd) => PathBuf::from(cwd.clone()),
+            None => std::env::current_dir()?,
+        };
+
+        let curline = match self.provider.as_str() {
+            "grep" | "live_grep" | "recent_grep" | "todo" => to_grep_line(&self.target),
+            _ => self.target.clone(),
+        };
+
+        let params = json!({
+            "provider_id": self.provider,
+            "cwd": cwd,
+            "no_cache": true,
+            "source_fpath": "",
+            "display_winwidth": winwidth,
+            // Only the preview size decides the number of preview lines.
+            "preview_winheight": 0,
+            "query": self.query,
+        });
+
+        crate::stdio_server::preview_on_move(curline, self.preview_size, params).await
+    }
+
+    pub async fn run(&self, params: Params) -> Result<()> {
+        crate::config::initialize_config_file(params.config_file.clone());
+
+        let result = self.preview(params.winwidth).await?;
+        println!("{}", serde_json::to_string_pretty(&result)?);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_grep_line() {
+        assert_eq!(to_grep_line("src/lib.rs:10"), "src/lib.rs:10:1:");
+        assert_eq!(to_grep_line("src/lib.rs:10:5:fn"), "src/lib.rs:10:5:fn");
+        assert_eq!(to_grep_line("src/lib.rs"), "src/lib.rs");
+        assert_eq!(to_grep_line("C:lib.rs"), "C:lib.rs");
+    }
+
+    #[tokio::test]
+    async fn test_preview_file_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let content = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(dir.join("lines.txt"), content).unwrap();
+
+        let preview = Preview::parse_from([
+            "preview",
+            "--provider",
+            "grep",
+            "--target",
+            "lines.txt:10",
+            "--cwd",
+            dir.to_str().unwrap(),
+            "--preview-size",
+            "2",
+        ]);
+        let result = preview.preview(None).await.unwrap();
+        assert_eq!(
+            result["lines"],
+            json!(["./lines.txt:10", "line 9", "line 10", "line 11", "line 12"])
+        );
+        assert_eq!(result["hi_lnum"], 2);
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Parser;
+
+use crate::stdio_server::{executable_exists, list_providers};
+
+/// List all the providers and whether their external tools are available.
+#[derive(Parser, Debug, Clone)]
+pub struct Providers {
+    /// Print the list in JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+impl Providers {
+    pub fn run(&self) -> Result<()> {
+        let providers = list_providers(executable_exists);
+
+        let stdout = std::io::stdout();
+        let mut lock = stdout.lock();
+
+        if self.json {
+            writeln!(lock, "{}", serde_json::to_string(&providers)?)?;
+            return Ok(());
+        }
+
+        for provider in providers {
+            let missing_tools = provider
+                .external_tools
+                .iter()
+                .filter(|tool| !tool.available)
+                .map(|tool| tool.name)
+                .collect::<Vec<_>>();
+            if missing_tools.is_empty() {
+                writeln!(lock, "{:<16}{}", provider.id, provider.description)?;
+            } else {
+                writeln!(
+                    lock,
+                    "{:<16}{} (unavailable, missing {})",
+                    provider.id,
+                    provider.description,
+                    missing_tools.join(", ")
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -62,6 +62,13 @@ pub struct Config {
     /// Priorities of the ignore config:
     ///   provider_ignores > provider_ignores > global_ignore
     pub provider_ignore: HashMap<String, IgnoreConfig>,
+    /// External previewer command per filetype or file extension, e.g., `rust = "bat --color=always"`.
+    ///
+    /// The file path is substituted for `{}` in the command, or appended if there is no `{}`.
+    /// The built-in file reader is used if the command is not specified or fails.
+    pub previewer: HashMap<String, String>,
+    /// Session configuration.
+    pub session: SessionConfig,
 }
 
 impl Config {
@@ -74,6 +81,30 @@ impl Config {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Terminate the session after being inactive for this many seconds, 0 (the default) to
+    /// never terminate the inactive sessions.
+    ///
+    /// The session is normally terminated by the client, this cleans up the sessions abandoned
+    /// by a misbehaving client.
+    pub idle_timeout: u64,
+    /// Maximum number of the match results cached for the repeated searches on the same source,
+    /// 0 to disable the cache.
+    pub score_cache_capacity: usize,
+}
+
+impl SessionConfig {
+    pub fn idle_timeout(&self) -> Option<std::time::Duration> {
+        if self.idle_timeout == 0 {
+            None
+        } else {
+            Some(std::time::Duration::from_secs(self.idle_timeout))
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
 pub struct IgnoreConfig {
@@ -102,8 +133,20 @@ mod tests {
 
           [provider-ignore.dumb_jump]
           comment-line = true
+
+          [previewer]
+          rust = "bat --color=always --style=plain"
+
+          [session]
+          idle-timeout = 600
 "#;
         let user_config: Config = toml::from_str(toml_content).unwrap();
+        assert_eq!(
+            user_config.session.idle_timeout(),
+            Some(std::time::Duration::from_secs(600))
+        );
+        // The inactive sessions are never terminated by default.
+        assert_eq!(Config::default().session.idle_timeout(), None);
         println!("{user_config:?}");
         println!("{}", toml::to_string(&user_config).unwrap());
     }
@@ -1,4 +1,5 @@
 mod app;
+mod archive;
 mod cache;
 mod config;
 mod datastore;
@@ -7,6 +8,7 @@ mod paths;
 mod previewer;
 mod process;
 mod recent_files;
+mod socket;
 mod stdio_server;
 mod utils;
 
@@ -0,0 +1,298 @@
+//! Preview the file using an external previewer chosen by the filetype, e.g., `bat`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Serialize, Serializer};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::process::tokio::shell_command;
+
+/// Placeholder of the file path in the previewer command.
+const PATH_PLACEHOLDER: &str = "{}";
+
+/// The previewer is killed if it does not produce the preview in time.
+const PREVIEWER_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Foreground color of the text set by the SGR escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 256 colors, serialized as a number.
+    Indexed(u8),
+    /// True color, serialized as `#rrggbb`.
+    Rgb(u8, u8, u8),
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Indexed(idx) => serializer.serialize_u8(*idx),
+            Self::Rgb(r, g, b) => serializer.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}")),
+        }
+    }
+}
+
+/// Highlight of the byte range `[start, end)` in the line stripped of the escape sequences.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnsiSpan {
+    pub start: usize,
+    pub end: usize,
+    pub fg: Option<Color>,
+    pub bold: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    fg: Option<Color>,
+    bold: bool,
+}
+
+impl Style {
+    fn apply_sgr(&mut self, params: &str) {
+        let mut codes = params
+            .split(';')
+            .map(|code| code.parse::<u8>().unwrap_or(0));
+        // `ESC[m` is the same as `ESC[0m`.
+        if params.is_empty() {
+            *self = Self::default();
+        }
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                30..=37 => self.fg = Some(Color::Indexed(code - 30)),
+                90..=97 => self.fg = Some(Color::Indexed(code - 90 + 8)),
+                39 => self.fg = None,
+                38 => match codes.next() {
+                    Some(5) => self.fg = codes.next().map(Color::Indexed),
+                    Some(2) => {
+                        if let (Some(r), Some(g), Some(b)) =
+                            (codes.next(), codes.next(), codes.next())
+                        {
+                            self.fg = Some(Color::Rgb(r, g, b));
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Strips the ANSI escape sequences in `line`, returning the plain text and the highlights.
+pub fn parse_ansi(line: &str) -> (String, Vec<AnsiSpan>) {
+    let mut text = String::with_capacity(line.len());
+    let mut spans = Vec::new();
+
+    let mut style = Style::default();
+    let mut span_start = 0;
+
+    let push_span = |style: Style, start: usize, end: usize, spans: &mut Vec<AnsiSpan>| {
+        if end > start && style != Style::default() {
+            spans.push(AnsiSpan {
+                start,
+                end,
+                fg: style.fg,
+                bold: style.bold,
+            });
+        }
+    };
+
+    let mut rest = line;
+    while let Some(esc) = rest.find('\x1b') {
+        text.push_str(&rest[..esc]);
+        rest = &rest[esc + 1..];
+
+        // Only the CSI sequences are recognized, the SGR ones are applied.
+        let csi = match rest.strip_prefix('[') {
+            Some(csi) => csi,
+            None => continue,
+        };
+        let final_idx = match csi.find(|c: char| ('\x40'..='\x7e').contains(&c)) {
+            Some(final_idx) => final_idx,
+            None => {
+                rest = "";
+                break;
+            }
+        };
+        if csi.as_bytes()[final_idx] == b'm' {
+            let mut new_style = style;
+            new_style.apply_sgr(&csi[..final_idx]);
+            if new_style != style {
+                push_span(style, span_start, text.len(), &mut spans);
+                style = new_style;
+                span_start = text.len();
+            }
+        }
+        rest = &csi[final_idx + 1..];
+    }
+    text.push_str(rest);
+    push_span(style, span_start, text.len(), &mut spans);
+
+    (text, spans)
+}
+
+/// Returns the previewer command for `path` in `previewers`, which maps the filetype or the
+/// file extension to the command.
+pub fn previewer_for<'a>(previewers: &'a HashMap<String, String>, path: &Path) -> Option<&'a str> {
+    if previewers.is_empty() {
+        return None;
+    }
+    crate::stdio_server::syntax_for(path)
+        .and_then(|filetype| previewers.get(filetype))
+        .or_else(|| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| previewers.get(ext))
+        })
+        .map(String::as_str)
+}
+
+fn quote_path(path: &Path) -> String {
+    let path = path.display().to_string();
+    if cfg!(target_os = "windows") {
+        format!("\"{path}\"")
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
+
+/// Returns the first `size` lines and their highlights produced by `previewer` for `path`.
+///
+/// The path is substituted for `{}` in `previewer`, or appended if there is no placeholder.
+/// Only the first `size` lines of the output are read, the previewer is killed once they are
+/// read or if it takes longer than [`PREVIEWER_TIMEOUT`].
+pub async fn run_previewer(
+    previewer: &str,
+    path: &Path,
+    size: usize,
+) -> Result<(Vec<String>, Vec<Vec<AnsiSpan>>)> {
+    let quoted_path = quote_path(path);
+    let cmd = if previewer.contains(PATH_PLACEHOLDER) {
+        previewer.replace(PATH_PLACEHOLDER, &quoted_path)
+    } else {
+        format!("{previewer} {quoted_path}")
+    };
+
+    let mut child = shell_command(&cmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture the stdout of `{cmd}`"))?;
+
+    let read_preview = async {
+        let mut reader = BufReader::new(stdout);
+        let mut lines = Vec::with_capacity(size);
+        let mut buf = Vec::new();
+        while lines.len() < size {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf).await? == 0 {
+                // The output ends before `size` lines, the exit status tells if it's complete.
+                let status = child.wait().await?;
+                if !status.success() {
+                    return Err(anyhow!("Previewer `{cmd}` exited with {:?}", status.code()));
+                }
+                break;
+            }
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+            }
+            lines.push(String::from_utf8_lossy(&buf).into_owned());
+        }
+        Ok(lines)
+    };
+
+    let lines = match tokio::time::timeout(PREVIEWER_TIMEOUT, read_preview).await {
+        Ok(lines) => lines?,
+        Err(_) => {
+            child.kill().await?;
+            return Err(anyhow!(
+                "Previewer `{cmd}` timed out after {PREVIEWER_TIMEOUT:?}"
+            ));
+        }
+    };
+
+    // The rest of the output is not needed.
+    if child.try_wait()?.is_none() {
+        child.kill().await?;
+    }
+
+    Ok(lines.iter().map(|line| parse_ansi(line)).unzip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ansi() {
+        let (text, spans) = parse_ansi("\x1b[1;31mfn\x1b[0m \x1b[38;2;255;128;0mmain\x1b[39m()");
+        assert_eq!(text, "fn main()");
+        assert_eq!(
+            spans,
+            vec![
+                AnsiSpan {
+                    start: 0,
+                    end: 2,
+                    fg: Some(Color::Indexed(1)),
+                    bold: true
+                },
+                AnsiSpan {
+                    start: 3,
+                    end: 7,
+                    fg: Some(Color::Rgb(255, 128, 0)),
+                    bold: false
+                },
+            ]
+        );
+        assert_eq!(serde_json::to_string(&spans[1].fg).unwrap(), "\"#ff8000\"");
+
+        // Non-SGR sequences are dropped.
+        assert_eq!(parse_ansi("\x1b[2Kplain\x1b[38;5;208m").0, "plain");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_stubbed_previewer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("main.rs");
+        std::fs::write(
+            &path,
+            "\x1b[31mfn\x1b[0m main() {}\nlet x = 1;\nlet y = 2;\n",
+        )
+        .unwrap();
+
+        let previewers = HashMap::from([("rs".to_string(), "cat".to_string())]);
+        assert!(previewer_for(&previewers, &dir.join("README.md")).is_none());
+        let previewer = previewer_for(&previewers, &path).unwrap();
+
+        let (lines, highlights) = run_previewer(previewer, &path, 2).await.unwrap();
+        assert_eq!(lines, vec!["fn main() {}", "let x = 1;"]);
+        assert_eq!(highlights[0][0].fg, Some(Color::Indexed(1)));
+        assert!(highlights[1].is_empty());
+
+        let (lines, _) = run_previewer("sed -n 3p {}", &path, 2).await.unwrap();
+        assert_eq!(lines, vec!["let y = 2;"]);
+
+        assert!(run_previewer("false", &path, 2).await.is_err());
+
+        // Only the needed lines of an endless output are read.
+        let (lines, _) = run_previewer("yes", &path, 3).await.unwrap();
+        assert_eq!(lines.len(), 3);
+
+        let now = std::time::Instant::now();
+        assert!(run_previewer("sleep 10; cat {}", &path, 2).await.is_err());
+        assert!(now.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod external;
 pub mod vim_help;
 
 use std::path::Path;
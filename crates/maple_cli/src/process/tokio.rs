@@ -45,6 +45,57 @@ pub fn shell_command(shell_cmd: impl AsRef<str>) -> Command {
     }
 }
 
+/// Streams `lines` into the stdin of `shell_cmd` and returns its stdout.
+///
+/// The input and output are processed concurrently so that a large input won't block on a full
+/// stdout pipe.
+pub async fn pipe_lines(
+    shell_cmd: impl AsRef<str>,
+    lines: impl Iterator<Item = String>,
+    cwd: &Path,
+) -> std::io::Result<String> {
+    use std::process::Stdio;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut child = shell_command(shell_cmd)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped; qed");
+    let mut stdout = child.stdout.take().expect("stdout is piped; qed");
+
+    let write_input = async move {
+        for line in lines {
+            stdin.write_all(line.as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+        }
+        // Closes stdin so that the command can see the EOF.
+        drop(stdin);
+        Ok::<_, std::io::Error>(())
+    };
+
+    let read_output = async move {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).await?;
+        Ok::<_, std::io::Error>(output)
+    };
+
+    let (write_result, output) = tokio::join!(write_input, read_output);
+
+    // The command may exit without consuming the whole input, e.g., `head -n 1`.
+    if let Err(e) = write_result {
+        if e.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(e);
+        }
+    }
+
+    child.wait().await?;
+
+    output
+}
+
 /// Unit type wrapper for [`tokio::process::Command`].
 #[derive(Debug)]
 pub struct TokioCommand(Command);
@@ -104,4 +155,13 @@ mod tests {
             HashSet::from_iter(tokio_cmd.lines().await.unwrap().into_iter())
         );
     }
+
+    #[tokio::test]
+    async fn test_pipe_lines() {
+        let lines = (0..1000).map(|i| format!("line {i}"));
+        let output = pipe_lines("wc -l", lines, &std::env::current_dir().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(output.trim(), "1000");
+    }
 }
@@ -1,9 +1,10 @@
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::path::Path;
 
 use chrono::prelude::*;
 use filter::SourceItem;
-use matcher::{Bonus, MatcherBuilder};
+use matcher::{Bonus, MatcherBuilder, RecentFiles};
 use serde::{Deserialize, Serialize};
 
 use crate::utils::UtcTime;
@@ -17,6 +18,25 @@ const MONTH: i64 = DAY * 30;
 /// Maximum number of recent files.
 const MAX_ENTRIES: u64 = 10_000;
 
+/// Reads the recently opened files from `path`.
+///
+/// The file can be either a JSON object mapping the file path to its last access timestamp,
+/// or a plaintext file with one path per line.
+pub fn read_recent_files(path: &Path) -> Option<RecentFiles> {
+    let content = std::fs::read_to_string(path).ok()?;
+
+    let is_json =
+        path.extension().is_some_and(|ext| ext == "json") || content.trim_start().starts_with('{');
+
+    if is_json {
+        let entries: HashMap<String, u64> = serde_json::from_str(&content).ok()?;
+        Some(RecentFiles::with_timestamps(entries.into_iter().collect()))
+    } else {
+        let lines: Vec<String> = content.lines().map(Into::into).collect();
+        Some(lines.into())
+    }
+}
+
 /// Preference for sorting the recent files.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum SortPreference {
@@ -171,7 +191,12 @@ impl SortedRecentFiles {
         });
     }
 
-    pub fn filter_on_query(&self, query: &str, cwd: String) -> Vec<filter::MatchedItem> {
+    pub fn filter_on_query(
+        &self,
+        query: &str,
+        cwd: String,
+        counters: &filter::stats::Counters,
+    ) -> Vec<filter::MatchedItem> {
         let mut cwd = cwd;
         cwd.push(std::path::MAIN_SEPARATOR);
 
@@ -187,7 +212,7 @@ impl SortedRecentFiles {
             .bonuses(vec![Bonus::cwd(cwd), Bonus::FileName])
             .build(query.into());
 
-        filter::par_filter(source_items, &matcher)
+        filter::par_filter(source_items, &matcher, counters)
     }
 
     /// Updates or inserts a new entry in a sorted way.
@@ -251,4 +276,21 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_read_recent_files_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recent_files.json");
+        std::fs::write(
+            &path,
+            r#"{"/tmp/new.rs": 1660000000, "/tmp/old.rs": 1650000000}"#,
+        )
+        .unwrap();
+
+        let recent_files = read_recent_files(&path).unwrap();
+        assert!(
+            recent_files.calc_bonus("/tmp/new.rs", 300)
+                > recent_files.calc_bonus("/tmp/old.rs", 300)
+        );
+    }
 }
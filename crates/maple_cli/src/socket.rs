@@ -0,0 +1,142 @@
+//! Talks to the editor over a Unix domain socket instead of the stdio pipes of parent process.
+//!
+//! The stdin and stdout of this process are redirected to the socket, so that the rest of
+//! program reading the stdin and printing to the stdout keeps working untouched.
+
+use std::io::Result;
+use std::path::Path;
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{Error, ErrorKind, Result, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+
+    use once_cell::sync::OnceCell;
+
+    /// Set if this process is listening on the socket, used for accepting the next connection.
+    static LISTENER: OnceCell<UnixListener> = OnceCell::new();
+
+    /// Returns a stream connected to the socket at `path`.
+    ///
+    /// Connects to `path` if someone is listening on it, otherwise listens on `path` and waits
+    /// for the first connection.
+    ///
+    /// A stale socket file nobody is listening on is replaced, any other kind of file at `path`
+    /// is never removed.
+    pub fn connect_or_listen(path: &Path) -> Result<UnixStream> {
+        if let Ok(stream) = UnixStream::connect(path) {
+            return Ok(stream);
+        }
+
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let listener = UnixListener::bind(path)?;
+        let (stream, _addr) = listener.accept()?;
+        let _ = LISTENER.set(listener);
+
+        Ok(stream)
+    }
+
+    /// Redirects the stdin and stdout of this process to `stream`.
+    pub fn redirect_stdio(stream: &UnixStream) -> Result<()> {
+        std::io::stdout().flush()?;
+
+        for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO] {
+            // SAFETY: both file descriptors are valid and owned by this process.
+            if unsafe { libc::dup2(stream.as_raw_fd(), fd) } == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the next connection and redirects the stdio to it, returns `false` if this
+    /// process is not listening on a socket.
+    pub fn accept_next() -> Result<bool> {
+        match LISTENER.get() {
+            Some(listener) => {
+                let (stream, _addr) = listener.accept()?;
+                redirect_stdio(&stream)?;
+                tracing::debug!("Reconnected to a new client");
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::accept_next;
+
+/// Redirects the stdin and stdout of this process to the Unix domain socket at `path`.
+#[cfg(unix)]
+pub fn redirect_stdio_to_socket(path: &Path) -> Result<()> {
+    let stream = unix::connect_or_listen(path)?;
+    unix::redirect_stdio(&stream)
+}
+
+#[cfg(not(unix))]
+pub fn redirect_stdio_to_socket(path: &Path) -> Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "Failed to use {}, the socket is only supported on Unix",
+            path.display()
+        ),
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn accept_next() -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::unix::connect_or_listen;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    #[test]
+    fn test_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("maple.sock");
+
+        // The socket file is left behind once the listener is dropped.
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let server = std::thread::spawn({
+            let path = path.clone();
+            move || connect_or_listen(&path).map(|_stream| ())
+        });
+        while UnixStream::connect(&path).is_err() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_never_remove_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("maple.sock");
+        std::fs::write(&path, "important").unwrap();
+
+        let err = connect_or_listen(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "important");
+    }
+}
@@ -1,24 +1,19 @@
 use std::io::BufRead;
+use std::time::Instant;
 
 use anyhow::Result;
 use once_cell::sync::OnceCell;
-use serde::Serialize;
 use serde_json::json;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
-use crate::stdio_server::impls::dumb_jump::DumbJumpProvider;
-use crate::stdio_server::impls::filer::FilerProvider;
-use crate::stdio_server::impls::recent_files::RecentFilesProvider;
-use crate::stdio_server::impls::DefaultProvider;
+use crate::stdio_server::impls::{
+    create_provider, executable_exists, list_providers, provider_event,
+};
 use crate::stdio_server::rpc::{Call, RpcClient};
-use crate::stdio_server::session::{ProviderEvent, SessionContext, SessionManager};
-
-/// Writes the response to stdout.
-pub fn write_response<T: Serialize>(msg: T) {
-    if let Ok(s) = serde_json::to_string(&msg) {
-        println!("Content-length: {}\n\n{}", s.len(), s);
-    }
-}
+use crate::stdio_server::session::{
+    idle_timeout, ProviderEvent, SessionContext, SessionManager, IDLE_SWEEP_INTERVAL,
+};
+use crate::stdio_server::write_response;
 
 fn loop_read_rpc_message(reader: impl BufRead, sink: &UnboundedSender<String>) {
     let mut reader = reader;
@@ -31,7 +26,11 @@ fn loop_read_rpc_message(reader: impl BufRead, sink: &UnboundedSender<String>) {
                         println!("Failed to send message, error: {}", e);
                     }
                 } else {
-                    println!("EOF reached");
+                    match crate::socket::accept_next() {
+                        Ok(true) => {}
+                        Ok(false) => println!("EOF reached"),
+                        Err(e) => tracing::error!(?e, "Failed to accept the next connection"),
+                    }
                 }
             }
             Err(error) => println!("Failed to read_line, error: {}", error),
@@ -44,7 +43,23 @@ async fn loop_handle_rpc_message(mut rx: UnboundedReceiver<String>) {
 
     let mut manager = SessionManager::default();
 
-    while let Some(msg) = rx.recv().await {
+    let idle_timeout = idle_timeout();
+    let mut idle_sweep = tokio::time::interval(IDLE_SWEEP_INTERVAL);
+
+    loop {
+        let msg = tokio::select! {
+            maybe_msg = rx.recv() => match maybe_msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = idle_sweep.tick() => {
+                if let Some(idle_timeout) = idle_timeout {
+                    manager.reap_idle_sessions(Instant::now(), idle_timeout);
+                }
+                continue;
+            }
+        };
+
         if let Ok(call) = serde_json::from_str::<Call>(msg.trim()) {
             // TODO: fix the clone
             match call.clone() {
@@ -52,7 +67,7 @@ async fn loop_handle_rpc_message(mut rx: UnboundedReceiver<String>) {
                     "exit" => manager.terminate(notification.session_id),
                     "on_init" => {
                         let context: SessionContext = call.clone().into();
-                        manager.new_session(call, Box::new(DefaultProvider::new(context)))
+                        manager.new_session(call, create_provider(context))
                     }
                     _ => {
                         tokio::spawn(async move {
@@ -69,14 +84,14 @@ async fn loop_handle_rpc_message(mut rx: UnboundedReceiver<String>) {
                         tracing::debug!(?msg, "🔽 stdio message(in)");
                     }
 
-                    match msg.method.as_str() {
+                    match provider_event(&msg.method) {
                         "init_ext_map" => {
-                            write_response(msg.parse_filetypedetect());
+                            write_response(msg.parse_filetypedetect()).await;
                         }
                         "preview/file" => {
                             tokio::spawn(async move {
                                 match msg.preview_file().await {
-                                    Ok(res) => write_response(res),
+                                    Ok(res) => write_response(res).await,
                                     Err(e) => tracing::error!(?e, "Failed to preview file"),
                                 }
                             });
@@ -84,39 +99,106 @@ async fn loop_handle_rpc_message(mut rx: UnboundedReceiver<String>) {
                         "quickfix" => {
                             tokio::spawn(async move {
                                 match msg.preview_quickfix().await {
-                                    Ok(res) => write_response(res),
+                                    Ok(res) => write_response(res).await,
                                     Err(e) => tracing::error!(?e, "Failed to preview quickfix"),
                                 }
                             });
                         }
-
-                        "dumb_jump/on_init" => {
-                            let context: SessionContext = call.clone().into();
-                            manager.new_session(call, Box::new(DumbJumpProvider::new(context)))
+                        "loclist" => {
+                            tokio::spawn(async move {
+                                match msg.preview_loclist().await {
+                                    Ok(res) => write_response(res).await,
+                                    Err(e) => tracing::error!(?e, "Failed to preview loclist"),
+                                }
+                            });
                         }
-                        "dumb_jump/on_typed" => manager.send(msg.session_id, OnTyped(msg)),
-                        "dumb_jump/on_move" => manager.send(msg.session_id, OnMove(msg)),
 
-                        "recent_files/on_init" => {
-                            let context: SessionContext = call.clone().into();
-                            manager.new_session(call, Box::new(RecentFilesProvider::new(context)))
+                        "clap#stats" => {
+                            // The current session by default.
+                            let session_id = msg.get_u64("session_id").unwrap_or(msg.session_id);
+                            let stats = manager.stats(session_id);
+                            write_response(msg.stats(session_id, stats)).await;
+                        }
+                        "clap#providers" => {
+                            tokio::spawn(async move {
+                                match tokio::task::spawn_blocking(|| {
+                                    list_providers(executable_exists)
+                                })
+                                .await
+                                {
+                                    Ok(providers) => {
+                                        write_response(json!({
+                                            "id": msg.id,
+                                            "result": { "providers": providers },
+                                        }))
+                                        .await
+                                    }
+                                    Err(e) => tracing::error!(?e, "Failed to list the providers"),
+                                }
+                            });
+                        }
+                        "clap#session_log" => {
+                            // The current session by default.
+                            let session_id = msg.get_u64("session_id").unwrap_or(msg.session_id);
+                            let events = manager.event_records(session_id).unwrap_or_default();
+                            write_response(json!({
+                                "id": msg.id,
+                                "result": { "session_id": session_id, "events": events },
+                            }))
+                            .await;
+                        }
+                        "clap#chain" => {
+                            // The session id following the current one by default.
+                            let next_session_id =
+                                msg.get_u64("next_session_id").unwrap_or(msg.session_id + 1);
+                            let started = msg.get_string("selected").and_then(|selected| {
+                                manager.start_chain(
+                                    msg.session_id,
+                                    next_session_id,
+                                    &selected,
+                                    msg.id,
+                                )
+                            });
+                            match started {
+                                Ok(init_call) => {
+                                    write_response(json!({
+                                        "id": msg.id,
+                                        "result": {
+                                            "session_id": next_session_id,
+                                            "provider_id": init_call.get_str("provider_id").ok(),
+                                            "cwd": init_call.get_cwd(),
+                                        },
+                                    }))
+                                    .await
+                                }
+                                Err(err) => {
+                                    write_response(
+                                        json!({ "error": err.to_string(), "id": msg.id }),
+                                    )
+                                    .await
+                                }
+                            }
                         }
-                        "recent_files/on_typed" => manager.send(msg.session_id, OnTyped(msg)),
-                        "recent_files/on_move" => manager.send(msg.session_id, OnMove(msg)),
 
-                        "filer/on_init" => {
+                        "on_init" => {
                             let context: SessionContext = call.clone().into();
-                            manager.new_session(call, Box::new(FilerProvider::new(context)))
+                            manager.new_session(call, create_provider(context))
                         }
-                        "filer/on_typed" => manager.send(msg.session_id, OnTyped(msg)),
-                        "filer/on_move" => manager.send(msg.session_id, OnMove(msg)),
-
                         "on_typed" => manager.send(msg.session_id, OnTyped(msg)),
                         "on_move" => manager.send(msg.session_id, OnMove(msg)),
+                        "clap#pipe_results" => manager.send(msg.session_id, PipeResults(msg)),
+                        "clap#select" => manager.send(msg.session_id, OnSelect(msg)),
+                        "clap#refresh" => manager.send(msg.session_id, Refresh(msg)),
+                        "clap#set_query" => manager.send(msg.session_id, SetQuery(msg)),
+                        "on_resize" => match msg.get_u64("winwidth") {
+                            Ok(winwidth) => manager.send(msg.session_id, OnResize { winwidth }),
+                            Err(e) => tracing::error!(?e, "Invalid on_resize message"),
+                        },
 
                         method => write_response(
                             json!({ "error": format!("unknown method: {}", method), "id": msg.id }),
-                        ),
+                        )
+                        .await,
                     }
                 }
             }
@@ -127,6 +209,8 @@ async fn loop_handle_rpc_message(mut rx: UnboundedReceiver<String>) {
 }
 
 pub async fn run_forever(reader: impl BufRead + Send + 'static) {
+    super::spawn_warm_up();
+
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
     tokio::spawn(async move {
         loop_read_rpc_message(reader, &tx);
@@ -1,27 +1,76 @@
 mod on_create;
 mod on_move;
 mod providers;
+mod registry;
 
 use std::ops::Deref;
 use std::sync::Arc;
 
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use serde_json::json;
 
-use filter::{FilterContext, ParSource};
-use matcher::MatcherBuilder;
+use filter::{FilterContext, ParSource, ScoreCache};
+use matcher::MatchScope;
 use types::{ClapItem, MatchedItem, SourceItem};
 
 use crate::command::ctags::recursive_tags::build_recursive_ctags_cmd;
 use crate::command::grep::RgTokioCommand;
-use crate::process::tokio::TokioCommand;
-use crate::stdio_server::session::{ClapProvider, SessionContext, SourceScale};
+use crate::process::tokio::{pipe_lines, TokioCommand};
+use crate::stdio_server::session::{
+    ClapProvider, JumpTarget, ProviderAction, SessionContext, SourceScale, FILE_ACTIONS,
+};
 use crate::stdio_server::{write_response, MethodCall};
 
-pub use self::on_create::initialize;
-pub use self::on_move::{OnMove, OnMoveHandler};
-pub use self::providers::{dumb_jump, filer, recent_files};
+pub use self::on_create::{initialize, refresh};
+pub use self::on_move::{OnMove, OnMoveHandler, Position};
+pub use self::providers::{
+    cargo_deps, colors, dumb_jump, env_vars, filer, git_hunks, git_refs, lines, loclist,
+    recent_files, recent_grep, rtp_scripts, snippets, todo,
+};
+pub use self::registry::{executable_exists, list_providers};
+
+/// Number of the results displayed on typed by default.
+const DEFAULT_DISPLAY_NUMBER: usize = 200;
+
+/// Match results cached across the sessions for the repeated searches.
+static SCORE_CACHE: Lazy<Mutex<ScoreCache>> = Lazy::new(|| {
+    Mutex::new(ScoreCache::new(
+        crate::config::config().session.score_cache_capacity,
+    ))
+});
+
+/// Returns the provider of `context.provider_id`, [`DefaultProvider`] is used unless the
+/// provider has a specialized one.
+pub fn create_provider(context: SessionContext) -> Box<dyn ClapProvider> {
+    match context.provider_id.as_str() {
+        "cargo_deps" => Box::new(cargo_deps::CargoDepsProvider::new(context)),
+        "colors" => Box::new(colors::ColorsProvider::new(context)),
+        "dumb_jump" => Box::new(dumb_jump::DumbJumpProvider::new(context)),
+        "env_vars" => Box::new(env_vars::EnvVarsProvider::new(context)),
+        "filer" => Box::new(filer::FilerProvider::new(context)),
+        "git_hunks" => Box::new(git_hunks::GitHunksProvider::new(context)),
+        "git_refs" => Box::new(git_refs::GitRefsProvider::new(context)),
+        "lines" => Box::new(lines::LinesProvider::new(context)),
+        "loclist" => Box::new(loclist::LoclistProvider::new(context)),
+        "recent_files" => Box::new(recent_files::RecentFilesProvider::new(context)),
+        "recent_grep" => Box::new(recent_grep::RecentGrepProvider::new(context)),
+        "rtp_scripts" => Box::new(rtp_scripts::RtpScriptsProvider::new(context)),
+        "snippets" => Box::new(snippets::SnippetsProvider::new(context)),
+        "todo" => Box::new(todo::TodoProvider::new(context)),
+        _ => Box::new(DefaultProvider::new(context)),
+    }
+}
+
+/// Returns the session event of `method` with the provider prefix stripped, e.g., `on_typed`
+/// of `filer/on_typed`, the other methods are returned as is.
+pub fn provider_event(method: &str) -> &str {
+    match method.split_once('/') {
+        Some((_provider_id, event @ ("on_init" | "on_typed" | "on_move"))) => event,
+        _ => method,
+    }
+}
 
 #[derive(Debug)]
 pub struct DefaultProvider {
@@ -37,6 +86,20 @@ impl DefaultProvider {
         }
     }
 
+    /// Takes the top entries and add an icon to each of them.
+    fn decorate_top_items(&self, matched_items: &[MatchedItem]) -> printer::DisplayLines {
+        printer::decorate_lines_with_truncation(
+            matched_items
+                .iter()
+                .take(self.context.display_number(DEFAULT_DISPLAY_NUMBER))
+                .cloned()
+                .collect(),
+            self.context.display_winwidth as usize,
+            self.context.icon,
+            self.context.truncation_mode(),
+        )
+    }
+
     /// `lnum` is 1-based.
     fn line_at(&self, lnum: usize) -> Option<String> {
         self.current_results
@@ -44,6 +107,22 @@ impl DefaultProvider {
             .get((lnum - 1) as usize)
             .map(|r| r.item.output_text().to_string())
     }
+
+    /// Returns the tag name of `line` in the tags providers and its jump target.
+    fn tag_target<'a>(&self, line: &'a str) -> Option<(&'a str, JumpTarget)> {
+        let (name, _lnum) = pattern::extract_tag_name(line)?.rsplit_once(':')?;
+        let (path, lnum) = match self.context.provider_id.as_str() {
+            "proj_tags" => {
+                let (lnum, path) = pattern::extract_proj_tags(line)?;
+                (self.context.cwd.join(path), lnum)
+            }
+            _ => (
+                self.context.start_buffer_path.clone(),
+                pattern::extract_buf_tags_lnum(line)?,
+            ),
+        };
+        Some((name, JumpTarget { path, lnum, col: 1 }))
+    }
 }
 
 #[async_trait::async_trait]
@@ -52,6 +131,39 @@ impl ClapProvider for DefaultProvider {
         &self.context
     }
 
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    fn default_match_scope(&self) -> MatchScope {
+        match self.context.provider_id.as_str() {
+            "files" | "git_files" => MatchScope::FileName,
+            "tags" | "proj_tags" => MatchScope::TagName,
+            "grep" | "live_grep" => MatchScope::GrepLine,
+            _ => MatchScope::Full,
+        }
+    }
+
+    fn actions(&self) -> Vec<ProviderAction> {
+        match self.context.provider_id.as_str() {
+            "files" | "git_files" => {
+                let mut actions = FILE_ACTIONS.to_vec();
+                actions.push(ProviderAction::Cd);
+                actions
+            }
+            "grep" | "live_grep" | "tags" | "proj_tags" | "history" => FILE_ACTIONS.to_vec(),
+            _ => vec![ProviderAction::Open],
+        }
+    }
+
+    fn multiple_targets(&self) -> bool {
+        matches!(self.context.provider_id.as_str(), "tags" | "proj_tags")
+    }
+
+    fn set_seeded_results(&self, matched_items: Vec<MatchedItem>) {
+        *self.current_results.lock() = matched_items;
+    }
+
     async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
         let msg_id = msg.id;
 
@@ -74,7 +186,7 @@ impl ClapProvider for DefaultProvider {
         let on_move_handler = on_move::OnMoveHandler::create(&msg, &self.context, curline)?;
         if let Err(error) = on_move_handler.handle().await {
             tracing::error!(?error, "Failed to handle OnMove event");
-            write_response(json!({"error": error.to_string(), "id": msg_id }));
+            write_response(json!({"error": error.to_string(), "id": msg_id })).await;
         }
         Ok(())
     }
@@ -82,47 +194,326 @@ impl ClapProvider for DefaultProvider {
     async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
         let query = msg.get_query();
 
-        let source_scale = self.context.state.source_scale.lock();
-
-        match source_scale.deref() {
-            SourceScale::Small { ref items, .. } => {
-                let matched_items =
-                    filter::par_filter_items(items, &self.context.fuzzy_matcher(query));
+        let display_lines = match self.context.state.source_scale.lock().deref() {
+            SourceScale::Small {
+                ref items,
+                fingerprint,
+                ..
+            } => {
+                let (matcher_builder, routed_query) =
+                    self.context.route_query(self.match_scope(), &query);
+                // The whole matcher configuration affects the results, not only the match scope.
+                let settings = (
+                    self.context.provider_id.as_str(),
+                    utility::calculate_hash(&matcher_builder),
+                );
+                let fuzzy_matcher = matcher_builder.build(routed_query.into());
+                let matched_items = if SCORE_CACHE.lock().is_enabled() {
+                    let fingerprint = ScoreCache::fingerprint(settings, *fingerprint);
+                    filter::par_filter_items_cached(
+                        items,
+                        &fuzzy_matcher,
+                        &query,
+                        fingerprint,
+                        &SCORE_CACHE,
+                        &self.context.state.counters,
+                    )
+                } else {
+                    filter::par_filter_items(items, &fuzzy_matcher, &self.context.state.counters)
+                };
                 let matched = matched_items.len();
-                // Take the first 200 entries and add an icon to each of them.
-                printer::decorate_lines(
-                    matched_items.iter().take(200).cloned().collect(),
-                    self.context.display_winwidth as usize,
-                    self.context.icon,
-                )
-                .print_on_typed(matched);
+                let display_lines = self.decorate_top_items(&matched_items);
                 let mut current_results = self.current_results.lock();
                 *current_results = matched_items;
+                Some((display_lines, matched))
             }
             SourceScale::Cache { ref path, .. } => {
+                let (matcher_builder, query) = self.context.route_query(self.match_scope(), &query);
                 if let Err(e) = filter::par_dyn_run(
-                    &query,
+                    query,
                     FilterContext::new(
                         self.context.icon,
-                        Some(40),
+                        Some(self.context.display_number(40)),
                         Some(self.context.display_winwidth as usize),
-                        MatcherBuilder::default()
-                            .match_scope(self.context.match_scope)
-                            .bonuses(self.context.match_bonuses.clone()),
-                    ),
+                        matcher_builder,
+                    )
+                    .counters(self.context.state.counters.clone()),
                     ParSource::File(path.clone()),
                 ) {
                     tracing::error!(error = ?e, "Error occured when filtering the cache source");
                 }
+                None
             }
             SourceScale::Large(_total) => {
                 //TODO: probably remove this variant?
+                None
             }
             SourceScale::Indefinite => {
                 // TODO: Note arbitrary shell command and use par_dyn_run later.
+                None
             }
+        };
+
+        if let Some((display_lines, matched)) = display_lines {
+            write_response(display_lines.on_typed_message(matched)).await;
+        }
+
+        Ok(())
+    }
+
+    async fn on_select(&mut self, msg: MethodCall) -> Result<()> {
+        if !self.multiple_targets() {
+            return Err(anyhow::anyhow!(
+                "Multiple jump targets are unsupported for provider {}",
+                self.context.provider_id
+            ));
+        }
+
+        let lnum = msg.get_u64("lnum")?;
+        let selected_name = self
+            .line_at(lnum as usize)
+            .and_then(|line| self.tag_target(&line).map(|(name, _)| name.to_string()));
+
+        // All the tags of the same name as the selected one.
+        let targets = match selected_name {
+            Some(selected_name) => self
+                .current_results
+                .lock()
+                .iter()
+                .filter_map(|r| {
+                    let line = r.item.output_text();
+                    let (name, target) = self.tag_target(&line)?;
+                    (name == selected_name).then_some(target)
+                })
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+
+        let provider_id = &self.context.provider_id;
+        write_response(
+            json!({ "id": msg.id, "provider_id": provider_id, "result": { "targets": targets } }),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    fn buffered_results(&self) -> Option<(printer::DisplayLines, usize)> {
+        let current_results = self.current_results.lock();
+        if current_results.is_empty() {
+            None
+        } else {
+            Some((
+                self.decorate_top_items(&current_results),
+                current_results.len(),
+            ))
         }
+    }
+
+    async fn on_pipe_results(&mut self, msg: MethodCall) -> Result<()> {
+        let shell_cmd = msg.get_string("cmd")?;
+
+        // Stream the lines one by one instead of cloning the whole result set.
+        let current_results = self.current_results.clone();
+        let lines = (0..).map_while(move |idx| {
+            current_results
+                .lock()
+                .get(idx)
+                .map(|r| r.item.output_text().to_string())
+        });
+
+        let output = pipe_lines(shell_cmd, lines, &self.context.cwd).await?;
+
+        let provider_id = &self.context.provider_id;
+        write_response(
+            json!({ "id": msg.id, "provider_id": provider_id, "result": { "output": output } }),
+        )
+        .await;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stdio_server::session::{cancel_job, is_job_running, spawn_session_job};
+
+    #[test]
+    fn test_provider_event() {
+        assert_eq!(provider_event("filer/on_typed"), "on_typed");
+        assert_eq!(provider_event("loclist/on_init"), "on_init");
+        assert_eq!(provider_event("on_move"), "on_move");
+        assert_eq!(provider_event("preview/file"), "preview/file");
+        assert_eq!(provider_event("clap#select"), "clap#select");
+    }
+
+    #[test]
+    fn test_default_match_scope() {
+        let files = DefaultProvider::new(SessionContext::new_test_context("files"));
+        assert_eq!(files.match_scope(), MatchScope::FileName);
+
+        let grep = DefaultProvider::new(SessionContext::new_test_context("grep"));
+        assert_eq!(grep.match_scope(), MatchScope::GrepLine);
+
+        let mut context = SessionContext::new_test_context("files");
+        context.match_scope = Some(MatchScope::Full);
+        assert_eq!(
+            DefaultProvider::new(context).match_scope(),
+            MatchScope::Full
+        );
+    }
+
+    #[test]
+    fn test_provider_actions() {
+        let files = DefaultProvider::new(SessionContext::new_test_context("files"));
+        assert!(files.actions().contains(&ProviderAction::Cd));
+
+        let grep = DefaultProvider::new(SessionContext::new_test_context("grep"));
+        assert!(grep.actions().contains(&ProviderAction::Open));
+        assert!(!grep.actions().contains(&ProviderAction::Cd));
+
+        assert_eq!(
+            serde_json::to_value(files.actions()).unwrap(),
+            json!(["open", "split", "vsplit", "tab", "cd"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_terminate_cancels_own_job() {
+        let provider = DefaultProvider::new(SessionContext::new_test_context("grep"));
+        let another = DefaultProvider::new(SessionContext::new_test_context("grep"));
+
+        let own_job_id = utility::calculate_hash(&"test_terminate_cancels_own_job");
+        let another_job_id = own_job_id.wrapping_add(1);
+        spawn_session_job(
+            provider.session_context(),
+            futures::future::pending(),
+            own_job_id,
+        );
+        spawn_session_job(
+            another.session_context(),
+            futures::future::pending(),
+            another_job_id,
+        );
+
+        provider.handle_terminate(0);
+
+        assert!(!is_job_running(own_job_id));
+        assert!(is_job_running(another_job_id));
+
+        assert!(cancel_job(another_job_id));
+    }
+
+    #[tokio::test]
+    async fn test_shared_session_job() {
+        let provider = DefaultProvider::new(SessionContext::new_test_context("grep"));
+        let another = DefaultProvider::new(SessionContext::new_test_context("grep"));
+
+        let job_id = utility::calculate_hash(&"test_shared_session_job");
+        assert!(spawn_session_job(
+            provider.session_context(),
+            futures::future::pending(),
+            job_id,
+        ));
+        assert!(!spawn_session_job(
+            another.session_context(),
+            futures::future::pending(),
+            job_id,
+        ));
+
+        // Still used by another session.
+        provider.handle_terminate(0);
+        assert!(is_job_running(job_id));
+
+        another.handle_terminate(1);
+        assert!(!is_job_running(job_id));
+    }
+
+    #[tokio::test]
+    async fn test_finished_session_job_is_pruned() {
+        let provider = DefaultProvider::new(SessionContext::new_test_context("grep"));
+        let spawned_jobs = provider.session_context().state.spawned_jobs.clone();
+
+        let job_id = utility::calculate_hash(&"test_finished_session_job_is_pruned");
+        assert!(spawn_session_job(
+            provider.session_context(),
+            async {},
+            job_id
+        ));
+        assert!(spawned_jobs.lock().contains(&job_id));
+
+        while is_job_running(job_id) {
+            tokio::task::yield_now().await;
+        }
+        assert!(spawned_jobs.lock().is_empty());
+    }
+
+    #[test]
+    fn test_session_display_number() {
+        let matched_items = (0..300)
+            .map(|i| {
+                MatchedItem::new(
+                    Arc::new(format!("line {i}")),
+                    Default::default(),
+                    Default::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut context = SessionContext::new_test_context("files");
+        context.number = Some(5);
+        let limited = DefaultProvider::new(context);
+        let default = DefaultProvider::new(SessionContext::new_test_context("grep"));
+
+        assert_eq!(limited.decorate_top_items(&matched_items).lines.len(), 5);
+        assert_eq!(
+            default.decorate_top_items(&matched_items).lines.len(),
+            DEFAULT_DISPLAY_NUMBER
+        );
+    }
+
+    #[test]
+    fn test_flush_on_terminate() {
+        let matched_items = ["foo", "foobar"]
+            .into_iter()
+            .map(|line| MatchedItem::new(Arc::new(line), Default::default(), Default::default()))
+            .collect::<Vec<_>>();
+
+        let provider = DefaultProvider::new(SessionContext::new_test_context("files"));
+        *provider.current_results.lock() = matched_items.clone();
+        assert!(provider.results_to_flush().is_none());
+
+        let mut context = SessionContext::new_test_context("files");
+        context.flush_on_terminate = true;
+        let provider = DefaultProvider::new(context);
+        assert!(provider.results_to_flush().is_none());
+
+        *provider.current_results.lock() = matched_items;
+        let (display_lines, matched) = provider.results_to_flush().unwrap();
+        assert_eq!(matched, 2);
+        assert_eq!(display_lines.lines, vec!["foo", "foobar"]);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_recreates_grep_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(dir.join("a.txt"), "first line\n").unwrap();
+
+        let mut context = SessionContext::new_test_context("grep");
+        context.cwd = dir.clone().try_into().unwrap();
+
+        let total = initialize(&context).await.unwrap().total();
+        assert_eq!(total, Some(1));
+
+        std::fs::write(dir.join("b.txt"), "second line\n").unwrap();
+
+        // The existing cache is still used without refreshing.
+        let total = initialize(&context).await.unwrap().total();
+        assert_eq!(total, Some(1));
+
+        let total = refresh(&context).await.unwrap().total();
+        assert_eq!(total, Some(2));
+    }
+}
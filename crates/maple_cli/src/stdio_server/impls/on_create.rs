@@ -10,6 +10,7 @@ use crate::command::ctags::recursive_tags::build_recursive_ctags_cmd;
 use crate::command::grep::RgTokioCommand;
 use crate::process::tokio::TokioCommand;
 use crate::stdio_server::session::{SessionContext, SourceScale};
+use crate::stdio_server::write_response;
 
 /// Threshold for large scale.
 const LARGE_SCALE: usize = 200_000;
@@ -26,7 +27,7 @@ pub async fn initialize(context: &SessionContext) -> Result<SourceScale> {
                 .into_iter()
                 .map(|line| Arc::new(SourceItem::from(line)) as Arc<dyn ClapItem>)
                 .collect::<Vec<_>>();
-            SourceScale::Small { total, items }
+            SourceScale::small(items)
         }
     };
 
@@ -42,10 +43,7 @@ pub async fn initialize(context: &SessionContext) -> Result<SourceScale> {
         }
         "tags" => {
             let items = crate::tools::ctags::buffer_tag_items(&context.start_buffer_path, false)?;
-            return Ok(SourceScale::Small {
-                total: items.len(),
-                items,
-            });
+            return Ok(SourceScale::small(items));
         }
         "proj_tags" => {
             let mut ctags_cmd = build_recursive_ctags_cmd(context.cwd.to_path_buf());
@@ -74,10 +72,12 @@ pub async fn initialize(context: &SessionContext) -> Result<SourceScale> {
                 }
             };
             let (total, path) = (digest.total, digest.cached_path);
-            let method = "clap#state#set_variable_string";
-            let name = "g:__clap_forerunner_tempfile";
-            let value = &path;
-            utility::println_json_with_length!(method, name, value);
+            write_response(json!({
+                "method": "clap#state#set_variable_string",
+                "name": "g:__clap_forerunner_tempfile",
+                "value": &path,
+            }))
+            .await;
             return Ok(SourceScale::Cache { total, path });
         }
         _ => {}
@@ -99,3 +99,11 @@ pub async fn initialize(context: &SessionContext) -> Result<SourceScale> {
 
     Ok(SourceScale::Indefinite)
 }
+
+/// Invalidates the cache of provider and performs the initialization again.
+pub async fn refresh(context: &SessionContext) -> Result<SourceScale> {
+    let mut context = context.clone();
+    // Bypass the existing cache so that it will be rebuilt, e.g., the rg cache of `grep`.
+    context.no_cache = true;
+    initialize(&context).await
+}
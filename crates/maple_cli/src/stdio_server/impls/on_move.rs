@@ -1,13 +1,18 @@
+use std::collections::HashMap;
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use anyhow::{anyhow, Context, Result};
 use once_cell::sync::Lazy;
-use serde_json::json;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
 
 use pattern::*;
 use types::PreviewInfo;
+use utility::LineIndex;
 
 use crate::previewer::{self, vim_help::HelpTagPreview};
 use crate::stdio_server::impls::providers::filer;
@@ -18,6 +23,49 @@ use crate::utils::{build_abs_path, display_width, truncate_absolute_path};
 
 static IS_FERESHING_CACHE: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
 
+/// Maximum number of the cached line indexes.
+const MAX_LINE_INDEXES: usize = 8;
+
+/// Line index of a file, with the modified time of file when it was built.
+type IndexedFile = (SystemTime, Arc<LineIndex>);
+
+/// Line indexes of the recently previewed large files.
+static LINE_INDEXES: Lazy<Mutex<HashMap<PathBuf, IndexedFile>>> = Lazy::new(Default::default);
+
+/// Returns the line index of `path`, built on the first preview of the file.
+fn line_index_of(path: &Path) -> std::io::Result<Arc<LineIndex>> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    if let Some((indexed_modified, line_index)) = LINE_INDEXES.lock().get(path) {
+        if *indexed_modified == modified {
+            return Ok(line_index.clone());
+        }
+    }
+
+    let line_index = Arc::new(LineIndex::build(path)?);
+    let mut line_indexes = LINE_INDEXES.lock();
+    if line_indexes.len() >= MAX_LINE_INDEXES {
+        line_indexes.clear();
+    }
+    line_indexes.insert(path.to_path_buf(), (modified, line_index.clone()));
+    Ok(line_index)
+}
+
+/// Returns the lines around `lnum` of `path`, the file larger than `max_file_size` is read by
+/// seeking from its line index instead of being loaded entirely.
+fn read_preview_lines(
+    path: &Path,
+    lnum: usize,
+    size: usize,
+    max_file_size: u64,
+) -> std::io::Result<PreviewInfo> {
+    if std::fs::metadata(path)?.len() > max_file_size {
+        let line_index = line_index_of(path)?;
+        utility::read_preview_lines_indexed(path, lnum, size, &line_index)
+    } else {
+        utility::read_preview_lines(path, lnum, size)
+    }
+}
+
 /// We want to preview a line of a file.
 #[derive(Debug, Clone)]
 pub struct Position {
@@ -31,6 +79,86 @@ impl Position {
     }
 }
 
+/// Match in a grep line, used for highlighting the match in the preview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    /// 1-based byte column of the match.
+    pub col: usize,
+    /// Matched text.
+    pub text: String,
+}
+
+impl GrepMatch {
+    /// Extracts the match from a grep line.
+    ///
+    /// The matched text is the query if specified, otherwise the word at the column of match.
+    fn from_grep_line(line: &str, query: Option<&str>) -> Option<Self> {
+        let (_fpath, _lnum, col, line_content) = extract_grep_position(line)?;
+
+        let text = match query {
+            Some(query) if !query.is_empty() => query.to_string(),
+            _ => line_content
+                .get(col.checked_sub(1)?..)?
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect(),
+        };
+
+        if text.is_empty() {
+            None
+        } else {
+            Some(Self { col, text })
+        }
+    }
+
+    /// Returns the byte range of the match in `line`.
+    ///
+    /// The match is searched in the whole line if it's not found at the original column, in
+    /// case of the file has been changed since searching.
+    fn span_in(&self, line: &str) -> Option<(usize, usize)> {
+        let start = self.col.checked_sub(1)?;
+        let end = start + self.text.len();
+        if line.get(start..end) == Some(self.text.as_str()) {
+            Some((start, end))
+        } else {
+            line.find(&self.text)
+                .map(|start| (start, start + self.text.len()))
+        }
+    }
+}
+
+/// Returns the line number and the match of the first line containing `query` in `path`.
+///
+/// The query is matched case-insensitively unless it contains an uppercase char.
+fn find_first_match(path: &Path, query: &str) -> Option<(usize, GrepMatch)> {
+    // Do not scan the huge file entirely.
+    const MAX_SCANNED_LINES: usize = 10_000;
+
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let lowercase_query = query.to_ascii_lowercase();
+
+    std::io::BufReader::new(std::fs::File::open(path).ok()?)
+        .lines()
+        .take(MAX_SCANNED_LINES)
+        .map_while(Result::ok)
+        .enumerate()
+        .find_map(|(idx, line)| {
+            let start = if case_sensitive {
+                line.find(query)?
+            } else {
+                line.to_ascii_lowercase().find(&lowercase_query)?
+            };
+            let text = line.get(start..start + query.len())?.to_string();
+            Some((
+                idx + 1,
+                GrepMatch {
+                    col: start + 1,
+                    text,
+                },
+            ))
+        })
+}
+
 /// Preview environment on Vim CursorMoved event.
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -40,7 +168,11 @@ pub enum OnMove {
     Filer(PathBuf),
     History(PathBuf),
     Grep(Position),
-    BLines(Position),
+    BLines {
+        position: Position,
+        /// Number of lines following the matched line to show in the preview.
+        context_after: Option<usize>,
+    },
     ProjTags(Position),
     BufferTags(Position),
     HelpTags {
@@ -63,7 +195,7 @@ impl OnMove {
               OnMove::Filer(path)
             },
             "files" | "git_files" => Self::Files(build_abs_path(&context.cwd, &curline)),
-            "recent_files" => Self::Files(PathBuf::from(&curline)),
+            "recent_files" | "cargo_deps" => Self::Files(PathBuf::from(&curline)),
             "history" => {
                 if curline.starts_with('~') {
                     Self::History(crate::utils::expand_tilde(curline))
@@ -78,7 +210,7 @@ impl OnMove {
                 path.push(&p);
                 Self::ProjTags(Position::new(path, lnum))
             }
-            "coc_location" | "grep" | "live_grep" => {
+            "coc_location" | "grep" | "live_grep" | "todo" | "recent_grep" => {
                 let mut try_extract_file_path = |line: &str| {
                     let (fpath, lnum, _col, cache_line) =
                         extract_grep_position(line).context("Couldn't extract grep position")?;
@@ -110,7 +242,11 @@ impl OnMove {
             "blines" => {
                 let lnum = extract_blines_lnum(&curline).context("Couldn't extract buffer lnum")?;
                 let path = context.start_buffer_path.clone();
-                Self::BLines(Position::new(path, lnum))
+                let context_after = msg.get_u64("context_after").ok().map(|n| n as usize);
+                Self::BLines {
+                    position: Position::new(path, lnum),
+                    context_after,
+                }
             }
             "tags" => {
                 let lnum =
@@ -165,6 +301,8 @@ pub struct OnMoveHandler<'a> {
     ///
     /// Currently only for the provider `grep`.
     pub cache_line: Option<String>,
+    /// Match of the grep line for highlighting it in the preview.
+    pub grep_match: Option<GrepMatch>,
 }
 
 impl<'a> OnMoveHandler<'a> {
@@ -178,74 +316,155 @@ impl<'a> OnMoveHandler<'a> {
             Some(line) => line,
             None => msg.get_curline(&context.provider_id)?,
         };
+        let query = msg.get_str("query").ok().filter(|query| !query.is_empty());
+        let grep_match = GrepMatch::from_grep_line(&curline, query);
         let (inner, cache_line) = OnMove::new(msg, curline, context)?;
+        let (inner, grep_match) = match inner {
+            OnMove::Grep(_) => (inner, grep_match),
+            // Center the preview on the first line matching the query.
+            OnMove::Files(path) => match query.and_then(|query| find_first_match(&path, query)) {
+                Some((lnum, grep_match)) => {
+                    (OnMove::Grep(Position::new(path, lnum)), Some(grep_match))
+                }
+                None => (OnMove::Files(path), None),
+            },
+            _ => (inner, None),
+        };
         Ok(Self {
             msg_id,
             size: context.sensible_preview_size(),
             context,
             inner,
             cache_line,
+            grep_match,
         })
     }
 
     pub async fn handle(&self) -> Result<()> {
+        if let Some(result) = self.preview().await? {
+            self.send_response(result).await;
+        }
+        Ok(())
+    }
+
+    /// Returns the preview result sent by [`Self::handle`], `None` if there is nothing to
+    /// preview.
+    pub async fn preview(&self) -> Result<Option<Value>> {
         use OnMove::*;
-        match &self.inner {
+        let result = match &self.inner {
             Filer(path) => {
                 if path.is_dir() {
                     self.preview_directory(&path)?
                 } else {
-                    self.preview_file(&path)?;
+                    self.preview_file(&path).await?
                 }
             }
-            Files(path) | History(path) => self.preview_file(&path)?,
-            BLines(position) | Grep(position) | ProjTags(position) | BufferTags(position) => {
-                self.preview_file_at(position).await
-            }
+            Files(path) | History(path) => self.preview_file(&path).await?,
+            BLines {
+                position,
+                context_after: Some(context_after),
+            } => self.preview_blines_with_context_after(position, *context_after)?,
+            BLines { position, .. }
+            | Grep(position)
+            | ProjTags(position)
+            | BufferTags(position) => self.preview_file_at(position).await,
             Commit(rev) => self.preview_commits(rev)?,
             HelpTags {
                 subject,
                 doc_filename,
                 runtimepath,
-            } => self.preview_help_subject(subject, doc_filename, runtimepath),
-        }
+            } => match self.preview_help_subject(subject, doc_filename, runtimepath) {
+                Some(result) => result,
+                None => return Ok(None),
+            },
+        };
 
-        Ok(())
+        Ok(Some(result))
     }
 
-    fn preview_commits(&self, rev: &str) -> std::io::Result<()> {
+    fn preview_commits(&self, rev: &str) -> std::io::Result<Value> {
         let stdout = self.context.execute(&format!("git show {rev}"))?;
         let stdout_str = String::from_utf8_lossy(&stdout);
         let lines = stdout_str
             .split('\n')
             .take(self.size * 2)
             .collect::<Vec<_>>();
-        self.send_response(json!({ "lines": lines }));
-        Ok(())
+        Ok(json!({ "lines": lines }))
     }
 
-    fn preview_help_subject(&self, subject: &str, doc_filename: &str, runtimepath: &str) {
+    fn preview_help_subject(
+        &self,
+        subject: &str,
+        doc_filename: &str,
+        runtimepath: &str,
+    ) -> Option<Value> {
         let preview_tag = HelpTagPreview::new(subject, doc_filename, runtimepath);
         if let Some((fname, lines)) = preview_tag.get_help_lines(self.size * 2) {
             let lines = std::iter::once(fname.clone())
                 .chain(lines.into_iter())
                 .collect::<Vec<_>>();
-            self.send_response(
-                json!({ "syntax": "help", "lines": lines, "hi_lnum": 1, "fname": fname }),
-            );
+            Some(json!({ "syntax": "help", "lines": lines, "hi_lnum": 1, "fname": fname }))
         } else {
             tracing::debug!(?preview_tag, "Can not find the preview help lines");
+            None
         }
     }
 
-    fn preview_directory<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+    fn preview_directory<P: AsRef<Path>>(&self, path: P) -> std::io::Result<Value> {
         let enable_icon = global().enable_icon;
         let lines = filer::read_dir_entries(&path, enable_icon, Some(2 * self.size))?;
-        self.send_response(json!({ "lines": lines, "is_dir": true }));
-        Ok(())
+        Ok(json!({ "lines": lines, "is_dir": true }))
     }
 
-    fn preview_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+    /// Previews the file using the external previewer configured for its filetype.
+    ///
+    /// Returns `None` if there is no previewer for the file or the previewer fails.
+    async fn try_preview_file_externally(&self, path: &Path) -> Option<Value> {
+        let previewer =
+            previewer::external::previewer_for(&crate::config::config().previewer, path)?;
+
+        match previewer::external::run_previewer(previewer, path, 2 * self.size).await {
+            Ok((lines, ansi_highlights)) => {
+                let fname = path.display().to_string();
+                let lines = std::iter::once(fname.clone())
+                    .chain(lines)
+                    .collect::<Vec<_>>();
+                // Highlights of the title line.
+                let ansi_highlights = std::iter::once(Vec::new())
+                    .chain(ansi_highlights)
+                    .collect::<Vec<_>>();
+                Some(json!({ "lines": lines, "fname": fname, "ansi_highlights": ansi_highlights }))
+            }
+            Err(error) => {
+                tracing::debug!(?error, previewer, "Failed to run the external previewer");
+                None
+            }
+        }
+    }
+
+    /// Returns the placeholder of the file beyond the preview size cap, `None` if the file is
+    /// within the cap.
+    fn too_large_file_placeholder(&self, path: &Path) -> Option<Value> {
+        let file_size = std::fs::metadata(path).ok()?.len();
+        (file_size > self.context.preview_file_size_cap).then(|| {
+            let fname = path.display().to_string();
+            let lines = vec![
+                fname.clone(),
+                format!("File too large to preview ({file_size} bytes)"),
+            ];
+            json!({ "lines": lines, "fname": fname })
+        })
+    }
+
+    async fn preview_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<Value> {
+        if let Some(placeholder) = self.too_large_file_placeholder(path.as_ref()) {
+            return Ok(placeholder);
+        }
+
+        if let Some(result) = self.try_preview_file_externally(path.as_ref()).await {
+            return Ok(result);
+        }
+
         let handle_io_error = |e: &std::io::Error| {
             if e.kind() == std::io::ErrorKind::NotFound {
                 tracing::debug!(
@@ -283,19 +502,59 @@ impl<'a> OnMoveHandler<'a> {
         };
 
         if let Some(syntax) = crate::stdio_server::vim::syntax_for(path.as_ref()) {
-            self.send_response(json!({ "lines": lines, "syntax": syntax }));
+            Ok(json!({ "lines": lines, "syntax": syntax }))
         } else {
-            self.send_response(json!({ "lines": lines, "fname": fname }));
+            Ok(json!({ "lines": lines, "fname": fname }))
         }
+    }
 
-        Ok(())
+    /// Previews the matched line of `blines` followed by `context_after` lines.
+    ///
+    /// The lines are read from the buffer content supplied on creating the session, the file on
+    /// disk is read only if the content is absent.
+    fn preview_blines_with_context_after(
+        &self,
+        position: &Position,
+        context_after: usize,
+    ) -> std::io::Result<Value> {
+        let Position { path, lnum } = position;
+
+        let PreviewInfo {
+            lines,
+            highlight_lnum,
+            ..
+        } = match self.context.buffer_lines {
+            Some(ref buffer_lines) => {
+                blines_context_after(buffer_lines.iter().cloned(), *lnum, context_after)
+            }
+            None => blines_context_after(
+                utility::read_first_lines(path, lnum + context_after)?,
+                *lnum,
+                context_after,
+            ),
+        };
+
+        let lines = std::iter::once(format!("{}:{lnum}", path.display()))
+            .chain(self.truncate_preview_lines(lines.into_iter()))
+            .collect::<Vec<_>>();
+
+        if let Some(syntax) = crate::stdio_server::vim::syntax_for(path) {
+            Ok(json!({ "lines": lines, "syntax": syntax, "hi_lnum": highlight_lnum }))
+        } else {
+            let fname = path.display().to_string();
+            Ok(json!({ "lines": lines, "fname": fname, "hi_lnum": highlight_lnum }))
+        }
     }
 
-    async fn preview_file_at(&self, position: &Position) {
+    async fn preview_file_at(&self, position: &Position) -> Value {
         tracing::debug!(?position, "Previewing file");
 
         let Position { path, lnum } = position;
 
+        if let Some(placeholder) = self.too_large_file_placeholder(path) {
+            return placeholder;
+        }
+
         let container_width = self.context.display_winwidth as usize;
         let fname = path.display().to_string();
 
@@ -312,7 +571,7 @@ impl<'a> OnMoveHandler<'a> {
             }
         };
 
-        match utility::read_preview_lines(path, *lnum, self.size) {
+        match read_preview_lines(path, *lnum, self.size, self.context.max_preview_file_size) {
             Ok(PreviewInfo {
                 lines,
                 highlight_lnum,
@@ -320,11 +579,19 @@ impl<'a> OnMoveHandler<'a> {
                 ..
             }) => {
                 let mut context_lines = Vec::new();
+                let mut hi_span = None;
 
                 // Some checks against the latest preview line.
                 if let Some(latest_line) = lines.get(highlight_lnum - 1) {
                     self.try_refresh_cache(latest_line);
 
+                    // The span beyond the truncated preview line is useless.
+                    hi_span = self
+                        .grep_match
+                        .as_ref()
+                        .and_then(|grep_match| grep_match.span_in(latest_line))
+                        .filter(|(_start, end)| *end <= self.max_line_width());
+
                     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
                         const BLACK_LIST: &[&str] =
                             &["log", "txt", "lock", "toml", "yaml", "mod", "conf"];
@@ -385,15 +652,17 @@ impl<'a> OnMoveHandler<'a> {
                     "<== message(out) preview file content",
                 );
 
-                if let Some(syntax) = crate::stdio_server::vim::syntax_for(path) {
-                    self.send_response(
-                        json!({ "lines": lines, "syntax": syntax, "hi_lnum": highlight_lnum }),
-                    );
+                let mut result = if let Some(syntax) = crate::stdio_server::vim::syntax_for(path) {
+                    json!({ "lines": lines, "syntax": syntax, "hi_lnum": highlight_lnum })
                 } else {
-                    self.send_response(
-                        json!({ "lines": lines, "fname": fname, "hi_lnum": highlight_lnum }),
-                    );
+                    json!({ "lines": lines, "fname": fname, "hi_lnum": highlight_lnum })
+                };
+
+                if let Some((start, end)) = hi_span {
+                    result["hi_span"] = json!([start, end]);
                 }
+
+                result
             }
             Err(err) => {
                 tracing::error!(
@@ -407,7 +676,7 @@ impl<'a> OnMoveHandler<'a> {
                     header_line,
                     format!("Error while previewing the file: {err}"),
                 ];
-                self.send_response(json!({ "lines": lines, "fname": fname }));
+                json!({ "lines": lines, "fname": fname })
             }
         }
     }
@@ -443,9 +712,10 @@ impl<'a> OnMoveHandler<'a> {
         }
     }
 
-    fn send_response(&self, result: serde_json::value::Value) {
+    async fn send_response(&self, result: serde_json::value::Value) {
         let provider_id = &self.context.provider_id;
-        write_response(json!({ "id": self.msg_id, "provider_id": provider_id, "result": result }));
+        write_response(json!({ "id": self.msg_id, "provider_id": provider_id, "result": result }))
+            .await;
     }
 
     /// Truncates the lines that are awfully long as vim might have some performence issue with
@@ -466,6 +736,26 @@ impl<'a> OnMoveHandler<'a> {
     }
 }
 
+/// Returns the line at `lnum` (1-based) followed by at most `context_after` lines of the buffer,
+/// the highlight line points to the line at `lnum`.
+fn blines_context_after(
+    buffer_lines: impl Iterator<Item = String>,
+    lnum: usize,
+    context_after: usize,
+) -> PreviewInfo {
+    let start = lnum.saturating_sub(1);
+    let lines = buffer_lines
+        .skip(start)
+        .take(context_after + 1)
+        .collect::<Vec<_>>();
+    PreviewInfo {
+        start,
+        end: start + lines.len(),
+        highlight_lnum: 1,
+        lines,
+    }
+}
+
 async fn context_tag_with_timeout(path: PathBuf, lnum: usize) -> Option<BufferTag> {
     const TIMEOUT: Duration = Duration::from_millis(300);
 
@@ -477,3 +767,176 @@ async fn context_tag_with_timeout(path: PathBuf, lnum: usize) -> Option<BufferTa
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grep_match_span() {
+        let grep_line = "src/lib.rs:10:9:let x = foo_bar(1);";
+
+        let grep_match = GrepMatch::from_grep_line(grep_line, None).unwrap();
+        assert_eq!(
+            grep_match,
+            GrepMatch {
+                col: 9,
+                text: "foo_bar".into()
+            }
+        );
+        assert_eq!(grep_match.span_in("let x = foo_bar(1);"), Some((8, 15)));
+
+        let grep_match = GrepMatch::from_grep_line(grep_line, Some("bar")).unwrap();
+        assert_eq!(grep_match.span_in("let x = foo_bar(1);"), Some((12, 15)));
+
+        // The file has been changed since searching.
+        let grep_match = GrepMatch::from_grep_line(grep_line, None).unwrap();
+        assert_eq!(
+            grep_match.span_in("    let x = foo_bar(1);"),
+            Some((12, 19))
+        );
+        assert_eq!(grep_match.span_in("let x = baz(1);"), None);
+    }
+
+    #[test]
+    fn test_preview_first_query_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.rs");
+        let content = (1..=30)
+            .map(|i| match i {
+                12 => "    let needle = Needle::new();".to_string(),
+                20 => "    needle.find();".to_string(),
+                _ => format!("// line {i}"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(&path, content).unwrap();
+
+        let (lnum, grep_match) = find_first_match(&path, "needle").unwrap();
+        assert_eq!(lnum, 12);
+        assert_eq!(
+            grep_match.span_in("    let needle = Needle::new();"),
+            Some((8, 14))
+        );
+
+        // Smart case.
+        let (lnum, grep_match) = find_first_match(&path, "Needle").unwrap();
+        assert_eq!(lnum, 12);
+        assert_eq!(grep_match.col, 18);
+        assert!(find_first_match(&path, "haystack").is_none());
+
+        let on_move_msg = |query: &str| -> MethodCall {
+            serde_json::from_value(json!({
+                "id": 1,
+                "method": "files/on_move",
+                "params": { "lnum": 1, "query": query },
+                "session_id": 0,
+            }))
+            .unwrap()
+        };
+        let context = SessionContext::new_test_context("files");
+        let curline = path.display().to_string();
+
+        let handler =
+            OnMoveHandler::create(&on_move_msg("needle"), &context, Some(curline.clone())).unwrap();
+        assert!(matches!(
+            handler.inner,
+            OnMove::Grep(Position { lnum: 12, .. })
+        ));
+        let preview = utility::read_preview_lines(&path, 12, handler.size).unwrap();
+        assert_eq!(
+            preview.lines[preview.highlight_lnum - 1],
+            "    let needle = Needle::new();"
+        );
+
+        // Fall back to the top of file.
+        let handler =
+            OnMoveHandler::create(&on_move_msg("haystack"), &context, Some(curline)).unwrap();
+        assert!(matches!(handler.inner, OnMove::Files(_)));
+    }
+
+    #[test]
+    fn test_blines_context_after() {
+        let buffer_lines = (1..=10).map(|i| format!("line {i}")).collect::<Vec<_>>();
+
+        let preview = blines_context_after(buffer_lines.clone().into_iter(), 3, 2);
+        assert_eq!(preview.lines, vec!["line 3", "line 4", "line 5"]);
+        assert_eq!(preview.lines[preview.highlight_lnum - 1], "line 3");
+        assert_eq!((preview.start, preview.end), (2, 5));
+
+        // Clamp at the end of buffer.
+        let preview = blines_context_after(buffer_lines.into_iter(), 9, 5);
+        assert_eq!(preview.lines, vec!["line 9", "line 10"]);
+        assert_eq!(preview.lines[preview.highlight_lnum - 1], "line 9");
+    }
+
+    #[tokio::test]
+    async fn test_blines_preview_reads_buffer_lines() {
+        let on_move_msg: MethodCall = serde_json::from_value(json!({
+            "id": 1,
+            "method": "on_move",
+            "params": { "lnum": 1, "context_after": 2 },
+            "session_id": 0,
+        }))
+        .unwrap();
+
+        // The buffer is not saved to the disk.
+        let dir = tempfile::tempdir().unwrap();
+        let mut context = SessionContext::new_test_context("blines");
+        context.start_buffer_path = dir.path().join("unsaved_buffer.txt");
+        context.buffer_lines = Some(Arc::new((1..=10).map(|i| format!("unsaved {i}")).collect()));
+
+        let handler =
+            OnMoveHandler::create(&on_move_msg, &context, Some("   3 unsaved 3".into())).unwrap();
+        let result = handler.preview().await.unwrap().unwrap();
+        assert_eq!(
+            result["lines"],
+            json!([
+                format!("{}:3", context.start_buffer_path.display()),
+                "unsaved 3",
+                "unsaved 4",
+                "unsaved 5"
+            ])
+        );
+        assert_eq!(result["hi_lnum"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_preview_file_size_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.log");
+        let content = (1..=10_000)
+            .map(|i| format!("log line {i}\n"))
+            .collect::<String>();
+        std::fs::write(&path, &content).unwrap();
+
+        // Seek from the line index beyond the soft limit.
+        let loaded = read_preview_lines(&path, 5000, 10, u64::MAX).unwrap();
+        let indexed = read_preview_lines(&path, 5000, 10, 1024).unwrap();
+        assert_eq!(indexed.lines, loaded.lines);
+        assert_eq!(indexed.highlight_lnum, loaded.highlight_lnum);
+        assert_eq!(indexed.lines[indexed.highlight_lnum - 1], "log line 5000");
+
+        let on_move_msg: MethodCall = serde_json::from_value(json!({
+            "id": 1,
+            "method": "grep/on_move",
+            "params": { "lnum": 1 },
+            "session_id": 0,
+        }))
+        .unwrap();
+        let curline = format!("{}:5000:1:log line 5000", path.display());
+        let mut context = SessionContext::new_test_context("grep");
+        context.preview_file_size_cap = content.len() as u64 - 1;
+
+        // Placeholder past the hard cap.
+        let handler = OnMoveHandler::create(&on_move_msg, &context, Some(curline)).unwrap();
+        let result = handler.preview().await.unwrap().unwrap();
+        assert_eq!(
+            result["lines"],
+            json!([
+                path.display().to_string(),
+                format!("File too large to preview ({} bytes)", content.len())
+            ])
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Provider for searching the source files of the crates downloaded from the cargo registry.
+//!
+//! - `cargo_deps/on_init`: the files under `registry` (`~/.cargo/registry/src` by default) are
+//!   listed. If `Cargo.lock` is found in the cwd, only the crates locked by it are listed unless
+//!   the param `all` is true.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use types::{ClapItem, MatchedItem, SourceItem};
+
+use crate::command::files::WalkOptions;
+use crate::stdio_server::impls::OnMoveHandler;
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// Returns the directory of the extracted crate sources, `$CARGO_HOME/registry/src`.
+fn default_registry_src() -> PathBuf {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| crate::utils::HOME_DIR.join(".cargo"))
+        .join("registry")
+        .join("src")
+}
+
+/// Returns the directory names `{name}-{version}` of the registry crates locked in `cargo_lock`.
+fn locked_crates(cargo_lock: &Path) -> Result<HashSet<String>> {
+    #[derive(Deserialize)]
+    struct LockedPackage {
+        name: String,
+        version: String,
+        source: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct CargoLock {
+        #[serde(default)]
+        package: Vec<LockedPackage>,
+    }
+
+    let cargo_lock: CargoLock = toml::from_str(&std::fs::read_to_string(cargo_lock)?)?;
+
+    Ok(cargo_lock
+        .package
+        .into_iter()
+        // The path and git dependencies are not in the registry.
+        .filter(|package| {
+            package.source.as_deref().is_some_and(|source| {
+                source.starts_with("registry+") || source.starts_with("sparse+")
+            })
+        })
+        .map(|package| format!("{}-{}", package.name, package.version))
+        .collect())
+}
+
+/// Returns the source directories of crates in `registry_src`, restricted to `locked` if any.
+///
+/// The layout of `registry_src` is `{registry_src}/{index}/{name}-{version}`.
+fn crate_dirs(registry_src: &Path, locked: Option<&HashSet<String>>) -> Vec<PathBuf> {
+    let read_subdirs = |dir: &Path| {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>()
+    };
+
+    let mut crate_dirs = read_subdirs(registry_src)
+        .iter()
+        .flat_map(|index_dir| read_subdirs(index_dir))
+        .filter(|crate_dir| {
+            locked.is_none_or(|locked| {
+                crate_dir
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| locked.contains(name))
+            })
+        })
+        .collect::<Vec<_>>();
+    crate_dirs.sort();
+    crate_dirs
+}
+
+/// Returns the absolute paths of the files in `crate_dirs`.
+fn crate_files(crate_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    crate_dirs
+        .iter()
+        .flat_map(|crate_dir| {
+            WalkOptions::with_default_skip_dirs()
+                .walk(crate_dir)
+                .map(move |path| crate_dir.join(path))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct CargoDepsProvider {
+    context: SessionContext,
+    items: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl CargoDepsProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    /// `lnum` is 1-based.
+    fn line_at(&self, lnum: usize) -> Option<String> {
+        let idx = lnum.checked_sub(1)?;
+        let current_results = self.current_results.lock();
+        if current_results.is_empty() {
+            self.items.get(idx).map(|item| item.raw_text().to_string())
+        } else {
+            current_results
+                .get(idx)
+                .map(|r| r.item.raw_text().to_string())
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for CargoDepsProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            registry: Option<PathBuf>,
+            all: Option<bool>,
+        }
+
+        let Params { registry, all } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        let registry_src = registry.unwrap_or_else(default_registry_src);
+        if !registry_src.is_dir() {
+            tracing::debug!(?registry_src, "The cargo registry does not exist");
+        }
+
+        let cargo_lock = self.context.cwd.join("Cargo.lock");
+        let locked = if all.unwrap_or(false) || !cargo_lock.is_file() {
+            None
+        } else {
+            match locked_crates(&cargo_lock) {
+                Ok(locked) => Some(locked),
+                Err(e) => {
+                    tracing::error!(error = ?e, ?cargo_lock, "Failed to parse Cargo.lock");
+                    None
+                }
+            }
+        };
+
+        let crate_dirs = crate_dirs(&registry_src, locked.as_ref());
+        self.items = tokio::task::spawn_blocking(move || crate_files(&crate_dirs))
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|path| Arc::new(SourceItem::from(path.display().to_string())) as Arc<dyn ClapItem>)
+            .collect();
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let msg_id = msg.id;
+
+        let curline = msg
+            .get_u64("lnum")
+            .ok()
+            .and_then(|lnum| self.line_at(lnum as usize));
+
+        let on_move_handler = OnMoveHandler::create(&msg, &self.context, curline)?;
+        if let Err(error) = on_move_handler.handle().await {
+            tracing::error!(?error, "Failed to handle OnMove event");
+            write_response(json!({"error": error.to_string(), "id": msg_id })).await;
+        }
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let query = msg.get_query();
+
+        let matched_items = filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        );
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locked_crate_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let registry_src = dir.join("registry/src");
+        let index_dir = registry_src.join("github.com-1ecc6299db9ec823");
+        for krate in ["serde-1.0.145", "serde-1.0.100", "anyhow-1.0.65"] {
+            std::fs::create_dir_all(index_dir.join(krate).join("src")).unwrap();
+            std::fs::write(index_dir.join(krate).join("src/lib.rs"), "").unwrap();
+        }
+
+        let cargo_lock = dir.join("Cargo.lock");
+        std::fs::write(
+            &cargo_lock,
+            r#"
+version = 3
+
+[[package]]
+name = "maple"
+version = "0.1.37"
+
+[[package]]
+name = "serde"
+version = "1.0.145"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "subprocess"
+version = "0.2.9"
+source = "git+https://github.com/hniksic/rust-subprocess#1234"
+"#,
+        )
+        .unwrap();
+
+        let locked = locked_crates(&cargo_lock).unwrap();
+        assert_eq!(locked, HashSet::from(["serde-1.0.145".to_string()]));
+
+        assert_eq!(
+            crate_files(&crate_dirs(&registry_src, Some(&locked))),
+            vec![index_dir.join("serde-1.0.145/src/lib.rs")]
+        );
+        assert_eq!(crate_files(&crate_dirs(&registry_src, None)).len(), 3);
+
+        // The missing registry is just empty.
+        assert!(crate_dirs(&dir.join("nonexistent"), Some(&locked)).is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+//! Provider for previewing and switching the colorschemes.
+//!
+//! RPC contract of live preview:
+//!
+//! - `colors/on_init`: the available colorschemes can be supplied via the param `colorschemes`,
+//!   otherwise they are collected from the `colors` directories in `runtimepath`.
+//! - `colors/on_move`: responds with `{ "colorscheme": name }`, the editor applies it for
+//!   the live preview.
+//! - The editor reverts to the original colorscheme on terminate unless one is selected,
+//!   selecting a colorscheme commits it.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use types::{ClapItem, MatchedItem};
+
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// Returns the sorted and deduplicated names of colorschemes found in `runtimepath`.
+fn colorschemes_in_runtimepath(runtimepath: &str) -> Vec<String> {
+    let mut colorschemes = runtimepath
+        .split(',')
+        .filter_map(|dir| std::fs::read_dir(Path::new(dir).join("colors")).ok())
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("vim") | Some("lua") => {
+                    path.file_stem().and_then(|s| s.to_str()).map(Into::into)
+                }
+                _ => None,
+            }
+        })
+        .collect::<Vec<String>>();
+    colorschemes.sort();
+    colorschemes.dedup();
+    colorschemes
+}
+
+#[derive(Debug)]
+pub struct ColorsProvider {
+    context: SessionContext,
+    colorschemes: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl ColorsProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            colorschemes: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    fn set_colorschemes(&mut self, colorschemes: Vec<String>) {
+        self.colorschemes = colorschemes
+            .into_iter()
+            .map(|name| Arc::new(name) as Arc<dyn ClapItem>)
+            .collect();
+    }
+
+    /// Returns the colorscheme at `lnum` (1-based) in the display window.
+    fn colorscheme_at(&self, lnum: usize) -> Option<String> {
+        let current_results = self.current_results.lock();
+        if current_results.is_empty() {
+            self.colorschemes
+                .get(lnum.checked_sub(1)?)
+                .map(|item| item.raw_text().to_string())
+        } else {
+            current_results
+                .get(lnum.checked_sub(1)?)
+                .map(|r| r.item.raw_text().to_string())
+        }
+    }
+
+    fn filter(&self, query: String) -> Vec<MatchedItem> {
+        filter::par_filter_items(
+            &self.colorschemes,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for ColorsProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            colorschemes: Option<Vec<String>>,
+        }
+
+        let Params { colorschemes } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        let colorschemes = colorschemes.unwrap_or_else(|| {
+            self.context
+                .runtimepath
+                .as_deref()
+                .map(colorschemes_in_runtimepath)
+                .unwrap_or_default()
+        });
+        self.set_colorschemes(colorschemes);
+
+        let total = self.colorschemes.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .colorschemes
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let colorscheme = self
+            .colorscheme_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No colorscheme at line {lnum}"))?;
+
+        let provider_id = &self.context.provider_id;
+        write_response(json!({
+            "id": msg.id,
+            "provider_id": provider_id,
+            "result": { "colorscheme": colorscheme },
+        }))
+        .await;
+
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = self.filter(msg.get_query());
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorschemes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for (plugin, scheme) in [
+            ("a", "gruvbox.vim"),
+            ("a", "README.md"),
+            ("b", "tokyonight.lua"),
+            ("c", "gruvbox.vim"),
+        ] {
+            let colors_dir = dir.join(plugin).join("colors");
+            std::fs::create_dir_all(&colors_dir).unwrap();
+            std::fs::write(colors_dir.join(scheme), "").unwrap();
+        }
+
+        let runtimepath = ["a", "b", "c", "nonexistent"]
+            .iter()
+            .map(|plugin| dir.join(plugin).display().to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            colorschemes_in_runtimepath(&runtimepath),
+            vec!["gruvbox", "tokyonight"]
+        );
+
+        let mut provider = ColorsProvider::new(SessionContext::new_test_context("colors"));
+        provider.set_colorschemes(vec![
+            "desert".into(),
+            "gruvbox".into(),
+            "gruvbox-material".into(),
+            "tokyonight".into(),
+        ]);
+        assert_eq!(provider.colorscheme_at(2), Some("gruvbox".into()));
+
+        let matched_items = provider.filter("gruv".into());
+        let mut matched = matched_items
+            .iter()
+            .map(|r| r.item.raw_text().to_string())
+            .collect::<Vec<_>>();
+        let second = matched[1].clone();
+        matched.sort();
+        assert_eq!(matched, vec!["gruvbox", "gruvbox-material"]);
+
+        *provider.current_results.lock() = matched_items;
+        assert_eq!(provider.colorscheme_at(2), Some(second));
+        assert_eq!(provider.colorscheme_at(3), None);
+    }
+}
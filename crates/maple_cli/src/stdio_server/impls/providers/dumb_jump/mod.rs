@@ -1,6 +1,7 @@
 mod searcher;
 
 use std::ops::Deref;
+use std::path::Path;
 use std::process::Output;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -21,7 +22,7 @@ use crate::paths::AbsPathBuf;
 use crate::stdio_server::impls::OnMoveHandler;
 use crate::stdio_server::rpc::Call;
 use crate::stdio_server::session::{
-    note_job_is_finished, register_job_successfully, ClapProvider, SessionContext,
+    note_job_is_finished, register_job_successfully, ClapProvider, JumpTarget, SessionContext,
 };
 use crate::stdio_server::{write_response, MethodCall};
 use crate::tools::ctags::{get_language, TagsGenerator, CTAGS_EXISTS};
@@ -191,7 +192,7 @@ async fn search_for_usages(
         }
     };
 
-    write_response(response);
+    write_response(response).await;
 
     SearchResults {
         usages,
@@ -200,6 +201,31 @@ async fn search_for_usages(
     }
 }
 
+/// Returns the jump targets of all the usages of the same kind as the `selected` one, e.g., all
+/// the definitions of the keyword if a definition is selected.
+fn jump_targets(usages: &Usages, selected: usize, cwd: &Path) -> Vec<JumpTarget> {
+    let selected_kind = match usages
+        .get_line(selected)
+        .and_then(pattern::extract_jump_line_info)
+    {
+        Some((kind, ..)) => kind,
+        None => return Vec::new(),
+    };
+
+    usages
+        .iter()
+        .filter_map(|usage| {
+            let (kind, fpath, lnum, col) = pattern::extract_jump_line_info(&usage.line)?;
+            (kind == selected_kind).then(|| JumpTarget {
+                path: cwd.join(fpath),
+                lnum,
+                col,
+            })
+        })
+        .unique()
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct DumbJumpProvider {
     context: SessionContext,
@@ -254,6 +280,14 @@ impl ClapProvider for DumbJumpProvider {
         &self.context
     }
 
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    fn multiple_targets(&self) -> bool {
+        true
+    }
+
     async fn on_create(&mut self, call: Call) {
         let (msg_id, params) = parse_msg(call.unwrap_method_call());
 
@@ -361,13 +395,34 @@ impl ClapProvider for DumbJumpProvider {
             let on_move_handler = OnMoveHandler::create(&msg, &self.context, Some(curline.into()))?;
             if let Err(error) = on_move_handler.handle().await {
                 tracing::error!(?error, "Failed to handle OnMove event");
-                write_response(json!({"error": error.to_string(), "id": msg_id }));
+                write_response(json!({"error": error.to_string(), "id": msg_id })).await;
             }
         }
 
         Ok(())
     }
 
+    async fn on_select(&mut self, msg: MethodCall) -> Result<()> {
+        let lnum = msg
+            .get_u64("lnum")
+            .map_err(|_| anyhow!("Missing `lnum` in {:?}", msg))?;
+
+        let usages = self
+            .current_usages
+            .as_ref()
+            .unwrap_or(&self.cached_results.usages);
+        let targets = jump_targets(usages, (lnum - 1) as usize, &self.context.cwd);
+
+        write_response(json!({
+            "id": msg.id,
+            "provider_id": "dumb_jump",
+            "result": { "targets": targets },
+        }))
+        .await;
+
+        Ok(())
+    }
+
     async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
         /*
         // TODO: early initialization
@@ -409,7 +464,7 @@ impl ClapProvider for DumbJumpProvider {
                 "force_execute": true,
                 "result": { "lines": lines, "indices": indices, "total": total },
             });
-            write_response(response);
+            write_response(response).await;
             self.current_usages.replace(refiltered.into());
             return Ok(());
         }
@@ -430,4 +485,31 @@ mod tests {
         let query_info = parse_query_info("'foo");
         println!("{:?}", query_info);
     }
+
+    #[test]
+    fn test_jump_targets_of_ambiguous_definition() {
+        let usages: Usages = vec![
+            Usage::new("[fn]src/a.rs:3:4:fn foo() {}".into(), vec![]),
+            Usage::new("[refs]src/b.rs:10:5:    foo();".into(), vec![]),
+            Usage::new("[fn]src/c.rs:7:8:    fn foo(x: u8) {}".into(), vec![]),
+        ]
+        .into();
+
+        let cwd = Path::new("/project");
+        let target = |path: &str, lnum, col| JumpTarget {
+            path: cwd.join(path),
+            lnum,
+            col,
+        };
+
+        let expected = vec![target("src/a.rs", 3, 4), target("src/c.rs", 7, 8)];
+        assert_eq!(jump_targets(&usages, 0, cwd), expected);
+        assert_eq!(jump_targets(&usages, 2, cwd), expected);
+
+        assert_eq!(
+            jump_targets(&usages, 1, cwd),
+            vec![target("src/b.rs", 10, 5)]
+        );
+        assert!(jump_targets(&usages, 3, cwd).is_empty());
+    }
 }
@@ -0,0 +1,227 @@
+//! Provider for searching the environment variables.
+//!
+//! - `env_vars/on_init`: the values of the sensitive variables, e.g., `GITHUB_TOKEN`, are masked
+//!   in the display unless the param `show_secrets` is true. The masked values can not be
+//!   searched either.
+//! - `env_vars/on_move`: responds with `{ "name": name, "value": value }`, the actual value is
+//!   for being inserted on selection.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use types::{ClapItem, MatchedItem};
+
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// Variables whose name contains one of these words are considered as sensitive.
+const SENSITIVE_WORDS: &[&str] = &["TOKEN", "SECRET", "KEY", "PASSWORD"];
+
+/// Displayed in place of the sensitive value, regardless of its length.
+const MASK: &str = "********";
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_uppercase();
+    SENSITIVE_WORDS.iter().any(|word| name.contains(word))
+}
+
+/// Returns the line `name=value` for display, masking the sensitive value unless `show_secrets`.
+fn display_env_var(name: &str, value: &str, show_secrets: bool) -> String {
+    if !show_secrets && is_sensitive(name) {
+        format!("{name}={MASK}")
+    } else {
+        format!("{name}={value}")
+    }
+}
+
+#[derive(Debug)]
+pub struct EnvVarsProvider {
+    context: SessionContext,
+    /// Sorted (name, value) pairs.
+    env_vars: Vec<(String, String)>,
+    items: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl EnvVarsProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            env_vars: Vec::new(),
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    fn set_env_vars(
+        &mut self,
+        env_vars: impl Iterator<Item = (String, String)>,
+        show_secrets: bool,
+    ) {
+        let mut env_vars = env_vars.collect::<Vec<_>>();
+        env_vars.sort();
+        self.items = env_vars
+            .iter()
+            .map(|(name, value)| {
+                Arc::new(display_env_var(name, value, show_secrets)) as Arc<dyn ClapItem>
+            })
+            .collect();
+        self.env_vars = env_vars;
+    }
+
+    /// Returns the variable displayed at `lnum`, which is 1-based.
+    fn env_var_at(&self, lnum: usize) -> Option<&(String, String)> {
+        let current_results = self.current_results.lock();
+        let line = if current_results.is_empty() {
+            self.items.get(lnum.checked_sub(1)?)?.raw_text().to_string()
+        } else {
+            current_results
+                .get(lnum.checked_sub(1)?)?
+                .item
+                .raw_text()
+                .to_string()
+        };
+        let (name, _) = line.split_once('=')?;
+        self.env_vars.iter().find(|(n, _)| n == name)
+    }
+
+    fn filter(&self, query: String) -> Vec<MatchedItem> {
+        filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for EnvVarsProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            show_secrets: bool,
+        }
+
+        let Params { show_secrets } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        self.set_env_vars(std::env::vars(), show_secrets);
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let (name, value) = self
+            .env_var_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No environment variable at line {lnum}"))?;
+
+        let provider_id = &self.context.provider_id;
+        write_response(json!({
+            "id": msg.id,
+            "provider_id": provider_id,
+            "result": { "name": name, "value": value },
+        }))
+        .await;
+
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = self.filter(msg.get_query());
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_vars() -> impl Iterator<Item = (String, String)> {
+        [
+            ("HOME", "/home/clap"),
+            ("GITHUB_TOKEN", "ghp_secret"),
+            ("aws_secret_access_key", "abc123"),
+            ("EDITOR", "vim"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+    }
+
+    fn displayed(provider: &EnvVarsProvider) -> Vec<&str> {
+        provider.items.iter().map(|item| item.raw_text()).collect()
+    }
+
+    #[test]
+    fn test_mask_secrets() {
+        let mut provider = EnvVarsProvider::new(SessionContext::new_test_context("env_vars"));
+
+        provider.set_env_vars(env_vars(), false);
+        assert_eq!(
+            displayed(&provider),
+            vec![
+                "EDITOR=vim",
+                "GITHUB_TOKEN=********",
+                "HOME=/home/clap",
+                "aws_secret_access_key=********",
+            ]
+        );
+        // The masked values are not searchable.
+        assert!(provider.filter("ghpsecret".into()).is_empty());
+        assert_eq!(provider.filter("home".into()).len(), 1);
+        // The actual value is returned on selection.
+        assert_eq!(
+            provider.env_var_at(2),
+            Some(&("GITHUB_TOKEN".to_string(), "ghp_secret".to_string()))
+        );
+
+        provider.set_env_vars(env_vars(), true);
+        assert_eq!(
+            displayed(&provider),
+            vec![
+                "EDITOR=vim",
+                "GITHUB_TOKEN=ghp_secret",
+                "HOME=/home/clap",
+                "aws_secret_access_key=abc123",
+            ]
+        );
+        assert_eq!(provider.filter("ghpsecret".into()).len(), 1);
+    }
+}
@@ -9,7 +9,7 @@ use icon::prepend_filer_icon;
 
 use crate::stdio_server::impls::{OnMove, OnMoveHandler};
 use crate::stdio_server::rpc::Call;
-use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::session::{ClapProvider, ProviderAction, SessionContext, FILE_ACTIONS};
 use crate::stdio_server::{write_response, MethodCall};
 use crate::utils::build_abs_path;
 
@@ -135,11 +135,22 @@ impl ClapProvider for FilerProvider {
         &self.context
     }
 
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    fn actions(&self) -> Vec<ProviderAction> {
+        let mut actions = FILE_ACTIONS.to_vec();
+        actions.push(ProviderAction::Cd);
+        actions
+    }
+
     async fn on_create(&mut self, call: Call) {
         write_response(
             handle_filer_message(call.unwrap_method_call())
                 .expect("Both Success and Error are returned"),
-        );
+        )
+        .await;
     }
 
     async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
@@ -159,6 +170,7 @@ impl ClapProvider for FilerProvider {
             context: &self.context,
             inner: OnMove::Filer(path.clone()),
             cache_line: None,
+            grep_match: None,
         };
         if let Err(err) = on_move_handler.handle().await {
             tracing::error!(?err, ?path, "Failed to handle filer OnMove");
@@ -167,13 +179,14 @@ impl ClapProvider for FilerProvider {
               "provider_id": "filer",
               "error": { "message": err.to_string(), "dir": path }
             });
-            write_response(res);
+            write_response(res).await;
         }
         Ok(())
     }
 
     async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
-        write_response(handle_filer_message(msg).expect("Both Success and Error are returned"));
+        write_response(handle_filer_message(msg).expect("Both Success and Error are returned"))
+            .await;
         Ok(())
     }
 }
@@ -0,0 +1,355 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use matcher::MatchScope;
+use types::{ClapItem, FuzzyText, MatchedItem};
+
+use crate::process::tokio::TokioCommand;
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// A hunk of the uncommitted changes, matched by the file path.
+#[derive(Debug)]
+struct DiffHunk {
+    path: String,
+    /// Line number of the first changed line in the new file, 1-based.
+    lnum: usize,
+    /// Hunk header, e.g., `@@ -1,3 +1,4 @@ fn main() {`.
+    header: String,
+    /// Lines of the hunk body, prefixed with ` `, `+` or `-`.
+    lines: Vec<String>,
+    /// Displayed text, e.g., `src/main.rs:2: +1 -0 fn main() {`.
+    text: String,
+}
+
+impl DiffHunk {
+    fn new(path: String, header: String, start: usize) -> Self {
+        Self {
+            path,
+            lnum: start,
+            header,
+            lines: Vec::new(),
+            text: String::new(),
+        }
+    }
+
+    /// Fills in the jump target and the displayed text once all the lines are collected.
+    fn finish(mut self) -> Self {
+        let leading_context = self
+            .lines
+            .iter()
+            .take_while(|line| line.starts_with(' '))
+            .count();
+        // The start line of a pure deletion is the line before the deleted ones, 0 if the
+        // deletion is at the beginning.
+        self.lnum = (self.lnum + leading_context).max(1);
+
+        let count = |prefix: char| {
+            self.lines
+                .iter()
+                .filter(|line| line.starts_with(prefix))
+                .count()
+        };
+        let (added, removed) = (count('+'), count('-'));
+        let context = self
+            .header
+            .splitn(3, "@@")
+            .nth(2)
+            .map(str::trim)
+            .unwrap_or_default();
+        self.text = format!("{}:{}: +{added} -{removed} {context}", self.path, self.lnum)
+            .trim_end()
+            .to_string();
+
+        self
+    }
+
+    /// Returns the header followed by the lines of hunk, at most `size` lines in total.
+    fn preview_lines(&self, size: usize) -> Vec<String> {
+        std::iter::once(&self.header)
+            .chain(self.lines.iter())
+            .take(size)
+            .cloned()
+            .collect()
+    }
+}
+
+impl ClapItem for DiffHunk {
+    fn raw_text(&self) -> &str {
+        &self.text
+    }
+
+    fn fuzzy_text(&self, _match_scope: MatchScope) -> Option<FuzzyText<'_>> {
+        Some(FuzzyText::new(&self.path, 0))
+    }
+}
+
+/// Returns the start line in the new file of the hunk header `@@ -a,b +c,d @@`.
+fn parse_hunk_start(header: &str) -> Option<usize> {
+    let new_range = header
+        .strip_prefix("@@ ")?
+        .split_whitespace()
+        .find_map(|range| range.strip_prefix('+'))?;
+    new_range.split(',').next()?.parse().ok()
+}
+
+/// Returns the hunks in the output of `git diff`.
+fn parse_diff_hunks(diff: &[String]) -> Vec<DiffHunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<DiffHunk> = None;
+    let mut path = String::new();
+    // The file header lines, e.g., `+++ b/src/main.rs`, precede the first hunk of each file.
+    let mut in_file_header = false;
+
+    for line in diff {
+        if line.starts_with("diff --git ") {
+            hunks.extend(current.take().map(DiffHunk::finish));
+            path.clear();
+            in_file_header = true;
+        } else if line.starts_with("@@ ") {
+            hunks.extend(current.take().map(DiffHunk::finish));
+            in_file_header = false;
+            if let Some(start) = parse_hunk_start(line) {
+                current = Some(DiffHunk::new(path.clone(), line.clone(), start));
+            }
+        } else if in_file_header {
+            if let Some(old_path) = line.strip_prefix("--- a/") {
+                path = old_path.to_string();
+            } else if let Some(new_path) = line.strip_prefix("+++ b/") {
+                // The old path is kept for a deleted file, `+++ /dev/null`.
+                path = new_path.to_string();
+            }
+        } else if let Some(ref mut hunk) = current {
+            // Skip `\ No newline at end of file`.
+            if !line.starts_with('\\') {
+                hunk.lines.push(line.clone());
+            }
+        }
+    }
+    hunks.extend(current.take().map(DiffHunk::finish));
+
+    hunks
+}
+
+/// Returns the hunks of the unstaged changes, or the staged ones if `staged` is true.
+///
+/// The `a/` and `b/` prefixes expected by [`parse_diff_hunks`] are passed explicitly, which
+/// could be changed by the config `diff.noprefix` or `diff.mnemonicPrefix` otherwise.
+async fn list_diff_hunks(cwd: &Path, staged: bool) -> std::io::Result<Vec<DiffHunk>> {
+    let cmd = if staged {
+        "git diff --no-color --no-ext-diff --src-prefix=a/ --dst-prefix=b/ --cached"
+    } else {
+        "git diff --no-color --no-ext-diff --src-prefix=a/ --dst-prefix=b/"
+    };
+    let lines = TokioCommand::new(cmd).current_dir(cwd).lines().await?;
+    Ok(parse_diff_hunks(&lines))
+}
+
+#[derive(Debug)]
+pub struct GitHunksProvider {
+    context: SessionContext,
+    hunks: Vec<Arc<DiffHunk>>,
+    items: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl GitHunksProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            hunks: Vec::new(),
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    fn set_hunks(&mut self, hunks: Vec<DiffHunk>) {
+        self.hunks = hunks.into_iter().map(Arc::new).collect();
+        self.items = self
+            .hunks
+            .iter()
+            .map(|hunk| hunk.clone() as Arc<dyn ClapItem>)
+            .collect();
+    }
+
+    /// Returns the hunk at `lnum` (1-based).
+    fn hunk_at(&self, lnum: usize) -> Option<Arc<DiffHunk>> {
+        let current_results = self.current_results.lock();
+        let text = if current_results.is_empty() {
+            self.items.get(lnum.checked_sub(1)?)?.raw_text().to_string()
+        } else {
+            current_results
+                .get(lnum.checked_sub(1)?)?
+                .item
+                .raw_text()
+                .to_string()
+        };
+        self.hunks.iter().find(|hunk| hunk.text == text).cloned()
+    }
+
+    fn filter(&self, query: String) -> Vec<MatchedItem> {
+        filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for GitHunksProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            staged: bool,
+        }
+
+        let Params { staged } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        match list_diff_hunks(&self.context.cwd, staged).await {
+            Ok(hunks) => self.set_hunks(hunks),
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to list the git diff hunks");
+                return;
+            }
+        }
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let hunk = self
+            .hunk_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No git diff hunk at line {lnum}"))?;
+
+        let lines = hunk.preview_lines(self.context.sensible_preview_size());
+        write_response(json!({
+            "id": msg.id,
+            "provider_id": self.context.provider_id,
+            "result": { "lines": lines, "syntax": "diff", "fname": hunk.path, "lnum": hunk.lnum },
+        }))
+        .await;
+
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = self.filter(msg.get_query());
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn test_git_hunks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+
+        let lines = (1..=20).map(|i| format!("line {i}\n")).collect::<String>();
+        std::fs::write(dir.join("src/main.rs"), &lines).unwrap();
+        std::fs::write(dir.join("README.md"), "# title\n").unwrap();
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-q", "-m", "initial commit"]);
+
+        // Two hunks far apart in one file and a staged change in another.
+        std::fs::write(
+            dir.join("src/main.rs"),
+            lines
+                .replace("line 2\n", "line 2 changed\n")
+                .replace("line 18\n", ""),
+        )
+        .unwrap();
+        std::fs::write(dir.join("README.md"), "# title\n\nnew paragraph\n").unwrap();
+        git(&dir, &["add", "README.md"]);
+        // The prefixes of the user config are overridden.
+        git(&dir, &["config", "diff.noprefix", "true"]);
+
+        let unstaged = list_diff_hunks(&dir, false).await.unwrap();
+        let targets = unstaged
+            .iter()
+            .map(|hunk| (hunk.path.as_str(), hunk.lnum))
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec![("src/main.rs", 2), ("src/main.rs", 18)]);
+        assert_eq!(unstaged[0].text, "src/main.rs:2: +1 -1");
+
+        let preview = unstaged[0].preview_lines(100);
+        assert!(preview[0].starts_with("@@ -1,5 +1,5 @@"));
+        assert!(preview.contains(&"-line 2".to_string()));
+        assert!(preview.contains(&"+line 2 changed".to_string()));
+        assert_eq!(unstaged[0].preview_lines(2).len(), 2);
+
+        let staged = list_diff_hunks(&dir, true).await.unwrap();
+        assert_eq!(staged.len(), 1);
+        assert_eq!(staged[0].path, "README.md");
+        assert_eq!(staged[0].lnum, 2);
+
+        git(&dir, &["config", "diff.noprefix", "false"]);
+        git(&dir, &["config", "diff.mnemonicPrefix", "true"]);
+        let staged = list_diff_hunks(&dir, true).await.unwrap();
+        assert_eq!(staged[0].path, "README.md");
+
+        let mut provider = GitHunksProvider::new(SessionContext::new_test_context("git_hunks"));
+        provider.set_hunks(unstaged.into_iter().chain(staged).collect());
+        let matched_items = provider.filter("readme".into());
+        assert_eq!(matched_items.len(), 1);
+        *provider.current_results.lock() = matched_items;
+        assert_eq!(provider.hunk_at(1).unwrap().path, "README.md");
+    }
+}
@@ -0,0 +1,368 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde_json::json;
+
+use matcher::MatchResult;
+use types::{ClapItem, MatchedItem};
+
+use crate::process::tokio::TokioCommand;
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RefKind {
+    Local,
+    Remote,
+    Tag,
+    /// HEAD not pointing to any branch.
+    Detached,
+}
+
+impl RefKind {
+    fn glyph(&self) -> char {
+        match self {
+            Self::Local => '\u{e725}',
+            Self::Remote => '\u{f0c2}',
+            Self::Tag => '\u{f02b}',
+            Self::Detached => '\u{f417}',
+        }
+    }
+}
+
+/// A git ref matched by its short name, e.g., `master`, `origin/master`, `v0.1`.
+#[derive(Debug)]
+struct GitRefItem {
+    name: String,
+    /// Full name of the ref, e.g., `refs/heads/master`.
+    refname: String,
+    kind: RefKind,
+}
+
+impl GitRefItem {
+    fn from_refname(refname: &str) -> Option<Self> {
+        let (kind, name) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+            (RefKind::Local, name)
+        } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+            // `origin/HEAD` is merely a symbolic ref of the remote default branch.
+            if name.ends_with("/HEAD") {
+                return None;
+            }
+            (RefKind::Remote, name)
+        } else if let Some(name) = refname.strip_prefix("refs/tags/") {
+            (RefKind::Tag, name)
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            refname: refname.to_string(),
+            kind,
+        })
+    }
+
+    fn detached_head() -> Self {
+        Self {
+            name: "HEAD".into(),
+            refname: "HEAD".into(),
+            kind: RefKind::Detached,
+        }
+    }
+}
+
+impl ClapItem for GitRefItem {
+    fn raw_text(&self) -> &str {
+        &self.name
+    }
+
+    fn match_result_callback(&self, match_result: MatchResult) -> MatchResult {
+        let mut match_result = match_result;
+        let offset = self.kind.glyph().len_utf8() + 1;
+        match_result.indices.iter_mut().for_each(|x| *x += offset);
+        match_result
+    }
+
+    fn output_text(&self) -> Cow<'_, str> {
+        format!("{} {}", self.kind.glyph(), self.name).into()
+    }
+}
+
+/// Returns the branches, remote branches and tags of the repo at `cwd`.
+///
+/// A detached HEAD is listed first.
+async fn list_git_refs(cwd: &Path) -> std::io::Result<Vec<GitRefItem>> {
+    let lines = TokioCommand::new(
+        "git for-each-ref --format='%(refname)' refs/heads refs/remotes refs/tags",
+    )
+    .current_dir(cwd)
+    .lines()
+    .await?;
+
+    let mut git_refs = Vec::with_capacity(lines.len() + 1);
+
+    // Fails in an empty repo without any commit, HEAD is not detached in that case.
+    let head = TokioCommand::new("git rev-parse --abbrev-ref HEAD")
+        .current_dir(cwd)
+        .lines()
+        .await
+        .unwrap_or_default();
+    if head.first().map(|s| s.as_str()) == Some("HEAD") {
+        git_refs.push(GitRefItem::detached_head());
+    }
+
+    git_refs.extend(
+        lines
+            .iter()
+            .filter_map(|refname| GitRefItem::from_refname(refname)),
+    );
+
+    Ok(git_refs)
+}
+
+/// Returns the most recent `n` commits of `refname`.
+///
+/// `refname` is passed to git as an argument directly, no shell is involved.
+async fn recent_commits(cwd: &Path, refname: &str, n: usize) -> std::io::Result<Vec<String>> {
+    let mut std_cmd = std::process::Command::new("git");
+    std_cmd.args([
+        "log",
+        "--oneline",
+        "--no-color",
+        &format!("--max-count={n}"),
+        refname,
+        "--",
+    ]);
+    TokioCommand::from(std_cmd).current_dir(cwd).lines().await
+}
+
+#[derive(Debug)]
+pub struct GitRefsProvider {
+    context: SessionContext,
+    git_refs: Vec<Arc<GitRefItem>>,
+    items: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl GitRefsProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            git_refs: Vec::new(),
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    fn set_git_refs(&mut self, git_refs: Vec<GitRefItem>) {
+        self.git_refs = git_refs.into_iter().map(Arc::new).collect();
+        self.items = self
+            .git_refs
+            .iter()
+            .map(|git_ref| git_ref.clone() as Arc<dyn ClapItem>)
+            .collect();
+    }
+
+    /// Returns the full name of the ref at `lnum` (1-based).
+    fn refname_at(&self, lnum: usize) -> Option<String> {
+        let current_results = self.current_results.lock();
+        let name = if current_results.is_empty() {
+            self.items.get(lnum.checked_sub(1)?)?.raw_text().to_string()
+        } else {
+            current_results
+                .get(lnum.checked_sub(1)?)?
+                .item
+                .raw_text()
+                .to_string()
+        };
+        self.git_refs
+            .iter()
+            .find(|git_ref| git_ref.name == name)
+            .map(|git_ref| git_ref.refname.clone())
+    }
+
+    fn filter(&self, query: String) -> Vec<MatchedItem> {
+        filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for GitRefsProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, _call: Call) {
+        match list_git_refs(&self.context.cwd).await {
+            Ok(git_refs) => self.set_git_refs(git_refs),
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to list the git refs");
+                return;
+            }
+        }
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let refname = self
+            .refname_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No git ref at line {lnum}"))?;
+
+        let provider_id = &self.context.provider_id;
+        match recent_commits(
+            &self.context.cwd,
+            &refname,
+            self.context.sensible_preview_size(),
+        )
+        .await
+        {
+            Ok(lines) => {
+                write_response(json!({
+                    "id": msg.id,
+                    "provider_id": provider_id,
+                    "result": { "lines": lines, "syntax": "git" },
+                }))
+                .await
+            }
+            Err(error) => {
+                tracing::error!(?error, ?refname, "Failed to preview the git ref");
+                write_response(json!({ "error": error.to_string(), "id": msg.id })).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = self.filter(msg.get_query());
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn test_git_refs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("repo");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        git(&dir, &["init", "-q", "-b", "main"]);
+        git(
+            &dir,
+            &["commit", "-q", "--allow-empty", "-m", "initial commit"],
+        );
+        git(&dir, &["tag", "v0.1"]);
+        git(&dir, &["checkout", "-q", "-b", "feature-preview"]);
+        git(
+            &dir,
+            &["commit", "-q", "--allow-empty", "-m", "add the preview"],
+        );
+
+        let mut provider = GitRefsProvider::new(SessionContext::new_test_context("git_refs"));
+        provider.set_git_refs(list_git_refs(&dir).await.unwrap());
+        let names = provider
+            .git_refs
+            .iter()
+            .map(|git_ref| (git_ref.name.as_str(), git_ref.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("feature-preview", RefKind::Local),
+                ("main", RefKind::Local),
+                ("v0.1", RefKind::Tag)
+            ]
+        );
+
+        let matched_items = provider.filter("feat".into());
+        assert_eq!(matched_items.len(), 1);
+        assert_eq!(
+            matched_items[0].display_text(),
+            format!("{} feature-preview", RefKind::Local.glyph())
+        );
+        *provider.current_results.lock() = matched_items;
+
+        let refname = provider.refname_at(1).unwrap();
+        assert_eq!(refname, "refs/heads/feature-preview");
+        let commits = recent_commits(&dir, &refname, 5).await.unwrap();
+        assert_eq!(commits.len(), 2);
+        assert!(commits[0].ends_with("add the preview"));
+        assert!(commits[1].ends_with("initial commit"));
+
+        let bare_dir = tmp.path().join("bare.git");
+        git(
+            &dir,
+            &["clone", "-q", "--bare", ".", bare_dir.to_str().unwrap()],
+        );
+        assert_eq!(list_git_refs(&bare_dir).await.unwrap().len(), 3);
+
+        git(&dir, &["checkout", "-q", "--detach", "main"]);
+        let git_refs = list_git_refs(&dir).await.unwrap();
+        assert_eq!(git_refs[0].kind, RefKind::Detached);
+        assert_eq!(recent_commits(&dir, "HEAD", 5).await.unwrap().len(), 1);
+
+        // The ref name is not interpreted by a shell.
+        git(&dir, &["branch", "it's-$(touch${IFS}pwned)"]);
+        assert_eq!(
+            recent_commits(&dir, "refs/heads/it's-$(touch${IFS}pwned)", 5)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(!dir.join("pwned").exists());
+    }
+}
@@ -0,0 +1,272 @@
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+
+use matcher::{MatchResult, Matcher};
+use types::{ClapItem, MatchedItem};
+
+use crate::stdio_server::impls::{OnMove, OnMoveHandler, Position};
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// A loaded buffer in the editor.
+#[derive(Debug, Clone, Deserialize)]
+struct Buffer {
+    bufnr: usize,
+    /// Short name for display, must not contain the whitespace.
+    name: String,
+    path: PathBuf,
+    /// Content of the buffer if it's not the same as the file on disk.
+    #[serde(default)]
+    lines: Option<Vec<String>>,
+}
+
+impl Buffer {
+    /// Returns an iterator of the non-empty lines and their line numbers.
+    ///
+    /// The lines are read from the file lazily unless the buffer content is supplied.
+    fn lines(&self) -> Box<dyn Iterator<Item = (usize, String)> + '_> {
+        let lines: Box<dyn Iterator<Item = String> + '_> = match self.lines {
+            Some(ref lines) => Box::new(lines.iter().cloned()),
+            None => match std::fs::File::open(&self.path) {
+                Ok(file) => Box::new(std::io::BufReader::new(file).lines().filter_map(Result::ok)),
+                Err(error) => {
+                    tracing::debug!(?error, path = ?self.path, "Failed to read the buffer");
+                    Box::new(std::iter::empty())
+                }
+            },
+        };
+
+        Box::new(
+            lines
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(idx, line)| (idx + 1, line)),
+        )
+    }
+
+    fn items(&self) -> impl Iterator<Item = Arc<dyn ClapItem>> + '_ {
+        self.lines().map(|(lnum, line)| {
+            Arc::new(BufferLineItem {
+                prefix: format!("[{}] {} {lnum:>4} ", self.bufnr, self.name),
+                line,
+            }) as Arc<dyn ClapItem>
+        })
+    }
+}
+
+/// A line of buffer, displayed in the format of `[bufnr] bufname lnum line`.
+#[derive(Debug)]
+struct BufferLineItem {
+    prefix: String,
+    line: String,
+}
+
+impl ClapItem for BufferLineItem {
+    fn raw_text(&self) -> &str {
+        &self.line
+    }
+
+    fn match_result_callback(&self, match_result: MatchResult) -> MatchResult {
+        let mut match_result = match_result;
+        let offset = self.prefix.len();
+        match_result.indices.iter_mut().for_each(|x| *x += offset);
+        match_result
+    }
+
+    fn output_text(&self) -> Cow<'_, str> {
+        format!("{}{}", self.prefix, self.line).into()
+    }
+}
+
+/// Returns the tuple of (bufnr, lnum) from the displayed line.
+fn parse_bufnr_and_lnum(display_line: &str) -> Option<(usize, usize)> {
+    let mut iter = display_line.split_whitespace();
+    let bufnr = iter
+        .next()?
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .parse()
+        .ok()?;
+    let lnum = iter.nth(1)?.parse().ok()?;
+    Some((bufnr, lnum))
+}
+
+/// Filters the lines of all buffers, which are streamed instead of being held in memory.
+fn search_buffers(buffers: &[Buffer], matcher: &Matcher) -> Vec<MatchedItem> {
+    let mut matched_items = buffers
+        .par_iter()
+        .flat_map_iter(|buffer| buffer.items().filter_map(|item| matcher.match_item(item)))
+        .collect::<Vec<_>>();
+    matched_items.par_sort_by(|a, b| b.score.cmp(&a.score));
+    matched_items
+}
+
+#[derive(Debug)]
+pub struct LinesProvider {
+    context: SessionContext,
+    buffers: Vec<Buffer>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl LinesProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            buffers: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    /// Returns the position of the buffer line at `lnum` (1-based) in the display window.
+    fn position_at(&self, lnum: usize) -> Option<Position> {
+        let current_results = self.current_results.lock();
+        let display_line = current_results.get(lnum.checked_sub(1)?)?.display_text();
+        let (bufnr, lnum) = parse_bufnr_and_lnum(&display_line)?;
+        self.buffers
+            .iter()
+            .find(|buffer| buffer.bufnr == bufnr)
+            .map(|buffer| Position::new(buffer.path.clone(), lnum))
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for LinesProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            buffers: Vec<Buffer>,
+        }
+
+        let Params { buffers } = call.unwrap_method_call().parse().unwrap_or_default();
+        self.buffers = buffers;
+
+        let total: usize = self
+            .buffers
+            .par_iter()
+            .map(|buffer| buffer.lines().count())
+            .sum();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .buffers
+            .iter()
+            .flat_map(|buffer| buffer.items())
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item, Default::default(), Default::default()))
+            .collect::<Vec<_>>();
+        let display_lines = self.decorate_lines(initial_items.clone());
+        write_response(display_lines.session_create_message()).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = initial_items;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let msg_id = msg.id;
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let position = self
+            .position_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No buffer line at line {lnum}"))?;
+
+        let on_move_handler = OnMoveHandler {
+            msg_id,
+            size: self.context.sensible_preview_size(),
+            inner: OnMove::Grep(position),
+            context: &self.context,
+            cache_line: None,
+            grep_match: None,
+        };
+        if let Err(error) = on_move_handler.handle().await {
+            tracing::error!(?error, "Failed to handle OnMove event");
+            write_response(json!({"error": error.to_string(), "id": msg_id })).await;
+        }
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let query = msg.get_query();
+
+        let matched_items = search_buffers(
+            &self.buffers,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+        );
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_buffers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.rs");
+        std::fs::write(&path, "fn main() {\n\n    let foo = 1;\n}\n").unwrap();
+
+        let buffers = vec![
+            Buffer {
+                bufnr: 1,
+                name: "main.rs".into(),
+                path: path.clone(),
+                lines: None,
+            },
+            Buffer {
+                bufnr: 12,
+                name: "lib.rs".into(),
+                path: "/tmp/lib.rs".into(),
+                lines: Some(vec!["pub fn foo() {}".into(), "pub fn bar() {}".into()]),
+            },
+        ];
+
+        let matcher = matcher::MatcherBuilder::default().build("foo".into());
+        let mut matched = search_buffers(&buffers, &matcher)
+            .iter()
+            .map(|matched_item| {
+                let display_line = matched_item.display_text();
+                let (bufnr, lnum) = parse_bufnr_and_lnum(&display_line).unwrap();
+                (bufnr, lnum, display_line.into_owned())
+            })
+            .collect::<Vec<_>>();
+        matched.sort();
+
+        assert_eq!(
+            matched,
+            vec![
+                (1, 3, "[1] main.rs    3     let foo = 1;".to_string()),
+                (12, 1, "[12] lib.rs    1 pub fn foo() {}".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Provider for the entries of the location list, which is scoped to a window unlike the
+//! quickfix list.
+//!
+//! - `loclist/on_init`: the entries formatted as the quickfix lines `path|lnum col c| text` are
+//!   supplied by the param `entries`, the window owning the list by the param `winid`.
+//! - `loclist/on_move`: previews the entry the same way as the quickfix list, along with `winid`.
+//! - `clap#select`: responds with the jump target of the selected entry in the window `winid`.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use types::{ClapItem, MatchedItem};
+
+use crate::stdio_server::rpc::{preview_qf_entry, Call, QfEntryParams};
+use crate::stdio_server::session::{ClapProvider, JumpTarget, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// Returns the path, line number and column of the location list entry `line`.
+///
+/// The column is 1 if the entry has no column.
+fn parse_loc_entry(line: &str) -> Option<(&str, usize, usize)> {
+    let mut parts = line.split('|');
+    let path = parts.next().filter(|path| !path.is_empty())?;
+    let position = parts.next()?;
+    let (lnum, col) = match position.split_once("col") {
+        Some((lnum, col)) => (lnum, col.trim().parse().ok()?),
+        None => (position, 1),
+    };
+    Some((path, lnum.trim().parse().ok()?, col))
+}
+
+/// Jump target of a location list entry, in the window owning the list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocTarget {
+    pub winid: u64,
+    #[serde(flatten)]
+    pub target: JumpTarget,
+}
+
+#[derive(Debug)]
+pub struct LoclistProvider {
+    context: SessionContext,
+    items: Vec<Arc<dyn ClapItem>>,
+    /// Results of the last query, `None` if no query has been typed.
+    current_results: Arc<Mutex<Option<Vec<MatchedItem>>>>,
+}
+
+impl LoclistProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    fn set_entries(&mut self, entries: Vec<String>) {
+        self.items = entries
+            .into_iter()
+            .map(|entry| Arc::new(entry) as Arc<dyn ClapItem>)
+            .collect();
+    }
+
+    fn winid(&self) -> Result<u64> {
+        self.context
+            .winid
+            .ok_or_else(|| anyhow!("Missing `winid` of the location list"))
+    }
+
+    /// `lnum` is 1-based.
+    fn line_at(&self, lnum: usize) -> Option<String> {
+        let idx = lnum.checked_sub(1)?;
+        let current_results = self.current_results.lock();
+        match current_results.as_ref() {
+            Some(current_results) => current_results
+                .get(idx)
+                .map(|r| r.item.raw_text().to_string()),
+            None => self.items.get(idx).map(|item| item.raw_text().to_string()),
+        }
+    }
+
+    /// Returns the preview of the entry at `lnum`, `winid` is included for the UI to jump in
+    /// the window owning the list.
+    fn preview_at(&self, lnum: usize) -> Result<Value> {
+        let curline = self
+            .line_at(lnum)
+            .ok_or_else(|| anyhow!("No location list entry at line {lnum}"))?;
+        let mut result = preview_qf_entry(QfEntryParams {
+            cwd: self.context.cwd.to_string(),
+            curline,
+            winwidth: self.context.display_winwidth,
+            winheight: self.context.preview_winheight,
+        })?;
+        result["winid"] = self.winid()?.into();
+        Ok(result)
+    }
+
+    /// Returns the jump target of the entry at `lnum`.
+    fn jump_target_at(&self, lnum: usize) -> Result<LocTarget> {
+        let line = self
+            .line_at(lnum)
+            .ok_or_else(|| anyhow!("No location list entry at line {lnum}"))?;
+        let (path, lnum, col) =
+            parse_loc_entry(&line).ok_or_else(|| anyhow!("Invalid location list entry {line}"))?;
+        Ok(LocTarget {
+            winid: self.winid()?,
+            target: JumpTarget {
+                path: self.context.cwd.join(path),
+                lnum,
+                col,
+            },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for LoclistProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            entries: Vec<String>,
+        }
+
+        let Params { entries } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        self.set_entries(entries);
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let result = self.preview_at(msg.get_u64("lnum")? as usize)?;
+
+        let provider_id = &self.context.provider_id;
+        write_response(json!({ "id": msg.id, "provider_id": provider_id, "result": result })).await;
+
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = filter::par_filter_items(
+            &self.items,
+            &self
+                .context
+                .fuzzy_matcher(self.match_scope(), msg.get_query()),
+            &self.context.state.counters,
+        );
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = Some(matched_items);
+
+        Ok(())
+    }
+
+    /// Sends back the jump target of the selected entry, which is in the window `winid`.
+    async fn on_select(&mut self, msg: MethodCall) -> Result<()> {
+        let target = self.jump_target_at(msg.get_u64("lnum")? as usize)?;
+
+        let provider_id = &self.context.provider_id;
+        write_response(json!({
+            "id": msg.id,
+            "provider_id": provider_id,
+            "result": { "targets": [target] },
+        }))
+        .await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_loc_entry() {
+        assert_eq!(
+            parse_loc_entry("src/lib.rs|10 col 5| unused variable"),
+            Some(("src/lib.rs", 10, 5))
+        );
+        assert_eq!(
+            parse_loc_entry("src/lib.rs|10| unused variable"),
+            Some(("src/lib.rs", 10, 1))
+        );
+        assert_eq!(parse_loc_entry("|| unused variable"), None);
+    }
+
+    #[test]
+    fn test_winid_threaded_through() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let content = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(dir.join("lines.txt"), content).unwrap();
+
+        let mut context = SessionContext::with_defaults("loclist", &dir).unwrap();
+        context.winid = Some(1001);
+        let mut provider = LoclistProvider::new(context);
+        provider.set_entries(vec![
+            "lines.txt|3 col 2| line 3".into(),
+            "lines.txt|10 col 1| line 10".into(),
+        ]);
+
+        let preview = provider.preview_at(2).unwrap();
+        assert_eq!(preview["winid"], 1001);
+        assert!(preview["lines"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("line 10")));
+
+        // The selection after filtering jumps in the window owning the list.
+        *provider.current_results.lock() = Some(vec![MatchedItem::new(
+            provider.items[0].clone(),
+            Default::default(),
+            Default::default(),
+        )]);
+        assert_eq!(
+            provider.jump_target_at(1).unwrap(),
+            LocTarget {
+                winid: 1001,
+                target: JumpTarget {
+                    path: dir.join("lines.txt"),
+                    lnum: 3,
+                    col: 2,
+                },
+            }
+        );
+        assert!(provider.jump_target_at(2).is_err());
+
+        provider.context.winid = None;
+        assert!(provider.jump_target_at(1).is_err());
+    }
+}
@@ -1,3 +1,14 @@
+pub mod cargo_deps;
+pub mod colors;
 pub mod dumb_jump;
+pub mod env_vars;
 pub mod filer;
+pub mod git_hunks;
+pub mod git_refs;
+pub mod lines;
+pub mod loclist;
 pub mod recent_files;
+pub mod recent_grep;
+pub mod rtp_scripts;
+pub mod snippets;
+pub mod todo;
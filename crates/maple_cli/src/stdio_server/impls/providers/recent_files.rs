@@ -11,7 +11,9 @@ use types::{MatchedItem, Score};
 use crate::datastore::RECENT_FILES_IN_MEMORY;
 use crate::stdio_server::impls::OnMoveHandler;
 use crate::stdio_server::rpc::Call;
-use crate::stdio_server::session::{ClapProvider, ProviderEvent, Session, SessionContext};
+use crate::stdio_server::session::{
+    ClapProvider, ProviderAction, ProviderEvent, Session, SessionContext, FILE_ACTIONS,
+};
 use crate::stdio_server::{write_response, MethodCall};
 
 async fn handle_recent_files_message(
@@ -36,30 +38,34 @@ async fn handle_recent_files_message(
         lnum,
     } = msg.parse_unsafe();
 
-    let mut recent_files = RECENT_FILES_IN_MEMORY.lock();
-
-    let ranked = if query.is_empty() || force_execute {
-        // Sort the initial list according to the cwd.
-        //
-        // This changes the order of existing recent file entries.
-        recent_files.sort_by_cwd(&cwd);
-
-        let mut cwd = cwd.clone();
-        cwd.push(std::path::MAIN_SEPARATOR);
-
-        recent_files
-            .entries
-            .iter()
-            .map(|entry| {
-                let item: Arc<dyn ClapItem> = Arc::new(entry.fpath.replacen(&cwd, "", 1));
-                // frecent_score will not be larger than i32::MAX.
-                MatchedItem::new(item, entry.frecent_score as Score, Default::default())
-            })
-            .collect::<Vec<_>>()
-    } else {
-        recent_files.filter_on_query(&query, cwd.clone())
+    let (ranked, initial_size) = {
+        let mut recent_files = RECENT_FILES_IN_MEMORY.lock();
+
+        let ranked = if query.is_empty() || force_execute {
+            // Sort the initial list according to the cwd.
+            //
+            // This changes the order of existing recent file entries.
+            recent_files.sort_by_cwd(&cwd);
+
+            let mut cwd = cwd.clone();
+            cwd.push(std::path::MAIN_SEPARATOR);
+
+            recent_files
+                .entries
+                .iter()
+                .map(|entry| {
+                    let item: Arc<dyn ClapItem> = Arc::new(entry.fpath.replacen(&cwd, "", 1));
+                    // frecent_score will not be larger than i32::MAX.
+                    MatchedItem::new(item, entry.frecent_score as Score, Default::default())
+                })
+                .collect::<Vec<_>>()
+        } else {
+            recent_files.filter_on_query(&query, cwd.clone(), &context.state.counters)
+        };
+
+        // The lock must not be held across the await of writing the response.
+        (ranked, recent_files.len())
     };
-    let initial_size = recent_files.len();
 
     let total = ranked.len();
 
@@ -85,8 +91,10 @@ async fn handle_recent_files_message(
     let printer::DisplayLines {
         lines,
         indices,
+        ids,
         truncated_map,
         icon_added,
+        ..
     } = printer::decorate_lines(
         ranked.iter().take(200).cloned().collect(),
         winwidth,
@@ -109,6 +117,7 @@ async fn handle_recent_files_message(
         json!({
             "lines": lines,
             "indices": indices,
+            "ids": ids,
             "total": total,
             "icon_added": icon_added,
             "initial_size": initial_size,
@@ -118,6 +127,7 @@ async fn handle_recent_files_message(
         json!({
             "lines": lines,
             "indices": indices,
+            "ids": ids,
             "truncated_map": truncated_map,
             "total": total,
             "icon_added": icon_added,
@@ -133,7 +143,7 @@ async fn handle_recent_files_message(
         "result": result,
     });
 
-    write_response(result);
+    write_response(result).await;
 
     ranked
 }
@@ -159,6 +169,14 @@ impl ClapProvider for RecentFilesProvider {
         &self.context
     }
 
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        Arc::make_mut(&mut self.context)
+    }
+
+    fn actions(&self) -> Vec<ProviderAction> {
+        FILE_ACTIONS.to_vec()
+    }
+
     async fn on_create(&mut self, call: Call) {
         let initial_lines =
             handle_recent_files_message(call.unwrap_method_call(), self.context.clone(), true)
@@ -183,7 +201,7 @@ impl ClapProvider for RecentFilesProvider {
             let on_move_handler = OnMoveHandler::create(&msg, &self.context, Some(curline))?;
             if let Err(e) = on_move_handler.handle().await {
                 tracing::error!(error = ?e, "Failed to handle OnMove event");
-                write_response(json!({"error": e.to_string(), "id": msg_id }));
+                write_response(json!({"error": e.to_string(), "id": msg_id })).await;
             }
         }
         Ok(())
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::json;
+
+use matcher::{ClapItem, MatchScope};
+use types::{GrepItem, MatchedItem};
+
+use crate::datastore::RECENT_FILES_IN_MEMORY;
+use crate::process::tokio::TokioCommand;
+use crate::stdio_server::impls::OnMoveHandler;
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{spawn_singleton_job, ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+type GrepItems = Arc<Vec<Arc<dyn ClapItem>>>;
+
+/// Contents of the recent files, keyed by the hash of file list.
+static CACHED_CONTENTS: Lazy<Mutex<HashMap<u64, GrepItems>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the paths in `files` that still exist.
+fn existing_files(files: impl IntoIterator<Item = String>) -> Vec<PathBuf> {
+    files
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Builds the ripgrep command printing every line of `paths`.
+fn rg_command(paths: &[PathBuf]) -> Command {
+    let mut cmd = Command::new("rg");
+    cmd.args([
+        "--column",
+        "--line-number",
+        "--no-heading",
+        "--with-filename",
+        "--color=never",
+        "",
+    ])
+    .args(paths);
+    cmd
+}
+
+/// Returns the grep lines of all the contents of `paths`.
+async fn grep_files(paths: &[PathBuf]) -> std::io::Result<Vec<Arc<dyn ClapItem>>> {
+    let lines = TokioCommand::from(rg_command(paths)).lines().await?;
+    Ok(lines
+        .into_iter()
+        .filter_map(|line| GrepItem::try_new(line).map(|item| Arc::new(item) as Arc<dyn ClapItem>))
+        .collect())
+}
+
+#[derive(Debug)]
+pub struct RecentGrepProvider {
+    context: SessionContext,
+    cache_key: u64,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl RecentGrepProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            cache_key: 0,
+            current_results: Default::default(),
+        }
+    }
+
+    fn items(&self) -> Option<GrepItems> {
+        CACHED_CONTENTS.lock().get(&self.cache_key).cloned()
+    }
+
+    /// `lnum` is 1-based.
+    fn line_at(&self, lnum: usize) -> Option<String> {
+        let idx = lnum.checked_sub(1)?;
+        let current_results = self.current_results.lock();
+        if current_results.is_empty() {
+            self.items()?
+                .get(idx)
+                .map(|item| item.raw_text().to_string())
+        } else {
+            current_results
+                .get(idx)
+                .map(|r| r.item.raw_text().to_string())
+        }
+    }
+}
+
+async fn send_initial_items(items: &[Arc<dyn ClapItem>], context: &SessionContext) {
+    let total = items.len();
+    write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+    let initial_items = items
+        .iter()
+        .take(context.display_number(100))
+        .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+        .collect();
+    let display_lines = printer::decorate_lines(
+        initial_items,
+        context.display_winwidth as usize,
+        context.icon,
+    );
+    write_response(display_lines.session_create_message()).await;
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for RecentGrepProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    fn default_match_scope(&self) -> MatchScope {
+        MatchScope::GrepLine
+    }
+
+    async fn on_create(&mut self, _call: Call) {
+        let paths = existing_files(
+            RECENT_FILES_IN_MEMORY
+                .lock()
+                .entries
+                .iter()
+                .map(|entry| entry.fpath.clone()),
+        );
+
+        self.cache_key = utility::calculate_hash(&("recent_grep", &paths));
+
+        if let Some(items) = self.items() {
+            send_initial_items(&items, &self.context).await;
+            return;
+        }
+
+        if paths.is_empty() {
+            send_initial_items(&[], &self.context).await;
+            return;
+        }
+
+        let cache_key = self.cache_key;
+        let context = self.context.clone();
+        spawn_singleton_job(
+            async move {
+                match grep_files(&paths).await {
+                    Ok(items) => {
+                        send_initial_items(&items, &context).await;
+                        CACHED_CONTENTS.lock().insert(cache_key, Arc::new(items));
+                    }
+                    Err(e) => tracing::error!(error = ?e, "Failed to grep the recent files"),
+                }
+            },
+            cache_key,
+        );
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let msg_id = msg.id;
+
+        let curline = msg
+            .get_u64("lnum")
+            .ok()
+            .and_then(|lnum| self.line_at(lnum as usize));
+
+        let on_move_handler = OnMoveHandler::create(&msg, &self.context, curline)?;
+        if let Err(error) = on_move_handler.handle().await {
+            tracing::error!(?error, "Failed to handle OnMove event");
+            write_response(json!({"error": error.to_string(), "id": msg_id })).await;
+        }
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let items = match self.items() {
+            Some(items) => items,
+            None => return Ok(()),
+        };
+
+        let query = msg.get_query();
+
+        let matched_items = filter::par_filter_items(
+            &items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        );
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matcher::MatcherBuilder;
+    use std::path::Path;
+
+    /// Returns `true` if the grep line of `item` belongs to `path`.
+    fn is_line_of(item: &MatchedItem, path: &Path) -> bool {
+        item.item
+            .raw_text()
+            .starts_with(path.to_str().expect("Fixture path is valid UTF-8"))
+    }
+
+    #[tokio::test]
+    async fn test_grep_recent_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let lib = dir.join("lib.rs");
+        let main = dir.join("main.rs");
+        std::fs::write(&lib, "pub fn parse_config() {}\nfn unrelated() {}\n").unwrap();
+        std::fs::write(&main, "fn main() {\n    parse_config();\n}\n").unwrap();
+
+        let paths = existing_files(
+            [&lib, &dir.join("deleted.rs"), &main]
+                .into_iter()
+                .map(|path| path.to_string_lossy().to_string()),
+        );
+        assert_eq!(paths, vec![lib.clone(), main.clone()]);
+
+        let items = grep_files(&paths).await.unwrap();
+        assert_eq!(items.len(), 5);
+
+        let matcher = MatcherBuilder::default()
+            .match_scope(MatchScope::GrepLine)
+            .build("parse_config".into());
+        let matched_items = filter::par_filter_items(&items, &matcher, &Default::default());
+        assert_eq!(matched_items.len(), 2);
+        assert!(matched_items.iter().any(|item| is_line_of(item, &lib)));
+        assert!(matched_items.iter().any(|item| is_line_of(item, &main)));
+    }
+}
@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use types::{ClapItem, MatchedItem};
+
+use crate::command::files::WalkOptions;
+use crate::stdio_server::impls::{OnMove, OnMoveHandler};
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+fn is_script(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "vim" || ext == "lua")
+}
+
+/// Returns the absolute paths of Vim scripts in the directories of `runtimepath`.
+///
+/// The non-existent directories are ignored.
+fn scripts_in_runtimepath(runtimepath: &str) -> Vec<String> {
+    let mut scripts = runtimepath
+        .split(',')
+        .map(Path::new)
+        .filter(|dir| dir.is_dir())
+        .flat_map(|dir| {
+            WalkOptions::with_default_skip_dirs()
+                .walk(dir)
+                .filter(|path| is_script(path))
+                .map(move |path| dir.join(path).display().to_string())
+        })
+        .collect::<Vec<_>>();
+    scripts.sort();
+    scripts.dedup();
+    scripts
+}
+
+#[derive(Debug)]
+pub struct RtpScriptsProvider {
+    context: SessionContext,
+    items: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl RtpScriptsProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    fn set_scripts(&mut self, scripts: Vec<String>) {
+        self.items = scripts
+            .into_iter()
+            .map(|script| Arc::new(script) as Arc<dyn ClapItem>)
+            .collect();
+    }
+
+    /// `lnum` is 1-based.
+    fn path_at(&self, lnum: usize) -> Option<PathBuf> {
+        let current_results = self.current_results.lock();
+        if current_results.is_empty() {
+            self.items
+                .get(lnum.checked_sub(1)?)
+                .map(|item| item.raw_text().into())
+        } else {
+            current_results
+                .get(lnum.checked_sub(1)?)
+                .map(|r| r.item.raw_text().into())
+        }
+    }
+
+    fn filter(&self, query: String) -> Vec<MatchedItem> {
+        filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for RtpScriptsProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            runtimepath: Option<String>,
+        }
+
+        let Params { runtimepath } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        let runtimepath = runtimepath.or_else(|| self.context.runtimepath.clone());
+        let scripts = match runtimepath {
+            Some(runtimepath) => {
+                tokio::task::spawn_blocking(move || scripts_in_runtimepath(&runtimepath))
+                    .await
+                    .unwrap_or_default()
+            }
+            None => {
+                tracing::error!("No runtimepath for searching the scripts");
+                return;
+            }
+        };
+        self.set_scripts(scripts);
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let msg_id = msg.id;
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let path = self
+            .path_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No script at line {lnum}"))?;
+
+        let on_move_handler = OnMoveHandler {
+            msg_id,
+            size: self.context.sensible_preview_size(),
+            inner: OnMove::Files(path),
+            context: &self.context,
+            cache_line: None,
+            grep_match: None,
+        };
+        if let Err(error) = on_move_handler.handle().await {
+            tracing::error!(?error, "Failed to handle OnMove event");
+            write_response(json!({"error": error.to_string(), "id": msg_id })).await;
+        }
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = self.filter(msg.get_query());
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_in_runtimepath() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for (file, content) in [
+            ("plugin-a/plugin/foo.vim", "command! Foo echo 1"),
+            ("plugin-a/lua/foo/init.lua", "return {}"),
+            ("plugin-a/doc/foo.txt", "*foo.txt*"),
+            ("plugin-b/autoload/bar.vim", "function! bar#baz() abort"),
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let runtimepath = ["plugin-a", "plugin-b", "nonexistent"]
+            .iter()
+            .map(|plugin| dir.join(plugin).display().to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let scripts = scripts_in_runtimepath(&runtimepath);
+        let display = |file: &str| dir.join(file).display().to_string();
+        assert_eq!(
+            scripts,
+            vec![
+                display("plugin-a/lua/foo/init.lua"),
+                display("plugin-a/plugin/foo.vim"),
+                display("plugin-b/autoload/bar.vim"),
+            ]
+        );
+
+        let mut provider = RtpScriptsProvider::new(SessionContext::new_test_context("rtp_scripts"));
+        provider.set_scripts(scripts);
+        let matched_items = provider.filter("autoloadbar".into());
+        assert_eq!(matched_items.len(), 1);
+        assert_eq!(
+            matched_items[0].item.raw_text(),
+            display("plugin-b/autoload/bar.vim")
+        );
+
+        *provider.current_results.lock() = matched_items;
+        assert_eq!(
+            provider.path_at(1),
+            Some(dir.join("plugin-b/autoload/bar.vim"))
+        );
+    }
+}
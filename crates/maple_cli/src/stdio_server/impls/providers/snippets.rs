@@ -0,0 +1,323 @@
+//! Provider for searching the snippets supplied by the client.
+//!
+//! - `snippets/on_init`: the param `snippets` is a list of `{ trigger, description, body }`, the
+//!   body can be a string or a list of lines. Both the trigger and description are searchable.
+//! - `snippets/on_move`: responds with `{ "trigger": trigger, "body": body, "lines": lines }`,
+//!   `lines` is the body rendered for the preview, the placeholders are marked and the tabs and
+//!   trailing spaces are visible. The raw body is for being inserted on selection.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use types::{ClapItem, MatchedItem};
+
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// Joins the body given as a list of lines.
+fn deserialize_body<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Body {
+        Text(String),
+        Lines(Vec<String>),
+    }
+
+    Ok(match Body::deserialize(deserializer)? {
+        Body::Text(text) => text,
+        Body::Lines(lines) => lines.join("\n"),
+    })
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Snippet {
+    trigger: String,
+    #[serde(default)]
+    description: String,
+    #[serde(deserialize_with = "deserialize_body")]
+    body: String,
+}
+
+/// Returns the placeholder at the start of `s` as `(number, default, len)`, `s` is the text
+/// after `$`, e.g., `1`, `{1}` or `{1:default}`.
+fn parse_placeholder(s: &str) -> Option<(&str, Option<&str>, usize)> {
+    if let Some(rest) = s.strip_prefix('{') {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return None;
+        }
+        let (number, rest) = rest.split_at(digits);
+        if rest.starts_with('}') {
+            return Some((number, None, digits + 2));
+        }
+        let rest = rest.strip_prefix(':')?;
+        // Find the matching brace, the default can have the nested placeholders.
+        let mut depth = 0;
+        let mut escaped = false;
+        for (idx, c) in rest.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    return Some((number, Some(&rest[..idx]), digits + idx + 3));
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    } else {
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        (digits > 0).then(|| (&s[..digits], None, digits))
+    }
+}
+
+/// Expands the placeholders in `text`, `${1:default}` is marked as `«default»` and `$1` as
+/// `«1»`.
+fn expand_placeholders(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(['$', '\\']) {
+        expanded.push_str(&rest[..pos]);
+        let c = rest[pos..].chars().next().expect("Found at pos; qed");
+        rest = &rest[pos + 1..];
+        if c == '\\' {
+            // Keeps the escaped char as is.
+            if let Some(escaped) = rest.chars().next() {
+                expanded.push(escaped);
+                rest = &rest[escaped.len_utf8()..];
+            }
+            continue;
+        }
+        match parse_placeholder(rest) {
+            Some((number, default, len)) => {
+                let content = match default {
+                    Some(default) => expand_placeholders(default),
+                    None => number.to_string(),
+                };
+                expanded.push('«');
+                expanded.push_str(&content);
+                expanded.push('»');
+                rest = &rest[len..];
+            }
+            None => expanded.push('$'),
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Renders `body` for the preview, the placeholders are marked and the tabs and trailing spaces
+/// are made visible like `:set list listchars=tab:→\ ,trail:·`.
+fn render_body(body: &str) -> Vec<String> {
+    expand_placeholders(body)
+        .lines()
+        .map(|line| {
+            let content = line.trim_end_matches(' ');
+            let trailing = line.len() - content.len();
+            format!("{}{}", content.replace('\t', "→   "), "·".repeat(trailing))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct SnippetsProvider {
+    context: SessionContext,
+    snippets: Vec<Snippet>,
+    items: Vec<Arc<dyn ClapItem>>,
+    current_results: Arc<Mutex<Vec<MatchedItem>>>,
+}
+
+impl SnippetsProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            snippets: Vec::new(),
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    /// Displays the snippets as `trigger  description`, the triggers are aligned.
+    fn set_snippets(&mut self, snippets: Vec<Snippet>) {
+        let width = snippets
+            .iter()
+            .map(|snippet| snippet.trigger.chars().count())
+            .max()
+            .unwrap_or_default();
+        self.items = snippets
+            .iter()
+            .map(|snippet| {
+                let line = format!("{:<width$}  {}", snippet.trigger, snippet.description);
+                Arc::new(line.trim_end().to_string()) as Arc<dyn ClapItem>
+            })
+            .collect();
+        self.snippets = snippets;
+    }
+
+    /// Returns the snippet displayed at `lnum`, which is 1-based.
+    fn snippet_at(&self, lnum: usize) -> Option<&Snippet> {
+        let current_results = self.current_results.lock();
+        let line = if current_results.is_empty() {
+            self.items.get(lnum.checked_sub(1)?)?.raw_text().to_string()
+        } else {
+            current_results
+                .get(lnum.checked_sub(1)?)?
+                .item
+                .raw_text()
+                .to_string()
+        };
+        let idx = self.items.iter().position(|item| item.raw_text() == line)?;
+        self.snippets.get(idx)
+    }
+
+    fn filter(&self, query: String) -> Vec<MatchedItem> {
+        filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for SnippetsProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            snippets: Vec<Snippet>,
+        }
+
+        let Params { snippets } = call.unwrap_method_call().parse().unwrap_or_else(|e| {
+            tracing::error!(error = ?e, "Failed to parse the snippets");
+            Default::default()
+        });
+
+        self.set_snippets(snippets);
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let lnum = msg.get_u64("lnum")? as usize;
+
+        let Snippet { trigger, body, .. } = self
+            .snippet_at(lnum)
+            .ok_or_else(|| anyhow::anyhow!("No snippet at line {lnum}"))?;
+
+        let lines = render_body(body);
+        let provider_id = &self.context.provider_id;
+        write_response(json!({
+            "id": msg.id,
+            "provider_id": provider_id,
+            "result": { "trigger": trigger, "body": body, "lines": lines },
+        }))
+        .await;
+
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let matched_items = self.filter(msg.get_query());
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = matched_items;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippets() -> Vec<Snippet> {
+        serde_json::from_value(json!([
+            {
+                "trigger": "fn",
+                "description": "function definition",
+                "body": "fn ${1:name}(${2}) {\n\t$0\n}",
+            },
+            {
+                "trigger": "dbg",
+                "description": "print debug value",
+                "body": ["dbg!(${1:expr});  "],
+            },
+            {
+                "trigger": "test",
+                "description": "unit test module",
+                "body": "#[cfg(test)]\nmod tests {\n\t#[test]\n\tfn ${1:it_works}() {}\n}",
+            },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_filter_by_description() {
+        let mut provider = SnippetsProvider::new(SessionContext::new_test_context("snippets"));
+        provider.set_snippets(snippets());
+
+        let matched_items = provider.filter("printdebug".into());
+        assert_eq!(matched_items.len(), 1);
+        assert_eq!(matched_items[0].item.raw_text(), "dbg   print debug value");
+
+        *provider.current_results.lock() = matched_items;
+        assert_eq!(provider.snippet_at(1).unwrap().trigger, "dbg");
+        assert_eq!(provider.snippet_at(1).unwrap().body, "dbg!(${1:expr});  ");
+
+        // The trigger is searchable too.
+        assert_eq!(provider.filter("test".into()).len(), 1);
+    }
+
+    #[test]
+    fn test_render_body() {
+        let snippets = snippets();
+        assert_eq!(
+            render_body(&snippets[0].body),
+            vec!["fn «name»(«2») {", "→   «0»", "}"]
+        );
+        assert_eq!(render_body(&snippets[1].body), vec!["dbg!(«expr»);··"]);
+        assert_eq!(
+            render_body("${1:outer ${2:inner}} \\$1 $HOME"),
+            vec!["«outer «inner»» $1 $HOME"]
+        );
+    }
+}
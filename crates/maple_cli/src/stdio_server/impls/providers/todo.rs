@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+
+use matcher::{MatchResult, MatchScope};
+use types::{ClapItem, FuzzyText, GrepItem, MatchedItem};
+
+use crate::process::tokio::TokioCommand;
+use crate::stdio_server::impls::OnMoveHandler;
+use crate::stdio_server::rpc::Call;
+use crate::stdio_server::session::{ClapProvider, SessionContext};
+use crate::stdio_server::{write_response, MethodCall};
+
+/// Tags searched by default.
+const DEFAULT_TAGS: &[&str] = &["TODO", "FIXME", "XXX", "HACK"];
+
+/// Returns the glyph for displaying the tag.
+fn tag_glyph(tag: &str) -> char {
+    match tag {
+        "TODO" => '\u{f0ae}',
+        "FIXME" => '\u{f188}',
+        "XXX" => '\u{f071}',
+        "HACK" => '\u{f0ad}',
+        _ => '\u{f075}',
+    }
+}
+
+/// Returns the tag in `tags` found first as a whole word in `line`.
+fn classify<'a>(line: &str, tags: &'a [String]) -> Option<&'a str> {
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric() && c != '_');
+
+    tags.iter()
+        .filter(|tag| !tag.is_empty())
+        .filter_map(|tag| {
+            line.match_indices(tag.as_str())
+                .map(|(start, _)| start)
+                .find(|&start| {
+                    is_boundary(line[..start].chars().last())
+                        && is_boundary(line[start + tag.len()..].chars().next())
+                })
+                .map(|start| (start, tag.as_str()))
+        })
+        .min_by_key(|(start, _)| *start)
+        .map(|(_, tag)| tag)
+}
+
+/// Builds the ripgrep command for searching the tags.
+///
+/// The tags and file type are passed as the arguments directly, no shell is involved.
+fn rg_todo_command(tags: &[String], file_type: Option<&str>) -> Command {
+    let mut cmd = Command::new("rg");
+    cmd.args([
+        "--column",
+        "--line-number",
+        "--no-heading",
+        "--color=never",
+        "--fixed-strings",
+        "-w",
+    ]);
+    for tag in tags {
+        cmd.arg("-e").arg(tag);
+    }
+    if let Some(file_type) = file_type {
+        cmd.arg(format!("--type={file_type}"));
+    }
+    cmd.args(["--", "."]);
+    cmd
+}
+
+/// A grep line containing one of the tags, displayed with the glyph of tag.
+#[derive(Debug)]
+struct TodoItem {
+    inner: GrepItem,
+    tag: String,
+}
+
+impl TodoItem {
+    fn try_new(line: String, tags: &[String]) -> Option<Self> {
+        let inner = GrepItem::try_new(line)?;
+        let tag = classify(inner.bonus_text(), tags)?.to_string();
+        Some(Self { inner, tag })
+    }
+
+    fn glyph(&self) -> char {
+        tag_glyph(&self.tag)
+    }
+}
+
+impl ClapItem for TodoItem {
+    fn raw_text(&self) -> &str {
+        self.inner.raw_text()
+    }
+
+    fn fuzzy_text(&self, match_scope: MatchScope) -> Option<FuzzyText<'_>> {
+        self.inner.fuzzy_text(match_scope)
+    }
+
+    fn bonus_text(&self) -> &str {
+        self.inner.bonus_text()
+    }
+
+    fn match_result_callback(&self, match_result: MatchResult) -> MatchResult {
+        let mut match_result = match_result;
+        let offset = self.glyph().len_utf8() + 1;
+        match_result.indices.iter_mut().for_each(|x| *x += offset);
+        match_result
+    }
+
+    fn output_text(&self) -> Cow<'_, str> {
+        format!("{} {}", self.glyph(), self.raw_text()).into()
+    }
+}
+
+#[derive(Debug)]
+pub struct TodoProvider {
+    context: SessionContext,
+    items: Vec<Arc<dyn ClapItem>>,
+    /// Results of the last query, `None` if no query has been typed.
+    current_results: Arc<Mutex<Option<Vec<MatchedItem>>>>,
+}
+
+impl TodoProvider {
+    pub fn new(context: SessionContext) -> Self {
+        Self {
+            context,
+            items: Vec::new(),
+            current_results: Default::default(),
+        }
+    }
+
+    /// `lnum` is 1-based.
+    fn line_at(&self, lnum: usize) -> Option<String> {
+        let idx = lnum.checked_sub(1)?;
+        let current_results = self.current_results.lock();
+        match current_results.as_ref() {
+            Some(current_results) => current_results
+                .get(idx)
+                .map(|r| r.item.raw_text().to_string()),
+            None => self.items.get(idx).map(|item| item.raw_text().to_string()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClapProvider for TodoProvider {
+    fn session_context(&self) -> &SessionContext {
+        &self.context
+    }
+
+    fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.context
+    }
+
+    fn default_match_scope(&self) -> MatchScope {
+        MatchScope::GrepLine
+    }
+
+    async fn on_create(&mut self, call: Call) {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            tags: Option<Vec<String>>,
+            file_type: Option<String>,
+        }
+
+        let Params { tags, file_type } = call.unwrap_method_call().parse().unwrap_or_default();
+
+        let tags = tags
+            .filter(|tags| !tags.is_empty())
+            .unwrap_or_else(|| DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect());
+
+        let lines = match TokioCommand::from(rg_todo_command(&tags, file_type.as_deref()))
+            .current_dir(&self.context.cwd)
+            .lines()
+            .await
+        {
+            Ok(lines) => lines,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to search the todo comments");
+                return;
+            }
+        };
+
+        self.items = lines
+            .into_iter()
+            .filter_map(|line| {
+                TodoItem::try_new(line, &tags).map(|item| Arc::new(item) as Arc<dyn ClapItem>)
+            })
+            .collect();
+
+        let total = self.items.len();
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
+
+        let initial_items = self
+            .items
+            .iter()
+            .take(self.context.display_number(100))
+            .map(|item| MatchedItem::new(item.clone(), Default::default(), Default::default()))
+            .collect();
+        let display_lines = self.decorate_lines(initial_items);
+        write_response(display_lines.session_create_message()).await;
+    }
+
+    async fn on_move(&mut self, msg: MethodCall) -> Result<()> {
+        let msg_id = msg.id;
+
+        // The displayed line is prefixed with the glyph, never fall back to it.
+        let curline = match msg
+            .get_u64("lnum")
+            .ok()
+            .and_then(|lnum| self.line_at(lnum as usize))
+        {
+            Some(curline) => curline,
+            None => return Ok(()),
+        };
+
+        let on_move_handler = OnMoveHandler::create(&msg, &self.context, Some(curline))?;
+        if let Err(error) = on_move_handler.handle().await {
+            tracing::error!(?error, "Failed to handle OnMove event");
+            write_response(json!({"error": error.to_string(), "id": msg_id })).await;
+        }
+        Ok(())
+    }
+
+    async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+        let query = msg.get_query();
+
+        let matched_items = filter::par_filter_items(
+            &self.items,
+            &self.context.fuzzy_matcher(self.match_scope(), query),
+            &self.context.state.counters,
+        );
+        let matched = matched_items.len();
+        let display_lines = self.decorate_lines(
+            matched_items
+                .iter()
+                .take(self.context.display_number(200))
+                .cloned()
+                .collect(),
+        );
+        write_response(display_lines.on_typed_message(matched)).await;
+
+        let mut current_results = self.current_results.lock();
+        *current_results = Some(matched_items);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_todo_classification() {
+        let tags: Vec<String> = DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect();
+
+        let fixture = [
+            ("src/lib.rs:1:4:// TODO: handle the error", Some("TODO")),
+            (
+                "src/lib.rs:9:8:    // FIXME(xlc) panics on empty input",
+                Some("FIXME"),
+            ),
+            ("src/main.rs:3:3:# XXX this is wrong", Some("XXX")),
+            (
+                "autoload/clap.vim:20:3:\" HACK: workaround for vim 8.0",
+                Some("HACK"),
+            ),
+            ("src/lib.rs:30:8:let TODOS = 1;", None),
+            ("src/lib.rs:31:8:let MY_FIXME = 1;", None),
+            ("TODO.md:1:1:nothing here", None),
+        ];
+
+        for (line, expected) in fixture {
+            let item = TodoItem::try_new(line.to_string(), &tags);
+            assert_eq!(item.as_ref().map(|item| item.tag.as_str()), expected);
+            if let Some(item) = item {
+                assert_eq!(
+                    item.output_text(),
+                    format!("{} {line}", tag_glyph(expected.unwrap()))
+                );
+            }
+        }
+
+        let custom_tags = vec!["NOTE".to_string()];
+        assert_eq!(classify("// NOTE: keep it", &custom_tags), Some("NOTE"));
+
+        // The custom tags are not necessarily uppercase.
+        let custom_tags = vec!["note".to_string(), "@todo".to_string(), "Todo".to_string()];
+        assert_eq!(classify("// note: keep it", &custom_tags), Some("note"));
+        assert_eq!(classify("/* @todo remove */", &custom_tags), Some("@todo"));
+        assert_eq!(classify("# Todo later, note", &custom_tags), Some("Todo"));
+        assert_eq!(classify("// notes and Todos", &custom_tags), None);
+        let item = TodoItem::try_new("src/lib.rs:2:4:// @todo remove".to_string(), &custom_tags);
+        assert_eq!(item.map(|item| item.tag), Some("@todo".to_string()));
+
+        let quoted_tags = vec!["NOTE".to_string(), "it's".to_string()];
+        let cmd = rg_todo_command(&quoted_tags, Some("rust; ls"));
+        assert_eq!(cmd.get_program(), "rg");
+        assert_eq!(
+            cmd.get_args().collect::<Vec<_>>(),
+            [
+                "--column",
+                "--line-number",
+                "--no-heading",
+                "--color=never",
+                "--fixed-strings",
+                "-w",
+                "-e",
+                "NOTE",
+                "-e",
+                "it's",
+                "--type=rust; ls",
+                "--",
+                "."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_at() {
+        let mut provider = TodoProvider::new(SessionContext::new_test_context("todo"));
+        let tags: Vec<String> = DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect();
+        provider.items = [
+            "src/lib.rs:1:4:// TODO: foo",
+            "src/lib.rs:2:4:// FIXME: bar",
+        ]
+        .into_iter()
+        .filter_map(|line| TodoItem::try_new(line.to_string(), &tags))
+        .map(|item| Arc::new(item) as Arc<dyn ClapItem>)
+        .collect();
+
+        assert_eq!(provider.line_at(0), None);
+        assert_eq!(
+            provider.line_at(2).as_deref(),
+            Some("src/lib.rs:2:4:// FIXME: bar")
+        );
+
+        // No fallback to the unfiltered items once nothing matches the query.
+        *provider.current_results.lock() = Some(Vec::new());
+        assert_eq!(provider.line_at(1), None);
+    }
+}
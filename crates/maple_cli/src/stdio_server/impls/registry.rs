@@ -0,0 +1,177 @@
+//! Registry of the providers, used for discovering the providers and diagnosing why a provider
+//! is unavailable.
+
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+
+use super::create_provider;
+use crate::stdio_server::session::{ClapProvider, ProviderAction, SessionContext};
+
+/// Static info of a provider.
+#[derive(Debug, Clone, Copy)]
+pub struct ProviderInfo {
+    pub id: &'static str,
+    pub description: &'static str,
+    /// Executables required by the provider.
+    pub external_tools: &'static [&'static str],
+}
+
+impl ProviderInfo {
+    const fn new(
+        id: &'static str,
+        description: &'static str,
+        external_tools: &'static [&'static str],
+    ) -> Self {
+        Self {
+            id,
+            description,
+            external_tools,
+        }
+    }
+}
+
+/// All the providers handled by maple, sorted by id.
+pub const PROVIDERS: &[ProviderInfo] = &[
+    ProviderInfo::new("blines", "Lines of the current buffer", &[]),
+    ProviderInfo::new(
+        "cargo_deps",
+        "Source files of the crates in the cargo registry",
+        &[],
+    ),
+    ProviderInfo::new("colors", "Colorschemes with live preview", &[]),
+    ProviderInfo::new(
+        "dumb_jump",
+        "Definitions and references using regex",
+        &["rg"],
+    ),
+    ProviderInfo::new("env_vars", "Environment variables", &[]),
+    ProviderInfo::new("filer", "Ivy-like file explorer", &[]),
+    ProviderInfo::new("files", "Files in the project", &[]),
+    ProviderInfo::new("git_files", "Files tracked by git", &["git"]),
+    ProviderInfo::new("git_hunks", "Changed hunks in git", &["git"]),
+    ProviderInfo::new("git_refs", "Branches and tags of git", &["git"]),
+    ProviderInfo::new("grep", "Lines of the project using ripgrep", &["rg"]),
+    ProviderInfo::new("lines", "Lines of all the loaded buffers", &[]),
+    ProviderInfo::new("live_grep", "Live search using ripgrep", &["rg"]),
+    ProviderInfo::new("loclist", "Entries of the location list of a window", &[]),
+    ProviderInfo::new("proj_tags", "Tags of the project", &["ctags"]),
+    ProviderInfo::new("recent_files", "Recently opened files", &[]),
+    ProviderInfo::new("recent_grep", "Lines of the recently opened files", &["rg"]),
+    ProviderInfo::new("rtp_scripts", "Scripts in the runtimepath", &[]),
+    ProviderInfo::new("snippets", "Snippets supplied by the client", &[]),
+    ProviderInfo::new("tags", "Tags of the current buffer", &["ctags"]),
+    ProviderInfo::new("todo", "TODO comments of the project", &["rg"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExternalTool {
+    pub name: &'static str,
+    pub available: bool,
+}
+
+/// Self-description of a provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderEntry {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub actions: Vec<ProviderAction>,
+    /// Whether the selected entry may resolve to multiple jump targets.
+    pub multiple_targets: bool,
+    pub external_tools: Vec<ExternalTool>,
+    /// Whether all the external tools are available.
+    pub available: bool,
+}
+
+/// Returns `true` if the executable `name` can be spawned.
+pub fn executable_exists(name: &str) -> bool {
+    Command::new(name)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Returns the entries of all the providers in [`PROVIDERS`].
+///
+/// `tool_exists` is checked once for each external tool.
+pub fn list_providers(tool_exists: impl Fn(&str) -> bool) -> Vec<ProviderEntry> {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir());
+
+    let mut checked_tools = HashMap::new();
+    let mut is_available = |name: &str| {
+        *checked_tools
+            .entry(name.to_string())
+            .or_insert_with(|| tool_exists(name))
+    };
+
+    PROVIDERS
+        .iter()
+        .map(|info| {
+            let (actions, multiple_targets) = SessionContext::with_defaults(info.id, &cwd)
+                .map(|context| {
+                    let provider = create_provider(context);
+                    (provider.actions(), provider.multiple_targets())
+                })
+                .unwrap_or_default();
+            let external_tools = info
+                .external_tools
+                .iter()
+                .map(|&name| ExternalTool {
+                    name,
+                    available: is_available(name),
+                })
+                .collect::<Vec<_>>();
+            ProviderEntry {
+                id: info.id,
+                description: info.description,
+                actions,
+                multiple_targets,
+                available: external_tools.iter().all(|tool| tool.available),
+                external_tools,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_providers() {
+        let entries = list_providers(|name| name != "rg");
+        let entry = |id: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.id == id)
+                .unwrap_or_else(|| panic!("{id} is not listed"))
+        };
+
+        let filer = entry("filer");
+        assert!(filer.available);
+        assert!(filer.actions.contains(&ProviderAction::Cd));
+
+        let grep = entry("grep");
+        assert!(!grep.available);
+        assert_eq!(
+            grep.external_tools,
+            vec![ExternalTool {
+                name: "rg",
+                available: false
+            }]
+        );
+
+        let git_refs = entry("git_refs");
+        assert!(git_refs.available);
+        assert_eq!(git_refs.actions, vec![ProviderAction::Open]);
+        assert!(!git_refs.multiple_targets);
+
+        assert!(entry("dumb_jump").multiple_targets);
+        assert!(entry("tags").multiple_targets);
+
+        assert!(entries.windows(2).all(|w| w[0].id < w[1].id));
+    }
+}
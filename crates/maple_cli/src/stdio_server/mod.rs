@@ -8,10 +8,12 @@ mod session_client;
 mod state;
 mod types;
 mod vim;
+mod writer;
 
 use std::io::prelude::*;
 use std::io::{BufReader, BufWriter};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -25,8 +27,11 @@ use self::session_client::SessionClient;
 use self::state::State;
 use self::types::GlobalEnv;
 
-pub use self::deprecated_runner::{run_forever, write_response};
+pub use self::deprecated_runner::run_forever;
+pub use self::impls::{executable_exists, list_providers};
 pub use self::rpc::{MethodCall, Notification};
+pub use self::vim::syntax_for;
+pub use self::writer::write_response;
 
 static GLOBAL_ENV: OnceCell<GlobalEnv> = OnceCell::new();
 
@@ -41,8 +46,36 @@ pub fn global() -> impl Deref<Target = GlobalEnv> {
     }
 }
 
+static WARMED_UP: AtomicBool = AtomicBool::new(false);
+
+/// Initializes the rayon thread pool and compiles the regex patterns and matcher, which
+/// otherwise slows down the first query.
+fn warm_up() {
+    use rayon::prelude::*;
+
+    (0..rayon::current_num_threads())
+        .into_par_iter()
+        .for_each(|_| {});
+
+    pattern::precompile();
+
+    let matcher = matcher::MatcherBuilder::default().build("warm up".into());
+    let item: Arc<dyn ::types::ClapItem> = Arc::new("crates/maple_cli/src/warm_up.rs");
+    matcher.match_item(item);
+
+    WARMED_UP.store(true, Ordering::SeqCst);
+    tracing::debug!("Warm-up finished");
+}
+
+/// Warms up in the background without blocking the startup.
+fn spawn_warm_up() {
+    tokio::task::spawn_blocking(warm_up);
+}
+
 /// Starts and keep running the server on top of stdio.
 pub async fn start() -> Result<()> {
+    spawn_warm_up();
+
     let (call_tx, call_rx) = tokio::sync::mpsc::unbounded_channel();
 
     let rpc_client = Arc::new(RpcClient::new(
@@ -57,3 +90,54 @@ pub async fn start() -> Result<()> {
 
     Ok(())
 }
+
+/// Returns the preview of `curline` rendered by the `on_move` handler of provider, without a
+/// live session.
+///
+/// `params` are the params of session, e.g., `provider_id` and `cwd`.
+pub async fn preview_on_move(
+    curline: String,
+    preview_size: u64,
+    params: serde_json::Value,
+) -> Result<serde_json::Value> {
+    GLOBAL_ENV
+        .get_or_init(|| GlobalEnv::new(false, false, types::PreviewConfig::Number(preview_size)));
+
+    let msg: MethodCall = serde_json::from_value(json!({
+        "id": 0,
+        "method": "on_move",
+        "params": params,
+        "session_id": 0,
+    }))?;
+    let context: session::SessionContext = msg.clone().into();
+
+    let mut on_move_handler = impls::OnMoveHandler::create(&msg, &context, Some(curline))?;
+    // No cache to refresh outside a session.
+    on_move_handler.cache_line.take();
+
+    on_move_handler
+        .preview()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Nothing to preview"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_warm_up() {
+        // No other test warms up, the flag is only set by this one.
+        assert!(!WARMED_UP.load(Ordering::SeqCst));
+
+        spawn_warm_up();
+
+        // The warm-up finishes in the background.
+        let now = std::time::Instant::now();
+        while !WARMED_UP.load(Ordering::SeqCst) {
+            assert!(now.elapsed() < std::time::Duration::from_secs(10));
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(WARMED_UP.load(Ordering::SeqCst));
+    }
+}
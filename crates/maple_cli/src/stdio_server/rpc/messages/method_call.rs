@@ -138,6 +138,18 @@ impl MethodCall {
         json!({ "method": "clap#ext#set", "ext_map": ext_map })
     }
 
+    /// Returns the counters of memory held by the filtering of session `session_id`, `stats` is
+    /// `None` if the session does not exist.
+    pub fn stats(self, session_id: u64, stats: Option<filter::stats::Stats>) -> Value {
+        if !filter::stats::ENABLED {
+            return json!({ "id": self.id, "error": "maple is not built with the `stats` feature" });
+        }
+        match stats {
+            Some(stats) => json!({ "id": self.id, "result": stats }),
+            None => json!({ "id": self.id, "error": format!("session {session_id} not found") }),
+        }
+    }
+
     pub async fn preview_file(self) -> Result<Value> {
         let msg_id = self.id;
 
@@ -176,47 +188,77 @@ impl MethodCall {
     }
 
     pub async fn preview_quickfix(self) -> Result<Value> {
-        use crate::previewer::{preview_file, preview_file_at};
-        use std::path::PathBuf;
+        let msg_id = self.id;
+
+        let result = preview_qf_entry(self.params.parse()?)?;
 
+        let value = json!({ "id": msg_id, "provider_id": "quickfix", "result": result });
+
+        Ok(value)
+    }
+
+    /// Same as [`Self::preview_quickfix`], the location list is scoped to window `winid`, which
+    /// is passed through for jumping in the window owning the list.
+    pub async fn preview_loclist(self) -> Result<Value> {
         let msg_id = self.id;
 
         #[derive(Deserialize)]
         struct InnerParams {
-            cwd: String,
-            curline: String,
-            winwidth: u64,
-            winheight: u64,
+            #[serde(flatten)]
+            entry: QfEntryParams,
+            winid: u64,
         }
 
-        let InnerParams {
-            cwd,
-            curline,
-            winwidth,
-            winheight,
-        } = self.params.parse()?;
-
-        let (p, lnum) = parse_quickfix_entry(curline.as_str())?;
-
-        let mut fpath: PathBuf = cwd.into();
-        fpath.push(p);
+        let InnerParams { entry, winid } = self.params.parse()?;
 
-        let result = if lnum == 0 {
-            let size = (winheight + 5) as usize;
-            let (lines, _) = preview_file(fpath.as_path(), size, winwidth as usize)?;
-            json!({ "event": "on_move", "lines": lines, "fname": fpath })
-        } else {
-            let size = (winheight / 2) as usize;
-            let (lines, hi_lnum) = preview_file_at(fpath.as_path(), size, winwidth as usize, lnum)?;
-            json!({ "event": "on_move", "lines": lines, "fname": fpath, "hi_lnum": hi_lnum })
-        };
+        let mut result = preview_qf_entry(entry)?;
+        result["winid"] = winid.into();
 
-        let value = json!({ "id": msg_id, "provider_id": "quickfix", "result": result });
+        let value = json!({ "id": msg_id, "provider_id": "loclist", "result": result });
 
         Ok(value)
     }
 }
 
+/// Params of previewing an entry of the quickfix list or location list.
+#[derive(Deserialize)]
+pub(crate) struct QfEntryParams {
+    pub cwd: String,
+    pub curline: String,
+    pub winwidth: u64,
+    pub winheight: u64,
+}
+
+/// Returns the preview of the quickfix or location list entry `curline`.
+pub(crate) fn preview_qf_entry(
+    QfEntryParams {
+        cwd,
+        curline,
+        winwidth,
+        winheight,
+    }: QfEntryParams,
+) -> Result<Value> {
+    use crate::previewer::{preview_file, preview_file_at};
+    use std::path::PathBuf;
+
+    let (p, lnum) = parse_quickfix_entry(curline.as_str())?;
+
+    let mut fpath: PathBuf = cwd.into();
+    fpath.push(p);
+
+    let result = if lnum == 0 {
+        let size = (winheight + 5) as usize;
+        let (lines, _) = preview_file(fpath.as_path(), size, winwidth as usize)?;
+        json!({ "event": "on_move", "lines": lines, "fname": fpath })
+    } else {
+        let size = (winheight / 2) as usize;
+        let (lines, hi_lnum) = preview_file_at(fpath.as_path(), size, winwidth as usize, lnum)?;
+        json!({ "event": "on_move", "lines": lines, "fname": fpath, "hi_lnum": hi_lnum })
+    };
+
+    Ok(result)
+}
+
 fn parse_quickfix_entry(line: &str) -> Result<(&str, usize)> {
     let mut splitted = line.split('|');
     let fpath = splitted
@@ -249,4 +291,37 @@ mod tests {
             ("test/bench/python/test_fuzzy_filter.vim".into(), 0usize)
         );
     }
+
+    #[tokio::test]
+    async fn test_preview_loclist() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let content = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(dir.join("lines.txt"), content).unwrap();
+
+        let msg: MethodCall = serde_json::from_value(json!({
+            "id": 1,
+            "method": "loclist",
+            "params": {
+                "cwd": dir,
+                "curline": "lines.txt|10 col 1| line 10",
+                "winwidth": 80,
+                "winheight": 10,
+                "winid": 1001,
+            },
+            "session_id": 1,
+        }))
+        .unwrap();
+
+        let value = msg.preview_loclist().await.unwrap();
+        assert_eq!(value["provider_id"], "loclist");
+        assert_eq!(value["result"]["winid"], 1001);
+        assert!(value["result"]["lines"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("line 10")));
+    }
 }
@@ -8,10 +8,15 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use anyhow::{anyhow, Result};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::mpsc::{
+    channel, unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender,
+};
 use tokio::sync::oneshot;
 
+use crate::stdio_server::writer::{OutputWriter, CHANNEL_CAPACITY};
+
 pub use self::messages::method_call::MethodCall;
+pub(crate) use self::messages::method_call::{preview_qf_entry, QfEntryParams};
 pub use self::messages::notification::Notification;
 pub use self::types::{Call, Error, ErrorCode, Failure, Output, Params, RawMessage, Success};
 
@@ -20,9 +25,10 @@ pub struct RpcClient {
     /// Id of request to Vim created from the Rust side.
     #[serde(skip_serializing)]
     id: AtomicU64,
-    /// Sender for sending message from Rust to Vim.
+    /// Sender for sending message from Rust to Vim, at most [`CHANNEL_CAPACITY`] messages can
+    /// be pending so that the senders wait for a slow client.
     #[serde(skip_serializing)]
-    output_writer_tx: UnboundedSender<RawMessage>,
+    output_writer_tx: Sender<RawMessage>,
     /// Sender for passing the Vim response of request initiated from Rust.
     #[serde(skip_serializing)]
     output_reader_tx: UnboundedSender<(u64, oneshot::Sender<Output>)>,
@@ -51,12 +57,9 @@ impl RpcClient {
             }
         });
 
-        let (output_writer_tx, output_writer_rx) = unbounded_channel();
-        tokio::spawn(async move {
-            if let Err(error) = loop_write(writer, output_writer_rx).await {
-                tracing::error!(?error, "Thread stdio-writer exited");
-            }
-        });
+        let (output_writer_tx, output_writer_rx) = channel(CHANNEL_CAPACITY);
+        let output_writer = OutputWriter::new(writer, CHANNEL_CAPACITY);
+        tokio::spawn(loop_write(output_writer, output_writer_rx));
 
         Self {
             id: Default::default(),
@@ -83,15 +86,16 @@ impl RpcClient {
         let (tx, rx) = oneshot::channel();
         self.output_reader_tx.send((id, tx))?;
         self.output_writer_tx
-            .send(RawMessage::MethodCall(method_call))?;
+            .send(RawMessage::MethodCall(method_call))
+            .await?;
         match rx.await? {
             Output::Success(ok) => Ok(serde_json::from_value(ok.result)?),
             Output::Failure(err) => Err(anyhow!("Error: {:?}", err)),
         }
     }
 
-    /// Sends a notification message to Vim.
-    pub fn notify(&self, method: impl AsRef<str>, params: impl Serialize) -> Result<()> {
+    /// Sends a notification message to Vim, waits until there is capacity if Vim falls behind.
+    pub async fn notify(&self, method: impl AsRef<str>, params: impl Serialize) -> Result<()> {
         let notification = Notification {
             method: method.as_ref().to_owned(),
             params: to_params(params)?,
@@ -99,13 +103,14 @@ impl RpcClient {
         };
 
         self.output_writer_tx
-            .send(RawMessage::Notification(notification))?;
+            .send(RawMessage::Notification(notification))
+            .await?;
 
         Ok(())
     }
 
-    /// Sends the response from Rust to Vim.
-    pub fn output(&self, id: u64, output_result: Result<impl Serialize>) -> Result<()> {
+    /// Sends the response from Rust to Vim, waits until there is capacity if Vim falls behind.
+    pub async fn output(&self, id: u64, output_result: Result<impl Serialize>) -> Result<()> {
         let output = match output_result {
             Ok(ok) => Output::Success(Success {
                 id,
@@ -121,7 +126,9 @@ impl RpcClient {
             }),
         };
 
-        self.output_writer_tx.send(RawMessage::Output(output))?;
+        self.output_writer_tx
+            .send(RawMessage::Output(output))
+            .await?;
 
         Ok(())
     }
@@ -174,22 +181,12 @@ fn loop_read(
     }
 }
 
-/// Keep writing the response from Rust backend to Vim via stdout.
-async fn loop_write(writer: impl Write, mut rx: UnboundedReceiver<RawMessage>) -> Result<()> {
-    let mut writer = writer;
-
+/// Keep forwarding the response from Rust backend to the writer of Vim.
+async fn loop_write(output_writer: OutputWriter, mut rx: Receiver<RawMessage>) {
     while let Some(msg) = rx.recv().await {
         tracing::debug!(?msg, "Sending back to the Vim side");
-        let s = serde_json::to_string(&msg)?;
-        // Use different convention for two reasons,
-        // 1. If using '\r\ncontent', nvim will receive output as `\r` + `content`, while vim
-        // receives `content`.
-        // 2. Without last line ending, vim output handler won't be triggered.
-        write!(writer, "Content-length: {}\n\n{}\n", s.len(), s)?;
-        writer.flush()?;
+        output_writer.write(msg).await;
     }
-
-    Ok(())
 }
 
 fn to_params(value: impl Serialize) -> Result<Params> {
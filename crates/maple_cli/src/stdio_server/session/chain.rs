@@ -0,0 +1,168 @@
+//! Chaining a follow-up provider seeded from the selection of the current session, e.g., grep
+//! in the directory selected in `files`.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::stdio_server::rpc::{Call, MethodCall, Params};
+use crate::stdio_server::types::ProviderId;
+
+/// How the selection seeds the follow-up provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainSeed {
+    /// The selected directory becomes the working directory, the parent directory is used if a
+    /// file is selected.
+    #[default]
+    Cwd,
+    /// The selected line becomes the initial query.
+    Query,
+}
+
+/// Follow-up provider specified by `chain` in the create params.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainSpec {
+    pub provider_id: ProviderId,
+    #[serde(default)]
+    pub seed: ChainSeed,
+}
+
+/// Chain of a session, the follow-up inherits the create params of current session.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    spec: ChainSpec,
+    init_params: Map<String, Value>,
+}
+
+impl Chain {
+    /// Returns the chain specified in the create params of `init_call` if any.
+    pub fn from_init_call(init_call: &Call) -> Option<Self> {
+        let params = match init_call {
+            Call::MethodCall(method_call) => &method_call.params,
+            Call::Notification(notification) => &notification.params,
+        };
+        let init_params = match params {
+            Params::Map(map) => map,
+            _ => return None,
+        };
+
+        let chain = init_params.get("chain").filter(|chain| !chain.is_null())?;
+        match serde_json::from_value(chain.clone()) {
+            Ok(spec) => {
+                let mut init_params = init_params.clone();
+                init_params.remove("chain");
+                Some(Self { spec, init_params })
+            }
+            Err(err) => {
+                tracing::error!(?err, "Invalid chain in the create params");
+                None
+            }
+        }
+    }
+
+    /// Returns the create call of the follow-up session `session_id` seeded from `selected`.
+    pub fn next_init_call(&self, id: u64, session_id: u64, selected: &str) -> Result<MethodCall> {
+        let mut params = self.init_params.clone();
+        params.insert("provider_id".into(), self.spec.provider_id.as_str().into());
+
+        let selected = selected.trim();
+        match self.spec.seed {
+            ChainSeed::Cwd => {
+                let cwd = params
+                    .get("cwd")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let path = Path::new(cwd).join(selected);
+                let dir = if path.is_file() {
+                    path.parent().map(Path::to_path_buf).unwrap_or(path)
+                } else {
+                    path
+                };
+                if !dir.is_dir() {
+                    return Err(anyhow!("{} is not a directory", dir.display()));
+                }
+                params.insert("cwd".into(), dir.display().to_string().into());
+                params.remove("query");
+            }
+            ChainSeed::Query => {
+                params.insert("query".into(), selected.into());
+            }
+        }
+
+        Ok(MethodCall {
+            id,
+            method: format!("{}/on_init", self.spec.provider_id.as_str()),
+            params: Params::Map(params),
+            session_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stdio_server::session::SessionContext;
+
+    fn init_call(cwd: &Path, chain: Value) -> Call {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "method": "on_init",
+            "params": {
+                "provider_id": "files",
+                "cwd": cwd,
+                "no_cache": false,
+                "source_fpath": "",
+                "query": "lib",
+                "chain": chain,
+            },
+            "session_id": 1,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_chain_into_grep() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("crates/filter")).unwrap();
+        std::fs::write(dir.join("crates/filter/lib.rs"), "").unwrap();
+        let dir = dir.canonicalize().unwrap();
+
+        let chain = Chain::from_init_call(&init_call(
+            &dir,
+            serde_json::json!({ "provider_id": "grep" }),
+        ))
+        .unwrap();
+
+        for selected in ["crates/filter", "crates/filter/lib.rs"] {
+            let next_call = chain.next_init_call(2, 2, selected).unwrap();
+            assert_eq!(next_call.session_id, 2);
+
+            let context: SessionContext = next_call.into();
+            assert_eq!(context.provider_id.as_str(), "grep");
+            assert_eq!(context.cwd.as_path(), dir.join("crates/filter"));
+        }
+
+        assert!(chain.next_init_call(2, 2, "crates/nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_chain_seed_query() {
+        let cwd = std::env::current_dir().unwrap();
+        let chain = Chain::from_init_call(&init_call(
+            &cwd,
+            serde_json::json!({ "provider_id": "grep", "seed": "query" }),
+        ))
+        .unwrap();
+
+        let next_call = chain.next_init_call(2, 2, "fn main").unwrap();
+        assert_eq!(next_call.get_query(), "fn main");
+        assert_eq!(next_call.get_cwd(), cwd.display().to_string());
+
+        // No chain is specified.
+        assert!(Chain::from_init_call(&init_call(&cwd, Value::Null)).is_none());
+    }
+}
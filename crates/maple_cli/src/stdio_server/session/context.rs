@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{atomic::AtomicBool, Arc};
+use std::time::Duration;
 
 use anyhow::Result;
 use filter::Query;
@@ -8,18 +9,27 @@ use parking_lot::Mutex;
 use serde::Deserialize;
 
 use icon::{Icon, IconKind};
-use matcher::{ClapItem, MatchScope};
+use matcher::{Bonus, ClapItem, FuzzyAlgorithm, MatchScope, MatcherBuilder, RecentlyModified};
 use types::MatchedItem;
 
+use super::SessionJobs;
 use crate::paths::AbsPathBuf;
 use crate::stdio_server::rpc::{Call, MethodCall, Notification, Params};
 use crate::stdio_server::types::ProviderId;
 use crate::tools::ctags::BufferTag;
 
-const DEFAULT_DISPLAY_WINWIDTH: u64 = 100;
-
 const DEFAULT_PREVIEW_WINHEIGHT: u64 = 30;
 
+/// In milliseconds.
+const DEFAULT_MIN_DEBOUNCE_DELAY: u64 = 50;
+/// In milliseconds.
+const DEFAULT_MAX_DEBOUNCE_DELAY: u64 = 500;
+
+/// In bytes.
+const DEFAULT_MAX_PREVIEW_FILE_SIZE: u64 = 32 * 1024 * 1024;
+/// In bytes.
+const DEFAULT_PREVIEW_FILE_SIZE_CAP: u64 = 1024 * 1024 * 1024;
+
 /// This type represents the scale of filtering source.
 #[derive(Debug, Clone)]
 pub enum SourceScale {
@@ -37,6 +47,8 @@ pub enum SourceScale {
     Small {
         total: usize,
         items: Vec<Arc<dyn ClapItem>>,
+        /// Fingerprint of the items, see [`filter::ScoreCache::source_fingerprint`].
+        fingerprint: u64,
     },
 
     /// Unknown scale, but the cache exists.
@@ -50,6 +62,16 @@ impl Default for SourceScale {
 }
 
 impl SourceScale {
+    pub fn small(items: Vec<Arc<dyn ClapItem>>) -> Self {
+        let fingerprint =
+            filter::ScoreCache::source_fingerprint(items.iter().map(|item| item.raw_text()));
+        Self::Small {
+            total: items.len(),
+            items,
+            fingerprint,
+        }
+    }
+
     pub fn total(&self) -> Option<usize> {
         match self {
             Self::Large(total) | Self::Small { total, .. } | Self::Cache { total, .. } => {
@@ -93,6 +115,19 @@ impl SourceScale {
     }
 }
 
+/// Matching config of the queries starting with a prefix char, e.g., `>` for the commands of a
+/// combined provider.
+///
+/// The prefix is stripped from the query and only the items starting with the same char are
+/// matched, the unprefixed query matches the items without any of the prefixes.
+#[derive(Debug, Clone, Default)]
+pub struct QueryPrefix {
+    /// Overrides the match scope of provider if specified.
+    pub match_scope: Option<MatchScope>,
+    /// Replaces the bonuses of session if specified.
+    pub bonus: Option<Bonus>,
+}
+
 // TODO: cache the buffer tags per session.
 #[derive(Debug, Clone)]
 pub struct CachedBufTags {
@@ -100,11 +135,24 @@ pub struct CachedBufTags {
     pub tags: Vec<BufferTag>,
 }
 
+/// Cumulative time spent on the filtering of a session.
+#[derive(Debug, Default)]
+pub struct FilterTime {
+    pub spent: Duration,
+    /// Whether the matcher has been downgraded to the substring matching.
+    pub downgraded: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct SessionState {
     pub is_running: Arc<AtomicBool>,
     pub source_scale: Arc<Mutex<SourceScale>>,
     pub buf_tags_cache: Arc<Mutex<HashMap<PathBuf, CachedBufTags>>>,
+    /// Ids of the running background jobs spawned or shared by this session.
+    pub spawned_jobs: SessionJobs,
+    pub filter_time: Arc<Mutex<FilterTime>>,
+    /// Counters of the memory held by the filtering of this session.
+    pub counters: Arc<filter::stats::Counters>,
 }
 
 #[derive(Debug, Clone)]
@@ -117,10 +165,40 @@ pub struct SessionContext {
     pub display_winwidth: u64,
     pub preview_winheight: u64,
     pub icon: Icon,
-    pub match_scope: MatchScope,
-    pub match_bonuses: Vec<matcher::Bonus>,
+    /// Overrides the default number of displayed results if specified.
+    pub number: Option<usize>,
+    /// Overrides the default match scope of provider if specified.
+    pub match_scope: Option<MatchScope>,
+    /// Query-independent config of the matcher, built once on creating the session.
+    pub matcher_builder: MatcherBuilder,
+    /// Prefix chars of the query switching the matching config.
+    pub query_prefixes: HashMap<char, QueryPrefix>,
     pub source_cmd: Option<String>,
     pub runtimepath: Option<String>,
+    /// Minimum interval between the starts of two `on_typed` invocations, no limit if zero.
+    pub min_interval_between_typed: Duration,
+    /// Lower bound of the debounce delay adapted to the duration of filtering.
+    pub min_debounce_delay: Duration,
+    /// Upper bound of the debounce delay adapted to the duration of filtering.
+    pub max_debounce_delay: Duration,
+    /// Files larger than this are previewed by seeking from a line index instead of being read
+    /// entirely, in bytes.
+    pub max_preview_file_size: u64,
+    /// Files larger than this are not previewed, in bytes.
+    pub preview_file_size_cap: u64,
+    /// Flush the buffered results of provider on terminate instead of a clean teardown.
+    pub flush_on_terminate: bool,
+    /// Once the cumulative filtering time of session exceeds this budget, the fuzzy matching is
+    /// downgraded to the cheaper substring matching for the rest of session.
+    pub filter_time_budget: Option<Duration>,
+    /// Query applied to the initial results on creating the session, e.g., the word under cursor
+    /// for `grep`.
+    pub initial_query: Option<String>,
+    /// Content of the start buffer supplied on creating the session, which may differ from the
+    /// file on disk when the buffer is modified.
+    pub buffer_lines: Option<Arc<Vec<String>>>,
+    /// Window owning the list of the window-scoped providers, e.g., `loclist`.
+    pub winid: Option<u64>,
     pub state: SessionState,
 }
 
@@ -131,6 +209,19 @@ impl SessionContext {
         Ok(out.stdout)
     }
 
+    /// Returns the number of results to display, `default` is used unless the session has one.
+    pub fn display_number(&self, default: usize) -> usize {
+        self.number.unwrap_or(default)
+    }
+
+    /// Returns how the long lines of this provider are truncated.
+    pub fn truncation_mode(&self) -> printer::TruncationMode {
+        match self.provider_id.as_str() {
+            "files" | "git_files" => printer::TruncationMode::Middle,
+            _ => printer::TruncationMode::Auto,
+        }
+    }
+
     /// Size for fulfilling the preview window.
     pub fn sensible_preview_size(&self) -> usize {
         std::cmp::max(
@@ -139,11 +230,76 @@ impl SessionContext {
         )
     }
 
-    pub fn fuzzy_matcher(&self, query: impl Into<Query>) -> matcher::Matcher {
-        // TODO: bonuses
-        matcher::MatcherBuilder::default()
-            .match_scope(self.match_scope)
-            .build(query.into())
+    /// Returns the matcher of `query`, only the query-specific part is built on each call.
+    ///
+    /// The bonuses of the session, e.g., the file name bonus of the file providers, apply to the
+    /// small sources filtered with this matcher too, the same as the large sources.
+    pub fn fuzzy_matcher(
+        &self,
+        match_scope: MatchScope,
+        query: impl AsRef<str>,
+    ) -> matcher::Matcher {
+        let (matcher_builder, query) = self.route_query(match_scope, query.as_ref());
+        matcher_builder.build(Query::from(query))
+    }
+
+    /// Returns the matcher builder of `query` routed by its prefix and the query with the prefix
+    /// stripped.
+    pub fn route_query<'q>(
+        &self,
+        match_scope: MatchScope,
+        query: &'q str,
+    ) -> (MatcherBuilder, &'q str) {
+        let mut matcher_builder = self.matcher_builder.clone().match_scope(match_scope);
+        if self.is_matcher_downgraded() {
+            matcher_builder = matcher_builder.fuzzy_algo(FuzzyAlgorithm::Substring);
+        }
+
+        if self.query_prefixes.is_empty() {
+            return (matcher_builder, query);
+        }
+
+        let sigils = self.query_prefixes.keys().copied().collect();
+        let mut chars = query.chars();
+        match chars
+            .next()
+            .and_then(|c| self.query_prefixes.get(&c).map(|prefix| (c, prefix)))
+        {
+            Some((sigil, prefix)) => {
+                let mut matcher_builder = matcher_builder.sigils(Some(sigil), sigils);
+                if let Some(match_scope) = prefix.match_scope {
+                    matcher_builder = matcher_builder.match_scope(match_scope);
+                }
+                if let Some(ref bonus) = prefix.bonus {
+                    matcher_builder = matcher_builder.bonuses(vec![bonus.clone()]);
+                }
+                (matcher_builder, chars.as_str())
+            }
+            None => (matcher_builder.sigils(None, sigils), query),
+        }
+    }
+
+    /// Adds `elapsed` to the cumulative filtering time of session, returns `true` if the
+    /// matcher is downgraded due to the budget being exceeded by this filtering.
+    pub fn record_filter_time(&self, elapsed: Duration) -> bool {
+        let budget = match self.filter_time_budget {
+            Some(budget) => budget,
+            None => return false,
+        };
+
+        let mut filter_time = self.state.filter_time.lock();
+        filter_time.spent += elapsed;
+        if !filter_time.downgraded && filter_time.spent > budget {
+            filter_time.downgraded = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` if the matcher is downgraded to the substring matching.
+    pub fn is_matcher_downgraded(&self) -> bool {
+        self.state.filter_time.lock().downgraded
     }
 
     pub fn set_source_scale(&self, new: SourceScale) {
@@ -152,6 +308,12 @@ impl SessionContext {
     }
 
     fn from_params(params: Params) -> Self {
+        #[derive(Deserialize)]
+        struct QueryPrefixParams {
+            match_scope: Option<String>,
+            bonus: Option<String>,
+        }
+
         #[derive(Deserialize)]
         struct InnerParams {
             provider_id: ProviderId,
@@ -164,6 +326,31 @@ impl SessionContext {
             source_cmd: Option<String>,
             runtimepath: Option<String>,
             enable_icon: Option<bool>,
+            number: Option<usize>,
+            match_scope: Option<String>,
+            /// In milliseconds.
+            min_interval_between_typed: Option<u64>,
+            /// In milliseconds.
+            min_debounce_delay: Option<u64>,
+            /// In milliseconds.
+            max_debounce_delay: Option<u64>,
+            /// In bytes.
+            max_preview_file_size: Option<u64>,
+            /// In bytes.
+            preview_file_size_cap: Option<u64>,
+            flush_on_terminate: Option<bool>,
+            /// In milliseconds, no budget if not specified or 0.
+            filter_time_budget: Option<u64>,
+            initial_query: Option<String>,
+            buffer_lines: Option<Vec<String>>,
+            winid: Option<u64>,
+            /// Recently opened file list for adding a bonus to the score.
+            recent_files: Option<PathBuf>,
+            /// Add a bonus to the files modified recently.
+            recently_modified: Option<bool>,
+            /// Matching config of each prefix char of the query, e.g.,
+            /// `{">": {"match_scope": "full", "bonus": "acronym"}}`.
+            query_prefixes: Option<HashMap<String, QueryPrefixParams>>,
         }
 
         let InnerParams {
@@ -177,16 +364,25 @@ impl SessionContext {
             source_cmd,
             runtimepath,
             enable_icon,
+            number,
+            match_scope,
+            min_interval_between_typed,
+            min_debounce_delay,
+            max_debounce_delay,
+            max_preview_file_size,
+            preview_file_size_cap,
+            flush_on_terminate,
+            filter_time_budget,
+            initial_query,
+            buffer_lines,
+            winid,
+            recent_files,
+            recently_modified,
+            query_prefixes,
         } = params
             .parse()
             .expect("Failed to deserialize SessionContext");
 
-        let match_scope = match provider_id.as_str() {
-            "tags" | "proj_tags" => MatchScope::TagName,
-            "grep" | "live_grep" => MatchScope::GrepLine,
-            _ => MatchScope::Full,
-        };
-
         let icon = if enable_icon.unwrap_or(false) {
             match provider_id.as_str() {
                 "tags" => Icon::Enabled(IconKind::BufferTags),
@@ -199,10 +395,42 @@ impl SessionContext {
             Icon::Null
         };
 
-        let match_bonuses = match provider_id.as_str() {
-            "files" | "git_files" | "filer" => vec![matcher::Bonus::FileName],
+        let mut match_bonuses = match provider_id.as_str() {
+            "files" | "git_files" | "filer" => vec![Bonus::FileName],
             _ => vec![],
         };
+        if let Some(recent_files) = recent_files
+            .as_deref()
+            .and_then(crate::recent_files::read_recent_files)
+        {
+            match_bonuses.push(Bonus::RecentFiles(recent_files));
+        }
+        if recently_modified.unwrap_or(false) {
+            match_bonuses.push(Bonus::RecentlyModified(RecentlyModified::new(
+                cwd.to_path_buf(),
+            )));
+        }
+
+        let query_prefixes = query_prefixes
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(prefix, QueryPrefixParams { match_scope, bonus })| {
+                let mut chars = prefix.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some((
+                        c,
+                        QueryPrefix {
+                            match_scope: match_scope.map(Into::into),
+                            bonus: bonus.map(Into::into),
+                        },
+                    )),
+                    _ => {
+                        tracing::error!(?prefix, "The query prefix must be a single char");
+                        None
+                    }
+                }
+            })
+            .collect();
 
         Self {
             provider_id,
@@ -210,22 +438,66 @@ impl SessionContext {
             no_cache,
             debounce: debounce.unwrap_or(true),
             start_buffer_path: source_fpath,
-            display_winwidth: display_winwidth.unwrap_or(DEFAULT_DISPLAY_WINWIDTH),
+            display_winwidth: printer::normalize_winwidth(display_winwidth.map(|w| w as usize))
+                as u64,
             preview_winheight: preview_winheight.unwrap_or(DEFAULT_PREVIEW_WINHEIGHT),
             source_cmd,
             runtimepath,
-            match_scope,
-            match_bonuses,
+            match_scope: match_scope.map(Into::into),
+            matcher_builder: MatcherBuilder::default().bonuses(match_bonuses),
+            query_prefixes,
             icon,
+            number,
+            min_interval_between_typed: Duration::from_millis(
+                min_interval_between_typed.unwrap_or(0),
+            ),
+            min_debounce_delay: Duration::from_millis(
+                min_debounce_delay.unwrap_or(DEFAULT_MIN_DEBOUNCE_DELAY),
+            ),
+            max_debounce_delay: Duration::from_millis(
+                max_debounce_delay.unwrap_or(DEFAULT_MAX_DEBOUNCE_DELAY),
+            ),
+            max_preview_file_size: max_preview_file_size.unwrap_or(DEFAULT_MAX_PREVIEW_FILE_SIZE),
+            preview_file_size_cap: preview_file_size_cap.unwrap_or(DEFAULT_PREVIEW_FILE_SIZE_CAP),
+            flush_on_terminate: flush_on_terminate.unwrap_or(false),
+            filter_time_budget: filter_time_budget
+                .filter(|budget| *budget > 0)
+                .map(Duration::from_millis),
+            initial_query: initial_query.filter(|query| !query.is_empty()),
+            buffer_lines: buffer_lines.map(Arc::new),
+            winid,
             state: SessionState {
                 is_running: Arc::new(true.into()),
                 source_scale: Arc::new(Mutex::new(SourceScale::Indefinite)),
                 buf_tags_cache: Arc::new(Mutex::new(HashMap::new())),
+                spawned_jobs: Default::default(),
+                filter_time: Arc::new(Mutex::new(FilterTime::default())),
+                counters: Default::default(),
             },
         }
     }
 }
 
+impl SessionContext {
+    /// Constructs a new instance of [`SessionContext`] of `provider_id` with all the default
+    /// options, used for inspecting the provider outside of a session.
+    pub fn with_defaults(provider_id: &str, cwd: &std::path::Path) -> Result<Self> {
+        let params = serde_json::from_value(serde_json::json!({
+            "provider_id": provider_id,
+            "cwd": cwd,
+            "no_cache": false,
+            "source_fpath": "",
+        }))?;
+        Ok(Self::from_params(params))
+    }
+
+    /// Constructs a new instance of [`SessionContext`] for the tests.
+    #[cfg(test)]
+    pub fn new_test_context(provider_id: &str) -> Self {
+        Self::with_defaults(provider_id, &std::env::current_dir().unwrap()).unwrap()
+    }
+}
+
 impl From<MethodCall> for SessionContext {
     fn from(method_call: MethodCall) -> Self {
         Self::from_params(method_call.params)
@@ -247,3 +519,153 @@ impl From<Call> for SessionContext {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matcher_builder_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let recent_files = dir.path().join("recent_files");
+        std::fs::write(&recent_files, "crates/printer/src/lib.rs\n").unwrap();
+
+        let params = serde_json::from_value(serde_json::json!({
+            "provider_id": "files",
+            "cwd": std::env::current_dir().unwrap(),
+            "no_cache": false,
+            "source_fpath": "",
+            "recent_files": recent_files,
+        }))
+        .unwrap();
+        let context = SessionContext::from_params(params);
+
+        let score = |context: &SessionContext, query: &str| {
+            context
+                .fuzzy_matcher(MatchScope::Full, query)
+                .match_item(Arc::new("crates/printer/src/lib.rs"))
+                .unwrap()
+                .score
+        };
+
+        let no_bonus_context = SessionContext::new_test_context("files");
+        let score_with_bonus = score(&context, "lib");
+        assert!(score_with_bonus > score(&no_bonus_context, "lib"));
+
+        // The recent files are not read again on the new queries.
+        std::fs::remove_file(&recent_files).unwrap();
+        assert!(score(&context, "printer") > score(&no_bonus_context, "printer"));
+        assert_eq!(score(&context, "lib"), score_with_bonus);
+    }
+
+    #[test]
+    fn test_small_source_matcher_bonuses() {
+        let score = |matcher: matcher::Matcher| {
+            matcher
+                .match_item(Arc::new("crates/printer/src/lib.rs"))
+                .unwrap()
+                .score
+        };
+        let no_bonus_score = score(
+            MatcherBuilder::default()
+                .match_scope(MatchScope::Full)
+                .build("lib".into()),
+        );
+
+        // The file name bonus applies to the file providers only.
+        let files = SessionContext::new_test_context("files");
+        assert!(score(files.fuzzy_matcher(MatchScope::Full, "lib")) > no_bonus_score);
+        let tags = SessionContext::new_test_context("tags");
+        assert_eq!(
+            score(tags.fuzzy_matcher(MatchScope::Full, "lib")),
+            no_bonus_score
+        );
+    }
+    #[test]
+    fn test_query_prefixes() {
+        let params = serde_json::from_value(serde_json::json!({
+            "provider_id": "combined",
+            "cwd": std::env::current_dir().unwrap(),
+            "no_cache": false,
+            "source_fpath": "",
+            "query_prefixes": { ">": { "bonus": "acronym" }, "@": { "match_scope": "filename" } },
+        }))
+        .unwrap();
+        let context = SessionContext::from_params(params);
+        assert!(matches!(
+            context.query_prefixes[&'>'].bonus,
+            Some(Bonus::Acronym)
+        ));
+
+        let items: Vec<Arc<dyn ClapItem>> = vec![
+            Arc::new("src/foo.rs"),
+            Arc::new(">foo_command"),
+            Arc::new("@foo/bar.rs"),
+        ];
+        let matched = |query: &str| {
+            let matcher = context.fuzzy_matcher(MatchScope::Full, query);
+            items
+                .iter()
+                .filter_map(|item| matcher.match_item(item.clone()))
+                .map(|matched_item| {
+                    (
+                        matched_item.item.raw_text().to_string(),
+                        matched_item.indices,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matched("foo"), vec![("src/foo.rs".into(), vec![4, 5, 6])]);
+        // The stripped prefix is not matched.
+        assert_eq!(
+            matched(">foo"),
+            vec![(">foo_command".into(), vec![1, 2, 3])]
+        );
+        // The match scope is routed by the prefix.
+        assert_eq!(matched("@bar"), vec![("@foo/bar.rs".into(), vec![5, 6, 7])]);
+        assert!(matched("@foo").is_empty());
+    }
+
+    #[test]
+    fn test_filter_time_budget() {
+        let params = serde_json::from_value(serde_json::json!({
+            "provider_id": "files",
+            "cwd": std::env::current_dir().unwrap(),
+            "no_cache": false,
+            "source_fpath": "",
+            "filter_time_budget": 100,
+        }))
+        .unwrap();
+        let context = SessionContext::from_params(params);
+
+        // `abc` matches `a_b_c` fuzzily but not as a substring.
+        let is_fuzzy_match = |context: &SessionContext| {
+            context
+                .fuzzy_matcher(MatchScope::Full, "abc")
+                .match_item(Arc::new("a_b_c"))
+                .is_some()
+        };
+
+        assert!(!context.record_filter_time(Duration::from_millis(60)));
+        assert!(!context.is_matcher_downgraded());
+        assert!(is_fuzzy_match(&context));
+
+        // Exceeds the budget.
+        assert!(context.record_filter_time(Duration::from_millis(50)));
+        assert!(context.is_matcher_downgraded());
+        assert!(!is_fuzzy_match(&context));
+        assert!(context
+            .fuzzy_matcher(MatchScope::Full, "b_c")
+            .match_item(Arc::new("a_b_c"))
+            .is_some());
+
+        // Downgraded only once.
+        assert!(!context.record_filter_time(Duration::from_millis(50)));
+
+        // No budget by default.
+        let context = SessionContext::new_test_context("files");
+        assert!(!context.record_filter_time(Duration::from_secs(3600)));
+        assert!(is_fuzzy_match(&context));
+    }
+}
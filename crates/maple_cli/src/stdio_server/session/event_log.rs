@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// Maximum number of records kept per session, the oldest one is dropped once it's full.
+const CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRecord {
+    /// Milliseconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// Short display of the event, e.g., `OnTyped, msg_id: 3`.
+    pub event: String,
+    /// Error occurred on processing the event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Recent events of a session, for debugging the session independently of the global logs.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    records: Arc<Mutex<VecDeque<EventRecord>>>,
+}
+
+impl EventLog {
+    fn push(&self, event: String, error: Option<String>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut records = self.records.lock();
+        if records.len() == CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(EventRecord {
+            timestamp,
+            event,
+            error,
+        });
+    }
+
+    /// Records the reception of `event`.
+    pub fn record(&self, event: impl Into<String>) {
+        self.push(event.into(), None);
+    }
+
+    /// Records the error occurred on processing `event`.
+    pub fn record_error(&self, event: impl Into<String>, error: &anyhow::Error) {
+        self.push(event.into(), Some(format!("{error:#}")));
+    }
+
+    /// Returns the records from the oldest to the latest.
+    pub fn records(&self) -> Vec<EventRecord> {
+        self.records.lock().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_is_bounded() {
+        let event_log = EventLog::default();
+        for i in 0..CAPACITY + 10 {
+            event_log.record(format!("OnTyped, msg_id: {i}"));
+        }
+        event_log.record_error("OnMove", &anyhow::anyhow!("No line"));
+
+        let records = event_log.records();
+        assert_eq!(records.len(), CAPACITY);
+        assert_eq!(records[0].event, "OnTyped, msg_id: 11");
+        assert_eq!(records[CAPACITY - 1].event, "OnMove");
+        assert_eq!(records[CAPACITY - 1].error.as_deref(), Some("No line"));
+    }
+}
@@ -1,9 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use anyhow::{anyhow, Result};
+use filter::stats::{Counters, Stats};
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{ClapProvider, SessionContext};
-use crate::stdio_server::rpc::Call;
+use super::{Chain, ClapProvider, EventLog, EventRecord, SessionContext};
+use crate::stdio_server::impls::create_provider;
+use crate::stdio_server::rpc::{Call, MethodCall};
 use crate::stdio_server::session::{Session, SessionId};
 use crate::stdio_server::ProviderEvent;
 
@@ -34,10 +39,29 @@ impl ProviderEventSender {
     }
 }
 
+/// Maximum number of the sessions whose event logs are kept, including the terminated ones.
+const MAX_EVENT_LOGS: usize = 8;
+
+/// Interval of sweeping the inactive sessions.
+pub const IDLE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Returns the configured timeout of the inactive sessions, `None` if they are kept forever.
+pub fn idle_timeout() -> Option<Duration> {
+    crate::config::config().session.idle_timeout()
+}
+
 /// This structs manages all the created sessions tracked by the session id.
 #[derive(Debug, Default)]
 pub struct SessionManager {
     sessions: HashMap<SessionId, ProviderEventSender>,
+    /// Event logs of the recent sessions, still available after the session is terminated.
+    event_logs: BTreeMap<SessionId, EventLog>,
+    /// Follow-up providers of the sessions specified in the create params.
+    chains: HashMap<SessionId, Chain>,
+    /// Time of the last event sent to each session.
+    last_activity: HashMap<SessionId, Instant>,
+    /// Counters of the memory held by the filtering of each session.
+    counters: HashMap<SessionId, Arc<Counters>>,
 }
 
 impl SessionManager {
@@ -48,7 +72,23 @@ impl SessionManager {
         if self.exists(session_id) {
             tracing::error!(session_id, "Skipped as given session already exists");
         } else {
+            if let Some(chain) = Chain::from_init_call(&init_call) {
+                self.chains.insert(session_id, chain);
+            }
+
+            self.counters.insert(
+                session_id,
+                provider_handle.session_context().state.counters.clone(),
+            );
+
             let (session, session_sender) = Session::new(session_id, provider_handle);
+            self.event_logs
+                .insert(session_id, session.event_log.clone());
+            // The session ids are increasing, drop the oldest logs.
+            while self.event_logs.len() > MAX_EVENT_LOGS {
+                let oldest = *self.event_logs.keys().next().expect("Not empty; qed");
+                self.event_logs.remove(&oldest);
+            }
             session.start_event_loop();
 
             session_sender
@@ -59,6 +99,7 @@ impl SessionManager {
                 session_id,
                 ProviderEventSender::new(session_sender, session_id),
             );
+            self.last_activity.insert(session_id, Instant::now());
         }
     }
 
@@ -67,17 +108,83 @@ impl SessionManager {
         self.sessions.contains_key(&session_id)
     }
 
+    /// Returns the recent events of session `session_id`.
+    pub fn event_records(&self, session_id: SessionId) -> Option<Vec<EventRecord>> {
+        self.event_logs.get(&session_id).map(EventLog::records)
+    }
+
+    /// Returns the snapshot of the counters of session `session_id`.
+    pub fn stats(&self, session_id: SessionId) -> Option<Stats> {
+        self.counters
+            .get(&session_id)
+            .map(|counters| counters.snapshot())
+    }
+
+    /// Starts the follow-up session `next_session_id` of session `session_id` seeded from the
+    /// `selected` line, returns the create call of the follow-up session.
+    ///
+    /// The chain is consumed, it must be started before the session is terminated.
+    pub fn start_chain(
+        &mut self,
+        session_id: SessionId,
+        next_session_id: SessionId,
+        selected: &str,
+        id: u64,
+    ) -> Result<MethodCall> {
+        let chain = self
+            .chains
+            .remove(&session_id)
+            .ok_or_else(|| anyhow!("No chain specified for session {session_id}"))?;
+
+        let init_call = chain.next_init_call(id, next_session_id, selected)?;
+        let context: SessionContext = init_call.clone().into();
+        self.new_session(
+            Call::MethodCall(init_call.clone()),
+            create_provider(context),
+        );
+
+        Ok(init_call)
+    }
+
     /// Stop the session task by sending [`ProviderEvent::Terminate`].
     pub fn terminate(&mut self, session_id: SessionId) {
+        self.chains.remove(&session_id);
+        self.last_activity.remove(&session_id);
+        self.counters.remove(&session_id);
         if let Some(sender) = self.sessions.remove(&session_id) {
             sender.send(ProviderEvent::Terminate);
         }
     }
 
+    /// Terminates the sessions without any event since `idle_timeout` before `now`, returns
+    /// the ids of terminated sessions.
+    pub fn reap_idle_sessions(&mut self, now: Instant, idle_timeout: Duration) -> Vec<SessionId> {
+        let idle_sessions = self
+            .last_activity
+            .iter()
+            .filter(|(_, last_activity)| {
+                now.saturating_duration_since(**last_activity) >= idle_timeout
+            })
+            .map(|(session_id, _)| *session_id)
+            .collect::<Vec<_>>();
+
+        for session_id in &idle_sessions {
+            tracing::debug!(
+                session_id,
+                ?idle_timeout,
+                "Terminating the inactive session"
+            );
+            self.terminate(*session_id);
+        }
+
+        idle_sessions
+    }
+
     /// Dispatch the session event to the background session task accordingly.
-    pub fn send(&self, session_id: SessionId, event: ProviderEvent) {
+    pub fn send(&mut self, session_id: SessionId, event: ProviderEvent) {
         if let Some(sender) = self.sessions.get(&session_id) {
             sender.send(event);
+            self.last_activity.insert(session_id, Instant::now());
         } else {
             tracing::error!(
                 session_id,
@@ -87,3 +194,51 @@ impl SessionManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reap_idle_sessions() {
+        let mut manager = SessionManager::default();
+
+        let mut receivers = HashMap::new();
+        for session_id in [1, 2] {
+            let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+            manager
+                .sessions
+                .insert(session_id, ProviderEventSender::new(sender, session_id));
+            receivers.insert(session_id, receiver);
+        }
+
+        let start = Instant::now();
+        manager.last_activity.insert(1, start);
+        manager
+            .last_activity
+            .insert(2, start + Duration::from_secs(50));
+
+        let idle_timeout = Duration::from_secs(60);
+        assert!(manager
+            .reap_idle_sessions(start + Duration::from_secs(30), idle_timeout)
+            .is_empty());
+
+        assert_eq!(
+            manager.reap_idle_sessions(start + Duration::from_secs(70), idle_timeout),
+            vec![1]
+        );
+        assert!(!manager.exists(1));
+        assert!(manager.exists(2));
+        assert!(matches!(
+            receivers.get_mut(&1).unwrap().try_recv(),
+            Ok(ProviderEvent::Terminate)
+        ));
+        assert!(receivers.get_mut(&2).unwrap().try_recv().is_err());
+
+        assert_eq!(
+            manager.reap_idle_sessions(start + Duration::from_secs(110), idle_timeout),
+            vec![2]
+        );
+        assert!(!manager.exists(2));
+    }
+}
@@ -1,49 +1,74 @@
+mod chain;
 mod context;
+mod event_log;
 mod manager;
 
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::fmt::Debug;
+use std::path::PathBuf;
 use std::sync::{atomic::Ordering, Arc};
 use std::time::Duration;
 
 use anyhow::Result;
 use futures::Future;
+use matcher::MatchScope;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use serde_json::json;
 use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
 use tokio::time::Instant;
+use types::{ClapItem, MatchedItem};
 
-use crate::stdio_server::impls::initialize;
+use crate::stdio_server::impls::{initialize, refresh};
 use crate::stdio_server::rpc::Call;
 use crate::stdio_server::types::ProviderId;
-use crate::stdio_server::MethodCall;
+use crate::stdio_server::{write_response, MethodCall};
 
+pub use self::chain::Chain;
 pub use self::context::{SessionContext, SourceScale};
-pub use self::manager::SessionManager;
+pub use self::event_log::{EventLog, EventRecord};
+pub use self::manager::{idle_timeout, SessionManager, IDLE_SWEEP_INTERVAL};
 
-static BACKGROUND_JOBS: Lazy<Arc<Mutex<HashSet<u64>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(HashSet::default())));
+/// Join handles of the background jobs by the job id, used for cancelling the jobs.
+type JobHandles = HashMap<u64, Option<JoinHandle<()>>>;
 
+/// Background jobs tracked by the job id.
+static BACKGROUND_JOBS: Lazy<Arc<Mutex<JobHandles>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::default())));
+
+/// Spawns a new job unless there is already one with the same `job_id`.
+///
+/// Returns `true` if the job is spawned.
 pub fn spawn_singleton_job(
-    task_future: impl Future<Output = ()> + Send + Sync + 'static,
+    task_future: impl Future<Output = ()> + Send + 'static,
     job_id: u64,
-) {
-    if register_job_successfully(job_id) {
-        tokio::spawn(async move {
-            task_future.await;
-            note_job_is_finished(job_id)
-        });
+) -> bool {
+    // Hold the lock until the join handle is inserted, which ensures the job can only be
+    // removed after being registered.
+    let mut background_jobs = BACKGROUND_JOBS.lock();
+    match background_jobs.entry(job_id) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            let join_handle = tokio::spawn(async move {
+                task_future.await;
+                note_job_is_finished(job_id)
+            });
+            entry.insert(Some(join_handle));
+            true
+        }
     }
 }
 
 pub fn register_job_successfully(job_id: u64) -> bool {
     let mut background_jobs = BACKGROUND_JOBS.lock();
-    if background_jobs.contains(&job_id) {
-        false
-    } else {
-        background_jobs.insert(job_id);
-        true
+    match background_jobs.entry(job_id) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(None);
+            true
+        }
     }
 }
 
@@ -52,35 +77,488 @@ pub fn note_job_is_finished(job_id: u64) {
     background_jobs.remove(&job_id);
 }
 
+/// Returns `true` if the job with given `job_id` is still running.
+pub fn is_job_running(job_id: u64) -> bool {
+    BACKGROUND_JOBS.lock().contains_key(&job_id)
+}
+
+/// Cancels the job spawned by [`spawn_singleton_job`], returns `true` if the job was running.
+pub fn cancel_job(job_id: u64) -> bool {
+    let join_handle = {
+        let mut background_jobs = BACKGROUND_JOBS.lock();
+        match background_jobs.get(&job_id) {
+            Some(Some(_)) => background_jobs.remove(&job_id).flatten(),
+            _ => None,
+        }
+    };
+    match join_handle {
+        Some(join_handle) => {
+            join_handle.abort();
+            // The aborted job never gets to detach itself from the sessions.
+            detach_session_job(job_id);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Ids of the running background jobs of a session.
+pub type SessionJobs = Arc<Mutex<HashSet<u64>>>;
+
+/// Sessions sharing each running session job, the job is cancelled once all of them are
+/// terminated.
+///
+/// The lock is always taken before the lock of [`BACKGROUND_JOBS`] if both are needed.
+static SESSION_JOBS: Lazy<Mutex<HashMap<u64, Vec<SessionJobs>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Removes the finished or cancelled job `job_id` from all the sessions sharing it.
+fn detach_session_job(job_id: u64) {
+    if let Some(sessions) = SESSION_JOBS.lock().remove(&job_id) {
+        for session_jobs in sessions {
+            session_jobs.lock().remove(&job_id);
+        }
+    }
+}
+
+/// Spawns a singleton job on behalf of the session, which will be cancelled on terminating
+/// the session unless it's still shared by other sessions.
+///
+/// If the session job with the same `job_id` is already running, the session shares it instead.
+/// The job id is removed from the sessions once the job is finished.
+///
+/// Returns `true` if the job is spawned.
+pub fn spawn_session_job(
+    context: &SessionContext,
+    task_future: impl Future<Output = ()> + Send + 'static,
+    job_id: u64,
+) -> bool {
+    let session_jobs = &context.state.spawned_jobs;
+
+    // Hold the lock until the session is attached, so that the job can't detach itself before.
+    let mut all_session_jobs = SESSION_JOBS.lock();
+    let spawned = spawn_singleton_job(
+        async move {
+            task_future.await;
+            detach_session_job(job_id);
+        },
+        job_id,
+    );
+
+    if spawned {
+        all_session_jobs.insert(job_id, vec![session_jobs.clone()]);
+        session_jobs.lock().insert(job_id);
+    } else if let Some(sessions) = all_session_jobs.get_mut(&job_id) {
+        if !sessions.iter().any(|s| Arc::ptr_eq(s, session_jobs)) {
+            sessions.push(session_jobs.clone());
+            session_jobs.lock().insert(job_id);
+        }
+    }
+
+    spawned
+}
+
+/// Detaches the session from all of its jobs, the jobs no longer shared by any session are
+/// cancelled.
+///
+/// Returns the ids of cancelled jobs.
+fn release_session_jobs(session_jobs: &SessionJobs) -> Vec<u64> {
+    let orphan_jobs = {
+        let mut all_session_jobs = SESSION_JOBS.lock();
+        session_jobs
+            .lock()
+            .drain()
+            .filter(|job_id| match all_session_jobs.get_mut(job_id) {
+                Some(sessions) => {
+                    sessions.retain(|s| !Arc::ptr_eq(s, session_jobs));
+                    sessions.is_empty()
+                }
+                None => false,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    orphan_jobs
+        .into_iter()
+        .filter(|job_id| cancel_job(*job_id))
+        .collect()
+}
+
+/// Forerunner job building the cache of each provider, tracked along with the cwd it's building
+/// the cache for.
+static FORERUNNER_JOBS: Lazy<Mutex<HashMap<String, (PathBuf, u64)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Cancels the in-flight forerunner job of provider in `context` if it was started for another
+/// cwd, whose cache is irrelevant now.
+///
+/// Returns `true` if a job was cancelled.
+pub fn cancel_stale_forerunner_job(context: &SessionContext) -> bool {
+    let mut forerunner_jobs = FORERUNNER_JOBS.lock();
+    let provider_id = context.provider_id.as_str();
+    match forerunner_jobs.get(provider_id) {
+        Some((cwd, job_id)) if cwd != context.cwd.as_path() => {
+            let job_id = *job_id;
+            forerunner_jobs.remove(provider_id);
+            let cancelled = cancel_job(job_id);
+            if cancelled {
+                tracing::debug!(
+                    provider_id,
+                    job_id,
+                    "Cancelled the forerunner job of old cwd"
+                );
+            }
+            cancelled
+        }
+        _ => false,
+    }
+}
+
+/// Spawns the forerunner job building the cache of provider in `context`, the forerunner job of
+/// the previous cwd is cancelled first.
+///
+/// Returns `true` if the job is spawned.
+pub fn spawn_forerunner_job(
+    context: &SessionContext,
+    task_future: impl Future<Output = ()> + Send + 'static,
+    job_id: u64,
+) -> bool {
+    cancel_stale_forerunner_job(context);
+
+    let spawned = spawn_session_job(context, task_future, job_id);
+    if spawned {
+        FORERUNNER_JOBS.lock().insert(
+            context.provider_id.as_str().to_string(),
+            (context.cwd.to_path_buf(), job_id),
+        );
+    }
+    spawned
+}
+
+/// Returns the id of job refreshing the source of provider in `context`.
+fn refresh_job_id(context: &SessionContext) -> u64 {
+    utility::calculate_hash(&(
+        "refresh",
+        context.provider_id.as_str(),
+        context.cwd.as_path(),
+    ))
+}
+
 pub type SessionId = u64;
 
-fn process_source_scale(source_scale: SourceScale, context: &SessionContext) {
+/// If the debounce timer isn't active, it will be set to expire "never",
+/// which is actually just 1 year in the future.
+const NEVER: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+// https://github.com/denoland/deno/blob/1fb5858009f598ce3f917f9f49c466db81f4d9b0/cli/lsp/diagnostics.rs#L141
+//
+// Debounce timer delay. 150ms between keystrokes is about 45 WPM, so we
+// want something that is longer than that, but not too long to
+// introduce detectable UI delay; 200ms is a decent compromise.
+//
+// Add extra 50ms delay.
+const DEFAULT_DEBOUNCE_DELAY: Duration = Duration::from_millis(200 + 50);
+
+/// Debounce delay adapted to the duration of the recent `on_typed` handlers, short when the
+/// filtering is cheap and long when it's expensive.
+#[derive(Debug, Clone, Copy)]
+struct AdaptiveDebounce {
+    delay: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl AdaptiveDebounce {
+    fn new(min: Duration, max: Duration) -> Self {
+        let max = max.max(min);
+        Self {
+            delay: DEFAULT_DEBOUNCE_DELAY.clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Moves the delay halfway towards twice the duration `elapsed` of the last `on_typed`.
+    fn note_on_typed_elapsed(&mut self, elapsed: Duration) {
+        let target = (elapsed * 2).clamp(self.min, self.max);
+        self.delay = (self.delay + target) / 2;
+    }
+}
+
+/// Returns the earliest instant the next `on_typed` can be started at if it's too early for now.
+fn throttle_deadline(
+    last_on_typed: Option<Instant>,
+    min_interval: Duration,
+    now: Instant,
+) -> Option<Instant> {
+    last_on_typed
+        .map(|last| last + min_interval)
+        .filter(|deadline| *deadline > now)
+}
+
+/// Returns the items of `source_scale` matching the initial query of session, `None` if there
+/// is no initial query or the source is not small enough to be filtered on creating the session.
+fn seed_initial_query(
+    source_scale: &SourceScale,
+    context: &SessionContext,
+    match_scope: MatchScope,
+) -> Option<Vec<MatchedItem>> {
+    match (context.initial_query.as_deref(), source_scale) {
+        (Some(query), SourceScale::Small { items, .. }) => Some(filter::par_filter_items(
+            items,
+            &context.fuzzy_matcher(match_scope, query),
+            &context.state.counters,
+        )),
+        _ => None,
+    }
+}
+
+/// Returns the lines displayed on creating the session, `seeded_items` are displayed instead of
+/// the initial lines of source if specified.
+fn initial_display_lines(
+    source_scale: &SourceScale,
+    context: &SessionContext,
+    seeded_items: Option<Vec<MatchedItem>>,
+) -> Option<printer::DisplayLines> {
+    let number = context.display_number(100);
+    let lines = match seeded_items {
+        Some(seeded_items) => seeded_items.into_iter().take(number).collect(),
+        None => source_scale.initial_lines(number)?,
+    };
+    Some(printer::decorate_lines_with_truncation(
+        lines,
+        context.display_winwidth as usize,
+        context.icon,
+        context.truncation_mode(),
+    ))
+}
+
+async fn process_source_scale(
+    source_scale: SourceScale,
+    context: &SessionContext,
+    seeded_items: Option<Vec<MatchedItem>>,
+) {
     if let Some(total) = source_scale.total() {
-        let method = "s:set_total_size";
-        utility::println_json_with_length!(total, method);
+        write_response(json!({ "total": total, "method": "s:set_total_size" })).await;
     }
 
-    if let Some(lines) = source_scale.initial_lines(100) {
-        printer::decorate_lines(lines, context.display_winwidth as usize, context.icon)
-            .print_on_session_create();
+    if let Some(display_lines) = initial_display_lines(&source_scale, context, seeded_items) {
+        write_response(display_lines.session_create_message()).await;
     }
 
     context.set_source_scale(source_scale);
 }
 
+/// Item displayed as the text rewritten by [`ClapProvider::format_display`].
+#[derive(Debug)]
+struct FormattedItem {
+    inner: Arc<dyn ClapItem>,
+    display_text: String,
+}
+
+impl ClapItem for FormattedItem {
+    fn raw_text(&self) -> &str {
+        self.inner.raw_text()
+    }
+
+    fn output_text(&self) -> Cow<'_, str> {
+        self.display_text.as_str().into()
+    }
+
+    fn icon(&self, icon: icon::Icon) -> Option<icon::IconType> {
+        self.inner.icon(icon)
+    }
+}
+
+/// Maps the match `indices` of `original` to `formatted`, the indices in the rewritten part are
+/// dropped.
+fn adjust_indices(original: &str, formatted: &str, indices: &[usize]) -> Vec<usize> {
+    let original = original.chars().collect::<Vec<_>>();
+    let formatted = formatted.chars().collect::<Vec<_>>();
+
+    let prefix = original
+        .iter()
+        .zip(formatted.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(formatted[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let suffix_start = original.len() - suffix;
+    let formatted_suffix_start = formatted.len() - suffix;
+
+    indices
+        .iter()
+        .filter_map(|&idx| {
+            if idx < prefix {
+                Some(idx)
+            } else if idx >= suffix_start {
+                Some(idx - suffix_start + formatted_suffix_start)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Logical action on the selected entry, bound to the keys by the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderAction {
+    Open,
+    Split,
+    Vsplit,
+    Tab,
+    /// Changes the working directory to the directory of entry.
+    Cd,
+    Delete,
+}
+
+/// Actions supported by the providers of which the entry is a file location.
+pub const FILE_ACTIONS: [ProviderAction; 4] = [
+    ProviderAction::Open,
+    ProviderAction::Split,
+    ProviderAction::Vsplit,
+    ProviderAction::Tab,
+];
+
+/// Location of a jump target resolved from the selected entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct JumpTarget {
+    pub path: PathBuf,
+    /// 1-based line number.
+    pub lnum: usize,
+    /// 1-based column.
+    pub col: usize,
+}
+
+/// Adds the time `provider` spent on an `on_typed` call to the filtering time of session,
+/// notifies the UI once the matcher is downgraded due to the budget being exceeded.
+async fn record_filter_time(provider: &dyn ClapProvider, elapsed: Duration) {
+    let context = provider.session_context();
+    if context.record_filter_time(elapsed) {
+        tracing::debug!(
+            provider_id = %context.provider_id,
+            "Filtering time budget exceeded, downgraded to the substring matching"
+        );
+        write_response(json!({
+            "method": "s:notify_matcher_downgraded",
+            "fuzzy_algo": "substring",
+        }))
+        .await;
+    }
+}
+
+/// Sends the actions supported by `provider` to the UI.
+async fn send_actions(provider: &dyn ClapProvider) {
+    let actions = provider.actions();
+    let multiple_targets = provider.multiple_targets();
+    write_response(json!({
+        "actions": actions,
+        "multiple_targets": multiple_targets,
+        "method": "s:set_actions",
+    }))
+    .await;
+}
+
 #[async_trait::async_trait]
 pub trait ClapProvider: Debug + Send + Sync + 'static {
     fn session_context(&self) -> &SessionContext;
 
+    fn session_context_mut(&mut self) -> &mut SessionContext;
+
+    /// Returns the match scope used when it's not specified in the session context.
+    fn default_match_scope(&self) -> MatchScope {
+        MatchScope::Full
+    }
+
+    /// Returns the match scope of this provider.
+    fn match_scope(&self) -> MatchScope {
+        self.session_context()
+            .match_scope
+            .unwrap_or_else(|| self.default_match_scope())
+    }
+
+    /// Returns the text of `item` to display, which has no effect on the matching.
+    fn format_display<'a>(&self, item: &'a dyn ClapItem) -> Cow<'a, str> {
+        item.output_text()
+    }
+
+    /// Returns the lines to display for `matched_items`, formatted by [`Self::format_display`].
+    fn decorate_lines(&self, matched_items: Vec<MatchedItem>) -> printer::DisplayLines {
+        let context = self.session_context();
+        let matched_items = matched_items
+            .into_iter()
+            .map(|matched_item| {
+                let formatted = {
+                    let output_text = matched_item.item.output_text();
+                    let formatted = self.format_display(matched_item.item.as_ref());
+                    (formatted != output_text).then(|| {
+                        let indices =
+                            adjust_indices(&output_text, &formatted, &matched_item.indices);
+                        (formatted.into_owned(), indices)
+                    })
+                };
+                match formatted {
+                    Some((display_text, indices)) => {
+                        let item = FormattedItem {
+                            inner: matched_item.item,
+                            display_text,
+                        };
+                        MatchedItem::new(Arc::new(item), matched_item.score, indices)
+                    }
+                    None => matched_item,
+                }
+            })
+            .collect();
+        printer::decorate_lines(
+            matched_items,
+            context.display_winwidth as usize,
+            context.icon,
+        )
+    }
+
+    /// Returns the actions supported by this provider, emitted to the UI on creating the session.
+    fn actions(&self) -> Vec<ProviderAction> {
+        vec![ProviderAction::Open]
+    }
+
+    /// Returns `true` if the selected entry may resolve to multiple jump targets, in which case
+    /// the UI can request all of them via [`Self::on_select`] instead of jumping to the entry.
+    fn multiple_targets(&self) -> bool {
+        false
+    }
+
+    /// Keeps the initial results filtered by the initial query of session, which are displayed
+    /// on creating the session instead of the initial lines of source.
+    fn set_seeded_results(&self, _matched_items: Vec<MatchedItem>) {}
+
     async fn on_create(&mut self, _call: Call) {
         const TIMEOUT: Duration = Duration::from_millis(300);
 
         let context = self.session_context();
 
+        // The cache being built for the previous cwd is useless once the cwd has changed.
+        cancel_stale_forerunner_job(context);
+
         // TODO: blocking on_create for the swift providers like `tags`.
         match tokio::time::timeout(TIMEOUT, initialize(context)).await {
             Ok(scale_result) => match scale_result {
-                Ok(scale) => process_source_scale(scale, context),
+                Ok(scale) => {
+                    let seeded_items = seed_initial_query(&scale, context, self.match_scope());
+                    if let Some(ref matched_items) = seeded_items {
+                        self.set_seeded_results(matched_items.clone());
+                    }
+                    process_source_scale(scale, context, seeded_items).await
+                }
                 Err(e) => tracing::error!(?e, "Error occurred on creating session"),
             },
             Err(_) => {
@@ -92,7 +570,8 @@ pub trait ClapProvider: Debug + Send + Sync + 'static {
                         let rg_cmd =
                             crate::command::grep::RgTokioCommand::new(context.cwd.to_path_buf());
                         let job_id = utility::calculate_hash(&rg_cmd);
-                        spawn_singleton_job(
+                        spawn_forerunner_job(
+                            context,
                             async move {
                                 let _ = rg_cmd.create_cache().await;
                             },
@@ -111,10 +590,89 @@ pub trait ClapProvider: Debug + Send + Sync + 'static {
 
     async fn on_typed(&mut self, msg: MethodCall) -> Result<()>;
 
+    /// Invalidates the cache of provider and re-runs the initialization in the background.
+    ///
+    /// The initial lines are re-emitted once the refresh is done.
+    async fn on_refresh(&mut self, _msg: MethodCall) -> Result<()> {
+        let context = self.session_context();
+        let job_id = refresh_job_id(context);
+        let refresh_context = context.clone();
+
+        let spawned = spawn_session_job(
+            context,
+            async move {
+                match refresh(&refresh_context).await {
+                    Ok(scale) => process_source_scale(scale, &refresh_context, None).await,
+                    Err(err) => tracing::error!(?err, "Failed to refresh the provider"),
+                }
+            },
+            job_id,
+        );
+
+        if !spawned {
+            tracing::debug!(job_id, "The refresh is already in progress");
+        }
+
+        Ok(())
+    }
+
+    /// Updates the width of display window and re-emits the buffered results decorated at the
+    /// new width, the filter is not re-run.
+    async fn on_resize(&mut self, winwidth: u64) -> Result<()> {
+        self.session_context_mut().display_winwidth =
+            printer::normalize_winwidth(Some(winwidth as usize)) as u64;
+
+        if let Some((display_lines, matched)) = self.buffered_results() {
+            write_response(display_lines.on_typed_message(matched)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Pipes the current results to an external command and sends back its output.
+    async fn on_pipe_results(&mut self, _msg: MethodCall) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Piping the results is unsupported for provider {}",
+            self.session_context().provider_id
+        ))
+    }
+
+    /// Sends back all the jump targets of the selected entry, e.g., all the definitions of the
+    /// selected symbol.
+    async fn on_select(&mut self, _msg: MethodCall) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Multiple jump targets are unsupported for provider {}",
+            self.session_context().provider_id
+        ))
+    }
+
+    /// Returns the ranked results held by the provider and the total number of matched items.
+    fn buffered_results(&self) -> Option<(printer::DisplayLines, usize)> {
+        None
+    }
+
+    /// Returns the buffered results to emit on terminate if `flush_on_terminate` is enabled.
+    fn results_to_flush(&self) -> Option<(printer::DisplayLines, usize)> {
+        if self.session_context().flush_on_terminate {
+            self.buffered_results()
+        } else {
+            None
+        }
+    }
+
     /// Sets the running signal to false, in case of the forerunner thread is still working.
+    ///
+    /// The background jobs spawned by this session are cancelled too.
     fn handle_terminate(&self, session_id: u64) {
+        if let Some((display_lines, matched)) = self.results_to_flush() {
+            tokio::spawn(write_response(display_lines.on_typed_message(matched)));
+        }
+
         let context = self.session_context();
         context.state.is_running.store(false, Ordering::SeqCst);
+        for job_id in release_session_jobs(&context.state.spawned_jobs) {
+            tracing::debug!(session_id, job_id, "Cancelled the background job");
+        }
         tracing::debug!(
           session_id,
             provider_id = %context.provider_id,
@@ -129,12 +687,24 @@ pub struct Session {
     /// Each provider session can have its own message processing logic.
     pub provider: Box<dyn ClapProvider>,
     pub event_recv: tokio::sync::mpsc::UnboundedReceiver<ProviderEvent>,
+    /// Recent events received by this session.
+    pub event_log: EventLog,
 }
 
 #[derive(Debug, Clone)]
 pub enum ProviderEvent {
     OnTyped(MethodCall),
     OnMove(MethodCall),
+    PipeResults(MethodCall),
+    /// Requests all the jump targets of the selected entry.
+    OnSelect(MethodCall),
+    Refresh(MethodCall),
+    /// Applies the query pushed by the editor immediately, bypassing the debounce.
+    SetQuery(MethodCall),
+    /// The display window is resized to `winwidth`.
+    OnResize {
+        winwidth: u64,
+    },
     Create(Call),
     Terminate,
 }
@@ -145,6 +715,11 @@ impl ProviderEvent {
         match self {
             Self::OnTyped(msg) => format!("OnTyped, msg_id: {}", msg.id).into(),
             Self::OnMove(msg) => format!("OnMove, msg_id: {}", msg.id).into(),
+            Self::PipeResults(msg) => format!("PipeResults, msg_id: {}", msg.id).into(),
+            Self::OnSelect(msg) => format!("OnSelect, msg_id: {}", msg.id).into(),
+            Self::Refresh(msg) => format!("Refresh, msg_id: {}", msg.id).into(),
+            Self::SetQuery(msg) => format!("SetQuery, msg_id: {}", msg.id).into(),
+            Self::OnResize { winwidth } => format!("OnResize, winwidth: {winwidth}").into(),
             Self::Create(_) => "Create".into(),
             Self::Terminate => "Terminate".into(),
         }
@@ -162,6 +737,7 @@ impl Session {
             session_id,
             provider,
             event_recv: session_receiver,
+            event_log: EventLog::default(),
         };
 
         (session, session_sender)
@@ -178,17 +754,10 @@ impl Session {
     }
 
     async fn run_event_loop_with_debounce(mut self) {
-        // https://github.com/denoland/deno/blob/1fb5858009f598ce3f917f9f49c466db81f4d9b0/cli/lsp/diagnostics.rs#L141
-        //
-        // Debounce timer delay. 150ms between keystrokes is about 45 WPM, so we
-        // want something that is longer than that, but not too long to
-        // introduce detectable UI delay; 200ms is a decent compromise.
-        //
-        // Add extra 50ms delay.
-        const DELAY: Duration = Duration::from_millis(200 + 50);
-        // If the debounce timer isn't active, it will be set to expire "never",
-        // which is actually just 1 year in the future.
-        const NEVER: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+        let context = self.provider.session_context();
+        let min_interval = context.min_interval_between_typed;
+        let mut debounce =
+            AdaptiveDebounce::new(context.min_debounce_delay, context.max_debounce_delay);
 
         tracing::debug!(
             session_id = self.session_id,
@@ -197,6 +766,7 @@ impl Session {
         );
 
         let mut pending_on_typed = None;
+        let mut last_on_typed = None;
 
         let debounce_timer = tokio::time::sleep(NEVER);
         tokio::pin!(debounce_timer);
@@ -207,18 +777,65 @@ impl Session {
                     match maybe_event {
                         Some(event) => {
                             tracing::debug!(event = ?event.short_display(), "Received an event");
+                            self.event_log.record(event.short_display());
 
                             match event {
                                 ProviderEvent::Terminate => self.provider.handle_terminate(self.session_id),
-                                ProviderEvent::Create(call) => self.provider.on_create(call).await,
+                                ProviderEvent::Create(call) => {
+                                    send_actions(self.provider.as_ref()).await;
+                                    self.provider.on_create(call).await
+                                }
                                 ProviderEvent::OnMove(msg) => {
                                     if let Err(err) = self.provider.on_move(msg).await {
                                         tracing::error!(?err, "Error processing ProviderEvent::OnMove");
+                                        self.event_log.record_error("OnMove", &err);
+                                    }
+                                }
+                                ProviderEvent::PipeResults(msg) => {
+                                    let msg_id = msg.id;
+                                    if let Err(err) = self.provider.on_pipe_results(msg).await {
+                                        tracing::error!(?err, "Error processing ProviderEvent::PipeResults");
+                                        self.event_log.record_error("PipeResults", &err);
+                                        // The caller is waiting for the response of this call.
+                                        write_response(json!({ "error": err.to_string(), "id": msg_id })).await;
+                                    }
+                                }
+                                ProviderEvent::OnSelect(msg) => {
+                                    if let Err(err) = self.provider.on_select(msg).await {
+                                        tracing::error!(?err, "Error processing ProviderEvent::OnSelect");
+                                        self.event_log.record_error("OnSelect", &err);
+                                    }
+                                }
+                                ProviderEvent::Refresh(msg) => {
+                                    if let Err(err) = self.provider.on_refresh(msg).await {
+                                        tracing::error!(?err, "Error processing ProviderEvent::Refresh");
+                                        self.event_log.record_error("Refresh", &err);
+                                    }
+                                }
+                                ProviderEvent::OnResize { winwidth } => {
+                                    if let Err(err) = self.provider.on_resize(winwidth).await {
+                                        tracing::error!(?err, "Error processing ProviderEvent::OnResize");
+                                        self.event_log.record_error("OnResize", &err);
                                     }
                                 }
                                 ProviderEvent::OnTyped(msg) => {
                                     pending_on_typed.replace(msg);
-                                    debounce_timer.as_mut().reset(Instant::now() + DELAY);
+                                    debounce_timer.as_mut().reset(Instant::now() + debounce.delay());
+                                }
+                                ProviderEvent::SetQuery(msg) => {
+                                    // The pushed query supersedes the pending one.
+                                    pending_on_typed.take();
+                                    let now = Instant::now();
+                                    debounce_timer.as_mut().reset(now + NEVER);
+
+                                    last_on_typed.replace(now);
+                                    if let Err(err) = self.provider.on_typed(msg).await {
+                                        tracing::error!(?err, "Error processing ProviderEvent::SetQuery");
+                                        self.event_log.record_error("SetQuery", &err);
+                                    }
+                                    let elapsed = now.elapsed();
+                                    debounce.note_on_typed_elapsed(elapsed);
+                                    record_filter_time(self.provider.as_ref(), elapsed).await;
                                 }
                             }
                           }
@@ -226,35 +843,494 @@ impl Session {
                       }
                 }
                 _ = debounce_timer.as_mut(), if pending_on_typed.is_some() => {
+                    let now = Instant::now();
+
+                    // Keep the latest query pending until the rate limit allows.
+                    if let Some(deadline) = throttle_deadline(last_on_typed, min_interval, now) {
+                        debounce_timer.as_mut().reset(deadline);
+                        continue;
+                    }
+
                     let msg = pending_on_typed.take().expect("Checked as Some above; qed");
-                    debounce_timer.as_mut().reset(Instant::now() + NEVER);
+                    debounce_timer.as_mut().reset(now + NEVER);
 
+                    last_on_typed.replace(now);
                     if let Err(err) = self.provider.on_typed(msg).await {
                         tracing::error!(?err, "Error processing ProviderEvent::OnTyped");
+                        self.event_log.record_error("OnTyped", &err);
                     }
+                    let elapsed = now.elapsed();
+                    debounce.note_on_typed_elapsed(elapsed);
+                    record_filter_time(self.provider.as_ref(), elapsed).await;
                 }
             }
         }
     }
 
     async fn run_event_loop_without_debounce(mut self) {
-        while let Some(event) = self.event_recv.recv().await {
-            tracing::debug!(event = ?event.short_display(), "Received an event");
-
-            match event {
-                ProviderEvent::Create(call) => self.provider.on_create(call).await,
-                ProviderEvent::Terminate => self.provider.handle_terminate(self.session_id),
-                ProviderEvent::OnMove(msg) => {
-                    if let Err(err) = self.provider.on_move(msg).await {
-                        tracing::debug!(?err, "Error processing ProviderEvent::OnMove");
+        let min_interval = self.provider.session_context().min_interval_between_typed;
+
+        let mut pending_on_typed = None;
+        let mut last_on_typed = None;
+
+        let throttle_timer = tokio::time::sleep(NEVER);
+        tokio::pin!(throttle_timer);
+
+        loop {
+            tokio::select! {
+                maybe_event = self.event_recv.recv() => {
+                    let event = match maybe_event {
+                        Some(event) => event,
+                        None => break, // channel has closed.
+                    };
+
+                    tracing::debug!(event = ?event.short_display(), "Received an event");
+                    self.event_log.record(event.short_display());
+
+                    match event {
+                        ProviderEvent::Create(call) => {
+                            send_actions(self.provider.as_ref()).await;
+                            self.provider.on_create(call).await
+                        }
+                        ProviderEvent::Terminate => self.provider.handle_terminate(self.session_id),
+                        ProviderEvent::OnMove(msg) => {
+                            if let Err(err) = self.provider.on_move(msg).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::OnMove");
+                                self.event_log.record_error("OnMove", &err);
+                            }
+                        }
+                        ProviderEvent::PipeResults(msg) => {
+                            let msg_id = msg.id;
+                            if let Err(err) = self.provider.on_pipe_results(msg).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::PipeResults");
+                                self.event_log.record_error("PipeResults", &err);
+                                // The caller is waiting for the response of this call.
+                                write_response(json!({ "error": err.to_string(), "id": msg_id })).await;
+                            }
+                        }
+                        ProviderEvent::OnSelect(msg) => {
+                            if let Err(err) = self.provider.on_select(msg).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::OnSelect");
+                                self.event_log.record_error("OnSelect", &err);
+                            }
+                        }
+                        ProviderEvent::Refresh(msg) => {
+                            if let Err(err) = self.provider.on_refresh(msg).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::Refresh");
+                                self.event_log.record_error("Refresh", &err);
+                            }
+                        }
+                        ProviderEvent::OnResize { winwidth } => {
+                            if let Err(err) = self.provider.on_resize(winwidth).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::OnResize");
+                                self.event_log.record_error("OnResize", &err);
+                            }
+                        }
+                        ProviderEvent::OnTyped(msg) => {
+                            let now = Instant::now();
+                            let throttled = throttle_deadline(last_on_typed, min_interval, now);
+
+                            if let Some(deadline) = throttled {
+                                // Queue the latest query until the rate limit allows.
+                                pending_on_typed.replace(msg);
+                                throttle_timer.as_mut().reset(deadline);
+                                continue;
+                            }
+
+                            last_on_typed.replace(now);
+                            if let Err(err) = self.provider.on_typed(msg).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::OnTyped");
+                                self.event_log.record_error("OnTyped", &err);
+                            }
+                            record_filter_time(self.provider.as_ref(), now.elapsed()).await;
+                        }
+                        ProviderEvent::SetQuery(msg) => {
+                            // The pushed query supersedes the throttled one.
+                            pending_on_typed.take();
+                            let now = Instant::now();
+                            throttle_timer.as_mut().reset(now + NEVER);
+
+                            last_on_typed.replace(now);
+                            if let Err(err) = self.provider.on_typed(msg).await {
+                                tracing::debug!(?err, "Error processing ProviderEvent::SetQuery");
+                                self.event_log.record_error("SetQuery", &err);
+                            }
+                            record_filter_time(self.provider.as_ref(), now.elapsed()).await;
+                        }
                     }
                 }
-                ProviderEvent::OnTyped(msg) => {
+                _ = throttle_timer.as_mut(), if pending_on_typed.is_some() => {
+                    let msg = pending_on_typed.take().expect("Checked as Some above; qed");
+                    let now = Instant::now();
+                    throttle_timer.as_mut().reset(now + NEVER);
+
+                    last_on_typed.replace(now);
                     if let Err(err) = self.provider.on_typed(msg).await {
                         tracing::debug!(?err, "Error processing ProviderEvent::OnTyped");
+                        self.event_log.record_error("OnTyped", &err);
                     }
+                    record_filter_time(self.provider.as_ref(), now.elapsed()).await;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct RecordingProvider {
+        context: SessionContext,
+        on_typed_calls: Arc<Mutex<Vec<(Instant, String)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ClapProvider for RecordingProvider {
+        fn session_context(&self) -> &SessionContext {
+            &self.context
+        }
+
+        fn session_context_mut(&mut self) -> &mut SessionContext {
+            &mut self.context
+        }
+
+        async fn on_move(&mut self, _msg: MethodCall) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+            self.on_typed_calls
+                .lock()
+                .push((Instant::now(), msg.get_query()));
+            Ok(())
+        }
+    }
+
+    /// Provider holding the results of the last filtering.
+    #[derive(Debug)]
+    struct BufferedProvider {
+        context: SessionContext,
+        results: Vec<MatchedItem>,
+        on_typed_calls: Arc<Mutex<Vec<(Instant, String)>>>,
+        /// Window width of each decoration of the buffered results.
+        decorated_widths: Arc<Mutex<Vec<u64>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ClapProvider for BufferedProvider {
+        fn session_context(&self) -> &SessionContext {
+            &self.context
+        }
+
+        fn session_context_mut(&mut self) -> &mut SessionContext {
+            &mut self.context
+        }
+
+        fn buffered_results(&self) -> Option<(printer::DisplayLines, usize)> {
+            self.decorated_widths
+                .lock()
+                .push(self.context.display_winwidth);
+            Some((
+                self.decorate_lines(self.results.clone()),
+                self.results.len(),
+            ))
+        }
+
+        async fn on_move(&mut self, _msg: MethodCall) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_typed(&mut self, msg: MethodCall) -> Result<()> {
+            self.on_typed_calls
+                .lock()
+                .push((Instant::now(), msg.get_query()));
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct HomeProvider {
+        context: SessionContext,
+        home: String,
+    }
+
+    #[async_trait::async_trait]
+    impl ClapProvider for HomeProvider {
+        fn session_context(&self) -> &SessionContext {
+            &self.context
+        }
+
+        fn session_context_mut(&mut self) -> &mut SessionContext {
+            &mut self.context
+        }
+
+        fn format_display<'a>(&self, item: &'a dyn ClapItem) -> Cow<'a, str> {
+            let text = item.output_text();
+            if text.starts_with(&self.home) {
+                format!("~{}", &text[self.home.len()..]).into()
+            } else {
+                text
+            }
+        }
+
+        async fn on_move(&mut self, _msg: MethodCall) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_typed(&mut self, _msg: MethodCall) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_format_display() {
+        let provider = HomeProvider {
+            context: SessionContext::new_test_context("recent_files"),
+            home: "/home/user".into(),
+        };
+
+        let matched_items = vec![
+            MatchedItem::new(
+                Arc::new("/home/user/src/lib.rs".to_string()),
+                Default::default(),
+                vec![1, 11, 15, 16, 17],
+            ),
+            MatchedItem::new(
+                Arc::new("/tmp/lib.rs".to_string()),
+                Default::default(),
+                vec![5, 6, 7],
+            ),
+        ];
+        let ids = matched_items
+            .iter()
+            .map(MatchedItem::id)
+            .collect::<Vec<_>>();
+
+        let display_lines = provider.decorate_lines(matched_items);
+        assert_eq!(display_lines.lines, vec!["~/src/lib.rs", "/tmp/lib.rs"]);
+        // The match in `/home/user` is dropped and the rest are shifted.
+        assert_eq!(display_lines.indices, vec![vec![2, 6, 7, 8], vec![5, 6, 7]]);
+        assert_eq!(display_lines.ids, ids);
+    }
+
+    #[tokio::test]
+    async fn test_cwd_change_cancels_forerunner_job() {
+        let old_context = SessionContext::new_test_context("grep");
+        let mut new_context = SessionContext::new_test_context("grep");
+        new_context.cwd = crate::paths::AbsPathBuf::try_from(std::env::temp_dir()).unwrap();
+        assert_ne!(old_context.cwd, new_context.cwd);
+
+        let job_id = utility::calculate_hash(&"test_cwd_change_cancels_forerunner_job");
+        assert!(spawn_forerunner_job(
+            &old_context,
+            std::future::pending(),
+            job_id
+        ));
+        assert!(is_job_running(job_id));
+
+        // The job is kept for the same cwd.
+        assert!(!cancel_stale_forerunner_job(&old_context));
+        assert!(is_job_running(job_id));
+
+        assert!(cancel_stale_forerunner_job(&new_context));
+        assert!(!is_job_running(job_id));
+    }
+
+    #[test]
+    fn test_seed_initial_query() {
+        let items = ["src/lib.rs", "README.md", "src/main.rs", "Cargo.toml"]
+            .into_iter()
+            .map(|line| Arc::new(line) as Arc<dyn ClapItem>)
+            .collect::<Vec<_>>();
+        let source_scale = SourceScale::small(items);
+        let display_lines = |context: &SessionContext| {
+            let seeded_items = seed_initial_query(&source_scale, context, MatchScope::Full);
+            initial_display_lines(&source_scale, context, seeded_items)
+                .unwrap()
+                .lines
+        };
+
+        let mut context = SessionContext::new_test_context("files");
+        assert_eq!(
+            display_lines(&context),
+            vec!["src/lib.rs", "README.md", "src/main.rs", "Cargo.toml"]
+        );
+
+        context.initial_query.replace("main".into());
+        assert_eq!(display_lines(&context), vec!["src/main.rs"]);
+
+        // No seeding for the source to be filtered dynamically.
+        assert!(seed_initial_query(&SourceScale::Large(100), &context, MatchScope::Full).is_none());
+    }
+
+    fn method_call(id: u64, method: &str, query: &str) -> MethodCall {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "method": method,
+            "params": { "query": query },
+            "session_id": 0,
+        }))
+        .unwrap()
+    }
+
+    fn on_typed_event(id: u64, query: &str) -> ProviderEvent {
+        ProviderEvent::OnTyped(method_call(id, "on_typed", query))
+    }
+
+    fn set_query_event(id: u64, query: &str) -> ProviderEvent {
+        ProviderEvent::SetQuery(method_call(id, "clap#set_query", query))
+    }
+
+    /// Advances the paused clock by `duration`, then lets the session handle the due timers.
+    async fn advance(duration: Duration) {
+        tokio::time::advance(duration).await;
+        // The expired timers are only fired once the runtime is idle.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    /// Returns the queries passed to `on_typed` of a new session after sending `events`.
+    async fn on_typed_queries(debounce: bool, events: Vec<ProviderEvent>) -> Vec<String> {
+        let mut context = SessionContext::new_test_context("blines");
+        context.debounce = debounce;
+
+        let on_typed_calls = Arc::new(Mutex::new(Vec::new()));
+        let provider = RecordingProvider {
+            context,
+            on_typed_calls: on_typed_calls.clone(),
+        };
+
+        let (session, session_sender) = Session::new(0, Box::new(provider));
+        session.start_event_loop();
+
+        for event in events {
+            session_sender.send(event).unwrap();
+        }
+
+        // Longer than the debounce delay.
+        advance(Duration::from_millis(500)).await;
+
+        let queries = on_typed_calls
+            .lock()
+            .iter()
+            .map(|(_, query)| query.clone())
+            .collect();
+        queries
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_query() {
+        // The query is applied without waiting for the debounce and the pending query is dropped.
+        let queries = on_typed_queries(
+            true,
+            vec![on_typed_event(0, "typed"), set_query_event(1, "pushed")],
+        )
+        .await;
+        assert_eq!(queries, vec!["pushed"]);
+
+        let queries = on_typed_queries(
+            false,
+            vec![on_typed_event(0, "typed"), set_query_event(1, "pushed")],
+        )
+        .await;
+        assert_eq!(queries, vec!["typed", "pushed"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_on_resize() {
+        let mut context = SessionContext::new_test_context("blines");
+        context.debounce = false;
+
+        let on_typed_calls = Arc::new(Mutex::new(Vec::new()));
+        let decorated_widths = Arc::new(Mutex::new(Vec::new()));
+        let provider = BufferedProvider {
+            context,
+            results: vec![MatchedItem::new(
+                Arc::new("fn main() {}".to_string()),
+                Default::default(),
+                vec![0, 1],
+            )],
+            on_typed_calls: on_typed_calls.clone(),
+            decorated_widths: decorated_widths.clone(),
+        };
+
+        let (session, session_sender) = Session::new(0, Box::new(provider));
+        session.start_event_loop();
+
+        session_sender
+            .send(ProviderEvent::OnResize { winwidth: 120 })
+            .unwrap();
+        advance(Duration::from_millis(100)).await;
+
+        // The buffered results are re-decorated at the new width without re-filtering.
+        assert_eq!(*decorated_widths.lock(), vec![120]);
+        assert!(on_typed_calls.lock().is_empty());
+    }
+
+    #[test]
+    fn test_adaptive_debounce() {
+        const MIN: Duration = Duration::from_millis(50);
+        const MAX: Duration = Duration::from_millis(500);
+
+        let mut debounce = AdaptiveDebounce::new(MIN, MAX);
+        assert_eq!(debounce.delay(), DEFAULT_DEBOUNCE_DELAY);
+
+        // Shrinks after the fast handlers.
+        let mut last_delay = debounce.delay();
+        for _ in 0..10 {
+            debounce.note_on_typed_elapsed(Duration::from_millis(1));
+            assert!(debounce.delay() < last_delay);
+            last_delay = debounce.delay();
+        }
+        assert!(debounce.delay() >= MIN);
+        assert!(debounce.delay() < MIN + Duration::from_millis(5));
+
+        // Grows after the slow handlers.
+        for _ in 0..10 {
+            debounce.note_on_typed_elapsed(Duration::from_secs(1));
+            assert!(debounce.delay() > last_delay);
+            last_delay = debounce.delay();
+        }
+        assert!(debounce.delay() <= MAX);
+        assert!(debounce.delay() > MAX - Duration::from_millis(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_min_interval_between_typed() {
+        const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+        let mut context = SessionContext::new_test_context("blines");
+        context.debounce = false;
+        context.min_interval_between_typed = MIN_INTERVAL;
+
+        let on_typed_calls = Arc::new(Mutex::new(Vec::new()));
+        let provider = RecordingProvider {
+            context,
+            on_typed_calls: on_typed_calls.clone(),
+        };
+
+        let (session, session_sender) = Session::new(0, Box::new(provider));
+        session.start_event_loop();
+
+        for (id, query) in ["a", "ab", "abc", "abcd"].into_iter().enumerate() {
+            session_sender
+                .send(on_typed_event(id as u64, query))
+                .unwrap();
+            advance(Duration::from_millis(10)).await;
+        }
+
+        advance(MIN_INTERVAL * 3).await;
+
+        let on_typed_calls = on_typed_calls.lock();
+        let queries = on_typed_calls
+            .iter()
+            .map(|(_, query)| query.as_str())
+            .collect::<Vec<_>>();
+        // The intermediate queries are dropped in favor of the latest one.
+        assert_eq!(queries, vec!["a", "abcd"]);
+        for pair in on_typed_calls.windows(2) {
+            assert!(pair[1].0 - pair[0].0 >= MIN_INTERVAL);
+        }
+    }
+}
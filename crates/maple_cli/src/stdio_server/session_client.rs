@@ -1,16 +1,16 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
 use parking_lot::Mutex;
 use serde_json::{json, Value};
 use tokio::sync::mpsc::UnboundedReceiver;
 
-use super::session::SessionManager;
+use super::session::{idle_timeout, SessionManager, IDLE_SWEEP_INTERVAL};
 use super::Notification;
-use crate::stdio_server::impls::dumb_jump::DumbJumpProvider;
-use crate::stdio_server::impls::filer::FilerProvider;
-use crate::stdio_server::impls::recent_files::RecentFilesProvider;
-use crate::stdio_server::impls::DefaultProvider;
+use crate::stdio_server::impls::{
+    create_provider, executable_exists, list_providers, provider_event,
+};
 use crate::stdio_server::rpc::{Call, MethodCall};
 use crate::stdio_server::session::SessionContext;
 use crate::stdio_server::state::State;
@@ -32,6 +32,19 @@ impl SessionClient {
 
     /// Entry of the bridge between Vim and Rust.
     pub async fn loop_call(&self, mut rx: UnboundedReceiver<Call>) {
+        if let Some(idle_timeout) = idle_timeout() {
+            let session_manager_mutex = self.session_manager_mutex.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(IDLE_SWEEP_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    session_manager_mutex
+                        .lock()
+                        .reap_idle_sessions(Instant::now(), idle_timeout);
+                }
+            });
+        }
+
         while let Some(call) = rx.recv().await {
             let session_client = self.clone();
             tokio::spawn(async move {
@@ -55,8 +68,8 @@ impl SessionClient {
                 let maybe_result = self.process_method_call(method_call).await?;
                 // Send back the result of method call.
                 if let Some(result) = maybe_result {
-                    let state = self.state_mutex.lock();
-                    state.vim.rpc_client.output(id, Ok(result))?;
+                    let rpc_client = self.state_mutex.lock().vim.rpc_client.clone();
+                    rpc_client.output(id, Ok(result)).await?;
                 }
             }
         }
@@ -72,7 +85,7 @@ impl SessionClient {
                 let mut session_manager = self.session_manager_mutex.lock();
                 let call = Call::Notification(notification);
                 let context: SessionContext = call.clone().into();
-                session_manager.new_session(call, Box::new(DefaultProvider::new(context)));
+                session_manager.new_session(call, create_provider(context));
                 Ok(())
             }
             "exit" => {
@@ -94,29 +107,65 @@ impl SessionClient {
             tracing::debug!(?msg, "==> stdio message(in)");
         }
 
-        let value = match msg.method.as_str() {
+        if msg.method == "filer/on_typed" {
+            let mut session_manager = self.session_manager_mutex.lock();
+            // TODO: send_and_wait_result
+            session_manager.send(msg.session_id, OnMove(msg));
+            return Ok(None);
+        }
+
+        let value = match provider_event(&msg.method) {
             "init_ext_map" => Some(msg.parse_filetypedetect()),
             "preview/file" => Some(msg.preview_file().await?),
             "quickfix" => Some(msg.preview_quickfix().await?),
-
-            "dumb_jump/on_init" => {
-                let mut session_manager = self.session_manager_mutex.lock();
-                let call = Call::MethodCall(msg);
-                let context: SessionContext = call.clone().into();
-                session_manager.new_session(call, Box::new(DumbJumpProvider::new(context)));
-                None
+            "loclist" => Some(msg.preview_loclist().await?),
+            "clap#stats" => {
+                // The current session by default.
+                let session_id = msg.get_u64("session_id").unwrap_or(msg.session_id);
+                let stats = self.session_manager_mutex.lock().stats(session_id);
+                Some(msg.stats(session_id, stats))
+            }
+            "clap#providers" => {
+                let providers =
+                    tokio::task::spawn_blocking(|| list_providers(executable_exists)).await?;
+                Some(json!({ "providers": providers }))
+            }
+            "clap#session_log" => {
+                // The current session by default.
+                let session_id = msg.get_u64("session_id").unwrap_or(msg.session_id);
+                let session_manager = self.session_manager_mutex.lock();
+                let events = session_manager
+                    .event_records(session_id)
+                    .unwrap_or_default();
+                Some(json!({ "session_id": session_id, "events": events }))
             }
-            "dumb_jump/on_typed" => {
+            "clap#chain" => {
+                // The session id following the current one by default.
+                let next_session_id = msg.get_u64("next_session_id").unwrap_or(msg.session_id + 1);
+                let selected = msg.get_string("selected")?;
                 let mut session_manager = self.session_manager_mutex.lock();
-                session_manager.send(msg.session_id, OnTyped(msg));
-                None
+                match session_manager.start_chain(
+                    msg.session_id,
+                    next_session_id,
+                    &selected,
+                    msg.id,
+                ) {
+                    Ok(init_call) => Some(json!({
+                        "session_id": next_session_id,
+                        "provider_id": init_call.get_str("provider_id")?,
+                        "cwd": init_call.get_cwd(),
+                    })),
+                    Err(err) => Some(json!({ "error": err.to_string() })),
+                }
             }
-            "dumb_jump/on_move" => {
+
+            "on_init" => {
                 let mut session_manager = self.session_manager_mutex.lock();
-                session_manager.send(msg.session_id, OnMove(msg));
+                let call = Call::MethodCall(msg);
+                let context: SessionContext = call.clone().into();
+                session_manager.new_session(call, create_provider(context));
                 None
             }
-
             "on_typed" => {
                 let mut session_manager = self.session_manager_mutex.lock();
                 session_manager.send(msg.session_id, OnTyped(msg));
@@ -127,41 +176,30 @@ impl SessionClient {
                 session_manager.send(msg.session_id, OnMove(msg));
                 None
             }
-
-            "recent_files/on_init" => {
-                let mut session_manager = self.session_manager_mutex.lock();
-                let call = Call::MethodCall(msg);
-                let context: SessionContext = call.clone().into();
-                session_manager.new_session(call, Box::new(RecentFilesProvider::new(context)));
-                None
-            }
-            "recent_files/on_typed" => {
+            "clap#pipe_results" => {
                 let mut session_manager = self.session_manager_mutex.lock();
-                session_manager.send(msg.session_id, OnTyped(msg));
+                session_manager.send(msg.session_id, PipeResults(msg));
                 None
             }
-            "recent_files/on_move" => {
+            "clap#select" => {
                 let mut session_manager = self.session_manager_mutex.lock();
-                session_manager.send(msg.session_id, OnMove(msg));
+                session_manager.send(msg.session_id, OnSelect(msg));
                 None
             }
-
-            "filer/on_init" => {
+            "clap#refresh" => {
                 let mut session_manager = self.session_manager_mutex.lock();
-                let call = Call::MethodCall(msg);
-                let context: SessionContext = call.clone().into();
-                session_manager.new_session(call, Box::new(FilerProvider::new(context)));
+                session_manager.send(msg.session_id, Refresh(msg));
                 None
             }
-            "filer/on_move" => {
+            "clap#set_query" => {
                 let mut session_manager = self.session_manager_mutex.lock();
-                session_manager.send(msg.session_id, OnMove(msg));
+                session_manager.send(msg.session_id, SetQuery(msg));
                 None
             }
-            "filer/on_typed" => {
+            "on_resize" => {
+                let winwidth = msg.get_u64("winwidth")?;
                 let mut session_manager = self.session_manager_mutex.lock();
-                // TODO: send_and_wait_result
-                session_manager.send(msg.session_id, OnMove(msg));
+                session_manager.send(msg.session_id, OnResize { winwidth });
                 None
             }
 
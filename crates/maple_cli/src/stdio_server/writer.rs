@@ -0,0 +1,139 @@
+//! Writes the messages to Vim on a dedicated thread.
+//!
+//! The pending messages are bounded, a slow client makes the senders wait for the capacity
+//! instead of blocking the threads of async runtime on a full pipe.
+
+use std::io::Write;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+/// Maximum number of the messages pending to be written.
+pub const CHANNEL_CAPACITY: usize = 128;
+
+static STDOUT_WRITER: Lazy<OutputWriter> =
+    Lazy::new(|| OutputWriter::new(std::io::stdout(), CHANNEL_CAPACITY));
+
+#[derive(Debug, Clone)]
+pub struct OutputWriter {
+    sender: Sender<String>,
+}
+
+impl OutputWriter {
+    /// Creates a new instance of [`OutputWriter`], at most `capacity` messages can be pending.
+    pub fn new(writer: impl Write + Send + 'static, capacity: usize) -> Self {
+        let (sender, receiver) = channel(capacity);
+        std::thread::Builder::new()
+            .name("stdio-writer".into())
+            .spawn(move || {
+                if let Err(error) = loop_write(writer, receiver) {
+                    tracing::error!(?error, "Thread stdio-writer exited");
+                }
+            })
+            .expect("Failed to spawn thread stdio-writer");
+        Self { sender }
+    }
+
+    /// Queues `msg` to write, waits until there is capacity if the client falls behind.
+    pub async fn write(&self, msg: impl Serialize) {
+        match serde_json::to_string(&msg) {
+            Ok(s) => {
+                if self.sender.send(s).await.is_err() {
+                    tracing::error!("Failed to send the message, stdio-writer has exited");
+                }
+            }
+            Err(error) => tracing::error!(?error, "Failed to serialize the message"),
+        }
+    }
+}
+
+/// Keep writing the messages until all the senders are dropped.
+fn loop_write(writer: impl Write, mut receiver: Receiver<String>) -> std::io::Result<()> {
+    let mut writer = writer;
+
+    while let Some(msg) = receiver.blocking_recv() {
+        // Use different convention for two reasons,
+        // 1. If using '\r\ncontent', nvim will receive output as `\r` + `content`, while vim
+        // receives `content`.
+        // 2. Without last line ending, vim output handler won't be triggered.
+        write!(writer, "Content-length: {}\n\n{}\n", msg.len(), msg)?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Writes the response to stdout.
+pub async fn write_response<T: Serialize>(msg: T) {
+    STDOUT_WRITER.write(msg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// Client reading one message per `delay`.
+    struct SlowWriter {
+        written: Arc<Mutex<Vec<u8>>>,
+        delay: Duration,
+    }
+
+    impl Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            std::thread::sleep(self.delay);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_reader() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let output_writer = OutputWriter::new(
+            SlowWriter {
+                written: written.clone(),
+                delay: Duration::from_millis(20),
+            },
+            2,
+        );
+
+        let producer = tokio::spawn(async move {
+            for id in 0..10 {
+                output_writer.write(json!({ "id": id })).await;
+            }
+        });
+
+        // The runtime keeps serving the other tasks while the producer is held up.
+        let now = Instant::now();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert!(now.elapsed() < Duration::from_millis(100));
+
+        tokio::time::timeout(Duration::from_secs(10), producer)
+            .await
+            .expect("Producer is deadlocked")
+            .unwrap();
+
+        // All the senders are dropped, the remaining messages are still written.
+        let expected = (0..10)
+            .map(|id| {
+                let msg = json!({ "id": id }).to_string();
+                format!("Content-length: {}\n\n{}\n", msg.len(), msg)
+            })
+            .collect::<String>();
+        let now = Instant::now();
+        while written.lock().len() < expected.len() {
+            assert!(now.elapsed() < Duration::from_secs(10));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(String::from_utf8(written.lock().clone()).unwrap(), expected);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Ranges of the lines added or modified in the working tree, the line number is 1-based.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangedLines(Vec<Range<usize>>);
+
+impl ChangedLines {
+    pub fn contains(&self, line_number: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&line_number))
+    }
+}
+
+/// Returns the range of new lines in the hunk header `@@ -a,b +c,d @@`.
+fn parse_hunk_range(header: &str) -> Option<Range<usize>> {
+    let new_range = header
+        .strip_prefix("@@ ")?
+        .split_whitespace()
+        .find_map(|range| range.strip_prefix('+'))?;
+    let mut parts = new_range.split(',');
+    let start: usize = parts.next()?.parse().ok()?;
+    let count: usize = match parts.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+    Some(start..start + count)
+}
+
+/// Returns the stdout of git command if it succeeds.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+/// Returns the lines of `path` changed against `HEAD`, including the staged changes.
+///
+/// Returns `None` if `path` is not tracked by git, all the lines of which are considered as
+/// changed then.
+pub fn changed_lines(path: &Path) -> Option<ChangedLines> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name()?.to_str()?;
+
+    git(dir, &["ls-files", "--error-unmatch", "--", file_name])?;
+
+    let diff = git(
+        dir,
+        &[
+            "diff",
+            "HEAD",
+            "--no-color",
+            "--no-ext-diff",
+            "--unified=0",
+            "--",
+            file_name,
+        ],
+    )?;
+
+    Some(ChangedLines(
+        diff.lines()
+            .filter(|line| line.starts_with("@@ "))
+            .filter_map(parse_hunk_range)
+            .collect(),
+    ))
+}
+
+/// Returns the files tracked by git under `dir` relative to `dir`, including the files of the
+/// submodules.
+///
+/// The files not present in the working tree, e.g., excluded by the sparse checkout, are
+/// skipped. Returns `None` if `dir` is not in a git repo.
+pub fn tracked_files(dir: &Path) -> Option<Vec<PathBuf>> {
+    let output = git(dir, &["ls-files", "-z", "--recurse-submodules"])?;
+    Some(
+        output
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .filter(|path| dir.join(path).is_file())
+            .collect(),
+    )
+}
+
+/// Changed lines of the files under a directory, computed lazily per file.
+#[derive(Debug)]
+pub struct ChangedFiles {
+    dir: PathBuf,
+    files: HashMap<PathBuf, Option<ChangedLines>>,
+}
+
+impl ChangedFiles {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if the line of `path` relative to the directory is changed or the file
+    /// is not tracked by git.
+    pub fn is_changed(&mut self, path: &Path, line_number: usize) -> bool {
+        let dir = &self.dir;
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| changed_lines(&dir.join(path)))
+            .as_ref()
+            .is_none_or(|changed_lines| changed_lines.contains(line_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn test_parse_hunk_range() {
+        assert_eq!(parse_hunk_range("@@ -3 +3 @@ fn main() {"), Some(3..4));
+        assert_eq!(parse_hunk_range("@@ -10,0 +11,2 @@"), Some(11..13));
+        assert_eq!(parse_hunk_range("@@ -5,2 +4,0 @@"), Some(4..4));
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("repo");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+
+        let lines = (1..=20).map(|i| format!("line {i}\n")).collect::<String>();
+        std::fs::write(dir.join("src/main.rs"), &lines).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), &lines).unwrap();
+
+        run_git(&dir, &["init", "-q"]);
+        run_git(&dir, &["add", "src/main.rs"]);
+        run_git(&dir, &["commit", "-q", "-m", "init"]);
+
+        let modified = lines
+            .replace("line 3\n", "line three\n")
+            .replace("line 15\n", "line 15\nnew 1\nnew 2\n")
+            .replace("line 18\n", "");
+        std::fs::write(dir.join("src/main.rs"), modified).unwrap();
+
+        let changed = changed_lines(&dir.join("src/main.rs")).unwrap();
+        let candidates = (1..=21)
+            .filter(|lnum| changed.contains(*lnum))
+            .collect::<Vec<_>>();
+        assert_eq!(candidates, vec![3, 16, 17]);
+
+        let mut changed_files = ChangedFiles::new(dir.clone());
+        assert!(changed_files.is_changed(Path::new("src/main.rs"), 16));
+        assert!(!changed_files.is_changed(Path::new("./src/main.rs"), 15));
+        // The untracked file has no diff, all the lines are included.
+        assert!(changed_lines(&dir.join("src/lib.rs")).is_none());
+        assert!(changed_files.is_changed(Path::new("src/lib.rs"), 1));
+
+        // The file outside a git repo.
+        let non_git = tmp.path().join("non_git.rs");
+        std::fs::write(&non_git, &lines).unwrap();
+        assert!(changed_lines(&non_git).is_none());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod ctags;
+pub mod git;
 pub mod gtags;
 pub mod ripgrep;
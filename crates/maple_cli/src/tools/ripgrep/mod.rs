@@ -41,6 +41,32 @@ pub fn get_language(file_extension: &str) -> Option<&&str> {
     RG_LANGUAGE_EXT_TABLE.get(file_extension)
 }
 
+/// The unit of the column number in the grep output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnEncoding {
+    /// Byte offset, same with ripgrep.
+    #[default]
+    Byte,
+    /// UTF-16 code units, as expected by the LSP clients.
+    Utf16,
+}
+
+impl std::str::FromStr for ColumnEncoding {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl<T: AsRef<str>> From<T> for ColumnEncoding {
+    fn from(s: T) -> Self {
+        match s.as_ref().to_lowercase().as_str() {
+            "utf16" | "utf-16" => Self::Utf16,
+            _ => Self::Byte,
+        }
+    }
+}
+
 /// Word represents the input query around by word boundries.
 #[derive(Clone, Debug)]
 pub struct Word {
@@ -116,6 +142,22 @@ impl Match {
         self.submatches.get(0).map(|x| x.start).unwrap_or_default()
     }
 
+    /// Returns the column of first match in the unit of `column_encoding`.
+    pub fn column_with_encoding(&self, column_encoding: ColumnEncoding) -> usize {
+        let column = self.column();
+        match column_encoding {
+            ColumnEncoding::Byte => column,
+            ColumnEncoding::Utf16 => {
+                let line = self.pattern();
+                match line.get(..column) {
+                    Some(preceding) => preceding.encode_utf16().count(),
+                    // The line is not valid UTF-8, keep the byte column.
+                    None => column,
+                }
+            }
+        }
+    }
+
     /// Returns true if the text line starts with `pat`.
     pub fn line_starts_with(&self, pat: &str) -> bool {
         self.lines.text().trim_start().starts_with(pat)
@@ -166,10 +208,14 @@ impl Match {
     /// Returns a pair of the formatted `String` and the offset of origin match indices.
     ///
     /// The formatted String is same with the output line using rg's -vimgrep option.
-    fn grep_line_format(&self, enable_icon: bool) -> (String, usize) {
+    fn grep_line_format(
+        &self,
+        enable_icon: bool,
+        column_encoding: ColumnEncoding,
+    ) -> (String, usize) {
         let path = self.path();
         let line_number = self.line_number();
-        let column = self.column();
+        let column = self.column_with_encoding(column_encoding);
         let pattern = self.pattern();
         let pattern = pattern.trim_end();
 
@@ -189,7 +235,16 @@ impl Match {
     }
 
     pub fn build_grep_line(&self, enable_icon: bool) -> (String, Vec<usize>) {
-        let (formatted, offset) = self.grep_line_format(enable_icon);
+        self.build_grep_line_with_encoding(enable_icon, ColumnEncoding::Byte)
+    }
+
+    /// Same with [`Match::build_grep_line`] but the column is in the unit of `column_encoding`.
+    pub fn build_grep_line_with_encoding(
+        &self,
+        enable_icon: bool,
+        column_encoding: ColumnEncoding,
+    ) -> (String, Vec<usize>) {
+        let (formatted, offset) = self.grep_line_format(enable_icon, column_encoding);
         let indices = self.match_indices(offset);
         (formatted, indices)
     }
@@ -254,3 +309,21 @@ impl Match {
         (formatted, indices)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_column() {
+        // `é` is 2 bytes in UTF-8 and `😀` is 4 bytes, 1 and 2 code units in UTF-16 respectively.
+        let line = r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"é😀 let foo = 1;\n"},"line_number":3,"absolute_offset":0,"submatches":[{"match":{"text":"foo"},"start":11,"end":14}]}}"#;
+        let mat = Match::try_from(line).unwrap();
+
+        assert_eq!(mat.column_with_encoding(ColumnEncoding::Byte), 11);
+        assert_eq!(mat.column_with_encoding(ColumnEncoding::Utf16), 8);
+
+        let (formatted, _) = mat.build_grep_line_with_encoding(false, ColumnEncoding::Utf16);
+        assert_eq!(formatted, "src/lib.rs:3:8:é😀 let foo = 1;");
+    }
+}
@@ -26,6 +26,34 @@ pub fn match_and_score_with_positions(
     needle: &str,
     haystack: &str,
     case_matching: CaseMatching,
+) -> Option<MatchWithPositions> {
+    match_and_score_with_separators(needle, haystack, case_matching, &[])
+}
+
+/// Same with [`match_and_score_with_positions`], but the gap penalty is reset on encountering
+/// any of `separators`.
+///
+/// The gap before a separator is not penalized, e.g., the directories before the basename.
+pub fn match_and_score_with_separators(
+    needle: &str,
+    haystack: &str,
+    case_matching: CaseMatching,
+    separators: &[char],
+) -> Option<MatchWithPositions> {
+    match_and_score_with_skipped(needle, haystack, case_matching, separators, &[])
+}
+
+/// Same with [`match_and_score_with_separators`], but any of `skipped` is skipped for free.
+///
+/// The scores are carried over the skipped chars as if they were absent, e.g., `getuser` matches
+/// `get_user` consecutively with `_` skipped. The skipped chars are never in the positions unless
+/// matched by the needle.
+pub fn match_and_score_with_skipped(
+    needle: &str,
+    haystack: &str,
+    case_matching: CaseMatching,
+    separators: &[char],
+    skipped: &[char],
 ) -> Option<MatchWithPositions> {
     let haystack_length = haystack.chars().count();
 
@@ -67,8 +95,16 @@ pub fn match_and_score_with_positions(
       let haystack = lowercase_haystack.as_deref().unwrap_or(haystack);
     */
 
-    matches(needle, haystack)
-        .map(|needle_length| score_with_positions(needle, needle_length, haystack, haystack_length))
+    matches(needle, haystack).map(|needle_length| {
+        score_with_positions(
+            needle,
+            needle_length,
+            haystack,
+            haystack_length,
+            separators,
+            skipped,
+        )
+    })
 }
 
 /// Searches for needle's chars in the haystack.
@@ -106,6 +142,8 @@ fn score_with_positions(
     needle_length: usize,
     haystack: &str,
     haystack_length: usize,
+    separators: &[char],
+    skipped: &[char],
 ) -> (Score, Vec<usize>) {
     // empty needle
     if needle_length == 0 {
@@ -118,7 +156,20 @@ fn score_with_positions(
     }
 
     #[allow(non_snake_case)]
-    let (D, M) = calculate_score(needle, needle_length, haystack, haystack_length);
+    let (D, M) = calculate_score(
+        needle,
+        needle_length,
+        haystack,
+        haystack_length,
+        separators,
+        skipped,
+    );
+
+    let needle_chars = needle.chars().collect::<Vec<_>>();
+    let haystack_chars = haystack.chars().collect::<Vec<_>>();
+    let is_skipped = |i: usize, j: usize| {
+        skipped.contains(&haystack_chars[j]) && !eq(needle_chars[i], haystack_chars[j])
+    };
 
     let mut positions = vec![0_usize; needle_length];
 
@@ -128,6 +179,12 @@ fn score_with_positions(
 
         for i in (0..needle_length).rev() {
             while j > 0_usize {
+                // The scores of the skipped chars are carried over from the previous ones.
+                if is_skipped(i, j) {
+                    j -= 1;
+                    continue;
+                }
+
                 let last = if i > 0 && j > 0 {
                     D.get(i - 1, j - 1)
                 } else {
@@ -159,6 +216,8 @@ fn calculate_score(
     needle_length: usize,
     haystack: &str,
     haystack_length: usize,
+    separators: &[char],
+    skipped: &[char],
 ) -> (Matrix, Matrix) {
     let bonus = compute_bonus(haystack, haystack_length);
 
@@ -172,15 +231,34 @@ fn calculate_score(
         } else {
             SCORE_GAP_INNER
         };
+        // Length of the leading gap and the best score without the gap penalty.
+        let mut leading_gap = 0;
+        let mut best_score = SCORE_MIN;
 
         for (j, h) in haystack.chars().enumerate() {
+            if skipped.contains(&h) && !eq(n, h) {
+                let d = if j > 0 { D.get(i, j - 1) } else { SCORE_MIN };
+                D.set(i, j, d);
+                M.set(i, j, prev_score);
+                continue;
+            }
+
+            if separators.contains(&h) && !eq(n, h) {
+                prev_score = prev_score.max(best_score);
+                leading_gap = 0;
+
+                D.set(i, j, SCORE_MIN);
+                M.set(i, j, prev_score);
+                continue;
+            }
+
             if eq(n, h) {
                 let bonus_score = bonus[j];
 
                 let score = match i {
                     0 => score_add(
                         bonus_score,
-                        score_mul(score_from_usize(j), SCORE_GAP_LEADING),
+                        score_mul(score_from_usize(leading_gap), SCORE_GAP_LEADING),
                     ),
                     _ if j > 0 => {
                         let m = score_add(M.get(i - 1, j - 1), bonus_score);
@@ -190,6 +268,7 @@ fn calculate_score(
                     _ => SCORE_MIN,
                 };
 
+                best_score = best_score.max(score);
                 prev_score = score.max(score_add(prev_score, gap_score));
 
                 D.set(i, j, score);
@@ -200,6 +279,8 @@ fn calculate_score(
                 D.set(i, j, SCORE_MIN);
                 M.set(i, j, prev_score);
             }
+
+            leading_gap += 1;
         }
     }
 
@@ -335,4 +416,37 @@ mod tests {
         );
         assert_eq!(result, None);
     }
+
+    #[test]
+    fn reset_gap_on_separator() {
+        let (shallow_score, _) =
+            match_and_score_with_separators("main", "main.rs", CaseMatching::Smart, &['/'])
+                .unwrap();
+        let (deep_score, positions) =
+            match_and_score_with_separators("main", "a/b/c/main.rs", CaseMatching::Smart, &['/'])
+                .unwrap();
+        assert_eq!(positions, vec![6, 7, 8, 9]);
+        assert_eq!(deep_score, shallow_score);
+
+        let (deep_score, _) =
+            match_and_score_with_positions("main", "a/b/c/main.rs", CaseMatching::Smart).unwrap();
+        assert!(deep_score < shallow_score);
+    }
+
+    #[test]
+    fn skip_separators() {
+        let skipped = &['_', '-', '.', ' '];
+        let (score, positions) =
+            match_and_score_with_skipped("getuser", "get_user", CaseMatching::Smart, &[], skipped)
+                .unwrap();
+        assert_eq!(positions, vec![0, 1, 2, 4, 5, 6, 7]);
+
+        let (consecutive_score, _) =
+            match_and_score_with_positions("getuser", "getusers", CaseMatching::Smart).unwrap();
+        assert!(score >= consecutive_score);
+
+        let (unskipped_score, _) =
+            match_and_score_with_positions("getuser", "get_user", CaseMatching::Smart).unwrap();
+        assert!(score > unskipped_score);
+    }
 }
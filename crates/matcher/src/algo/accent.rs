@@ -0,0 +1,63 @@
+//! Accent-insensitive matching, e.g., `cafe` matches `café`.
+
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+/// Returns `text` with the combining diacritics stripped and the index of original char of each
+/// remaining char.
+///
+/// A char is replaced with the base chars of its canonical decomposition (NFD) only if the
+/// decomposition has a combining mark, the other chars, e.g., Hangul syllables, are kept as is.
+/// The marks already decomposed in `text` are removed too. All the indices are char indices.
+pub fn strip_accents(text: &str) -> (String, Vec<usize>) {
+    let mut stripped = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+
+    for (idx, c) in text.chars().enumerate() {
+        if is_combining_mark(c) {
+            continue;
+        }
+
+        let start = stripped.len();
+        let mut has_mark = false;
+        decompose_canonical(c, |d| {
+            if is_combining_mark(d) {
+                has_mark = true;
+            } else {
+                stripped.push(d);
+            }
+        });
+
+        if !has_mark {
+            stripped.truncate(start);
+            stripped.push(c);
+        }
+        let pushed = stripped[start..].chars().count();
+        origins.extend(std::iter::repeat_n(idx, pushed));
+    }
+
+    (stripped, origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_accents() {
+        assert_eq!(strip_accents("café"), ("cafe".into(), vec![0, 1, 2, 3]));
+        assert_eq!(strip_accents("Ångström").0, "Angstrom");
+
+        // Decomposed `é`, the combining mark is dropped.
+        assert_eq!(
+            strip_accents("cafe\u{301}.txt"),
+            ("cafe.txt".into(), vec![0, 1, 2, 3, 5, 6, 7, 8])
+        );
+
+        // No decomposition for the chars without the diacritics.
+        assert_eq!(strip_accents("ß日本").0, "ß日本");
+        assert_eq!(
+            strip_accents("한글.txt"),
+            ("한글.txt".into(), vec![0, 1, 2, 3, 4, 5])
+        );
+    }
+}
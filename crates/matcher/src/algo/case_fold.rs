@@ -0,0 +1,114 @@
+//! Unicode full case folding used in the case-insensitive fuzzy matching, e.g., `strasse`
+//! matches `Straße`.
+
+/// Case folding applied to the query and the text in the case-insensitive matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CaseFolding {
+    /// Compares the chars case-insensitively one by one, `ß` only matches `ß`.
+    #[default]
+    Simple,
+    /// Unicode full case folding, a char may be folded into several chars, e.g., `ß` to `ss`.
+    Full,
+    /// Full case folding with the Turkic mappings, `I` is folded to `ı` and `İ` to `i`.
+    Turkic,
+}
+
+impl std::str::FromStr for CaseFolding {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl<T: AsRef<str>> From<T> for CaseFolding {
+    fn from(case_folding: T) -> Self {
+        match case_folding.as_ref().to_lowercase().as_str() {
+            "full" => Self::Full,
+            "turkic" => Self::Turkic,
+            _ => Self::Simple,
+        }
+    }
+}
+
+/// Returns the full case folding of `c` that differs from its lowercase mapping.
+fn full_fold(c: char) -> Option<&'static str> {
+    let folded = match c {
+        'ß' | 'ẞ' => "ss",
+        'ŉ' => "ʼn",
+        'ﬀ' => "ff",
+        'ﬁ' => "fi",
+        'ﬂ' => "fl",
+        'ﬃ' => "ffi",
+        'ﬄ' => "ffl",
+        'ﬅ' | 'ﬆ' => "st",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+/// Returns the folded `text` and the index of original char of each folded char.
+///
+/// All the indices are char indices.
+pub fn fold(text: &str, case_folding: CaseFolding) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+
+    let mut push = |idx: usize, chars: &mut dyn Iterator<Item = char>| {
+        for c in chars {
+            folded.push(c);
+            origins.push(idx);
+        }
+    };
+
+    for (idx, c) in text.chars().enumerate() {
+        match (case_folding, c) {
+            (CaseFolding::Turkic, 'I') => push(idx, &mut std::iter::once('ı')),
+            (CaseFolding::Turkic, 'İ') => push(idx, &mut std::iter::once('i')),
+            (CaseFolding::Simple, c) => push(idx, &mut c.to_lowercase()),
+            (_, c) => match full_fold(c) {
+                Some(s) => push(idx, &mut s.chars()),
+                None => push(idx, &mut c.to_lowercase()),
+            },
+        }
+    }
+
+    (folded, origins)
+}
+
+/// Maps the `indices` of folded chars to the indices of original chars using `origins` returned
+/// by [`fold`], the chars folded from the same original char are merged into one index.
+pub fn original_indices(indices: &[usize], origins: &[usize], offset: usize) -> Vec<usize> {
+    let mut original = indices
+        .iter()
+        .filter_map(|idx| origins.get(*idx))
+        .map(|idx| idx + offset)
+        .collect::<Vec<_>>();
+    original.dedup();
+    original
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_german() {
+        let (folded, origins) = fold("Straße", CaseFolding::Full);
+        assert_eq!(folded, "strasse");
+        assert_eq!(origins, vec![0, 1, 2, 3, 4, 4, 5]);
+
+        let (folded, _) = fold("Straße", CaseFolding::Simple);
+        assert_eq!(folded, "straße");
+
+        assert_eq!(
+            original_indices(&[0, 4, 5, 6], &origins, 10),
+            vec![10, 14, 15]
+        );
+    }
+
+    #[test]
+    fn test_fold_turkish() {
+        assert_eq!(fold("DİYARBAKIR", CaseFolding::Turkic).0, "diyarbakır");
+        assert_eq!(fold("DİYARBAKIR", CaseFolding::Full).0, "di\u{307}yarbakir");
+    }
+}
@@ -1,5 +1,8 @@
 // Re-export the fzy algorithm
-pub use extracted_fzy::{match_and_score_with_positions, MatchWithPositions};
+pub use extracted_fzy::{
+    match_and_score_with_positions, match_and_score_with_separators, match_and_score_with_skipped,
+    MatchWithPositions,
+};
 
 use extracted_fzy::CaseMatching;
 
@@ -10,12 +13,24 @@ pub fn fuzzy_indices(
     line: &str,
     query: &str,
     case_sensitive: types::CaseMatching,
+) -> Option<MatchResult> {
+    fuzzy_indices_with_separators(line, query, case_sensitive, &[], &[])
+}
+
+/// Same with [`fuzzy_indices`], but the gap penalty is reset on any of `separators` and any of
+/// `skipped` is skipped without the gap penalty.
+pub fn fuzzy_indices_with_separators(
+    line: &str,
+    query: &str,
+    case_sensitive: types::CaseMatching,
+    separators: &[char],
+    skipped: &[char],
 ) -> Option<MatchResult> {
     let case_sensitive = match case_sensitive {
         types::CaseMatching::Ignore => CaseMatching::Ignore,
         types::CaseMatching::Respect => CaseMatching::Respect,
         types::CaseMatching::Smart => CaseMatching::Smart,
     };
-    match_and_score_with_positions(query, line, case_sensitive)
+    match_and_score_with_skipped(query, line, case_sensitive, separators, skipped)
         .map(|(score, indices)| MatchResult::new(score as Score, indices))
 }
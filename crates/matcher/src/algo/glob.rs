@@ -0,0 +1,222 @@
+//! Matching the shell-like glob patterns, `*` matches any run of chars and `?` matches a
+//! single char, `\` escapes the next char.
+//!
+//! The pattern can match anywhere in the text unless it's anchored by a leading `^` or a
+//! trailing `$`, e.g., `^src/*.rs$` only matches the whole text.
+
+use types::{CaseMatching, FuzzyText};
+
+use crate::{MatchResult, Score};
+
+/// Chars of the pattern between two `*`, `None` stands for `?`.
+type Segment = Vec<Option<char>>;
+
+#[derive(Debug, Clone)]
+pub struct GlobPattern {
+    /// Original pattern, used for the smart case matching.
+    pattern: String,
+    /// Segments separated by `*`, the first/last one is empty if the pattern starts/ends with
+    /// `*`.
+    segments: Vec<Segment>,
+    anchor_start: bool,
+    anchor_end: bool,
+}
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> Self {
+        let (anchor_start, glob) = match pattern.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let (anchor_end, glob) = match glob.strip_suffix('$') {
+            Some(rest) if !rest.ends_with('\\') => (true, rest),
+            _ => (false, glob),
+        };
+
+        let mut segments = vec![Segment::new()];
+        let mut chars = glob.chars();
+        while let Some(c) = chars.next() {
+            let segment = segments.last_mut().expect("Segments are never empty; qed");
+            match c {
+                '*' => segments.push(Segment::new()),
+                '?' => segment.push(None),
+                '\\' => segment.push(Some(chars.next().unwrap_or('\\'))),
+                c => segment.push(Some(c)),
+            }
+        }
+
+        Self {
+            pattern: pattern.into(),
+            segments,
+            anchor_start,
+            anchor_end,
+        }
+    }
+
+    /// Returns the range of chars matched by the leftmost match in `text`.
+    fn find(&self, text: &[char], case_sensitive: bool) -> Option<(usize, usize)> {
+        let char_matches = |p: Option<char>, c: char| match p {
+            None => true,
+            Some(p) if case_sensitive => p == c,
+            Some(p) => p.to_lowercase().eq(c.to_lowercase()),
+        };
+        let matches_at = |segment: &Segment, pos: usize| {
+            pos + segment.len() <= text.len()
+                && segment
+                    .iter()
+                    .zip(&text[pos..])
+                    .all(|(&p, &c)| char_matches(p, c))
+        };
+        let find_from = |segment: &Segment, from: usize| {
+            (from..=text.len().checked_sub(segment.len())?).find(|&pos| matches_at(segment, pos))
+        };
+
+        let (first, rest) = self.segments.split_first()?;
+
+        let (last, middle) = match rest.split_last() {
+            Some(split) => split,
+            None => {
+                // No `*` in the pattern.
+                let start = if self.anchor_end {
+                    text.len().checked_sub(first.len())?
+                } else if self.anchor_start {
+                    0
+                } else {
+                    find_from(first, 0)?
+                };
+                if (self.anchor_start && start != 0) || !matches_at(first, start) {
+                    return None;
+                }
+                return Some((start, start + first.len()));
+            }
+        };
+
+        // The leftmost occurrence of each segment is taken, the following segments won't match
+        // after a later occurrence if they don't after the leftmost one.
+        let start = if self.anchor_start {
+            if !matches_at(first, 0) {
+                return None;
+            }
+            0
+        } else {
+            find_from(first, 0)?
+        };
+
+        let mut cursor = start + first.len();
+        for segment in middle {
+            cursor = find_from(segment, cursor)? + segment.len();
+        }
+
+        let end = if self.anchor_end {
+            let last_start = text.len().checked_sub(last.len())?;
+            if last_start < cursor || !matches_at(last, last_start) {
+                return None;
+            }
+            text.len()
+        } else if last.is_empty() {
+            // The trailing `*` takes the rest of text.
+            text.len()
+        } else {
+            find_from(last, cursor)? + last.len()
+        };
+
+        Some((start, end))
+    }
+
+    /// Returns the match result of `fuzzy_text`, all the chars covered by the match are
+    /// highlighted.
+    ///
+    /// The more literal chars matched and the less chars consumed by the wildcards, the higher
+    /// the score.
+    pub fn find_matches(
+        &self,
+        fuzzy_text: &FuzzyText,
+        case_matching: CaseMatching,
+    ) -> Option<MatchResult> {
+        let text = fuzzy_text.text.chars().collect::<Vec<_>>();
+        let case_sensitive = case_matching.is_case_sensitive(&self.pattern);
+
+        let (start, end) = self.find(&text, case_sensitive)?;
+
+        let literals = self
+            .segments
+            .iter()
+            .flatten()
+            .filter(|p| p.is_some())
+            .count();
+        let score = (2 * literals) as Score - (end - start - literals) as Score;
+
+        let indices = (start..end)
+            .map(|idx| idx + fuzzy_text.matching_start)
+            .collect();
+
+        Some(MatchResult::new(score, indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<Vec<usize>> {
+        GlobPattern::new(pattern)
+            .find_matches(&FuzzyText::new(text, 0), CaseMatching::Smart)
+            .map(|match_result| match_result.indices)
+    }
+
+    #[test]
+    fn test_glob_query() {
+        let paths = [
+            "src/lib.rs",
+            "src/main.rs",
+            "crates/filter/src/source.rs",
+            "src/main.c",
+            "tests/lib.rs",
+            "README.md",
+        ];
+        let matched = |pattern: &str| {
+            paths
+                .into_iter()
+                .filter(|path| find(pattern, path).is_some())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            matched("src/*.rs"),
+            vec!["src/lib.rs", "src/main.rs", "crates/filter/src/source.rs"]
+        );
+        assert_eq!(matched("^src/*.rs"), vec!["src/lib.rs", "src/main.rs"]);
+        assert_eq!(matched("src/????.*"), vec!["src/main.rs", "src/main.c"]);
+        assert_eq!(matched("*.md$"), vec!["README.md"]);
+        assert_eq!(matched("^lib.rs$"), Vec::<&str>::new());
+        assert_eq!(matched("SRC/*.RS"), Vec::<&str>::new());
+        assert_eq!(matched("src/*.RS"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_glob_indices() {
+        // The matched span is highlighted.
+        assert_eq!(
+            find("src/*.rs", "crates/src/lib.rs"),
+            Some((7..17).collect())
+        );
+        assert_eq!(find("l?b", "src/lib.rs"), Some(vec![4, 5, 6]));
+        // The trailing `*` takes the rest.
+        assert_eq!(find("lib*", "src/lib.rs"), Some((4..10).collect()));
+        // Escaped wildcards are literal.
+        assert_eq!(find("a\\*b", "a*b"), Some(vec![0, 1, 2]));
+        assert_eq!(find("a\\*b", "axb"), None);
+        assert_eq!(find("cost\\$", "cost$"), Some((0..5).collect()));
+    }
+
+    #[test]
+    fn test_glob_case_matching() {
+        let pattern = GlobPattern::new("readme*");
+        let text = FuzzyText::new("README.md", 0);
+        assert!(pattern.find_matches(&text, CaseMatching::Smart).is_some());
+        assert!(pattern.find_matches(&text, CaseMatching::Respect).is_none());
+        assert!(GlobPattern::new("Readme*")
+            .find_matches(&text, CaseMatching::Smart)
+            .is_none());
+    }
+}
@@ -1,16 +1,24 @@
+pub mod accent;
+pub mod case_fold;
 pub mod fzy;
+pub mod glob;
+pub mod path_segments;
 pub mod skim;
 pub mod substring;
+pub mod typo;
 
 use types::{CaseMatching, FuzzyText};
 
 use crate::MatchResult;
 
 /// Supported fuzzy match algorithm.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash)]
 pub enum FuzzyAlgorithm {
     Skim,
     Fzy,
+    /// Matches each whitespace-separated part of the query as a substring, much cheaper than
+    /// the fuzzy algorithms.
+    Substring,
 }
 
 impl std::str::FromStr for FuzzyAlgorithm {
@@ -25,6 +33,7 @@ impl<T: AsRef<str>> From<T> for FuzzyAlgorithm {
         match algo.as_ref().to_lowercase().as_str() {
             "skim" => Self::Skim,
             "fzy" => Self::Fzy,
+            "substring" => Self::Substring,
             _ => Self::Fzy,
         }
     }
@@ -43,6 +52,21 @@ impl FuzzyAlgorithm {
         query: &str,
         fuzzy_text: &FuzzyText,
         case_matching: CaseMatching,
+    ) -> Option<MatchResult> {
+        self.fuzzy_match_with_separators(query, fuzzy_text, case_matching, &[], &[])
+    }
+
+    /// Same with [`Self::fuzzy_match`], but the gap penalty is reset on any of `separators` and
+    /// any of `skipped` is skipped without the gap penalty.
+    ///
+    /// Only the fzy algorithm supports the separators, they are ignored by skim.
+    pub fn fuzzy_match_with_separators(
+        &self,
+        query: &str,
+        fuzzy_text: &FuzzyText,
+        case_matching: CaseMatching,
+        separators: &[char],
+        skipped: &[char],
     ) -> Option<MatchResult> {
         let FuzzyText {
             text,
@@ -50,8 +74,12 @@ impl FuzzyAlgorithm {
         } = fuzzy_text;
 
         let fuzzy_result = match self {
-            Self::Fzy => fzy::fuzzy_indices(text, query, case_matching),
+            Self::Fzy => {
+                fzy::fuzzy_indices_with_separators(text, query, case_matching, separators, skipped)
+            }
             Self::Skim => skim::fuzzy_indices(text, query, case_matching),
+            Self::Substring => substring::substr_indices(text, query, case_matching)
+                .map(|(score, indices)| MatchResult::new(score, indices)),
         };
         fuzzy_result.map(|MatchResult { score, indices }| {
             let mut indices = indices;
@@ -0,0 +1,112 @@
+//! Matching the query as a sequence of path segments separated by `/`, each segment is matched
+//! fuzzily within a component of the path.
+//!
+//! A segment followed by `/` in the query must match a directory, i.e., a component followed
+//! by `/` in the path, e.g., `src/main` matches `src/bin/main.rs` but not `main/src.rs`.
+
+use types::{CaseMatching, FuzzyText};
+
+use crate::{FuzzyAlgorithm, MatchResult, Score};
+
+#[derive(Debug, Clone)]
+struct Segment {
+    word: String,
+    /// Whether the segment must match a directory.
+    is_dir: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathSegments {
+    segments: Vec<Segment>,
+}
+
+impl PathSegments {
+    pub fn new(query: &str) -> Self {
+        let mut parts = query.split('/').peekable();
+        let mut segments = Vec::new();
+        while let Some(part) = parts.next() {
+            if !part.is_empty() {
+                segments.push(Segment {
+                    word: part.into(),
+                    is_dir: parts.peek().is_some(),
+                });
+            }
+        }
+        Self { segments }
+    }
+
+    /// Returns the match result of `fuzzy_text`, the sum of the scores of all the segments.
+    ///
+    /// Each segment takes the leftmost component it matches after the one matched by the
+    /// previous segment.
+    pub fn find_matches(
+        &self,
+        fuzzy_text: &FuzzyText,
+        fuzzy_algo: FuzzyAlgorithm,
+        case_matching: CaseMatching,
+    ) -> Option<MatchResult> {
+        if self.segments.is_empty() {
+            return None;
+        }
+
+        // (component, char offset of component, is directory)
+        let mut components = Vec::new();
+        let mut offset = fuzzy_text.matching_start;
+        let mut parts = fuzzy_text.text.split('/').peekable();
+        while let Some(part) = parts.next() {
+            let is_dir = parts.peek().is_some();
+            components.push((part, offset, is_dir));
+            offset += part.chars().count() + 1;
+        }
+
+        let mut score = Score::default();
+        let mut indices = Vec::new();
+        let mut components = components.into_iter();
+        for segment in &self.segments {
+            let match_result = components.by_ref().find_map(|(part, offset, is_dir)| {
+                if segment.is_dir && !is_dir {
+                    return None;
+                }
+                fuzzy_algo.fuzzy_match(&segment.word, &FuzzyText::new(part, offset), case_matching)
+            })?;
+            score += match_result.score;
+            indices.extend(match_result.indices);
+        }
+
+        Some(MatchResult::new(score, indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(query: &str, text: &str) -> Option<Vec<usize>> {
+        PathSegments::new(query)
+            .find_matches(
+                &FuzzyText::new(text, 0),
+                FuzzyAlgorithm::Fzy,
+                CaseMatching::Smart,
+            )
+            .map(|match_result| match_result.indices)
+    }
+
+    #[test]
+    fn test_path_segments() {
+        assert_eq!(find("s/m", "src/main.rs"), Some(vec![0, 4]));
+        assert_eq!(find("s/m", "smith.rs"), None);
+        assert_eq!(find("s/m", "crates/src/mod.rs"), Some(vec![5, 11]));
+
+        // The segment matches within a component.
+        assert_eq!(find("srcmain", "src/main.rs"), None);
+        assert_eq!(find("s/rcm", "src/main.rs"), None);
+
+        // The segment followed by `/` only matches a directory.
+        assert_eq!(find("main/", "src/main.rs"), None);
+        assert_eq!(find("src/", "src/main.rs"), Some(vec![0, 1, 2]));
+        assert_eq!(find("m/s", "src/main.rs"), None);
+
+        // The chars are counted instead of bytes.
+        assert_eq!(find("d/f", "dé/f.rs"), Some(vec![0, 3]));
+    }
+}
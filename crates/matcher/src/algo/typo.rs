@@ -0,0 +1,116 @@
+//! Fallback of the fuzzy matching tolerating the typos, e.g., `recieve` matches `receive`.
+
+use types::CaseMatching;
+
+use crate::{fzy, MatchResult, Score};
+
+/// Returns the optimal string alignment distance between `a` and `b`, i.e., the
+/// Damerau-Levenshtein distance in which no substring is edited more than once.
+fn osa_distance(a: &[char], b: &[char]) -> usize {
+    let (m, n) = (a.len(), b.len());
+
+    let mut d = vec![vec![0usize; n + 1]; m + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=m {
+        for j in 1..=n {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[m][n]
+}
+
+/// Returns the match result of the substring of `text` closest to `query` within `max_edits`
+/// edits (insertion, deletion, substitution and transposition).
+///
+/// The score is the score of the substring as if it was typed exactly, penalized by the
+/// number of edits.
+pub fn fuzzy_indices_with_typos(
+    text: &str,
+    query: &str,
+    max_edits: usize,
+    case_matching: CaseMatching,
+) -> Option<MatchResult> {
+    if max_edits == 0 || query.is_empty() {
+        return None;
+    }
+
+    let case_sensitive = case_matching.is_case_sensitive(query);
+    let normalize = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let (byte_offsets, text_chars): (Vec<usize>, Vec<char>) = text
+        .char_indices()
+        .map(|(idx, c)| (idx, normalize(c)))
+        .unzip();
+    let query_chars = query.chars().map(normalize).collect::<Vec<_>>();
+
+    // (edits, start, len) of the closest substring.
+    let mut closest: Option<(usize, usize, usize)> = None;
+
+    let min_len = query_chars.len().saturating_sub(max_edits).max(1);
+    let max_len = (query_chars.len() + max_edits).min(text_chars.len());
+    for len in min_len..=max_len {
+        for start in 0..=text_chars.len() - len {
+            let edits = osa_distance(&query_chars, &text_chars[start..start + len]);
+            if edits <= max_edits && closest.is_none_or(|(min_edits, ..)| edits < min_edits) {
+                closest.replace((edits, start, len));
+            }
+        }
+    }
+
+    let (edits, start, len) = closest?;
+
+    let byte_start = byte_offsets[start];
+    let byte_end = byte_offsets.get(start + len).copied().unwrap_or(text.len());
+
+    let MatchResult { score, indices } =
+        fzy::fuzzy_indices(text, &text[byte_start..byte_end], case_matching)?;
+
+    Some(MatchResult::new(score / (edits as Score + 1), indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osa_distance() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(osa_distance(&chars("recieve"), &chars("receive")), 1);
+        assert_eq!(osa_distance(&chars("recive"), &chars("receive")), 1);
+        assert_eq!(osa_distance(&chars("receive"), &chars("receive")), 0);
+        assert_eq!(osa_distance(&chars("abc"), &chars("xyz")), 3);
+    }
+
+    #[test]
+    fn test_fuzzy_indices_with_typos() {
+        let matched =
+            fuzzy_indices_with_typos("fn receive()", "recieve", 1, CaseMatching::Smart).unwrap();
+        assert_eq!(matched.indices, vec![3, 4, 5, 6, 7, 8, 9]);
+
+        assert!(
+            fuzzy_indices_with_typos("fn receive()", "rceieve", 1, CaseMatching::Smart).is_none()
+        );
+        assert!(
+            fuzzy_indices_with_typos("fn receive()", "recieve", 0, CaseMatching::Smart).is_none()
+        );
+    }
+}
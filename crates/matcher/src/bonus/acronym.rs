@@ -0,0 +1,72 @@
+//! Add a bonus score if the query matches the acronym of the text.
+
+use crate::Score;
+
+/// Returns the char indices of the word starts in `text`, i.e., the first char, the char
+/// following a separator and the uppercase char following a lowercase char (camelCase hump).
+///
+/// The indices count chars rather than bytes, the same as the match indices.
+fn word_starts(text: &str) -> Vec<usize> {
+    let mut prev: Option<char> = None;
+    text.chars()
+        .enumerate()
+        .filter_map(|(idx, cur)| {
+            let is_word_start = cur.is_alphanumeric()
+                && prev.is_none_or(|prev| {
+                    !prev.is_alphanumeric() || (prev.is_lowercase() && cur.is_uppercase())
+                });
+            prev = Some(cur);
+            is_word_start.then_some(idx)
+        })
+        .collect()
+}
+
+/// Returns a bonus score equal to the base score if every matched char can land on the start of
+/// a word, e.g., `gca` matches `get_cached_action`.
+///
+/// The base algorithm may prefer the consecutive matches (`ca` of `cached`), hence the matched
+/// chars are checked against the chars at the word starts instead of the `indices` themselves.
+pub(crate) fn calc_bonus_acronym(text: &str, score: Score, indices: &[usize]) -> Score {
+    if indices.is_empty() {
+        return 0;
+    }
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut initials = word_starts(text)
+        .into_iter()
+        .map(|idx| chars[idx].to_lowercase().to_string());
+    let is_acronym = indices.iter().all(|idx| {
+        chars.get(*idx).is_some_and(|c| {
+            let matched = c.to_lowercase().to_string();
+            initials.any(|initial| initial == matched)
+        })
+    });
+    if is_acronym {
+        score
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acronym_bonus() {
+        assert_eq!(calc_bonus_acronym("get_cached_action", 10, &[0, 4, 11]), 10);
+        assert_eq!(calc_bonus_acronym("getCachedAction", 10, &[0, 3, 9]), 10);
+        assert_eq!(calc_bonus_acronym("bigcatalogsection", 10, &[2, 3, 4]), 0);
+        assert_eq!(calc_bonus_acronym("get_cached_action", 10, &[0, 4, 12]), 0);
+        assert_eq!(calc_bonus_acronym("get_cached_action", 10, &[]), 0);
+        // The consecutive matches picked by the base algorithm are still an acronym.
+        assert_eq!(calc_bonus_acronym("get_cached_action", 10, &[0, 4, 5]), 10);
+
+        // The indices are char indices, the multi-byte chars shift the byte offsets.
+        assert_eq!(calc_bonus_acronym("ßet_cached_action", 10, &[0, 4, 11]), 10);
+        assert_eq!(
+            calc_bonus_acronym("日本_cached_action", 10, &[0, 3, 10]),
+            10
+        );
+        assert_eq!(calc_bonus_acronym("日本_cached_action", 10, &[0, 4, 11]), 0);
+    }
+}
@@ -0,0 +1,99 @@
+//! Add a penalty to the matches in the comment of a line.
+
+use crate::Score;
+
+use super::language::FileExtension;
+
+/// Returns the char position where the comment of `line` starts.
+///
+/// The comment prefixes inside a string literal are ignored.
+fn comment_start(line: &str, file_ext: &str) -> Option<usize> {
+    let comment_syntax = dumb_analyzer::get_comment_syntax(file_ext);
+
+    let mut in_string = false;
+    let mut escaped = false;
+    for (char_idx, (byte_idx, c)) in line.char_indices().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if comment_syntax
+            .iter()
+            .any(|prefix| line[byte_idx..].starts_with(prefix))
+        {
+            return Some(char_idx);
+        }
+    }
+
+    None
+}
+
+/// Deprioritizes the matches falling in the comment region of a line given the file extension.
+#[derive(Debug, Clone, Hash)]
+pub struct Comment(FileExtension);
+
+impl<T: AsRef<str>> From<T> for Comment {
+    fn from(s: T) -> Self {
+        Self(s.as_ref().into())
+    }
+}
+
+impl Comment {
+    /// Formula:
+    ///   bonus_score = -base_score / 2 * len(matched_elements_in_comment) / len(matched_elements)
+    pub fn calc_bonus(&self, bonus_text: &str, base_score: Score, indices: &[usize]) -> Score {
+        if indices.is_empty() {
+            return 0;
+        }
+
+        match comment_start(bonus_text, &self.0) {
+            Some(start) => {
+                let hits_in_comment = indices.iter().filter(|x| **x >= start).count();
+                -(base_score / 2) * hits_in_comment as Score / indices.len() as Score
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bonus, MatcherBuilder, SourceItem};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_comment_start() {
+        assert_eq!(comment_start("let x = 1; // parse", "rs"), Some(11));
+        assert_eq!(comment_start("/// parse", "rs"), Some(0));
+        assert_eq!(comment_start(r#"println!("// \" parse");"#, "rs"), None);
+        assert_eq!(comment_start("x = 1  # parse", "py"), Some(7));
+        assert_eq!(comment_start("let x = 1;", "rs"), None);
+    }
+
+    #[test]
+    fn test_deprioritize_comments() {
+        let score_of = |bonuses: Vec<Bonus>, line: &str| {
+            MatcherBuilder::default()
+                .bonuses(bonuses)
+                .build("parse".into())
+                .match_item(Arc::new(SourceItem::from(line.to_string())))
+                .unwrap()
+                .score
+        };
+
+        // `--` is not a comment in Rust, the fuzzy scores of the two lines are the same.
+        let in_code = "call(x); -- parse";
+        let in_comment = "call(x); // parse";
+        assert_eq!(score_of(vec![], in_code), score_of(vec![], in_comment));
+
+        let bonuses = vec![Bonus::Comment("rs".into())];
+        assert!(score_of(bonuses.clone(), in_comment) < score_of(bonuses.clone(), in_code));
+        assert_eq!(score_of(bonuses, in_code), score_of(vec![], in_code));
+    }
+}
@@ -5,7 +5,7 @@ use crate::Score;
 /// Used for recent_files provider.
 ///
 /// Each entry of recent_files provider is an absolute path String.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub struct Cwd {
     /// Absolute path String.
     pub abs_path: String,
@@ -6,7 +6,7 @@ use crate::Score;
 
 pub type FileExtension = String;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Language(FileExtension);
 
 impl<T: AsRef<str>> From<T> for Language {
@@ -1,33 +1,56 @@
+pub mod acronym;
+pub mod comment;
 pub mod cwd;
 pub mod filename;
 pub mod language;
+pub mod path_components;
 pub mod recent_files;
+pub mod recently_modified;
 
 use std::sync::Arc;
 
 use types::ClapItem;
 
+use self::acronym::calc_bonus_acronym;
+use self::comment::Comment;
 use self::cwd::Cwd;
 use self::filename::calc_bonus_file_name;
 use self::language::Language;
+use self::path_components::calc_bonus_path_components;
 use self::recent_files::RecentFiles;
+use self::recently_modified::RecentlyModified;
 
 use crate::Score;
 
 /// Tweak the matching score calculated by the base match algorithm.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub enum Bonus {
     /// Give a bonus if the needle matches in the basename of the haystack.
     ///
     /// Ref https://github.com/liuchengxu/vim-clap/issues/561
     FileName,
 
+    /// Give a bonus if every matched char is the start of a word, e.g., `gca` matches
+    /// `get_cached_action`.
+    Acronym,
+
+    /// Give a bonus for every matched char landing on the start of a path component, e.g.,
+    /// `s/m/m` matches `src/main/mod.rs`.
+    PathComponents,
+
     /// Give a bonus to for the keywords if the language type is known.
     Language(Language),
 
+    /// Give a penalty if the needle matches in the comment of the line, the comment syntax is
+    /// determined by the file extension.
+    Comment(Comment),
+
     /// Give a bonus if the item is in the list of recently opened files.
     RecentFiles(RecentFiles),
 
+    /// Give a bonus if the item is a file modified recently.
+    RecentlyModified(RecentlyModified),
+
     /// Give a bonus if the entry is an absolute file path and matches the cwd.
     Cwd(Cwd),
 
@@ -45,6 +68,8 @@ impl<T: AsRef<str>> From<T> for Bonus {
     fn from(s: T) -> Self {
         match s.as_ref().to_lowercase().as_str() {
             "filename" => Self::FileName,
+            "acronym" => Self::Acronym,
+            "path-components" => Self::PathComponents,
             _ => Self::None,
         }
     }
@@ -67,8 +92,14 @@ impl Bonus {
 
         match self {
             Self::FileName => calc_bonus_file_name(bonus_text, score, indices),
+            Self::Acronym => calc_bonus_acronym(bonus_text, score, indices),
+            Self::PathComponents => calc_bonus_path_components(bonus_text, score, indices),
             Self::RecentFiles(recent_files) => recent_files.calc_bonus(bonus_text, score),
+            Self::RecentlyModified(recently_modified) => {
+                recently_modified.calc_bonus(bonus_text, score)
+            }
             Self::Language(language) => language.calc_bonus(bonus_text, score),
+            Self::Comment(comment) => comment.calc_bonus(bonus_text, score, indices),
             Self::Cwd(cwd) => cwd.calc_bonus(bonus_text, score),
             Self::None => 0,
         }
@@ -0,0 +1,92 @@
+//! Add a bonus score if the query matches the starts of path components.
+
+use crate::Score;
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+/// Returns `true` if the char at `idx` is the start of a path component, i.e., the first char or
+/// the char following a separator.
+fn is_component_start(chars: &[char], idx: usize) -> bool {
+    match chars.get(idx) {
+        Some(cur) if !is_separator(*cur) => idx
+            .checked_sub(1)
+            .and_then(|prev_idx| chars.get(prev_idx))
+            .is_none_or(|prev| is_separator(*prev)),
+        _ => false,
+    }
+}
+
+/// Returns a bonus score proportional to the matched chars landing on the start of a path
+/// component, e.g., `s/m/m` matches `src/main/mod.rs`. The matched separators are not counted.
+///
+/// The indices count chars rather than bytes, the same as the match indices.
+///
+/// Formula:
+///   bonus_score = base_score * len(matched_component_starts) / len(matched_non_separators)
+pub(crate) fn calc_bonus_path_components(path: &str, score: Score, indices: &[usize]) -> Score {
+    let chars = path.chars().collect::<Vec<_>>();
+
+    let (hits, total) = indices
+        .iter()
+        .filter(|idx| chars.get(**idx).is_some_and(|c| !is_separator(*c)))
+        .fold((0, 0), |(hits, total), idx| {
+            if is_component_start(&chars, *idx) {
+                (hits + 1, total + 1)
+            } else {
+                (hits, total + 1)
+            }
+        });
+
+    if total == 0 {
+        0
+    } else {
+        score * hits / total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_components_bonus() {
+        // `s/m/m`
+        assert_eq!(
+            calc_bonus_path_components("src/main/mod.rs", 30, &[0, 3, 4, 8, 9]),
+            30
+        );
+        assert_eq!(
+            calc_bonus_path_components("crates/maple_cli/src/main.rs", 30, &[0, 7, 17]),
+            30
+        );
+        assert_eq!(
+            calc_bonus_path_components("crates/maple_cli/src/main.rs", 30, &[0, 2, 17]),
+            20
+        );
+        assert_eq!(
+            calc_bonus_path_components("crates\\maple_cli\\lib.rs", 30, &[7]),
+            30
+        );
+        assert_eq!(calc_bonus_path_components("src/main.rs", 30, &[3]), 0);
+        assert_eq!(calc_bonus_path_components("src/main.rs", 30, &[]), 0);
+        // The indices are char positions, `ド/メ` of `ドキュメント/メモ.md`.
+        assert_eq!(
+            calc_bonus_path_components("ドキュメント/メモ.md", 30, &[0, 6, 7]),
+            30
+        );
+        assert_eq!(
+            calc_bonus_path_components("ドキュメント/メモ.md", 30, &[1, 8]),
+            0
+        );
+    }
+
+    #[test]
+    fn test_component_starts_outrank_mid_components() {
+        // The same number of matched chars, only the positions differ.
+        let at_starts = calc_bonus_path_components("src/main/mod.rs", 30, &[0, 4, 9]);
+        let mid_components = calc_bonus_path_components("src/main/mod.rs", 30, &[1, 5, 10]);
+        assert!(at_starts > mid_components);
+    }
+}
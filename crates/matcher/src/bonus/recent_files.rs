@@ -1,20 +1,86 @@
+use std::sync::Arc;
+
 use crate::Score;
 
-#[derive(Debug, Clone)]
-pub struct RecentFiles(Vec<String>);
+/// Seconds of one day.
+const DAY: u64 = 24 * 60 * 60;
+
+/// The files are shared between the clones, which are made on each query.
+#[derive(Debug, Clone, Hash)]
+pub struct RecentFiles {
+    files: Arc<[String]>,
+    /// Last access time in seconds of each file, in the same order as `files`.
+    timestamps: Option<Arc<[u64]>>,
+    /// The most recent one of `timestamps`, computed once instead of per candidate.
+    latest_timestamp: u64,
+}
 
 impl RecentFiles {
+    /// Constructs a new instance of [`RecentFiles`] with the last access time of each file.
+    pub fn with_timestamps(entries: Vec<(String, u64)>) -> Self {
+        let (files, timestamps): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+        let latest_timestamp = timestamps.iter().max().copied().unwrap_or_default();
+        Self {
+            files: files.into(),
+            timestamps: Some(timestamps.into()),
+            latest_timestamp,
+        }
+    }
+
+    /// Returns `true` if `bonus_text` is part of any recent file.
+    pub fn contains(&self, bonus_text: &str) -> bool {
+        self.files.iter().any(|s| s.contains(bonus_text))
+    }
+
     pub fn calc_bonus(&self, bonus_text: &str, base_score: Score) -> Score {
-        if self.0.iter().any(|s| s.contains(bonus_text)) {
-            base_score / 3
-        } else {
-            0
+        let idx = match self.files.iter().position(|s| s.contains(bonus_text)) {
+            Some(idx) => idx,
+            None => return 0,
+        };
+
+        match self.timestamps {
+            Some(ref timestamps) => {
+                // The bonus decays with the age relative to the most recent file.
+                let age = self.latest_timestamp.saturating_sub(timestamps[idx]);
+                ((base_score / 3) as i64 * DAY as i64 / (DAY + age) as i64) as Score
+            }
+            None => base_score / 3,
         }
     }
 }
 
 impl From<Vec<String>> for RecentFiles {
-    fn from(inner: Vec<String>) -> Self {
-        Self(inner)
+    fn from(files: Vec<String>) -> Self {
+        Self {
+            files: files.into(),
+            timestamps: None,
+            latest_timestamp: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files_bonus_with_timestamps() {
+        let now = 1_660_000_000;
+        let recent_files = RecentFiles::with_timestamps(vec![
+            ("/home/xlc/a.rs".into(), now),
+            ("/home/xlc/b.rs".into(), now - DAY),
+            ("/home/xlc/c.rs".into(), now - 30 * DAY),
+        ]);
+        let bonus_a = recent_files.calc_bonus("/home/xlc/a.rs", 300);
+        let bonus_b = recent_files.calc_bonus("/home/xlc/b.rs", 300);
+        let bonus_c = recent_files.calc_bonus("/home/xlc/c.rs", 300);
+        assert_eq!(recent_files.latest_timestamp, now);
+        assert_eq!(bonus_a, 100);
+        assert!(bonus_a > bonus_b);
+        assert!(bonus_b > bonus_c);
+        assert_eq!(recent_files.calc_bonus("/home/xlc/d.rs", 300), 0);
+
+        let flat: RecentFiles = vec!["/home/xlc/a.rs".to_string()].into();
+        assert_eq!(flat.calc_bonus("/home/xlc/a.rs", 300), 100);
     }
 }
@@ -0,0 +1,124 @@
+//! Add a bonus score for the file modified recently.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Score;
+
+/// Seconds of one day.
+const DAY: u64 = 24 * 60 * 60;
+
+/// Returns the modification time of `path` in seconds since the unix epoch.
+fn mtime_secs(path: PathBuf) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// The files are statted lazily on calculating the bonus, which happens concurrently in the
+/// parallel filtering. The results are shared between the clones made on each query.
+#[derive(Debug, Clone)]
+pub struct RecentlyModified {
+    /// Directory the relative paths are resolved against.
+    root: PathBuf,
+    /// Seconds since the unix epoch, the age of file is relative to this.
+    now: u64,
+    /// Modification time of each file, `None` if the file can't be statted.
+    mtimes: Arc<RwLock<HashMap<String, Option<u64>>>>,
+}
+
+// The mtimes are merely the cache of the files under `root`.
+impl std::hash::Hash for RecentlyModified {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.root.hash(state);
+        self.now.hash(state);
+    }
+}
+
+impl RecentlyModified {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            now: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            mtimes: Default::default(),
+        }
+    }
+
+    fn mtime(&self, bonus_text: &str) -> Option<u64> {
+        if let Some(mtime) = self.mtimes.read().ok()?.get(bonus_text) {
+            return *mtime;
+        }
+
+        // Don't hold the lock while statting.
+        let mtime = mtime_secs(self.root.join(bonus_text));
+        if let Ok(mut mtimes) = self.mtimes.write() {
+            mtimes.insert(bonus_text.to_string(), mtime);
+        }
+        mtime
+    }
+
+    pub fn calc_bonus(&self, bonus_text: &str, base_score: Score) -> Score {
+        match self.mtime(bonus_text) {
+            Some(mtime) => {
+                // The bonus decays with the time since the last modification.
+                let age = self.now.saturating_sub(mtime);
+                ((base_score / 3) as i64 * DAY as i64 / (DAY + age) as i64) as Score
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bonus, MatcherBuilder, SourceItem};
+    use std::time::Duration;
+
+    #[test]
+    fn test_recently_modified_bonus() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        for dir in ["old", "new"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("lib.rs"), "").unwrap();
+        }
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("old/lib.rs"))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(10 * DAY))
+            .unwrap();
+
+        let matcher = MatcherBuilder::default()
+            .bonuses(vec![Bonus::RecentlyModified(RecentlyModified::new(
+                root.to_path_buf(),
+            ))])
+            .build("lib".into());
+        let score_of = |path: &str| {
+            matcher
+                .match_item(Arc::new(SourceItem::from(path.to_string())))
+                .unwrap()
+                .score
+        };
+
+        let old_breakdown = matcher
+            .score_breakdown(Arc::new(SourceItem::from("old/lib.rs".to_string())))
+            .unwrap();
+        let new_breakdown = matcher
+            .score_breakdown(Arc::new(SourceItem::from("new/lib.rs".to_string())))
+            .unwrap();
+        assert_eq!(old_breakdown.fuzzy_score, new_breakdown.fuzzy_score);
+        assert!(score_of("new/lib.rs") > score_of("old/lib.rs"));
+
+        // No bonus if the file can't be statted.
+        let missing_breakdown = matcher
+            .score_breakdown(Arc::new(SourceItem::from("del/lib.rs".to_string())))
+            .unwrap();
+        assert_eq!(missing_breakdown.bonus_scores, vec![0]);
+    }
+}
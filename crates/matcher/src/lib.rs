@@ -27,7 +27,7 @@
 //! //        ↓
 //! //    Apply BonusMatcher
 //! //        |
-//! //        |
+//! //        |  ScoreExpr: optionally replace the score.
 //! //        |
 //! //        ↓
 //! //   MatchResult
@@ -35,14 +35,24 @@
 
 mod algo;
 mod bonus;
+pub mod score_expr;
 
 use std::sync::Arc;
 
 // Re-export types
-pub use self::algo::{fzy, skim, substring, FuzzyAlgorithm};
+pub use self::algo::case_fold::CaseFolding;
+pub use self::algo::{
+    accent, case_fold, fzy, glob, path_segments, skim, substring, typo, FuzzyAlgorithm,
+};
+pub use self::bonus::comment::Comment;
 pub use self::bonus::cwd::Cwd;
 pub use self::bonus::language::Language;
+pub use self::bonus::recent_files::RecentFiles;
+pub use self::bonus::recently_modified::RecentlyModified;
 pub use self::bonus::Bonus;
+use crate::glob::GlobPattern;
+use crate::path_segments::PathSegments;
+use crate::score_expr::{ScoreExpr, Variables};
 use crate::substring::substr_indices;
 use types::{CaseMatching, MatchedItem};
 pub use types::{
@@ -73,6 +83,29 @@ impl InverseMatcher {
     }
 }
 
+/// Matcher of the mode of items in a combined source, the mode of an item is selected by its
+/// leading sigil, e.g., `>` for the commands.
+#[derive(Debug, Clone, Default)]
+pub struct SigilMatcher {
+    /// Sigil of the mode to match, `None` for the items without any of `sigils`.
+    sigil: Option<char>,
+    sigils: Vec<char>,
+}
+
+impl SigilMatcher {
+    pub fn new(sigil: Option<char>, sigils: Vec<char>) -> Self {
+        Self { sigil, sigils }
+    }
+
+    /// Returns `true` if the item of `match_text` is in the mode of this matcher.
+    pub fn is_match(&self, match_text: &str) -> bool {
+        match self.sigil {
+            Some(sigil) => match_text.starts_with(sigil),
+            None => !match_text.starts_with(self.sigils.as_slice()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ExactMatcher {
     exact_terms: Vec<ExactTerm>,
@@ -157,12 +190,27 @@ impl ExactMatcher {
     }
 }
 
+/// Separators skipped in the separator-insensitive fuzzy matching.
+pub const SKIPPED_SEPARATORS: &[char] = &['_', '-', '.', ' '];
+
 #[derive(Debug, Clone, Default)]
 pub struct FuzzyMatcher {
     match_scope: MatchScope,
     fuzzy_algo: FuzzyAlgorithm,
     fuzzy_terms: Vec<FuzzyTerm>,
     case_matching: CaseMatching,
+    /// Maximum number of typos tolerated when the strict fuzzy matching fails.
+    fuzziness: usize,
+    /// Bonus applied when the matched chars have exactly the same case as the query.
+    exact_case_bonus: Score,
+    /// Separators resetting the gap penalty, e.g., `/` in the file paths.
+    gap_separators: Vec<char>,
+    /// Whether [`SKIPPED_SEPARATORS`] are skipped without the gap penalty.
+    separator_insensitive: bool,
+    /// Case folding applied in the case-insensitive matching.
+    case_folding: CaseFolding,
+    /// Whether the combining diacritics are ignored in the matching.
+    accent_insensitive: bool,
 }
 
 impl FuzzyMatcher {
@@ -177,9 +225,165 @@ impl FuzzyMatcher {
             case_matching,
             fuzzy_algo,
             match_scope,
+            fuzziness: 0,
+            exact_case_bonus: 0,
+            gap_separators: Vec::new(),
+            separator_insensitive: false,
+            case_folding: CaseFolding::Simple,
+            accent_insensitive: false,
         }
     }
 
+    /// Tolerates at most `fuzziness` typos if the strict fuzzy matching fails.
+    pub fn with_fuzziness(mut self, fuzziness: usize) -> Self {
+        self.fuzziness = fuzziness;
+        self
+    }
+
+    /// Prefers the exactly-cased matches by `exact_case_bonus` in the case-insensitive matching.
+    pub fn with_exact_case_bonus(mut self, exact_case_bonus: Score) -> Self {
+        self.exact_case_bonus = exact_case_bonus;
+        self
+    }
+
+    /// Resets the gap penalty on any of `gap_separators` in the fuzzy matching.
+    pub fn with_gap_separators(mut self, gap_separators: Vec<char>) -> Self {
+        self.gap_separators = gap_separators;
+        self
+    }
+
+    /// Skips [`SKIPPED_SEPARATORS`] without the gap penalty in the fuzzy matching.
+    pub fn with_separator_insensitive(mut self, separator_insensitive: bool) -> Self {
+        self.separator_insensitive = separator_insensitive;
+        self
+    }
+
+    /// Folds the query and the text with `case_folding` in the case-insensitive matching.
+    pub fn with_case_folding(mut self, case_folding: CaseFolding) -> Self {
+        self.case_folding = case_folding;
+        self
+    }
+
+    /// Strips the combining diacritics from the query and the text in the fuzzy matching.
+    pub fn with_accent_insensitive(mut self, accent_insensitive: bool) -> Self {
+        self.accent_insensitive = accent_insensitive;
+        self
+    }
+
+    /// Returns the normalized `text` for the matching and the index of original char of each
+    /// normalized char.
+    fn normalize(&self, text: &str, fold_case: bool) -> (String, Vec<usize>) {
+        let (text, origins) = if self.accent_insensitive {
+            accent::strip_accents(text)
+        } else {
+            (text.to_string(), (0..text.chars().count()).collect())
+        };
+
+        if fold_case {
+            let (folded, folded_origins) = case_fold::fold(&text, self.case_folding);
+            let origins = folded_origins.into_iter().map(|idx| origins[idx]).collect();
+            (folded, origins)
+        } else {
+            (text, origins)
+        }
+    }
+
+    /// Returns `exact_case_bonus` if the chars at `indices` are identical to `query`.
+    fn calc_exact_case_bonus(
+        &self,
+        query: &str,
+        fuzzy_text: &FuzzyText,
+        indices: &[usize],
+    ) -> Score {
+        if self.exact_case_bonus == 0 || matches!(self.case_matching, CaseMatching::Respect) {
+            return 0;
+        }
+
+        let chars = fuzzy_text.text.chars().collect::<Vec<_>>();
+        let matched_chars = indices
+            .iter()
+            .map(|idx| {
+                idx.checked_sub(fuzzy_text.matching_start)
+                    .and_then(|i| chars.get(i))
+            })
+            .collect::<Option<Vec<_>>>();
+
+        match matched_chars {
+            Some(matched_chars)
+                if matched_chars.len() == query.chars().count()
+                    && matched_chars.iter().map(|c| **c).eq(query.chars()) =>
+            {
+                self.exact_case_bonus
+            }
+            _ => 0,
+        }
+    }
+
+    fn fuzzy_match_with_typos(&self, query: &str, fuzzy_text: &FuzzyText) -> Option<MatchResult> {
+        let fold_case = self.case_folding != CaseFolding::Simple
+            && !self.case_matching.is_case_sensitive(query);
+
+        if !fold_case && !self.accent_insensitive {
+            return self.fuzzy_match_with_case(query, fuzzy_text, self.case_matching);
+        }
+
+        // The normalized text may have a different number of chars, the indices are mapped back
+        // to the chars of original text.
+        let (normalized_query, _) = self.normalize(query, fold_case);
+        let (normalized_text, origins) = self.normalize(fuzzy_text.text, fold_case);
+        let case_matching = if fold_case {
+            CaseMatching::Respect
+        } else {
+            self.case_matching
+        };
+        self.fuzzy_match_with_case(
+            &normalized_query,
+            &FuzzyText::new(&normalized_text, 0),
+            case_matching,
+        )
+        .map(|MatchResult { score, indices }| {
+            let indices =
+                case_fold::original_indices(&indices, &origins, fuzzy_text.matching_start);
+            MatchResult::new(score, indices)
+        })
+    }
+
+    fn fuzzy_match_with_case(
+        &self,
+        query: &str,
+        fuzzy_text: &FuzzyText,
+        case_matching: CaseMatching,
+    ) -> Option<MatchResult> {
+        let skipped = if self.separator_insensitive {
+            SKIPPED_SEPARATORS
+        } else {
+            &[]
+        };
+        self.fuzzy_algo
+            .fuzzy_match_with_separators(
+                query,
+                fuzzy_text,
+                case_matching,
+                &self.gap_separators,
+                skipped,
+            )
+            .or_else(|| {
+                typo::fuzzy_indices_with_typos(
+                    fuzzy_text.text,
+                    query,
+                    self.fuzziness,
+                    case_matching,
+                )
+                .map(|MatchResult { score, indices }| {
+                    let indices = indices
+                        .into_iter()
+                        .map(|x| x + fuzzy_text.matching_start)
+                        .collect();
+                    MatchResult::new(score, indices)
+                })
+            })
+    }
+
     pub fn find_matches(&self, item: &Arc<dyn ClapItem>) -> Option<(Score, Vec<usize>)> {
         let fuzzy_len = self.fuzzy_terms.iter().map(|f| f.len()).sum();
 
@@ -191,11 +395,10 @@ impl FuzzyMatcher {
             for term in self.fuzzy_terms.iter() {
                 let query = &term.word;
                 if let Some(MatchResult { score, indices }) =
-                    self.fuzzy_algo
-                        .fuzzy_match(query, fuzzy_text, self.case_matching)
+                    self.fuzzy_match_with_typos(query, fuzzy_text)
                 {
+                    fuzzy_score += score + self.calc_exact_case_bonus(query, fuzzy_text, &indices);
                     fuzzy_indices.extend_from_slice(&indices);
-                    fuzzy_score += score;
                 } else {
                     return None;
                 }
@@ -226,16 +429,48 @@ impl BonusMatcher {
         self.bonuses
             .iter()
             .map(|b| b.bonus_score(item, base_score, base_indices))
-            .sum()
+            .fold(0, Score::saturating_add)
+    }
+
+    /// Returns the score of each bonus, in the same order as the bonuses.
+    pub fn calc_bonus_per_item(
+        &self,
+        item: &Arc<dyn ClapItem>,
+        base_score: Score,
+        base_indices: &[usize],
+    ) -> Vec<Score> {
+        self.bonuses
+            .iter()
+            .map(|b| b.bonus_score(item, base_score, base_indices))
+            .collect()
+    }
+
+    /// Returns `true` if `item` is one of the recent files.
+    pub fn is_recent(&self, item: &Arc<dyn ClapItem>) -> bool {
+        self.bonuses.iter().any(|bonus| match bonus {
+            Bonus::RecentFiles(recent_files) => recent_files.contains(item.bonus_text()),
+            _ => false,
+        })
     }
 }
 
-#[derive(Debug, Clone, Default)]
+/// The hash covers the whole configuration, two builders with the same hash build the matchers
+/// producing the same results.
+#[derive(Debug, Clone, Default, Hash)]
 pub struct MatcherBuilder {
     bonuses: Vec<Bonus>,
     fuzzy_algo: FuzzyAlgorithm,
     match_scope: MatchScope,
     case_matching: CaseMatching,
+    fuzziness: usize,
+    exact_case_bonus: Score,
+    gap_separators: Vec<char>,
+    separator_insensitive: bool,
+    case_folding: CaseFolding,
+    accent_insensitive: bool,
+    sigil: Option<char>,
+    sigils: Vec<char>,
+    score_expr: Option<ScoreExpr>,
 }
 
 impl MatcherBuilder {
@@ -259,12 +494,85 @@ impl MatcherBuilder {
         self
     }
 
+    /// Sets the maximum number of typos tolerated in the fuzzy matching, 0 by default.
+    pub fn fuzziness(mut self, fuzziness: usize) -> Self {
+        self.fuzziness = fuzziness;
+        self
+    }
+
+    /// Sets the bonus of exact-case matches in the case-insensitive matching, 0 by default.
+    pub fn exact_case_bonus(mut self, exact_case_bonus: Score) -> Self {
+        self.exact_case_bonus = exact_case_bonus;
+        self
+    }
+
+    /// Sets the separators resetting the gap penalty of fuzzy matching, none by default.
+    ///
+    /// With `/`, the directories before the basename of a deep path are not penalized.
+    pub fn gap_separators(mut self, gap_separators: Vec<char>) -> Self {
+        self.gap_separators = gap_separators;
+        self
+    }
+
+    /// Skips the separators `_`, `-`, `.` and space in the fuzzy matching without the gap
+    /// penalty, disabled by default.
+    ///
+    /// With this mode, `getuser` matches `get_user` as strongly as `getuser`.
+    pub fn separator_insensitive(mut self, separator_insensitive: bool) -> Self {
+        self.separator_insensitive = separator_insensitive;
+        self
+    }
+
+    /// Sets the case folding of the case-insensitive fuzzy matching, [`CaseFolding::Simple`] by
+    /// default.
+    ///
+    /// With [`CaseFolding::Full`], `strasse` matches `Straße`.
+    pub fn case_folding(mut self, case_folding: CaseFolding) -> Self {
+        self.case_folding = case_folding;
+        self
+    }
+
+    /// Ignores the combining diacritics in the fuzzy matching, disabled by default.
+    ///
+    /// With this mode, `cafe` matches `café`, the highlights are still on the original chars.
+    pub fn accent_insensitive(mut self, accent_insensitive: bool) -> Self {
+        self.accent_insensitive = accent_insensitive;
+        self
+    }
+
+    /// Only matches the items starting with `sigil` among the items of a combined source, or
+    /// the items not starting with any of `sigils` if `sigil` is `None`, all the items by
+    /// default.
+    ///
+    /// With `>` for the commands, the commands listed as `>cmd` are not matched along with the
+    /// files.
+    pub fn sigils(mut self, sigil: Option<char>, sigils: Vec<char>) -> Self {
+        self.sigil = sigil;
+        self.sigils = sigils;
+        self
+    }
+
+    /// Computes the final score with `score_expr` instead of the built-in scoring if specified.
+    pub fn score_expr(mut self, score_expr: Option<ScoreExpr>) -> Self {
+        self.score_expr = score_expr;
+        self
+    }
+
     pub fn build(self, query: Query) -> Matcher {
         let Self {
             bonuses,
             fuzzy_algo,
             match_scope,
             case_matching,
+            fuzziness,
+            exact_case_bonus,
+            gap_separators,
+            separator_insensitive,
+            case_folding,
+            accent_insensitive,
+            sigil,
+            sigils,
+            score_expr,
         } = self;
 
         let Query {
@@ -273,26 +581,129 @@ impl MatcherBuilder {
             fuzzy_terms,
         } = query;
 
+        let sigil_matcher = SigilMatcher::new(sigil, sigils);
         let inverse_matcher = InverseMatcher::new(inverse_terms);
         let exact_matcher = ExactMatcher::new(exact_terms, case_matching);
-        let fuzzy_matcher = FuzzyMatcher::new(fuzzy_terms, case_matching, fuzzy_algo, match_scope);
+        let fuzzy_matcher = FuzzyMatcher::new(fuzzy_terms, case_matching, fuzzy_algo, match_scope)
+            .with_fuzziness(fuzziness)
+            .with_exact_case_bonus(exact_case_bonus)
+            .with_gap_separators(gap_separators)
+            .with_separator_insensitive(separator_insensitive)
+            .with_case_folding(case_folding)
+            .with_accent_insensitive(accent_insensitive);
         let bonus_matcher = BonusMatcher::new(bonuses);
 
         Matcher {
+            sigil_matcher,
             inverse_matcher,
             exact_matcher,
             fuzzy_matcher,
             bonus_matcher,
+            alternatives: Vec::new(),
+            glob_pattern: None,
+            path_segments: None,
+            score_expr,
+        }
+    }
+
+    /// Builds a matcher matching the items against the glob `pattern` instead of a query.
+    pub fn build_glob(self, pattern: &str) -> Matcher {
+        Matcher {
+            glob_pattern: Some(GlobPattern::new(pattern)),
+            ..self.build("".into())
+        }
+    }
+
+    /// Builds a matcher matching the items against the `/`-separated path segments of `query`
+    /// instead of the fuzzy terms.
+    pub fn build_path_segments(self, query: &str) -> Matcher {
+        Matcher {
+            path_segments: Some(PathSegments::new(query)),
+            ..self.build("".into())
+        }
+    }
+
+    /// Builds a matcher matching the items that match any of `alternatives`.
+    ///
+    /// The best match among the alternatives is taken.
+    pub fn build_any(self, alternatives: impl IntoIterator<Item = Query>) -> Matcher {
+        Matcher {
+            alternatives: alternatives
+                .into_iter()
+                .map(|query| self.clone().build(query))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Components of the final score of a matched item, for debugging the ranking.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    pub exact_score: Score,
+    pub fuzzy_score: Score,
+    /// Score of each bonus, in the same order as the bonuses of matcher.
+    pub bonus_scores: Vec<Score>,
+    /// Score adjusted by [`ClapItem::match_result_callback`] of the item.
+    pub callback_score: Score,
+}
+
+impl ScoreBreakdown {
+    /// Returns the final score, which is the sum of all the components.
+    pub fn total(&self) -> Score {
+        self.exact_score
+            + self.fuzzy_score
+            + self.bonus_scores.iter().sum::<Score>()
+            + self.callback_score
+    }
+}
+
+/// Result of matching the text of an item, before the bonuses are added.
+struct TextMatch {
+    exact_score: Score,
+    /// Zero if no fuzzy term is matched.
+    fuzzy_score: Score,
+    exact_indices: Vec<usize>,
+    fuzzy_indices: Vec<usize>,
+    /// Whether the bonuses are calculated on the fuzzy match instead of the exact match.
+    fuzzy_matched: bool,
+}
+
+impl TextMatch {
+    /// Returns the base score and indices the bonuses are calculated on.
+    fn bonus_base(&self) -> (Score, &[usize]) {
+        if self.fuzzy_matched {
+            (self.fuzzy_score, &self.fuzzy_indices)
+        } else {
+            (self.exact_score, &self.exact_indices)
         }
     }
+
+    /// Returns all the indices, sorted and deduplicated.
+    fn into_indices(self) -> Vec<usize> {
+        let mut indices = self.exact_indices;
+        indices.extend_from_slice(&self.fuzzy_indices);
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Matcher {
+    sigil_matcher: SigilMatcher,
     inverse_matcher: InverseMatcher,
     exact_matcher: ExactMatcher,
     fuzzy_matcher: FuzzyMatcher,
     bonus_matcher: BonusMatcher,
+    /// Matchers of the alternatives, used instead of the matchers above if not empty.
+    alternatives: Vec<Matcher>,
+    /// Glob pattern used instead of the inverse, exact and fuzzy matchers if specified.
+    glob_pattern: Option<GlobPattern>,
+    /// Path segments used instead of the inverse, exact and fuzzy matchers if specified.
+    path_segments: Option<PathSegments>,
+    /// Expression computing the final score from the built-in score if specified.
+    score_expr: Option<ScoreExpr>,
 }
 
 impl Matcher {
@@ -301,8 +712,125 @@ impl Matcher {
         self.fuzzy_matcher.match_scope
     }
 
+    /// Returns the alternative matching `item` with the highest score.
+    fn best_alternative(&self, item: &Arc<dyn ClapItem>) -> Option<(&Matcher, MatchedItem)> {
+        self.alternatives
+            .iter()
+            .filter_map(|matcher| {
+                matcher
+                    .match_item(item.clone())
+                    .map(|matched_item| (matcher, matched_item))
+            })
+            .max_by_key(|(_, matched_item)| matched_item.score)
+    }
+
+    /// Returns `true` if the query is matched as a glob pattern or path segments.
+    fn is_pattern(&self) -> bool {
+        self.glob_pattern.is_some() || self.path_segments.is_some()
+    }
+
+    /// Returns the result of matching `item` against the glob pattern or path segments.
+    fn find_pattern_matches(&self, item: &Arc<dyn ClapItem>) -> Option<MatchResult> {
+        let fuzzy_text = item.fuzzy_text(self.match_scope())?;
+        let case_matching = self.fuzzy_matcher.case_matching;
+        if let Some(ref glob_pattern) = self.glob_pattern {
+            glob_pattern.find_matches(&fuzzy_text, case_matching)
+        } else {
+            self.path_segments.as_ref()?.find_matches(
+                &fuzzy_text,
+                self.fuzzy_matcher.fuzzy_algo,
+                case_matching,
+            )
+        }
+    }
+
     /// Actually performs the matching algorithm.
+    ///
+    /// The hidden keywords of `item` are tried if its text does not match, a match on the
+    /// keywords has no indices as they are not displayed.
     pub fn match_item(&self, item: Arc<dyn ClapItem>) -> Option<MatchedItem> {
+        self.match_item_explained(item, false)
+            .map(|(matched_item, _)| self.apply_score_expr(matched_item))
+    }
+
+    /// Returns the score components of `item`, the sum of which equals to the score of
+    /// [`Matcher::match_item`] without the score expression.
+    pub fn score_breakdown(&self, item: Arc<dyn ClapItem>) -> Option<ScoreBreakdown> {
+        self.match_item_explained(item, true)
+            .and_then(|(_, score_breakdown)| score_breakdown)
+    }
+
+    /// Matches `item`, the score breakdown is also returned if `explain` is true.
+    fn match_item_explained(
+        &self,
+        item: Arc<dyn ClapItem>,
+        explain: bool,
+    ) -> Option<(MatchedItem, Option<ScoreBreakdown>)> {
+        if !self.alternatives.is_empty() {
+            let (matcher, matched_item) = self.best_alternative(&item)?;
+            if explain {
+                return matcher.match_item_explained(item, true);
+            }
+            return Some((matched_item, None));
+        }
+
+        if !self.sigil_matcher.is_match(item.match_text()) {
+            return None;
+        }
+
+        if item.keywords().is_empty() {
+            self.match_text_of(item, explain)
+        } else {
+            self.match_text_of(item.clone(), explain)
+                .or_else(|| self.match_keywords(item, explain))
+        }
+    }
+
+    /// Replaces the score of `matched_item` with the result of score expression if any.
+    fn apply_score_expr(&self, mut matched_item: MatchedItem) -> MatchedItem {
+        if let Some(ref score_expr) = self.score_expr {
+            let match_text = matched_item.item.match_text();
+            let variables = Variables {
+                base_score: matched_item.score,
+                len: match_text.chars().count(),
+                path_depth: match_text.matches('/').count(),
+                is_recent: self.bonus_matcher.is_recent(&matched_item.item),
+            };
+            matched_item.score = score_expr.eval(&variables);
+        }
+        matched_item
+    }
+
+    /// Returns the best match of the hidden keywords of `item`.
+    fn match_keywords(
+        &self,
+        item: Arc<dyn ClapItem>,
+        explain: bool,
+    ) -> Option<(MatchedItem, Option<ScoreBreakdown>)> {
+        let (keyword_match, score_breakdown) = item
+            .keywords()
+            .iter()
+            .filter_map(|keyword| self.match_text_of(Arc::new(keyword.clone()), explain))
+            .max_by_key(|(matched_item, _)| matched_item.score)?;
+        Some((
+            MatchedItem::new(item, keyword_match.score, Vec::new()),
+            score_breakdown,
+        ))
+    }
+
+    /// Returns the result of matching the text of `item` before the bonuses are added.
+    fn find_text_match(&self, item: &Arc<dyn ClapItem>) -> Option<TextMatch> {
+        if self.is_pattern() {
+            let MatchResult { score, indices } = self.find_pattern_matches(item)?;
+            return Some(TextMatch {
+                exact_score: 0,
+                fuzzy_score: score,
+                exact_indices: Vec::new(),
+                fuzzy_indices: indices,
+                fuzzy_matched: true,
+            });
+        }
+
         let match_text = item.match_text();
 
         if match_text.is_empty() {
@@ -314,36 +842,71 @@ impl Matcher {
             return None;
         }
 
-        let (exact_score, mut indices) = self.exact_matcher.find_matches(match_text)?;
-
-        let (fuzzy_score, mut fuzzy_indices) = self.fuzzy_matcher.find_matches(&item)?;
+        let (exact_score, exact_indices) = self.exact_matcher.find_matches(match_text)?;
 
-        // Merge the results from multi matchers.
-        let match_result = if fuzzy_indices.is_empty() {
-            let bonus_score = self.bonus_matcher.calc_bonus(&item, exact_score, &indices);
+        let (fuzzy_score, mut fuzzy_indices) = self.fuzzy_matcher.find_matches(item)?;
 
-            indices.sort_unstable();
-            indices.dedup();
-
-            MatchResult::new(exact_score + bonus_score, indices)
-        } else {
-            fuzzy_indices.sort_unstable();
-            fuzzy_indices.dedup();
+        fuzzy_indices.sort_unstable();
+        fuzzy_indices.dedup();
 
-            let bonus_score = self
-                .bonus_matcher
-                .calc_bonus(&item, fuzzy_score, &fuzzy_indices);
+        let fuzzy_matched = !fuzzy_indices.is_empty();
 
-            indices.extend_from_slice(fuzzy_indices.as_slice());
-            indices.sort_unstable();
-            indices.dedup();
+        Some(TextMatch {
+            exact_score,
+            fuzzy_score: if fuzzy_matched { fuzzy_score } else { 0 },
+            exact_indices,
+            fuzzy_indices,
+            fuzzy_matched,
+        })
+    }
 
-            MatchResult::new(exact_score + bonus_score + fuzzy_score, indices)
+    /// Matches the text of `item`, regardless of the keywords.
+    ///
+    /// The score breakdown is also returned if `explain` is true, the bonuses are calculated
+    /// one by one in that case.
+    fn match_text_of(
+        &self,
+        item: Arc<dyn ClapItem>,
+        explain: bool,
+    ) -> Option<(MatchedItem, Option<ScoreBreakdown>)> {
+        let text_match = self.find_text_match(&item)?;
+
+        let (bonus_base_score, bonus_base_indices) = text_match.bonus_base();
+        let (bonus_score, bonus_scores) = if explain {
+            let bonus_scores =
+                self.bonus_matcher
+                    .calc_bonus_per_item(&item, bonus_base_score, bonus_base_indices);
+            let bonus_score = bonus_scores.iter().copied().fold(0, Score::saturating_add);
+            (bonus_score, bonus_scores)
+        } else {
+            let bonus_score =
+                self.bonus_matcher
+                    .calc_bonus(&item, bonus_base_score, bonus_base_indices);
+            (bonus_score, Vec::new())
         };
 
-        let MatchResult { score, indices } = item.match_result_callback(match_result);
-
-        Some(MatchedItem::new(item, score, indices))
+        let (exact_score, fuzzy_score) = (text_match.exact_score, text_match.fuzzy_score);
+        // The fuzzy score is `Score::MAX` if the text is identical to the query.
+        let score = exact_score
+            .saturating_add(fuzzy_score)
+            .saturating_add(bonus_score);
+
+        let MatchResult {
+            score: final_score,
+            indices,
+        } = item.match_result_callback(MatchResult::new(score, text_match.into_indices()));
+
+        let score_breakdown = explain.then(|| ScoreBreakdown {
+            exact_score,
+            fuzzy_score,
+            bonus_scores,
+            callback_score: final_score - score,
+        });
+
+        Some((
+            MatchedItem::new(item, final_score, indices),
+            score_breakdown,
+        ))
     }
 }
 
@@ -394,6 +957,282 @@ mod tests {
         assert!(matched_item2.score > matched_item1.score);
     }
 
+    #[test]
+    fn test_fuzziness() {
+        let item = || Arc::new("fn receive(&self)") as Arc<dyn ClapItem>;
+
+        let strict = MatcherBuilder::default().build("recieve".into());
+        assert!(strict.match_item(item()).is_none());
+
+        let exact = MatcherBuilder::default()
+            .build("receive".into())
+            .match_item(item())
+            .unwrap();
+        let tolerant = MatcherBuilder::default()
+            .fuzziness(1)
+            .build("recieve".into())
+            .match_item(item())
+            .unwrap();
+        assert_eq!(tolerant.indices, exact.indices);
+        assert!(tolerant.score < exact.score);
+    }
+
+    #[test]
+    fn test_exact_case_bonus() {
+        let exact_cased = || Arc::new("Foo_bar") as Arc<dyn ClapItem>;
+        let folded = || Arc::new("foo_bar") as Arc<dyn ClapItem>;
+
+        let matcher = MatcherBuilder::default()
+            .case_matching(CaseMatching::Ignore)
+            .build("Foo".into());
+        let exact_cased_score = matcher.match_item(exact_cased()).unwrap().score;
+        assert_eq!(
+            exact_cased_score,
+            matcher.match_item(folded()).unwrap().score
+        );
+
+        let matcher = MatcherBuilder::default()
+            .case_matching(CaseMatching::Ignore)
+            .exact_case_bonus(10)
+            .build("Foo".into());
+        assert_eq!(
+            matcher.match_item(exact_cased()).unwrap().score,
+            exact_cased_score + 10
+        );
+        assert!(
+            matcher.match_item(exact_cased()).unwrap().score
+                > matcher.match_item(folded()).unwrap().score
+        );
+    }
+
+    #[test]
+    fn test_gap_separators() {
+        let deep = || Arc::new("a/b/c/main.rs") as Arc<dyn ClapItem>;
+        let shallow = || Arc::new("main.rs") as Arc<dyn ClapItem>;
+        let fuzzy_score = |matcher: &Matcher, item: Arc<dyn ClapItem>| {
+            matcher.score_breakdown(item).unwrap().fuzzy_score
+        };
+
+        let matcher = MatcherBuilder::default().build("main".into());
+        assert!(fuzzy_score(&matcher, deep()) < fuzzy_score(&matcher, shallow()));
+
+        let matcher = MatcherBuilder::default()
+            .gap_separators(vec!['/'])
+            .build("main".into());
+        assert_eq!(
+            matcher.match_item(deep()).unwrap().indices,
+            vec![6, 7, 8, 9]
+        );
+        assert_eq!(
+            fuzzy_score(&matcher, deep()),
+            fuzzy_score(&matcher, shallow())
+        );
+    }
+
+    #[test]
+    fn test_case_folding() {
+        let match_indices = |case_folding: CaseFolding, query: &str, item: &'static str| {
+            MatcherBuilder::default()
+                .case_matching(CaseMatching::Ignore)
+                .case_folding(case_folding)
+                .build(query.into())
+                .match_item(Arc::new(item))
+                .map(|matched| matched.indices)
+        };
+
+        // German
+        assert!(match_indices(CaseFolding::Simple, "strasse", "Straße").is_none());
+        assert_eq!(
+            match_indices(CaseFolding::Full, "strasse", "Straße"),
+            Some(vec![0, 1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            match_indices(CaseFolding::Full, "STRASSE", "straße.rs"),
+            Some(vec![0, 1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            match_indices(CaseFolding::Full, "groß", "GROSS"),
+            Some(vec![0, 1, 2, 3, 4])
+        );
+
+        // Turkish
+        assert!(match_indices(CaseFolding::Full, "diyarbakır", "DİYARBAKIR").is_none());
+        assert_eq!(
+            match_indices(CaseFolding::Turkic, "diyarbakır", "DİYARBAKIR"),
+            Some((0..10).collect())
+        );
+        assert_eq!(
+            match_indices(CaseFolding::Full, "istanbul", "İSTANBUL"),
+            Some((0..8).collect())
+        );
+    }
+
+    #[test]
+    fn test_accent_insensitive() {
+        let match_indices = |accent_insensitive: bool, query: &str, item: &'static str| {
+            MatcherBuilder::default()
+                .accent_insensitive(accent_insensitive)
+                .build(query.into())
+                .match_item(Arc::new(item))
+                .map(|matched| matched.indices)
+        };
+
+        assert!(match_indices(false, "cafe", "menu/café.txt").is_none());
+        assert_eq!(
+            match_indices(true, "cafe", "menu/café.txt"),
+            Some(vec![5, 6, 7, 8])
+        );
+        assert_eq!(
+            match_indices(true, "café", "menu/cafe.txt"),
+            Some(vec![5, 6, 7, 8])
+        );
+        assert_eq!(
+            match_indices(true, "resume", "Résumé.pdf"),
+            Some((0..6).collect())
+        );
+
+        // The decomposed mark is not highlighted, the indices after it are still on the
+        // original chars.
+        assert_eq!(
+            match_indices(true, "cafetxt", "cafe\u{301}.txt"),
+            Some(vec![0, 1, 2, 3, 6, 7, 8])
+        );
+
+        // Along with the full case folding.
+        let indices = MatcherBuilder::default()
+            .case_matching(CaseMatching::Ignore)
+            .case_folding(CaseFolding::Full)
+            .accent_insensitive(true)
+            .build("strasse".into())
+            .match_item(Arc::new("Straße_Zürich.md"))
+            .map(|matched| matched.indices);
+        assert_eq!(indices, Some(vec![0, 1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_separator_insensitive() {
+        let items = ["get_all_users", "get-user", "get_user", "target_user"]
+            .into_iter()
+            .map(|item| Arc::new(item) as Arc<dyn ClapItem>)
+            .collect::<Vec<_>>();
+        let rank = |matcher: &Matcher| {
+            let mut scored = items
+                .iter()
+                .filter_map(|item| {
+                    matcher
+                        .match_item(item.clone())
+                        .map(|matched| (matched.score, item.raw_text().to_string()))
+                })
+                .collect::<Vec<_>>();
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            scored
+        };
+
+        let matcher = MatcherBuilder::default()
+            .separator_insensitive(true)
+            .build("getuser".into());
+        assert_eq!(
+            matcher.match_item(Arc::new("get_user")).unwrap().indices,
+            vec![0, 1, 2, 4, 5, 6, 7]
+        );
+        let ranked = rank(&matcher);
+        assert_eq!(ranked[0].0, ranked[1].0);
+        assert!(["get_user", "get-user"].contains(&ranked[0].1.as_str()));
+        assert!(["get_user", "get-user"].contains(&ranked[1].1.as_str()));
+
+        let default_score = MatcherBuilder::default()
+            .build("getuser".into())
+            .match_item(Arc::new("get_user"))
+            .unwrap()
+            .score;
+        assert!(ranked[0].0 > default_score);
+    }
+
+    #[test]
+    fn test_build_any() {
+        let item = |line: &str| Arc::new(line.to_string()) as Arc<dyn ClapItem>;
+        let matcher = MatcherBuilder::default().build_any(["error".into(), "warn".into()]);
+
+        assert!(matcher.match_item(item("error: disk is full")).is_some());
+        assert!(matcher
+            .match_item(item("warn: disk is almost full"))
+            .is_some());
+        assert!(matcher.match_item(item("info: disk is fine")).is_none());
+
+        let line = "warn: retrying after the error";
+        let error = MatcherBuilder::default()
+            .build("error".into())
+            .match_item(item(line))
+            .unwrap();
+        let warn = MatcherBuilder::default()
+            .build("warn".into())
+            .match_item(item(line))
+            .unwrap();
+        let best = if error.score > warn.score {
+            error
+        } else {
+            warn
+        };
+
+        let matched_item = matcher.match_item(item(line)).unwrap();
+        assert_eq!(matched_item.score, best.score);
+        assert_eq!(matched_item.indices, best.indices);
+        assert_eq!(
+            matcher.score_breakdown(item(line)).unwrap().total(),
+            best.score
+        );
+    }
+
+    #[test]
+    fn test_build_path_segments() {
+        let item = |line: &str| Arc::new(line.to_string()) as Arc<dyn ClapItem>;
+        let matcher = MatcherBuilder::default().build_path_segments("s/m");
+
+        let matched_item = matcher.match_item(item("src/main.rs")).unwrap();
+        assert_eq!(matched_item.indices, vec![0, 4]);
+        assert_eq!(
+            matcher
+                .score_breakdown(item("src/main.rs"))
+                .unwrap()
+                .total(),
+            matched_item.score
+        );
+        assert!(matcher.match_item(item("smith.rs")).is_none());
+    }
+
+    #[test]
+    fn test_field_separator() {
+        let matcher = MatcherBuilder::default().build("hidden".into());
+        let item: Arc<dyn ClapItem> = Arc::new(SourceItem::from(
+            "visible text\x1fhidden keywords".to_string(),
+        ));
+        let matched_item = matcher.match_item(item).unwrap();
+        assert_eq!(matched_item.display_text(), "visible text");
+        assert!(matched_item.indices.is_empty());
+
+        let matcher = MatcherBuilder::default().build("visible".into());
+        let item: Arc<dyn ClapItem> = Arc::new(SourceItem::from(
+            "visible text\x1fhidden keywords".to_string(),
+        ));
+        assert!(matcher.match_item(item).is_none());
+
+        // Lines without the separator are unaffected.
+        let item: Arc<dyn ClapItem> = Arc::new(SourceItem::from("visible text".to_string()));
+        let matched_item = matcher.match_item(item).unwrap();
+        assert_eq!(matched_item.display_text(), "visible text");
+        assert_eq!(matched_item.indices, vec![0, 1, 2, 3, 4, 5, 6]);
+
+        // The items with the fuzzy text but no separator are still matched on the raw line.
+        let item = SourceItem::new(
+            "visible text".to_string(),
+            Some(("hidden".to_string(), 0)),
+            None,
+        );
+        assert_eq!(item.match_text(), "visible text");
+        let item = SourceItem::from("visible text\x1fhidden keywords".to_string());
+        assert_eq!(item.match_text(), "hidden keywords");
+    }
+
     #[test]
     fn test_match_scope_filename() {
         let query = "lib";
@@ -452,6 +1291,27 @@ mod tests {
         assert!(matched_item1.score < matched_item2.score);
     }
 
+    #[test]
+    fn test_acronym_bonus() {
+        let lines = vec!["bigcatalogsection", "get_cached_action"];
+        let query: Query = "gca".into();
+        let matcher = MatcherBuilder::default()
+            .bonuses(vec![Bonus::Acronym])
+            .build(query);
+        let matched_item1 = matcher
+            .match_item(Arc::new(lines[0]) as Arc<dyn ClapItem>)
+            .unwrap();
+        let matched_item2 = matcher
+            .match_item(Arc::new(lines[1]) as Arc<dyn ClapItem>)
+            .unwrap();
+        assert!(matched_item1.score < matched_item2.score);
+
+        let breakdown = matcher
+            .score_breakdown(Arc::new(lines[1]) as Arc<dyn ClapItem>)
+            .unwrap();
+        assert_eq!(breakdown.bonus_scores, vec![breakdown.fuzzy_score]);
+    }
+
     #[test]
     fn test_exact_search_term_bonus() {
         let lines = vec!["function foo qwer", "function foo"];
@@ -467,6 +1327,161 @@ mod tests {
         assert!(matched_item1.score < matched_item2.score);
     }
 
+    #[derive(Debug)]
+    struct CommandItem {
+        name: &'static str,
+        keywords: Vec<String>,
+    }
+
+    impl ClapItem for CommandItem {
+        fn raw_text(&self) -> &str {
+            self.name
+        }
+
+        fn keywords(&self) -> &[String] {
+            &self.keywords
+        }
+    }
+
+    #[test]
+    fn test_match_keywords() {
+        let item: Arc<dyn ClapItem> = Arc::new(CommandItem {
+            name: "Git commit",
+            keywords: vec!["save".into(), "checkpoint".into()],
+        });
+        let matcher = |query: &str| MatcherBuilder::default().build(query.into());
+
+        // Matched via a keyword, no indices as the keywords are not displayed.
+        let matched_item = matcher("checkpt").match_item(item.clone()).unwrap();
+        assert!(matched_item.indices.is_empty());
+        assert_eq!(matched_item.display_text(), "Git commit");
+
+        // The display text is preferred.
+        let matched_item = matcher("commit").match_item(item.clone()).unwrap();
+        assert_eq!(matched_item.indices, vec![4, 5, 6, 7, 8, 9]);
+
+        assert!(matcher("push").match_item(item).is_none());
+    }
+
+    #[test]
+    fn test_score_breakdown() {
+        let lines = vec![
+            "autoload/clap/filter.vim",
+            "crates/maple_cli/src/command/filter.rs",
+            "lua/fzy_filter.lua",
+        ];
+        let matcher = MatcherBuilder::default()
+            .bonuses(vec![
+                Bonus::FileName,
+                Bonus::RecentFiles(
+                    vec!["crates/maple_cli/src/command/filter.rs".to_string()].into(),
+                ),
+            ])
+            .build("'fil .rs$ ter".into());
+        for line in lines {
+            let item: Arc<dyn ClapItem> = Arc::new(line);
+            match matcher.match_item(item.clone()) {
+                Some(matched_item) => {
+                    let breakdown = matcher.score_breakdown(item).unwrap();
+                    assert_eq!(breakdown.bonus_scores.len(), 2);
+                    assert_eq!(breakdown.total(), matched_item.score);
+                }
+                None => assert!(matcher.score_breakdown(item).is_none()),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct BoostedItem(&'static str);
+
+    impl ClapItem for BoostedItem {
+        fn raw_text(&self) -> &str {
+            self.0
+        }
+
+        fn match_result_callback(&self, match_result: MatchResult) -> MatchResult {
+            MatchResult::new(match_result.score + 7, match_result.indices)
+        }
+    }
+
+    #[test]
+    fn test_score_breakdown_of_keywords_and_callback() {
+        let matcher = |query: &str| {
+            MatcherBuilder::default()
+                .bonuses(vec![Bonus::FileName])
+                .build(query.into())
+        };
+
+        let item: Arc<dyn ClapItem> = Arc::new(BoostedItem("src/filter.rs"));
+        let matcher_of_filter = matcher("filter");
+        let matched_item = matcher_of_filter.match_item(item.clone()).unwrap();
+        let breakdown = matcher_of_filter.score_breakdown(item).unwrap();
+        assert_eq!(breakdown.callback_score, 7);
+        assert_eq!(breakdown.total(), matched_item.score);
+
+        let item: Arc<dyn ClapItem> = Arc::new(CommandItem {
+            name: "Git commit",
+            keywords: vec!["save".into(), "checkpoint".into()],
+        });
+        let matcher_of_keyword = matcher("checkpt");
+        let matched_item = matcher_of_keyword.match_item(item.clone()).unwrap();
+        let breakdown = matcher_of_keyword.score_breakdown(item).unwrap();
+        assert_eq!(breakdown.total(), matched_item.score);
+        assert_eq!(breakdown.bonus_scores.len(), 1);
+    }
+
+    #[test]
+    fn test_score_expr() {
+        let lines = [
+            "crates/maple_cli/src/stdio_server/filter.rs",
+            "crates/filter/src/lib.rs",
+            "filter.vim",
+        ];
+        let ranked = |score_expr: Option<&str>| {
+            let matcher = MatcherBuilder::default()
+                .bonuses(vec![Bonus::RecentFiles(
+                    vec!["crates/maple_cli/src/stdio_server/filter.rs".to_string()].into(),
+                )])
+                .score_expr(score_expr.map(|expr| ScoreExpr::parse(expr).unwrap()))
+                .build("filter".into());
+            let mut matched_items = lines
+                .iter()
+                .filter_map(|line| matcher.match_item(Arc::new(*line)))
+                .collect::<Vec<_>>();
+            matched_items.sort_by_key(|matched_item| std::cmp::Reverse(matched_item.score));
+            matched_items
+                .into_iter()
+                .map(|matched_item| matched_item.item.raw_text().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let builtin = ranked(None);
+        assert_eq!(builtin.len(), lines.len());
+
+        // Prefer the shallow paths.
+        assert_eq!(
+            ranked(Some("base_score - 1000 * path_depth")),
+            vec![
+                "filter.vim",
+                "crates/filter/src/lib.rs",
+                "crates/maple_cli/src/stdio_server/filter.rs"
+            ]
+        );
+
+        // Recent files always go first.
+        assert_eq!(
+            ranked(Some("is_recent ? 10000 : -len")),
+            vec![
+                "crates/maple_cli/src/stdio_server/filter.rs",
+                "filter.vim",
+                "crates/filter/src/lib.rs"
+            ]
+        );
+
+        // The expression is a pure function of the built-in score.
+        assert_eq!(ranked(Some("base_score")), builtin);
+    }
+
     #[test]
     fn test_search_syntax() {
         let items = vec![
@@ -0,0 +1,509 @@
+//! Tiny expression language for customizing the final score of the matched items, e.g.,
+//! `base_score - 10 * path_depth + (is_recent ? 50 : 0)`.
+//!
+//! The expression only has the integer arithmetic over a few variables of the matched item,
+//! any invalid expression is rejected on parsing, the evaluation never fails.
+//!
+//! - Variables: `base_score`, `len`, `path_depth`, `is_recent`.
+//! - Operators: `+ - * / %`, `< <= > >= == !=`, `&& || !`, `cond ? a : b`, parentheses.
+//! - Functions: `min(a, b)`, `max(a, b)`.
+//!
+//! The comparisons and logical operators evaluate to 1 or 0, a division by zero evaluates to 0.
+
+use std::fmt;
+
+use crate::Score;
+
+/// Maximum length of an expression.
+const MAX_LEN: usize = 1024;
+
+/// Maximum nesting depth of an expression.
+const MAX_DEPTH: usize = 32;
+
+/// Variables of a matched item referenced by the expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    /// Score computed by the built-in scoring, including the bonuses.
+    BaseScore,
+    /// Number of chars of the match text.
+    Len,
+    /// Number of `/` in the match text.
+    PathDepth,
+    /// 1 if the item is in the recent files, 0 otherwise.
+    IsRecent,
+}
+
+impl Variable {
+    fn from_ident(ident: &str) -> Option<Self> {
+        match ident {
+            "base_score" => Some(Self::BaseScore),
+            "len" => Some(Self::Len),
+            "path_depth" => Some(Self::PathDepth),
+            "is_recent" => Some(Self::IsRecent),
+            _ => None,
+        }
+    }
+}
+
+/// Values of the variables of a matched item.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Variables {
+    pub base_score: Score,
+    pub len: usize,
+    pub path_depth: usize,
+    pub is_recent: bool,
+}
+
+impl Variables {
+    fn get(&self, variable: Variable) -> i64 {
+        match variable {
+            Variable::BaseScore => self.base_score as i64,
+            Variable::Len => self.len as i64,
+            Variable::PathDepth => self.path_depth as i64,
+            Variable::IsRecent => self.is_recent as i64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+    Min,
+    Max,
+}
+
+impl BinaryOp {
+    fn apply(self, lhs: i64, rhs: i64) -> i64 {
+        match self {
+            Self::Add => lhs.saturating_add(rhs),
+            Self::Sub => lhs.saturating_sub(rhs),
+            Self::Mul => lhs.saturating_mul(rhs),
+            Self::Div => lhs.checked_div(rhs).unwrap_or(0),
+            Self::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+            Self::Lt => (lhs < rhs) as i64,
+            Self::Le => (lhs <= rhs) as i64,
+            Self::Gt => (lhs > rhs) as i64,
+            Self::Ge => (lhs >= rhs) as i64,
+            Self::Eq => (lhs == rhs) as i64,
+            Self::Ne => (lhs != rhs) as i64,
+            Self::And => (lhs != 0 && rhs != 0) as i64,
+            Self::Or => (lhs != 0 || rhs != 0) as i64,
+            Self::Min => lhs.min(rhs),
+            Self::Max => lhs.max(rhs),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(i64),
+    Variable(Variable),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, variables: &Variables) -> i64 {
+        match self {
+            Self::Number(n) => *n,
+            Self::Variable(variable) => variables.get(*variable),
+            Self::Neg(expr) => expr.eval(variables).saturating_neg(),
+            Self::Not(expr) => (expr.eval(variables) == 0) as i64,
+            Self::Binary(op, lhs, rhs) => op.apply(lhs.eval(variables), rhs.eval(variables)),
+            Self::Conditional(cond, then, otherwise) => {
+                if cond.eval(variables) != 0 {
+                    then.eval(variables)
+                } else {
+                    otherwise.eval(variables)
+                }
+            }
+        }
+    }
+}
+
+/// Error of an invalid score expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreExprError {
+    /// Byte offset in the expression where the error occurs.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScoreExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid score expression at {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl std::error::Error for ScoreExprError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    /// Operators and punctuations.
+    Symbol(&'static str),
+}
+
+/// Symbols sorted by length so that the longest one is taken first.
+const SYMBOLS: [&str; 19] = [
+    "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "?", ":", "(", ")",
+    ",",
+];
+
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, ScoreExprError> {
+    let error = |position: usize, message: String| ScoreExprError { position, message };
+
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut end = pos;
+            while let Some(&(i, c)) = chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = s[pos..end]
+                .parse()
+                .map_err(|_| error(pos, format!("number `{}` is too large", &s[pos..end])))?;
+            tokens.push((pos, Token::Number(number)));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = pos;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((pos, Token::Ident(s[pos..end].to_string())));
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| s[pos..].starts_with(*symbol))
+                .ok_or_else(|| {
+                    error(pos, format!("unexpected `{}`", &s[pos..pos + c.len_utf8()]))
+                })?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push((pos, Token::Symbol(symbol)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, from the lowest precedence to the highest:
+///
+/// `?:`, `||`, `&&`, comparisons, `+ -`, `* / %`, unary `- !`, primary.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    cursor: usize,
+    depth: usize,
+    /// Length of the expression, the position of the errors at the end.
+    len: usize,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> ScoreExprError {
+        let position = self
+            .tokens
+            .get(self.cursor)
+            .map(|(pos, _)| *pos)
+            .unwrap_or(self.len);
+        ScoreExprError {
+            position,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.cursor).map(|(_, token)| token)
+    }
+
+    /// Consumes the next token if it's any of `symbols`.
+    fn eat(&mut self, symbols: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(symbol)) if symbols.contains(symbol) => {
+                let symbol = *symbol;
+                self.cursor += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), ScoreExprError> {
+        self.eat(&[symbol])
+            .map(|_| ())
+            .ok_or_else(|| self.error(format!("expected `{symbol}`")))
+    }
+
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, ScoreExprError>,
+    ) -> Result<T, ScoreExprError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("expression is nested too deeply"));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn conditional(&mut self) -> Result<Expr, ScoreExprError> {
+        self.nested(|parser| {
+            let cond = parser.binary(0)?;
+            if parser.eat(&["?"]).is_none() {
+                return Ok(cond);
+            }
+            let then = parser.conditional()?;
+            parser.expect(":")?;
+            let otherwise = parser.conditional()?;
+            Ok(Expr::Conditional(
+                Box::new(cond),
+                Box::new(then),
+                Box::new(otherwise),
+            ))
+        })
+    }
+
+    /// Parses the binary operators of `level` and higher.
+    fn binary(&mut self, level: usize) -> Result<Expr, ScoreExprError> {
+        const LEVELS: [&[(&str, BinaryOp)]; 5] = [
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+        ];
+
+        let ops = match LEVELS.get(level) {
+            Some(ops) => *ops,
+            None => return self.unary(),
+        };
+        let symbols = ops.iter().map(|(symbol, _)| *symbol).collect::<Vec<_>>();
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(symbol) = self.eat(&symbols) {
+            let op = ops
+                .iter()
+                .find(|(s, _)| *s == symbol)
+                .map(|(_, op)| *op)
+                .expect("Symbol is one of the ops; qed");
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScoreExprError> {
+        match self.eat(&["-", "!"]) {
+            Some(symbol) => self.nested(|parser| {
+                let expr = Box::new(parser.unary()?);
+                Ok(if symbol == "-" {
+                    Expr::Neg(expr)
+                } else {
+                    Expr::Not(expr)
+                })
+            }),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScoreExprError> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.error("unexpected end of expression")),
+        };
+
+        match token {
+            Token::Number(n) => {
+                self.cursor += 1;
+                Ok(Expr::Number(n))
+            }
+            Token::Ident(ident) => {
+                if let Some(variable) = Variable::from_ident(&ident) {
+                    self.cursor += 1;
+                    return Ok(Expr::Variable(variable));
+                }
+                let op = match ident.as_str() {
+                    "min" => BinaryOp::Min,
+                    "max" => BinaryOp::Max,
+                    _ => return Err(self.error(format!("unknown identifier `{ident}`"))),
+                };
+                self.cursor += 1;
+                self.expect("(")?;
+                let lhs = self.conditional()?;
+                self.expect(",")?;
+                let rhs = self.conditional()?;
+                self.expect(")")?;
+                Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+            }
+            Token::Symbol("(") => {
+                self.cursor += 1;
+                let expr = self.conditional()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Symbol(symbol) => Err(self.error(format!("unexpected `{symbol}`"))),
+        }
+    }
+}
+
+/// Expression computing the final score of a matched item from its [`Variables`].
+#[derive(Debug, Clone)]
+pub struct ScoreExpr {
+    source: String,
+    expr: Expr,
+}
+
+// The expression is parsed from the source.
+impl std::hash::Hash for ScoreExpr {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+    }
+}
+
+impl ScoreExpr {
+    pub fn parse(s: &str) -> Result<Self, ScoreExprError> {
+        if s.len() > MAX_LEN {
+            return Err(ScoreExprError {
+                position: MAX_LEN,
+                message: format!("expression is longer than {MAX_LEN} bytes"),
+            });
+        }
+
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            cursor: 0,
+            depth: 0,
+            len: s.len(),
+        };
+        let expr = parser.conditional()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected trailing token"));
+        }
+
+        Ok(Self {
+            source: s.to_string(),
+            expr,
+        })
+    }
+
+    /// Returns the original text of expression.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the final score, saturated to the range of [`Score`].
+    pub fn eval(&self, variables: &Variables) -> Score {
+        self.expr
+            .eval(variables)
+            .clamp(Score::MIN as i64, Score::MAX as i64) as Score
+    }
+}
+
+impl std::str::FromStr for ScoreExpr {
+    type Err = ScoreExprError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str, variables: Variables) -> Score {
+        ScoreExpr::parse(expr).unwrap().eval(&variables)
+    }
+
+    #[test]
+    fn test_eval_score_expr() {
+        let variables = Variables {
+            base_score: 100,
+            len: 12,
+            path_depth: 2,
+            is_recent: true,
+        };
+
+        assert_eq!(eval("base_score", variables), 100);
+        assert_eq!(eval("base_score - 10 * path_depth", variables), 80);
+        assert_eq!(eval("(base_score - len) / 2", variables), 44);
+        assert_eq!(
+            eval("is_recent ? base_score * 2 : base_score", variables),
+            200
+        );
+        assert_eq!(eval("!is_recent || len > 20 ? 1 : -1", variables), -1);
+        assert_eq!(eval("min(len, 10) + max(path_depth, 5)", variables), 15);
+        assert_eq!(eval("1 ? 2 : 3 ? 4 : 5", variables), 2);
+        assert_eq!(eval("-base_score % 7", variables), -2);
+
+        // No panic on the arithmetic errors.
+        assert_eq!(eval("base_score / 0", variables), 0);
+        assert_eq!(eval("base_score % (len - 12)", variables), 0);
+        assert_eq!(
+            eval("99999999999 * 99999999999 * 99999999999", variables),
+            Score::MAX
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_score_expr() {
+        let error = |expr: &str| ScoreExpr::parse(expr).unwrap_err();
+
+        assert_eq!(error("base_score +").position, 12);
+        assert_eq!(
+            error("base_score + foo").message,
+            "unknown identifier `foo`"
+        );
+        assert_eq!(error("len = 1").position, 4);
+        assert_eq!(error("std::process::exit(1)").position, 0);
+        assert_eq!(error("(len").message, "expected `)`");
+        assert_eq!(error("len len").message, "unexpected trailing token");
+        assert_eq!(error("min(len)").message, "expected `,`");
+        assert!(ScoreExpr::parse("").is_err());
+        assert!(ScoreExpr::parse("99999999999999999999").is_err());
+        assert!(ScoreExpr::parse(&"(".repeat(100)).is_err());
+        assert!(ScoreExpr::parse(&"-".repeat(100)).is_err());
+        assert!(ScoreExpr::parse(&"1+".repeat(1000)).is_err());
+    }
+}
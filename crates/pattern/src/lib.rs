@@ -25,6 +25,22 @@ static COMMIT_RE: Lazy<Regex> =
 
 static GTAGS: Lazy<Regex> = Lazy::new(|| Regex::new(r"(.*)\s+(\d+)\s+(.*)").unwrap());
 
+/// Compiles all the regex patterns ahead of the first use.
+pub fn precompile() {
+    for regex in [
+        &GREP_POS,
+        &DUMB_JUMP_LINE,
+        &GREP_STRIP_FPATH,
+        &TAG_RE,
+        &BUFFER_TAGS,
+        &PROJ_TAGS,
+        &COMMIT_RE,
+        &GTAGS,
+    ] {
+        Lazy::force(regex);
+    }
+}
+
 pub fn parse_gtags(line: &str) -> Option<(usize, &str, &str)> {
     let cap = GTAGS.captures(line)?;
     let lnum = cap.get(2).map(|x| x.as_str()).and_then(parse_lnum)?;
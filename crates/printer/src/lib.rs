@@ -5,12 +5,14 @@ mod trimmer;
 mod truncation;
 
 use icon::{Icon, ICON_LEN};
-use types::MatchedItem;
+use types::{MatchedItem, Score};
+use unicode_width::UnicodeWidthChar;
 use utility::{println_json, println_json_with_length};
 
 pub use self::truncation::{
-    truncate_grep_lines, truncate_long_matched_lines, truncate_long_matched_lines_v0,
-    LinesTruncatedMap,
+    normalize_winwidth, truncate_grep_lines, truncate_long_matched_lines,
+    truncate_long_matched_lines_v0, truncate_long_matched_lines_with_mode, LinesTruncatedMap,
+    TruncationMode, DEFAULT_WINWIDTH, MAX_WINWIDTH, MIN_WINWIDTH,
 };
 
 /// 1. Truncate the line.
@@ -21,71 +23,98 @@ pub struct DisplayLines {
     pub lines: Vec<String>,
     /// Position of highlights in the lines above.
     pub indices: Vec<Vec<usize>>,
+    /// Stable ids of the lines above, used for tracking the selections across the queries.
+    pub ids: Vec<u64>,
     /// A map of the line number to the original untruncated line.
     pub truncated_map: LinesTruncatedMap,
     /// An icon is added to the head of line.
     ///
     /// The icon is added after the truncating processing.
     pub icon_added: bool,
+    /// Score of each line, only sent if requested, e.g., `--show-score`.
+    ///
+    /// The scores are sent as a separate field instead of being added to the lines, so that the
+    /// matching is not affected.
+    pub scores: Option<Vec<Score>>,
 }
 
 impl DisplayLines {
     pub fn new(
         lines: Vec<String>,
         indices: Vec<Vec<usize>>,
+        ids: Vec<u64>,
         truncated_map: LinesTruncatedMap,
         icon_added: bool,
     ) -> Self {
         Self {
             lines,
             indices,
+            ids,
             truncated_map,
             icon_added,
+            scores: None,
         }
     }
 
-    pub fn print_on_session_create(&self) {
+    /// Returns the message of initial lines sent on creating the session.
+    pub fn session_create_message(&self) -> serde_json::Value {
         let Self {
             lines,
+            ids,
             truncated_map,
             icon_added,
             ..
         } = self;
-        #[allow(non_upper_case_globals)]
-        const method: &str = "s:init_display";
-        println_json_with_length!(method, lines, icon_added, truncated_map);
+        serde_json::json!({
+            "method": "s:init_display",
+            "lines": lines,
+            "ids": ids,
+            "icon_added": icon_added,
+            "truncated_map": truncated_map,
+        })
     }
 
-    pub fn print_on_typed(&self, total: usize) {
+    /// Returns the message of filtered results sent on typed.
+    pub fn on_typed_message(&self, total: usize) -> serde_json::Value {
         let Self {
             lines,
             indices,
+            ids,
             truncated_map,
             icon_added,
+            ..
         } = self;
-
-        #[allow(non_upper_case_globals)]
-        const method: &str = "s:process_filter_message";
-        println_json_with_length!(total, lines, indices, truncated_map, icon_added, method);
+        serde_json::json!({
+            "total": total,
+            "lines": lines,
+            "indices": indices,
+            "ids": ids,
+            "truncated_map": truncated_map,
+            "icon_added": icon_added,
+            "method": "s:process_filter_message",
+        })
     }
 
     pub fn print_on_dyn_run(&self, matched: usize, processed: usize) {
         let Self {
             lines,
             indices,
+            ids,
             truncated_map,
             icon_added,
+            ..
         } = self;
 
         #[allow(non_upper_case_globals)]
         const method: &str = "s:process_filter_message";
         if truncated_map.is_empty() {
-            println_json_with_length!(method, lines, indices, icon_added, matched, processed);
+            println_json_with_length!(method, lines, indices, ids, icon_added, matched, processed);
         } else {
             println_json_with_length!(
                 method,
                 lines,
                 indices,
+                ids,
                 icon_added,
                 matched,
                 processed,
@@ -102,31 +131,55 @@ impl DisplayLines {
         let Self {
             lines,
             indices,
+            ids,
             truncated_map,
             icon_added,
+            scores,
         } = self;
 
         #[allow(non_upper_case_globals)]
         const method: &str = "s:process_filter_message";
-        if let Some(total_processed) = maybe_total_processed {
-            println_json_with_length!(
+        match (maybe_total_processed, scores) {
+            (Some(total_processed), Some(scores)) => println_json_with_length!(
                 method,
                 lines,
                 indices,
+                ids,
+                icon_added,
+                truncated_map,
+                total_matched,
+                total_processed,
+                scores
+            ),
+            (Some(total_processed), None) => println_json_with_length!(
+                method,
+                lines,
+                indices,
+                ids,
                 icon_added,
                 truncated_map,
                 total_matched,
                 total_processed
-            );
-        } else {
-            println_json_with_length!(
+            ),
+            (None, Some(scores)) => println_json_with_length!(
                 method,
                 lines,
                 indices,
+                ids,
+                icon_added,
+                truncated_map,
+                total_matched,
+                scores
+            ),
+            (None, None) => println_json_with_length!(
+                method,
+                lines,
+                indices,
+                ids,
                 icon_added,
                 truncated_map,
                 total_matched
-            );
+            ),
         }
     }
 
@@ -134,23 +187,162 @@ impl DisplayLines {
         let Self {
             lines,
             indices,
+            ids,
             truncated_map,
             icon_added,
+            scores,
         } = self;
 
-        println_json!(lines, indices, truncated_map, icon_added, total);
+        match scores {
+            Some(scores) => {
+                println_json!(
+                    lines,
+                    indices,
+                    ids,
+                    truncated_map,
+                    icon_added,
+                    total,
+                    scores
+                )
+            }
+            None => println_json!(lines, indices, ids, truncated_map, icon_added, total),
+        }
     }
 }
 
+/// Returns the icon followed by the spaces to occupy `column_width` cells in total.
+///
+/// At least one space is placed after the icon, in case of the icon is wider than the column.
+pub fn pad_icon(icon: char, column_width: usize) -> String {
+    let icon_width = UnicodeWidthChar::width(icon).unwrap_or(1);
+    let padding = column_width.saturating_sub(icon_width).max(1);
+    format!("{icon}{}", " ".repeat(padding))
+}
+
+/// Returns the ranges of display columns occupied by the chars at `indices` of `text`, the
+/// adjacent chars are merged into one range.
+///
+/// Unlike `indices`, the columns take the tabs expanded to the next multiple of `tabstop`
+/// into account, which is what the editor actually renders.
+pub fn display_column_ranges(text: &str, indices: &[usize], tabstop: usize) -> Vec<(usize, usize)> {
+    let acc_width = trimmer::v1::accumulate_text_width(text, tabstop.max(1));
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &idx in indices {
+        let end = match acc_width.get(idx) {
+            Some(&end) => end,
+            None => continue,
+        };
+        let start = if idx == 0 { 0 } else { acc_width[idx - 1] };
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+}
+
 /// Returns the info of the truncated top items ranked by the filtering score.
 pub fn decorate_lines(
     matched_items: Vec<MatchedItem>,
     winwidth: usize,
     icon: Icon,
+) -> DisplayLines {
+    decorate_lines_with_icon_column(matched_items, winwidth, icon, None)
+}
+
+/// Same with [`decorate_lines`], but the score of each line is attached if `show_score` is true.
+pub fn decorate_lines_with_score(
+    matched_items: Vec<MatchedItem>,
+    winwidth: usize,
+    icon: Icon,
+    show_score: bool,
+) -> DisplayLines {
+    let scores = if show_score {
+        Some(
+            matched_items
+                .iter()
+                .map(|matched_item| matched_item.score)
+                .collect(),
+        )
+    } else {
+        None
+    };
+    DisplayLines {
+        scores,
+        ..decorate_lines(matched_items, winwidth, icon)
+    }
+}
+
+/// Same with [`decorate_lines`], but the long lines are truncated in `truncation_mode`.
+pub fn decorate_lines_with_truncation(
+    matched_items: Vec<MatchedItem>,
+    winwidth: usize,
+    icon: Icon,
+    truncation_mode: TruncationMode,
+) -> DisplayLines {
+    decorate(matched_items, winwidth, icon, None, truncation_mode)
+}
+
+/// Same with [`decorate_lines`], but the icon is padded to `icon_column_width` cells if specified
+/// so that the text is aligned regardless of the display width of icon.
+pub fn decorate_lines_with_icon_column(
+    matched_items: Vec<MatchedItem>,
+    winwidth: usize,
+    icon: Icon,
+    icon_column_width: Option<usize>,
+) -> DisplayLines {
+    decorate(
+        matched_items,
+        winwidth,
+        icon,
+        icon_column_width,
+        TruncationMode::Auto,
+    )
+}
+
+fn decorate(
+    matched_items: Vec<MatchedItem>,
+    winwidth: usize,
+    icon: Icon,
+    icon_column_width: Option<usize>,
+    truncation_mode: TruncationMode,
 ) -> DisplayLines {
     let mut matched_items = matched_items;
-    let mut truncated_map = truncate_long_matched_lines(matched_items.iter_mut(), winwidth, None);
-    if let Some(icon_kind) = icon.icon_kind() {
+    let mut truncated_map = truncate_long_matched_lines_with_mode(
+        matched_items.iter_mut(),
+        winwidth,
+        None,
+        truncation_mode,
+    );
+    let ids = matched_items.iter().map(MatchedItem::id).collect();
+    if let (Some(icon_kind), Some(column_width)) = (icon.icon_kind(), icon_column_width) {
+        let (lines, indices): (Vec<_>, Vec<Vec<usize>>) = matched_items
+            .into_iter()
+            .enumerate()
+            .map(|(idx, matched_item)| {
+                let display_text = matched_item.display_text();
+                let icon_prefix = if let Some(output_text) = truncated_map.get_mut(&(idx + 1)) {
+                    let icon = matched_item
+                        .item
+                        .icon(icon)
+                        .expect("Icon must be provided if specified");
+                    let icon_prefix = pad_icon(icon, column_width);
+                    *output_text = format!("{icon_prefix}{output_text}");
+                    icon_prefix
+                } else {
+                    pad_icon(icon_kind.icon(&display_text), column_width)
+                };
+                (
+                    format!("{icon_prefix}{display_text}"),
+                    matched_item.shifted_indices(icon_prefix.len()),
+                )
+            })
+            .unzip();
+
+        DisplayLines::new(lines, indices, ids, truncated_map, true)
+    } else if let Some(icon_kind) = icon.icon_kind() {
         let (lines, indices): (Vec<_>, Vec<Vec<usize>>) = matched_items
             .into_iter()
             .enumerate()
@@ -170,7 +362,7 @@ pub fn decorate_lines(
             })
             .unzip();
 
-        DisplayLines::new(lines, indices, truncated_map, true)
+        DisplayLines::new(lines, indices, ids, truncated_map, true)
     } else {
         let (lines, indices): (Vec<_>, Vec<_>) = matched_items
             .into_iter()
@@ -182,40 +374,53 @@ pub fn decorate_lines(
             })
             .unzip();
 
-        DisplayLines::new(lines, indices, truncated_map, false)
+        DisplayLines::new(lines, indices, ids, truncated_map, false)
     }
 }
 
 /// Prints the results of filter::sync_run() to stdout.
+///
+/// The score of each result is printed too if `show_score` is true.
 pub fn print_sync_filter_results(
     matched_items: Vec<MatchedItem>,
     number: Option<usize>,
     winwidth: usize,
     icon: Icon,
+    show_score: bool,
 ) {
     if let Some(number) = number {
         let total_matched = matched_items.len();
         let mut matched_items = matched_items;
         matched_items.truncate(number);
-        decorate_lines(matched_items, winwidth, icon).print_json(total_matched);
+        decorate_lines_with_score(matched_items, winwidth, icon, show_score)
+            .print_json(total_matched);
     } else {
         matched_items.iter().for_each(|matched_item| {
             let indices = &matched_item.indices;
             let text = matched_item.display_text();
-            println_json!(text, indices);
+            let id = matched_item.id();
+            if show_score {
+                let score = matched_item.score;
+                println_json!(text, indices, id, score);
+            } else {
+                println_json!(text, indices, id);
+            }
         });
     }
 }
 
 /// Prints the results of filter::dyn_run() to stdout.
+///
+/// The score of each result is printed too if `show_score` is true.
 pub fn print_dyn_matched_items(
     matched_items: Vec<MatchedItem>,
     total_matched: usize,
     total_processed: Option<usize>,
     winwidth: usize,
     icon: Icon,
+    show_score: bool,
 ) {
-    decorate_lines(matched_items, winwidth, icon)
+    decorate_lines_with_score(matched_items, winwidth, icon, show_score)
         .print_on_dyn_run_finished(total_matched, total_processed);
 }
 
@@ -229,6 +434,7 @@ pub(crate) mod tests {
     use rayon::prelude::*;
     use std::sync::Arc;
     use types::{ClapItem, Query};
+    use unicode_width::UnicodeWidthStr;
 
     pub(crate) fn wrap_matches(line: &str, indices: &[usize]) -> String {
         let mut ret = String::new();
@@ -336,6 +542,26 @@ pub(crate) mod tests {
         };
     }
 
+    #[test]
+    fn test_extreme_winwidth() {
+        let line = "crates/maple_cli/src/stdio_server/impls/providers/dumb_jump/searcher.rs";
+        for (winwidth, truncated) in [(0, true), (u16::MAX as usize, false)] {
+            let display_lines = decorate_lines(
+                filter_single_line(line.to_string(), "searcher"),
+                winwidth,
+                Icon::Null,
+            );
+            let display_text = &display_lines.lines[0];
+            assert!(display_text.width() <= normalize_winwidth(Some(winwidth)));
+            assert!(display_text.contains("searcher"));
+            assert_eq!(!display_lines.truncated_map.is_empty(), truncated);
+        }
+
+        assert_eq!(normalize_winwidth(None), DEFAULT_WINWIDTH);
+        assert_eq!(normalize_winwidth(Some(0)), MIN_WINWIDTH);
+        assert_eq!(normalize_winwidth(Some(u16::MAX as usize)), MAX_WINWIDTH);
+    }
+
     #[test]
     fn test_grep_line() {
         test_printer!(
@@ -373,4 +599,179 @@ pub(crate) mod tests {
             (QUERY, "srlis", None, 50)
         );
     }
+
+    #[test]
+    fn test_icon_column() {
+        // Single-width and double-width icons.
+        for icon in ['\u{e7a8}', '\u{1f4c1}'] {
+            let icon_prefix = pad_icon(icon, 3);
+            assert_eq!(icon_prefix.width(), 3);
+            assert!(icon_prefix.starts_with(icon));
+        }
+        assert_eq!(pad_icon('\u{1f4c1}', 1), "\u{1f4c1} ");
+
+        let matched_items = ["src/lib.rs", "Cargo.toml"]
+            .into_iter()
+            .flat_map(|line| filter_single_line(line.to_string(), "l"))
+            .collect::<Vec<_>>();
+
+        let icon = Icon::Enabled(icon::IconKind::File);
+        let display_lines =
+            decorate_lines_with_icon_column(matched_items.clone(), 100, icon, Some(3));
+        for ((line, indices), matched_item) in display_lines
+            .lines
+            .iter()
+            .zip(display_lines.indices.iter())
+            .zip(matched_items.iter())
+        {
+            let text = matched_item.display_text();
+            let icon_prefix = line.strip_suffix(text.as_ref()).unwrap();
+            // The text starts at the same column.
+            assert_eq!(icon_prefix.width(), 3);
+            // The highlights still point to the matched chars.
+            assert_eq!(
+                indices,
+                &matched_item
+                    .indices
+                    .iter()
+                    .map(|idx| idx + icon_prefix.len())
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        // The default decoration is unchanged.
+        let display_lines = decorate_lines(matched_items.clone(), 100, icon);
+        assert_eq!(
+            display_lines.indices[0][0],
+            matched_items[0].indices[0] + ICON_LEN
+        );
+    }
+
+    #[test]
+    fn test_display_column_ranges() {
+        let matched_item = filter_single_line(String::from("\t\tfoo"), "foo").remove(0);
+        assert_eq!(matched_item.indices, vec![2, 3, 4]);
+        assert_eq!(
+            display_column_ranges("\t\tfoo", &matched_item.indices, 4),
+            vec![(8, 11)]
+        );
+        assert_eq!(
+            display_column_ranges("\t\tfoo", &matched_item.indices, 8),
+            vec![(16, 19)]
+        );
+        // A tab following some text only expands to the next tabstop.
+        assert_eq!(
+            display_column_ranges("ab\tc", &[0, 3], 4),
+            vec![(0, 1), (4, 5)]
+        );
+    }
+
+    #[test]
+    fn test_middle_truncation() {
+        let line = "crates/maple_cli/src/stdio_server/impls/providers/dumb_jump/searcher.rs";
+        let winwidth = 40;
+
+        let display_lines = decorate_lines_with_truncation(
+            filter_single_line(line.to_string(), "searcher"),
+            winwidth,
+            Icon::Null,
+            TruncationMode::Middle,
+        );
+        let display_text = &display_lines.lines[0];
+        assert_eq!(display_text, "crates/../dumb_jump/searcher.rs");
+        assert!(display_text.width() <= winwidth);
+        assert_eq!(display_lines.truncated_map.get(&1).unwrap(), line);
+        let highlighted = display_lines.indices[0]
+            .iter()
+            .filter_map(|i| display_text.chars().nth(*i))
+            .collect::<String>();
+        assert_eq!(highlighted, "searcher");
+
+        // The lines are elided even without any matches.
+        let display_lines = decorate_lines_with_truncation(
+            vec![MatchedItem::new(
+                Arc::new(SourceItem::from(line.to_string())),
+                Default::default(),
+                Default::default(),
+            )],
+            winwidth,
+            Icon::Null,
+            TruncationMode::Middle,
+        );
+        assert_eq!(display_lines.lines[0], "crates/../dumb_jump/searcher.rs");
+
+        // The short lines are untouched.
+        let display_lines = decorate_lines_with_truncation(
+            filter_single_line(String::from("src/lib.rs"), "lib"),
+            winwidth,
+            Icon::Null,
+            TruncationMode::Middle,
+        );
+        assert_eq!(display_lines.lines[0], "src/lib.rs");
+        assert!(display_lines.truncated_map.is_empty());
+    }
+
+    #[test]
+    fn test_show_score() {
+        let matched_items = filter_single_line(String::from("crates/printer/src/lib.rs"), "print")
+            .into_iter()
+            .chain(filter_single_line(String::from("src/main.rs"), "main"))
+            .collect::<Vec<_>>();
+        let scores = matched_items
+            .iter()
+            .map(|matched_item| matched_item.score)
+            .collect::<Vec<_>>();
+
+        let display_lines = decorate_lines_with_score(matched_items.clone(), 62, Icon::Null, true);
+        assert_eq!(display_lines.scores, Some(scores));
+
+        // The lines and highlights are identical to the ones without the scores.
+        let without_scores = decorate_lines_with_score(matched_items, 62, Icon::Null, false);
+        assert!(without_scores.scores.is_none());
+        assert_eq!(display_lines.lines, without_scores.lines);
+        assert_eq!(display_lines.indices, without_scores.indices);
+
+        // The scores are not part of the text to match on the subsequent filtering.
+        let refiltered = display_lines
+            .lines
+            .iter()
+            .flat_map(|line| filter_single_line(line.clone(), "print"))
+            .map(|matched_item| matched_item.score)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            refiltered,
+            filter_single_line(String::from("crates/printer/src/lib.rs"), "print")
+                .into_iter()
+                .map(|matched_item| matched_item.score)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_stable_item_id() {
+        let line = "crates/printer/src/lib.rs";
+        let another_line = "crates/printer/src/truncation.rs";
+
+        let ids_of = |query: &str| {
+            let matched_items = filter_single_line(line.to_string(), query)
+                .into_iter()
+                .chain(filter_single_line(another_line.to_string(), query))
+                .collect::<Vec<_>>();
+            decorate_lines(matched_items, 20, Icon::Null).ids
+        };
+
+        let ids1 = ids_of("src");
+        let ids2 = ids_of("printer");
+        assert_eq!(ids1, ids2);
+        assert_ne!(ids1[0], ids1[1]);
+
+        // Exact as a JSON number in any client.
+        assert!(ids1.iter().all(|id| *id < 1 << 53));
+        let ids_json = serde_json::to_string(&ids1).unwrap();
+        let decoded: Vec<f64> = serde_json::from_str(&ids_json).unwrap();
+        assert_eq!(
+            decoded.into_iter().map(|id| id as u64).collect::<Vec<_>>(),
+            ids1
+        );
+    }
 }
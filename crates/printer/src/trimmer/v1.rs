@@ -14,7 +14,7 @@ fn display_width(text: &str, tabstop: usize) -> usize {
 }
 
 /// Return an array in which arr[i] stores the display width till char[i] for `text`.
-fn accumulate_text_width(text: &str, tabstop: usize) -> Vec<usize> {
+pub(crate) fn accumulate_text_width(text: &str, tabstop: usize) -> Vec<usize> {
     let mut ret = Vec::with_capacity(text.chars().count());
     let mut w = 0;
     for ch in text.chars() {
@@ -156,6 +156,92 @@ pub fn trim_text(
     }
 }
 
+/// Elides the middle of a path to make it fit into the container.
+///
+/// The leading directory and the trailing basename are kept, the tail is extended to the
+/// directories right before the basename as much as possible:
+///
+/// ```text
+/// src/deeply/nested/dirs/of/main.rs -> src/../of/main.rs
+/// ```
+///
+/// If the basename can't fit along with the leading directory, the leading directory is dropped
+/// to keep as much of the basename as possible. The indices in the elided part are dropped.
+pub fn trim_middle(
+    text: &str,
+    indices: &[usize],
+    container_width: usize,
+    tabstop: usize,
+) -> Option<(String, Vec<usize>)> {
+    const ELLIPSIS: &str = "..";
+
+    let acc_width = accumulate_text_width(text, tabstop);
+
+    // Width for diplaying the whole text.
+    let full_width = *acc_width.last()?;
+
+    if full_width <= container_width {
+        return None;
+    }
+
+    let chars = text.chars().collect::<Vec<_>>();
+    let budget = container_width.saturating_sub(ELLIPSIS.len());
+
+    let width_before = |idx: usize| if idx == 0 { 0 } else { acc_width[idx - 1] };
+    // Start of the longest suffix of `text` fitting into `width`.
+    let suffix_start = |width: usize| {
+        (0..chars.len())
+            .find(|&idx| full_width - width_before(idx) <= width)
+            .unwrap_or(chars.len())
+    };
+
+    let basename_start = chars
+        .iter()
+        .rposition(|&c| c == '/')
+        .map_or(0, |idx| idx + 1);
+    // The leading directory including the separator, e.g., `src/` or `/home/`.
+    let head_len = chars
+        .iter()
+        .skip(1)
+        .position(|&c| c == '/')
+        .map_or(0, |idx| idx + 2);
+    let head_width = width_before(head_len);
+
+    let (head_len, tail_start) = if head_len > 0
+        && head_len < basename_start
+        && head_width + full_width - width_before(basename_start - 1) <= budget
+    {
+        // `src/../nested/main.rs` instead of `src/..ted/main.rs`.
+        let start = suffix_start(budget - head_width);
+        let tail_start = (start..basename_start)
+            .find(|&idx| chars[idx] == '/')
+            .unwrap_or(basename_start);
+        (head_len, tail_start)
+    } else {
+        (0, suffix_start(budget))
+    };
+
+    let text = format!(
+        "{}{ELLIPSIS}{}",
+        chars[..head_len].iter().collect::<String>(),
+        chars[tail_start..].iter().collect::<String>()
+    );
+    let indices = indices
+        .iter()
+        .filter_map(|&idx| {
+            if idx < head_len {
+                Some(idx)
+            } else if idx >= tail_start {
+                Some(idx - tail_start + head_len + ELLIPSIS.len())
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Some((text, indices))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,4 +344,56 @@ mod tests {
             assert_eq!(highlighted, highlighted_got);
         }
     }
+
+    #[test]
+    fn test_trim_middle() {
+        let text = "crates/maple_cli/src/stdio_server/impls/providers/dumb_jump/searcher.rs";
+        let highlighted_of = |text: &str, indices: &[usize]| {
+            indices
+                .iter()
+                .filter_map(|i| text.chars().nth(*i))
+                .collect::<String>()
+        };
+
+        // `crates` and `searcher` of the basename.
+        let indices = (0..6).chain(60..68).collect::<Vec<_>>();
+        assert_eq!(highlighted_of(text, &indices), "cratessearcher");
+
+        let test_cases = [
+            // container_width, display_line, highlighted
+            (40, "crates/../dumb_jump/searcher.rs", "cratessearcher"),
+            (30, "crates/../searcher.rs", "cratessearcher"),
+            (21, "crates/../searcher.rs", "cratessearcher"),
+            // No room for the leading directory.
+            (20, "..b_jump/searcher.rs", "searcher"),
+            (15, "..p/searcher.rs", "searcher"),
+        ];
+
+        for (container_width, display_line, highlighted) in test_cases {
+            let (display_line_got, indices_post) =
+                trim_middle(text, &indices, container_width, 4).unwrap();
+            assert_eq!(display_line_got, display_line);
+            assert!(display_line_got.chars().count() <= container_width);
+            assert_eq!(
+                highlighted_of(&display_line_got, &indices_post),
+                highlighted
+            );
+        }
+
+        // The highlights in the elided part are dropped.
+        let (display_line, indices) = trim_middle(text, &[0, 20, 62], 21, 4).unwrap();
+        assert_eq!(display_line, "crates/../searcher.rs");
+        assert_eq!(indices, vec![0, 12]);
+
+        // The leading separator of an absolute path is not a directory.
+        assert_eq!(
+            trim_middle("/home/user/.cargo/registry/src/serde/de/mod.rs", &[], 24, 4)
+                .unwrap()
+                .0,
+            "/home/../serde/de/mod.rs"
+        );
+
+        // Fits already.
+        assert!(trim_middle(text, &indices, text.len(), 4).is_none());
+    }
 }
@@ -17,6 +17,22 @@ pub type VimLineNumber = usize;
 ///
 pub type LinesTruncatedMap = HashMap<VimLineNumber, String>;
 
+/// Width of the display window used if not reported by the client.
+pub const DEFAULT_WINWIDTH: usize = 100;
+
+/// The width reported by the client is clamped into [`MIN_WINWIDTH`, `MAX_WINWIDTH`] in case of
+/// the bogus values, e.g., 0.
+pub const MIN_WINWIDTH: usize = 20;
+
+pub const MAX_WINWIDTH: usize = 1000;
+
+/// Returns a sane width of the display window given the one reported by the client.
+pub fn normalize_winwidth(winwidth: Option<usize>) -> usize {
+    winwidth
+        .unwrap_or(DEFAULT_WINWIDTH)
+        .clamp(MIN_WINWIDTH, MAX_WINWIDTH)
+}
+
 /// sign column width 2
 #[cfg(not(test))]
 const WINWIDTH_OFFSET: usize = 4;
@@ -24,24 +40,41 @@ const WINWIDTH_OFFSET: usize = 4;
 #[cfg(test)]
 const WINWIDTH_OFFSET: usize = 0;
 
+/// How a line too long to fit into the window is truncated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationMode {
+    /// Cut the left and/or right end of the line to keep the matched part visible.
+    #[default]
+    Auto,
+    /// Elide the middle of a path to keep both the leading directory and the basename.
+    Middle,
+}
+
 fn truncate_line_v1(
     line: &str,
     indices: &mut [usize],
     winwidth: usize,
     skipped: Option<usize>,
+    mode: TruncationMode,
 ) -> Option<(String, Vec<usize>)> {
-    use crate::trimmer::v1::trim_text;
+    use crate::trimmer::v1::{trim_middle, trim_text};
 
-    if line.is_empty() || indices.is_empty() {
+    // The path can be elided even if nothing is matched, e.g., on creating the session.
+    if line.is_empty() || (indices.is_empty() && mode == TruncationMode::Auto) {
         return None;
     }
 
+    let trim = |text: &str, indices: &[usize], container_width: usize, tabstop: usize| match mode {
+        TruncationMode::Auto => trim_text(text, indices, container_width, tabstop),
+        TruncationMode::Middle => trim_middle(text, indices, container_width, tabstop),
+    };
+
     if let Some(skipped) = skipped {
-        let container_width = winwidth - skipped;
+        let container_width = winwidth.saturating_sub(skipped);
         let text = line.chars().skip(skipped).collect::<String>();
         indices.iter_mut().for_each(|x| *x -= 2);
         // TODO: tabstop is not always 4, `:h vim9-differences`
-        trim_text(&text, indices, container_width, 4).map(|(text, mut indices)| {
+        trim(&text, indices, container_width, 4).map(|(text, mut indices)| {
             (
                 format!("{}{}", line.chars().take(skipped).collect::<String>(), text),
                 {
@@ -51,7 +84,7 @@ fn truncate_line_v1(
             )
         })
     } else {
-        trim_text(line, indices, winwidth, 4)
+        trim(line, indices, winwidth, 4)
     }
 }
 
@@ -67,9 +100,19 @@ pub fn truncate_long_matched_lines(
     items: IterMut<MatchedItem>,
     winwidth: usize,
     skipped: Option<usize>,
+) -> LinesTruncatedMap {
+    truncate_long_matched_lines_with_mode(items, winwidth, skipped, TruncationMode::Auto)
+}
+
+/// Same with [`truncate_long_matched_lines`], but the lines are truncated in `mode`.
+pub fn truncate_long_matched_lines_with_mode(
+    items: IterMut<MatchedItem>,
+    winwidth: usize,
+    skipped: Option<usize>,
+    mode: TruncationMode,
 ) -> LinesTruncatedMap {
     let mut truncated_map = HashMap::new();
-    let winwidth = winwidth - WINWIDTH_OFFSET;
+    let winwidth = normalize_winwidth(Some(winwidth)) - WINWIDTH_OFFSET;
     items.enumerate().for_each(|(lnum, mut matched_item)| {
         let output_text = matched_item.item.output_text();
 
@@ -78,9 +121,13 @@ pub fn truncate_long_matched_lines(
             let truncated_output_text: String = output_text.chars().take(1000).collect();
             matched_item.display_text = Some(truncated_output_text);
             matched_item.indices.retain(|&x| x < 1000);
-        } else if let Some((truncated_output_text, truncated_indices)) =
-            truncate_line_v1(&output_text, &mut matched_item.indices, winwidth, skipped)
-        {
+        } else if let Some((truncated_output_text, truncated_indices)) = truncate_line_v1(
+            &output_text,
+            &mut matched_item.indices,
+            winwidth,
+            skipped,
+            mode,
+        ) {
             truncated_map.insert(lnum + 1, output_text.to_string());
 
             matched_item.display_text = Some(truncated_output_text);
@@ -96,7 +143,7 @@ pub fn truncate_long_matched_lines_v0(
     skipped: Option<usize>,
 ) -> LinesTruncatedMap {
     let mut truncated_map = HashMap::new();
-    let winwidth = winwidth - WINWIDTH_OFFSET;
+    let winwidth = normalize_winwidth(Some(winwidth)) - WINWIDTH_OFFSET;
     items.enumerate().for_each(|(lnum, matched_item)| {
         let output_text = matched_item.item.output_text();
 
@@ -120,7 +167,7 @@ pub fn truncate_grep_lines(
 ) -> (Vec<String>, Vec<Vec<usize>>, LinesTruncatedMap) {
     let mut truncated_map = HashMap::new();
     let mut lnum = 0usize;
-    let winwidth = winwidth - WINWIDTH_OFFSET;
+    let winwidth = normalize_winwidth(Some(winwidth)) - WINWIDTH_OFFSET;
     let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = lines
         .into_iter()
         .zip(indices.into_iter())
@@ -128,7 +175,7 @@ pub fn truncate_grep_lines(
             lnum += 1;
 
             if let Some((truncated_line, truncated_indices)) =
-                truncate_line_v1(&line, &mut indices, winwidth, skipped)
+                truncate_line_v1(&line, &mut indices, winwidth, skipped, TruncationMode::Auto)
             {
                 truncated_map.insert(lnum, line);
                 (truncated_line, truncated_indices)
@@ -10,8 +10,8 @@ pub use self::search_term::{
     TermType,
 };
 pub use self::source_item::{
-    extract_fuzzy_text, AsAny, ClapItem, FileNameItem, FuzzyText, GrepItem, MatchScope,
-    MatchedItem, SourceItem,
+    extract_fuzzy_text, AsAny, BufferLine, ClapItem, FileNameItem, FuzzyText, GrepItem, MatchScope,
+    MatchedItem, SourceItem, FIELD_SEPARATOR,
 };
 
 /// The preview content is usually part of a file.
@@ -25,7 +25,7 @@ pub struct PreviewInfo {
     pub lines: Vec<String>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Hash)]
 pub enum CaseMatching {
     Ignore,
     Respect,
@@ -40,7 +40,7 @@ impl<'a> FuzzyText<'a> {
 /// The location that a match should look in.
 ///
 /// Given a query, the match scope can refer to a full string or a substring.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MatchScope {
     Full,
     /// `:Clap tags`, `:Clap proj_tags`
@@ -96,6 +96,11 @@ pub trait ClapItem: AsAny + std::fmt::Debug + Send + Sync + 'static {
         extract_fuzzy_text(self.match_text(), match_scope)
     }
 
+    /// Hidden texts matched against when the match text does not match, never displayed.
+    fn keywords(&self) -> &[String] {
+        &[]
+    }
+
     // TODO: Each bonus can have its own range of `bonus_text`, make use of MatchScope.
     /// Text for calculating the bonus score to tweak the initial matching score.
     fn bonus_text(&self) -> &str {
@@ -215,6 +220,47 @@ impl ClapItem for FileNameItem {
     }
 }
 
+/// A line of the buffer shared by all the lines of a source, e.g., a file read at once.
+///
+/// Unlike [`SourceItem`] which owns a copy of the line, it only references the range of line in
+/// the buffer, no allocation is made for the line itself.
+#[derive(Debug, Clone)]
+pub struct BufferLine {
+    buffer: Arc<String>,
+    start: usize,
+    len: usize,
+}
+
+impl BufferLine {
+    /// Returns the lines of `buffer`, the line terminators `\n` and `\r\n` are excluded.
+    pub fn split(buffer: Arc<String>) -> impl Iterator<Item = Self> {
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            if start >= buffer.len() {
+                return None;
+            }
+            let end = buffer[start..]
+                .find('\n')
+                .map_or(buffer.len(), |pos| start + pos);
+            let line = &buffer[start..end];
+            let len = line.strip_suffix('\r').unwrap_or(line).len();
+            let buffer_line = Self {
+                buffer: buffer.clone(),
+                start,
+                len,
+            };
+            start = end + 1;
+            Some(buffer_line)
+        })
+    }
+}
+
+impl ClapItem for BufferLine {
+    fn raw_text(&self) -> &str {
+        &self.buffer[self.start..self.start + self.len]
+    }
+}
+
 /// This type represents multiple kinds of concrete Clap item from providers like grep,
 /// proj_tags, files, etc.
 #[derive(Debug, Clone)]
@@ -229,12 +275,20 @@ pub struct SourceItem {
     pub output_text: Option<String>,
 }
 
+/// Separator splitting a source line into the display text and the filter text, i.e.,
+/// `display_text\x1ffilter_text`.
+pub const FIELD_SEPARATOR: char = '\x1f';
+
 impl From<String> for SourceItem {
     fn from(raw: String) -> Self {
+        let (fuzzy_text, output_text) = match raw.split_once(FIELD_SEPARATOR) {
+            Some((display, filter)) => (Some((filter.to_string(), 0)), Some(display.to_string())),
+            None => (None, None),
+        };
         Self {
             raw,
-            fuzzy_text: None,
-            output_text: None,
+            fuzzy_text,
+            output_text,
         }
     }
 }
@@ -280,6 +334,26 @@ impl ClapItem for SourceItem {
         self.fuzzy_text_or_exact_using_match_scope(match_scope)
     }
 
+    fn match_text(&self) -> &str {
+        // Only the line split by the separator is matched on its filter text, `fuzzy_text` set
+        // otherwise is not used for the other matching.
+        match self.raw.split_once(FIELD_SEPARATOR) {
+            Some((_display, filter)) => filter,
+            None => &self.raw,
+        }
+    }
+
+    fn match_result_callback(&self, match_result: MatchResult) -> MatchResult {
+        // The matched filter text is hidden, nothing to highlight in the display text.
+        if self.raw.contains(FIELD_SEPARATOR) {
+            let mut match_result = match_result;
+            match_result.indices.clear();
+            match_result
+        } else {
+            match_result
+        }
+    }
+
     fn output_text(&self) -> Cow<'_, str> {
         self.output_text_or_raw().into()
     }
@@ -334,6 +408,20 @@ impl MatchedItem {
         }
     }
 
+    /// Returns the id of item, which is stable for the same line regardless of the query.
+    ///
+    /// The hash is truncated to 53 bits so that the id is exact in any JSON decoder, which may
+    /// parse the numbers as doubles or as signed 64-bit integers like Vim.
+    pub fn id(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        const ID_MASK: u64 = (1 << 53) - 1;
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.item.raw_text().hash(&mut hasher);
+        hasher.finish() & ID_MASK
+    }
+
     /// Returns the match indices shifted by `offset`.
     pub fn shifted_indices(&self, offset: usize) -> Vec<usize> {
         self.indices.iter().map(|x| x + offset).collect()
@@ -1,5 +1,5 @@
 use std::fs::{read_dir, remove_dir_all, remove_file, File};
-use std::io::{BufRead, BufReader, Error, ErrorKind, Lines, Read, Result};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Lines, Read, Result, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
 use types::PreviewInfo;
@@ -100,16 +100,22 @@ fn initial_buffer_size(file: &File) -> usize {
     file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0)
 }
 
+/// Returns the range of lines `[start, end)` to preview around `target_line` and the line
+/// number of `target_line` in the range.
+fn preview_range(target_line: usize, size: usize) -> (usize, usize, usize) {
+    if target_line > size {
+        (target_line - size, target_line + size, size)
+    } else {
+        (0, 2 * size, target_line)
+    }
+}
+
 fn read_preview_lines_impl<P: AsRef<Path>>(
     path: P,
     target_line: usize,
     size: usize,
 ) -> Result<PreviewInfo> {
-    let (start, end, highlight_lnum) = if target_line > size {
-        (target_line - size, target_line + size, size)
-    } else {
-        (0, 2 * size, target_line)
-    };
+    let (start, end, highlight_lnum) = preview_range(target_line, size);
 
     let mut filebuf: Vec<u8> = Vec::new();
 
@@ -147,6 +153,109 @@ fn read_preview_lines_impl<P: AsRef<Path>>(
         })
 }
 
+/// Number of lines between two adjacent entries of [`LineIndex`].
+pub const LINE_INDEX_STEP: usize = 1024;
+
+/// Sparse index of the byte offsets of lines in a file, every [`LINE_INDEX_STEP`] lines.
+///
+/// It's built by one scan of the file, then the lines anywhere in the file can be read by
+/// seeking to the nearest indexed line instead of scanning from the start.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// Byte offset of the line `i * LINE_INDEX_STEP` at `i`.
+    offsets: Vec<u64>,
+    /// Total number of lines.
+    lines: usize,
+    /// Total bytes of the file.
+    len: u64,
+}
+
+impl LineIndex {
+    pub fn build<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = BufReader::with_capacity(64 * 1024, File::open(path)?);
+        let mut offsets = vec![0];
+        let mut lines = 0;
+        let mut pos = 0;
+        // Whether the last line is terminated by a newline.
+        let mut terminated = true;
+        loop {
+            let len = {
+                let buf = reader.fill_buf()?;
+                if buf.is_empty() {
+                    break;
+                }
+                for newline in memchr::memchr_iter(b'\n', buf) {
+                    lines += 1;
+                    if lines % LINE_INDEX_STEP == 0 {
+                        offsets.push(pos + newline as u64 + 1);
+                    }
+                }
+                terminated = buf.ends_with(b"\n");
+                buf.len()
+            };
+            pos += len as u64;
+            reader.consume(len);
+        }
+        if !terminated {
+            lines += 1;
+        }
+        Ok(Self {
+            offsets,
+            lines,
+            len: pos,
+        })
+    }
+
+    /// Returns the total number of lines.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Returns the byte range of the `block`-th run of [`LINE_INDEX_STEP`] lines, the last
+    /// block may have fewer lines. `None` if the block is beyond the end of file.
+    pub fn block_range(&self, block: usize) -> Option<(u64, u64)> {
+        let start = *self.offsets.get(block)?;
+        let end = self.offsets.get(block + 1).copied().unwrap_or(self.len);
+        (start < end).then_some((start, end))
+    }
+
+    /// Returns the nearest indexed line at or before `line` and its byte offset.
+    fn seek_point(&self, line: usize) -> (usize, u64) {
+        let idx = (line / LINE_INDEX_STEP).min(self.offsets.len() - 1);
+        (idx * LINE_INDEX_STEP, self.offsets[idx])
+    }
+}
+
+/// Same with [`read_preview_lines`], but the lines are read by seeking from `line_index` of
+/// `path` instead of loading the entire file, for the large files.
+pub fn read_preview_lines_indexed<P: AsRef<Path>>(
+    path: P,
+    target_line: usize,
+    size: usize,
+    line_index: &LineIndex,
+) -> Result<PreviewInfo> {
+    let (start, end, highlight_lnum) = preview_range(target_line, size);
+    let (indexed_line, offset) = line_index.seek_point(start);
+
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let lines = BufReader::new(file)
+        .split(b'\n')
+        .skip(start - indexed_line)
+        .take(end - start)
+        .filter_map(|line| line.ok())
+        // trim_end() to get rid of ^M on Windows.
+        .map(|line| String::from_utf8_lossy(&line).trim_end().to_string())
+        .collect();
+
+    Ok(PreviewInfo {
+        start,
+        end,
+        highlight_lnum,
+        lines,
+    })
+}
+
 /// Returns an iterator of `n` lines of `filename` from the line number `from`.
 pub fn read_lines_from<P: AsRef<Path>>(
     path: P,
@@ -200,4 +309,32 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_read_preview_lines_indexed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines.txt");
+        let content = (1..=5000)
+            .map(|i| format!("line {i}\r\n"))
+            .collect::<String>();
+        std::fs::write(&path, &content).unwrap();
+
+        let line_index = LineIndex::build(&path).unwrap();
+        assert_eq!(line_index.offsets.len(), 5);
+        assert_eq!(line_index.lines(), 5000);
+        let first_block_len = content
+            .split_inclusive('\n')
+            .take(1024)
+            .map(|l| l.len() as u64)
+            .sum();
+        assert_eq!(line_index.block_range(0), Some((0, first_block_len)));
+        assert!(line_index.block_range(5).is_none());
+
+        for target_line in [1, 10, 1024, 1030, 2047, 4096, 4999, 5000, 6000] {
+            let expected = read_preview_lines_impl(&path, target_line, 10).unwrap();
+            let indexed = read_preview_lines_indexed(&path, target_line, 10, &line_index).unwrap();
+            assert_eq!(indexed.lines, expected.lines, "target_line: {target_line}");
+            assert_eq!(indexed.highlight_lnum, expected.highlight_lnum);
+        }
+    }
 }
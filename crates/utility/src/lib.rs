@@ -12,7 +12,8 @@ mod macros;
 
 pub use self::io::{
     clap_cache_dir, create_or_overwrite, read_first_lines, read_lines, read_lines_from,
-    read_preview_lines, remove_dir_contents,
+    read_preview_lines, read_preview_lines_indexed, remove_dir_contents, LineIndex,
+    LINE_INDEX_STEP,
 };
 
 /// Returns true if the `dir` is a git repo, including git submodule.
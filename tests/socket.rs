@@ -0,0 +1,110 @@
+//! Talks to `maple rpc` over a Unix domain socket like the editor does.
+
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+fn spawn_maple(socket: &Path) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_maple"))
+        .arg("--socket")
+        .arg(socket)
+        .arg("rpc")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+/// Sends a `clap#session_log` request and returns the body of its response.
+fn request_session_log(stream: UnixStream) -> String {
+    let mut writer = stream.try_clone().unwrap();
+    writeln!(
+        writer,
+        r#"{{"id":1,"method":"clap#session_log","params":{{}},"session_id":0}}"#
+    )
+    .unwrap();
+
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        let len = header
+            .trim()
+            .strip_prefix("Content-length: ")
+            .and_then(|len| len.parse::<usize>().ok())
+            .unwrap_or_else(|| panic!("Invalid header: {header:?}"));
+
+        let mut separator = String::new();
+        reader.read_line(&mut separator).unwrap();
+
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).unwrap();
+        let body = String::from_utf8(body).unwrap();
+
+        // Skip the trailing line ending of message.
+        let mut line_ending = String::new();
+        reader.read_line(&mut line_ending).unwrap();
+
+        if body.contains(r#""id":1"#) {
+            return body;
+        }
+    }
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("maple_test_{name}.sock"));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_rpc_listening_on_socket() {
+    let path = socket_path("listening");
+    let mut maple = spawn_maple(&path);
+
+    let stream = loop {
+        if let Ok(stream) = UnixStream::connect(&path) {
+            break stream;
+        }
+        assert!(maple.try_wait().unwrap().is_none(), "maple exited");
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let response = request_session_log(stream);
+    assert!(response.contains(r#""events":[]"#), "{response}");
+
+    maple.kill().unwrap();
+    maple.wait().unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_rpc_connecting_to_socket() {
+    let path = socket_path("connecting");
+    let listener = UnixListener::bind(&path).unwrap();
+    let mut maple = spawn_maple(&path);
+
+    let (stream, _addr) = listener.accept().unwrap();
+    let response = request_session_log(stream);
+    assert!(response.contains(r#""events":[]"#), "{response}");
+
+    maple.kill().unwrap();
+    maple.wait().unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_rpc_never_removes_regular_file() {
+    let path = socket_path("regular_file");
+    std::fs::write(&path, "important").unwrap();
+
+    let status = spawn_maple(&path).wait().unwrap();
+    assert!(!status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "important");
+
+    std::fs::remove_file(path).unwrap();
+}